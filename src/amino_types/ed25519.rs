@@ -41,17 +41,22 @@ impl TryFrom<PubKeyResponse> for PublicKey {
     }
 }
 
-impl From<PublicKey> for PubKeyResponse {
-    fn from(public_key: PublicKey) -> PubKeyResponse {
-        if let PublicKey::Ed25519(ref pk) = public_key {
-            PubKeyResponse {
+impl TryFrom<PublicKey> for PubKeyResponse {
+    type Error = Error;
+
+    // Only Ed25519 keys can be encoded in Amino `PubKeyResponse`s: other key
+    // types require the Protobuf protocol (Tendermint v0.34+)
+    fn try_from(public_key: PublicKey) -> Result<PubKeyResponse, Error> {
+        match public_key {
+            PublicKey::Ed25519(ref pk) => Ok(PubKeyResponse {
                 pub_key_ed25519: pk.as_bytes().to_vec(),
-            }
-        } else {
-            unimplemented!(
-                "PubKeyResponse unimplemented for this type: {:?}",
-                public_key
-            );
+            }),
+            other => Err(format_err!(
+                error::Kind::InvalidKey,
+                "unsupported key type for legacy protocol (use v0.34+): {:?}",
+                other
+            )
+            .into()),
         }
     }
 }
@@ -171,7 +176,7 @@ mod tests {
         assert_eq!(got, want);
 
         // and back:
-        let round_trip_pk: PubKeyResponse = got.into();
+        let round_trip_pk: PubKeyResponse = got.try_into().unwrap();
         assert_eq!(round_trip_pk, orig);
     }
 
@@ -184,4 +189,16 @@ mod tests {
         // we expect this to panic:
         let _got: PublicKey = empty_msg.try_into().unwrap();
     }
+
+    #[test]
+    fn rejects_secp256k1_pubkey_response() {
+        let public_key = PublicKey::from_raw_secp256k1(&[
+            0x02, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce,
+            0x87, 0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81,
+            0x5b, 0x16, 0xf8, 0x17, 0x98,
+        ])
+        .unwrap();
+
+        assert!(PubKeyResponse::try_from(public_key).is_err());
+    }
 }
@@ -10,7 +10,6 @@ use super::{
 use crate::{config::validator::ProtocolVersion, rpc};
 use bytes::BufMut;
 use bytes_v0_5::BytesMut as BytesMutV05;
-use ed25519_dalek as ed25519;
 use once_cell::sync::Lazy;
 use prost::Message as _;
 use prost_amino::{EncodeError, Message};
@@ -58,20 +57,20 @@ pub struct SignProposalRequest {
 }
 
 #[derive(Clone, PartialEq, Message)]
-struct CanonicalProposal {
+pub struct CanonicalProposal {
     #[prost_amino(uint32, tag = "1")]
-    msg_type: u32, /* this is a byte in golang, which is a varint encoded UInt8 (using amino's
-                    * EncodeUvarint) */
+    pub msg_type: u32, /* this is a byte in golang, which is a varint encoded UInt8 (using amino's
+                        * EncodeUvarint) */
     #[prost_amino(sfixed64)]
-    height: i64,
+    pub height: i64,
     #[prost_amino(sfixed64)]
-    round: i64,
+    pub round: i64,
     #[prost_amino(sfixed64)]
-    pol_round: i64,
+    pub pol_round: i64,
     #[prost_amino(message)]
-    block_id: Option<CanonicalBlockId>,
+    pub block_id: Option<CanonicalBlockId>,
     #[prost_amino(message)]
-    timestamp: Option<TimeMsg>,
+    pub timestamp: Option<TimeMsg>,
     #[prost_amino(string)]
     pub chain_id: String,
 }
@@ -169,9 +168,9 @@ impl SignableMsg for SignProposalRequest {
 
         Ok(true)
     }
-    fn set_signature(&mut self, sig: &ed25519::Signature) {
+    fn set_signature(&mut self, sig: &[u8]) {
         if let Some(ref mut prop) = self.proposal {
-            prop.signature = sig.as_ref().to_vec();
+            prop.signature = sig.to_vec();
         }
     }
     fn validate(&self) -> Result<(), validate::Error> {
@@ -1,7 +1,6 @@
 use super::validate;
 use crate::config::validator::ProtocolVersion;
 use bytes::BufMut;
-use ed25519_dalek as ed25519;
 use prost_amino::{DecodeError, EncodeError};
 use tendermint::{chain, consensus};
 
@@ -15,8 +14,8 @@ pub trait SignableMsg {
         sign_bytes: &mut B,
     ) -> Result<bool, EncodeError>;
 
-    /// Set the Ed25519 signature on the underlying message
-    fn set_signature(&mut self, sig: &ed25519::Signature);
+    /// Set the (Ed25519 or ECDSA) signature on the underlying message
+    fn set_signature(&mut self, sig: &[u8]);
     fn validate(&self) -> Result<(), validate::Error>;
     fn consensus_state(&self) -> Option<consensus::State>;
     fn height(&self) -> Option<i64>;
@@ -10,7 +10,6 @@ use super::{
 use crate::{config::validator::ProtocolVersion, rpc};
 use bytes::BufMut;
 use bytes_v0_5::BytesMut as BytesMutV05;
-use ed25519_dalek as ed25519;
 use once_cell::sync::Lazy;
 use prost::Message as _;
 use prost_amino::{error::EncodeError, Message};
@@ -229,9 +228,9 @@ impl SignableMsg for SignVoteRequest {
 
         Ok(true)
     }
-    fn set_signature(&mut self, sig: &ed25519::Signature) {
+    fn set_signature(&mut self, sig: &[u8]) {
         if let Some(ref mut vt) = self.vote {
-            vt.signature = sig.as_ref().to_vec();
+            vt.signature = sig.to_vec();
         }
     }
     fn validate(&self) -> Result<(), validate::Error> {
@@ -1,10 +1,19 @@
 //! Abscissa `Application` for the KMS
 
-use crate::{commands::KmsCommand, config::KmsConfig};
+use crate::{
+    commands::KmsCommand,
+    config::KmsConfig,
+    key_utils,
+    logging::{self, LogFormat},
+    shutdown,
+};
 use abscissa_core::{
     application::{self, AppCell},
     config::{self, CfgCell},
-    trace, Application, FrameworkError, StandardPaths,
+    terminal::component::Terminal,
+    trace, Application, Component, FrameworkError,
+    FrameworkErrorKind::ComponentError,
+    StandardPaths,
 };
 
 /// Application state
@@ -55,8 +64,17 @@ impl Application for KmsApplication {
     /// beyond the default ones provided by the framework, this is the place
     /// to do so.
     fn register_components(&mut self, command: &Self::Cmd) -> Result<(), FrameworkError> {
+        // Components may spawn threads (e.g. the Tokio runtime), which must
+        // inherit a signal mask blocking the signals `tmkms start` handles
+        if let KmsCommand::Start(_) = command {
+            shutdown::block_signals().map_err(|e| ComponentError.context(e))?;
+        }
+
         #[allow(unused_mut)]
-        let mut components = self.framework_components(command)?;
+        let mut components = match command.log_format() {
+            LogFormat::Text => self.framework_components(command)?,
+            LogFormat::Json => self.json_logging_components(command)?,
+        };
 
         #[cfg(feature = "tx-signer")]
         components.push(Box::new(abscissa_tokio::TokioComponent::new()?));
@@ -73,6 +91,7 @@ impl Application for KmsApplication {
     fn after_config(&mut self, config: Self::Cfg) -> Result<(), FrameworkError> {
         let mut component_registry = self.state.components_mut();
         component_registry.after_config(&config)?;
+        key_utils::permissions::init(config.secrets.clone());
         self.config.set_once(config);
         Ok(())
     }
@@ -81,8 +100,35 @@ impl Application for KmsApplication {
     fn tracing_config(&self, command: &KmsCommand) -> trace::Config {
         if command.verbose() {
             trace::Config::verbose()
+        } else if command.quiet() {
+            trace::Config::from("warn".to_owned())
         } else {
             trace::Config::default()
         }
     }
 }
+
+impl KmsApplication {
+    /// Framework components for JSON logging: abscissa's `Tracing` component
+    /// only supports its default text format, so install our own subscriber
+    /// in its place
+    fn json_logging_components(
+        &mut self,
+        command: &KmsCommand,
+    ) -> Result<Vec<Box<dyn Component<Self>>>, FrameworkError> {
+        logging::init_json(tracing_filter(command))?;
+        Ok(vec![Box::new(Terminal::new(self.term_colors(command)))])
+    }
+}
+
+/// Get the tracing filter from command-line options (matches the filters
+/// used by `trace::Config`)
+fn tracing_filter(command: &KmsCommand) -> &'static str {
+    if command.verbose() {
+        "debug"
+    } else if command.quiet() {
+        "warn"
+    } else {
+        "info"
+    }
+}
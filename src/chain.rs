@@ -3,11 +3,13 @@
 mod guard;
 mod registry;
 pub mod state;
+pub mod watchdog;
 
 pub use self::{
     guard::Guard,
     registry::{GlobalRegistry, Registry, REGISTRY},
     state::State,
+    watchdog::Watchdog,
 };
 use crate::{
     config::{chain::ChainConfig, KmsConfig},
@@ -15,7 +17,10 @@ use crate::{
     keyring::{self, KeyRing},
     prelude::*,
 };
-use std::{path::PathBuf, sync::Mutex};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 pub use tendermint::chain::Id;
 
 /// Information about a particular Tendermint blockchain network
@@ -28,17 +33,15 @@ pub struct Chain {
 
     /// State from the last block signed for this chain
     pub state: Mutex<State>,
+
+    /// Missed-signature watchdog for this chain (if configured)
+    pub watchdog: Option<Arc<Watchdog>>,
 }
 
 impl Chain {
     /// Attempt to create a `Chain` state from the given configuration
     pub fn from_config(config: &ChainConfig) -> Result<Chain, Error> {
-        let state_file = match config.state_file {
-            Some(ref path) => path.to_owned(),
-            None => PathBuf::from(&format!("{}_priv_validator_state.json", config.id)),
-        };
-
-        let mut state = State::load_state(state_file)?;
+        let mut state = State::load_state(&config.id, state_file_path(config))?;
 
         if let Some(ref hook) = config.state_hook {
             match state::hook::run(hook) {
@@ -48,22 +51,49 @@ impl Chain {
                         return Err(e);
                     } else {
                         // fail open: note the error to the log and proceed anyway
-                        error!("error invoking state hook for chain {}: {}", config.id, e);
+                        error!(chain_id = %config.id, "error invoking state hook: {}", e);
                     }
                 }
             }
         }
 
+        let watchdog = match &config.watchdog {
+            Some(watchdog_config) => Some(Arc::new(Watchdog::new(&config.id, watchdog_config)?)),
+            None => None,
+        };
+
         Ok(Self {
             id: config.id.clone(),
             keyring: KeyRing::new(config.key_format.clone()),
             state: Mutex::new(state),
+            watchdog,
         })
     }
+
+    /// Create a `Chain` for inspecting the keys configured for it (e.g. with
+    /// `tmkms keys list`), without loading or creating its state file,
+    /// running its state hook, or starting its watchdog
+    pub fn without_state(config: &ChainConfig) -> Chain {
+        Self {
+            id: config.id.clone(),
+            keyring: KeyRing::new(config.key_format.clone()),
+            state: Mutex::new(State::initial(&config.id, &state_file_path(config))),
+            watchdog: None,
+        }
+    }
+}
+
+/// Path to the state file for the given chain
+fn state_file_path(config: &ChainConfig) -> PathBuf {
+    match config.state_file {
+        Some(ref path) => path.to_owned(),
+        None => PathBuf::from(&format!("{}_priv_validator_state.json", config.id)),
+    }
 }
 
-/// Initialize the chain registry from the configuration file
-pub fn load_config(config: &KmsConfig) -> Result<(), Error> {
+/// Initialize the chain registry from the configuration file, returning the
+/// signing providers' background tasks (which the caller must start)
+pub fn load_config(config: &KmsConfig) -> Result<keyring::Tasks, Error> {
     for config in &config.chain {
         REGISTRY.register(Chain::from_config(config)?)?;
     }
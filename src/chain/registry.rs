@@ -23,6 +23,25 @@ impl Registry {
         &mut self,
         chain_id: &Id,
         signer: keyring::ecdsa::Signer,
+    ) -> Result<(), Error> {
+        self.add_ecdsa_key(chain_id, signer)
+    }
+
+    /// Add a consensus key to a keyring for a chain stored in the registry
+    pub fn add_consensus_key(
+        &mut self,
+        chain_id: &Id,
+        signer: keyring::ed25519::Signer,
+    ) -> Result<(), Error> {
+        self.add_ed25519_key(chain_id, signer)
+    }
+
+    /// Add an ECDSA key (of either type) to a keyring for a chain stored in
+    /// the registry
+    pub fn add_ecdsa_key(
+        &mut self,
+        chain_id: &Id,
+        signer: keyring::ecdsa::Signer,
     ) -> Result<(), Error> {
         let chain = self.0.get_mut(chain_id).ok_or_else(|| {
             format_err!(
@@ -36,8 +55,9 @@ impl Registry {
         chain.keyring.add_ecdsa(signer)
     }
 
-    /// Add a consensus key to a keyring for a chain stored in the registry
-    pub fn add_consensus_key(
+    /// Add an Ed25519 key (of either type) to a keyring for a chain stored in
+    /// the registry
+    pub fn add_ed25519_key(
         &mut self,
         chain_id: &Id,
         signer: keyring::ed25519::Signer,
@@ -9,6 +9,7 @@ pub mod hook;
 pub use self::error::{StateError, StateErrorKind};
 
 use crate::{
+    chain,
     error::{Error, ErrorKind::*},
     prelude::*,
 };
@@ -22,13 +23,14 @@ use tendermint::consensus;
 
 /// State tracking for double signing prevention
 pub struct State {
+    chain_id: chain::Id,
     consensus_state: consensus::State,
     state_file_path: PathBuf,
 }
 
 impl State {
-    /// Load the state from the given path
-    pub fn load_state<P>(path: P) -> Result<Self, Error>
+    /// Load the state for the given chain from the given path
+    pub fn load_state<P>(chain_id: &chain::Id, path: P) -> Result<Self, Error>
     where
         P: AsRef<Path>,
     {
@@ -44,12 +46,13 @@ impl State {
                 })?;
 
                 Ok(Self {
+                    chain_id: chain_id.clone(),
                     consensus_state,
                     state_file_path: path.as_ref().to_owned(),
                 })
             }
             Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                Self::write_initial_state(path.as_ref())
+                Self::write_initial_state(chain_id, path.as_ref())
             }
             Err(e) => Err(Error::from(e)),
         }
@@ -141,9 +144,17 @@ impl State {
                 new_state.height = output.latest_block_height;
                 self.consensus_state = new_state;
 
-                info!("updated block height from hook: {}", hook_height);
+                info!(
+                    chain_id = %self.chain_id,
+                    height = hook_height,
+                    "updated block height from hook: {}",
+                    hook_height
+                );
             } else {
                 warn!(
+                    chain_id = %self.chain_id,
+                    height = last_height,
+                    hook_height,
                     "hook block height more than sanity limit: {} (delta: {}, max: {})",
                     output.latest_block_height,
                     delta,
@@ -152,8 +163,12 @@ impl State {
             }
         } else {
             warn!(
+                chain_id = %self.chain_id,
+                height = last_height,
+                hook_height,
                 "hook block height less than current? current: {}, hook: {}",
-                last_height, hook_height
+                last_height,
+                hook_height
             );
         }
 
@@ -161,26 +176,36 @@ impl State {
     }
 
     /// Write the initial state to the given path on disk
-    fn write_initial_state(path: &Path) -> Result<Self, Error> {
+    fn write_initial_state(chain_id: &chain::Id, path: &Path) -> Result<Self, Error> {
+        let initial_state = Self::initial(chain_id, path);
+        initial_state.sync_to_disk()?;
+        Ok(initial_state)
+    }
+
+    /// Create the initial state for the given chain (without writing it to
+    /// the given path)
+    pub(crate) fn initial(chain_id: &chain::Id, path: &Path) -> Self {
         let mut consensus_state = consensus::State::default();
 
         // TODO(tarcieri): correct upstream `tendermint-rs` default height to 0
         // Set the initial block height to 0 to indicate we've never signed a block
         consensus_state.height = 0u32.into();
 
-        let initial_state = Self {
+        Self {
+            chain_id: chain_id.clone(),
             consensus_state,
             state_file_path: path.to_owned(),
-        };
-
-        initial_state.sync_to_disk()?;
-
-        Ok(initial_state)
+        }
     }
 
     /// Sync the current state to disk
     fn sync_to_disk(&self) -> io::Result<()> {
         debug!(
+            chain_id = %self.chain_id,
+            height = self.consensus_state.height.value(),
+            round = self.consensus_state.round.value(),
+            step = self.consensus_state.step,
+            block_id = %self.consensus_state.block_id_prefix(),
             "writing new consensus state to {}: {:?}",
             self.state_file_path.display(),
             &self.consensus_state
@@ -194,9 +219,11 @@ impl State {
 
         let mut state_file = NamedTempFile::new_in(state_file_dir)?;
         state_file.write_all(json.as_bytes())?;
+        state_file.as_file().sync_all()?;
         state_file.persist(&self.state_file_path)?;
 
         debug!(
+            chain_id = %self.chain_id,
             "successfully wrote new consensus state to {}",
             self.state_file_path.display(),
         );
@@ -216,6 +243,8 @@ mod tests {
     const EXAMPLE_DOUBLE_SIGN_BLOCK_ID: &str =
         "2470A41F3243C6BCD7AD2DFF8A8D83A71D29D307B5326C227F734A1A512FE47D";
 
+    const EXAMPLE_CHAIN_ID: &str = "test_chain_id";
+
     const EXAMPLE_PATH: &str = "/tmp/tmp_state.json";
 
     /// Macro for compactly expressing a consensus state
@@ -243,6 +272,7 @@ mod tests {
             #[test]
             fn $name() {
                 State {
+                    chain_id: EXAMPLE_CHAIN_ID.parse().unwrap(),
                     consensus_state: $old_state,
                     state_file_path: EXAMPLE_PATH.into(),
                 }
@@ -258,6 +288,7 @@ mod tests {
             #[test]
             fn $name() {
                 let err = State {
+                    chain_id: EXAMPLE_CHAIN_ID.parse().unwrap(),
                     consensus_state: $old_state,
                     state_file_path: EXAMPLE_PATH.into(),
                 }
@@ -0,0 +1,352 @@
+//! Missed-signature watchdog: detects when the KMS has stopped signing for a
+//! chain, either because no signature has been produced for too long or
+//! because the chain (as reported by a Tendermint RPC endpoint) has moved
+//! ahead of the last height we signed.
+
+use crate::{
+    chain,
+    config::chain::WatchdogConfig,
+    error::{Error, ErrorKind::*},
+    http,
+    notify::{self, EventKind},
+    prelude::*,
+    shutdown,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Default maximum number of blocks our last signature may lag behind the
+/// chain's latest height
+pub const DEFAULT_MAX_HEIGHT_LAG: u64 = 10;
+
+/// Default interval between checks (in seconds)
+pub const DEFAULT_CHECK_INTERVAL_SECS: u64 = 10;
+
+/// Exit status used when a watchdog trips in strict mode
+pub const STRICT_EXIT_CODE: i32 = 3;
+
+/// Timeout for Tendermint RPC requests
+const RPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Missed-signature watchdog for a particular chain
+pub struct Watchdog {
+    /// Chain this watchdog is monitoring
+    chain_id: chain::Id,
+
+    /// Maximum amount of time without a signature
+    max_idle: Duration,
+
+    /// Tendermint RPC `/status` URL (if configured)
+    rpc_status_url: Option<http::Url>,
+
+    /// Maximum number of blocks our last signature may lag behind the chain
+    max_height_lag: u64,
+
+    /// Interval between checks
+    check_interval: Duration,
+
+    /// Exit the process when the watchdog trips
+    strict: bool,
+
+    /// Time and height of the last successful signature
+    last_signature: Mutex<LastSignature>,
+
+    /// Is the watchdog currently tripped?
+    tripped: AtomicBool,
+}
+
+/// Time and height of the last successful signature
+#[derive(Copy, Clone, Debug)]
+struct LastSignature {
+    /// Time of the last signature (or when the watchdog was created)
+    time: Instant,
+
+    /// Height of the last signature (if we've signed anything)
+    height: Option<u64>,
+}
+
+impl Watchdog {
+    /// Create a new watchdog for the given chain
+    pub fn new(chain_id: &chain::Id, config: &WatchdogConfig) -> Result<Self, Error> {
+        let rpc_status_url = match &config.rpc_addr {
+            Some(addr) => Some(addr.parse::<http::Url>()?.with_path("/status")),
+            None => None,
+        };
+
+        Ok(Self {
+            chain_id: chain_id.clone(),
+            max_idle: Duration::from_secs(config.max_idle_secs),
+            rpc_status_url,
+            max_height_lag: config.max_height_lag.unwrap_or(DEFAULT_MAX_HEIGHT_LAG),
+            check_interval: Duration::from_secs(
+                config
+                    .check_interval_secs
+                    .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS),
+            ),
+            strict: config.strict,
+            last_signature: Mutex::new(LastSignature {
+                time: Instant::now(),
+                height: None,
+            }),
+            tripped: AtomicBool::new(false),
+        })
+    }
+
+    /// Record a successful signature at the given height
+    pub fn record_signature(&self, height: u64) {
+        let mut last_signature = self.last_signature.lock().unwrap();
+        last_signature.time = Instant::now();
+        last_signature.height = Some(height);
+    }
+
+    /// Get the height of the last successful signature
+    pub fn last_signed_height(&self) -> Option<u64> {
+        self.last_signature.lock().unwrap().height
+    }
+
+    /// Is the watchdog currently tripped?
+    pub fn is_tripped(&self) -> bool {
+        self.tripped.load(Ordering::SeqCst)
+    }
+
+    /// Check whether we're keeping up with the chain, given its latest
+    /// height (if known)
+    pub fn check(&self, latest_height: Option<u64>) -> Result<(), Error> {
+        let last_signature = *self.last_signature.lock().unwrap();
+        let idle = last_signature.time.elapsed();
+
+        if idle > self.max_idle {
+            fail!(
+                WatchdogError,
+                "no signatures for {}s (max {}s)",
+                idle.as_secs(),
+                self.max_idle.as_secs()
+            );
+        }
+
+        if let (Some(signed_height), Some(latest_height)) = (last_signature.height, latest_height) {
+            let lag = latest_height.saturating_sub(signed_height);
+
+            if lag > self.max_height_lag {
+                fail!(
+                    WatchdogError,
+                    "last signed height {} is {} blocks behind chain height {} (max {})",
+                    signed_height,
+                    lag,
+                    latest_height,
+                    self.max_height_lag
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Query the chain's latest height via Tendermint RPC (if configured)
+    pub fn latest_height(&self) -> Result<Option<u64>, Error> {
+        let url = match &self.rpc_status_url {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+
+        let response = http::get(url, RPC_TIMEOUT)?;
+
+        if !response.is_success() {
+            fail!(HttpError, "unexpected HTTP status: {}", response.status);
+        }
+
+        parse_status_height(&response.body).map(Some)
+    }
+
+    /// Spawn a thread which periodically checks this watchdog
+    pub fn spawn(self: Arc<Self>) -> Result<(), Error> {
+        thread::Builder::new()
+            .name(format!("watchdog@{}", &self.chain_id))
+            .spawn(move || {
+                while !shutdown::requested() {
+                    thread::sleep(self.check_interval);
+
+                    if self.run_check() && !shutdown::requested() {
+                        error!(chain_id = %self.chain_id, "strict mode: shutting down");
+
+                        // Shut down gracefully so in-flight signing requests
+                        // (and their state file updates) complete before we exit
+                        shutdown::request_exit(STRICT_EXIT_CODE);
+                    }
+                }
+            })?;
+
+        Ok(())
+    }
+
+    /// Perform a check, logging and sending notifications if the watchdog
+    /// trips. Returns whether the KMS should shut down (i.e. the watchdog
+    /// tripped in strict mode).
+    fn run_check(&self) -> bool {
+        let latest_height = match self.latest_height() {
+            Ok(height) => height,
+            Err(e) => {
+                warn!(
+                    chain_id = %self.chain_id,
+                    "watchdog couldn't get latest block height via RPC: {}",
+                    e
+                );
+                None
+            }
+        };
+
+        match self.check(latest_height) {
+            Ok(()) => {
+                if self.tripped.swap(false, Ordering::SeqCst) {
+                    info!(chain_id = %self.chain_id, "watchdog recovered: signing resumed");
+                }
+
+                false
+            }
+            Err(e) => {
+                error!(
+                    chain_id = %self.chain_id,
+                    height = ?self.last_signed_height(),
+                    latest_height = ?latest_height,
+                    "watchdog tripped: {}",
+                    e
+                );
+
+                if !self.tripped.swap(true, Ordering::SeqCst) {
+                    let mut event = notify::Event::new(
+                        EventKind::MissedSignatures,
+                        &self.chain_id,
+                        "watchdog",
+                        &e,
+                    );
+
+                    if let Some(height) = self.last_signed_height() {
+                        event = event.height(height);
+                    }
+
+                    notify::send(event);
+                }
+
+                self.strict
+            }
+        }
+    }
+}
+
+/// Parse the latest block height from a Tendermint RPC `/status` response
+fn parse_status_height(body: &[u8]) -> Result<u64, Error> {
+    let json: serde_json::Value = serde_json::from_slice(body)?;
+
+    // Tendermint v0.34 wraps responses in a JSON-RPC envelope
+    let status = json.get("result").unwrap_or(&json);
+
+    status
+        .pointer("/sync_info/latest_block_height")
+        .and_then(|height| match height {
+            serde_json::Value::String(s) => s.parse().ok(),
+            other => other.as_u64(),
+        })
+        .ok_or_else(|| format_err!(ParseError, "no latest_block_height in RPC status").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    const EXAMPLE_STATUS: &str = r#"{
+        "jsonrpc": "2.0",
+        "id": -1,
+        "result": {
+            "sync_info": {
+                "latest_block_height": "1042",
+                "catching_up": false
+            }
+        }
+    }"#;
+
+    fn watchdog(config: WatchdogConfig) -> Watchdog {
+        Watchdog::new(&"test_chain_id".parse().unwrap(), &config).unwrap()
+    }
+
+    #[test]
+    fn trips_when_idle() {
+        let watchdog = watchdog(WatchdogConfig {
+            max_idle_secs: 0,
+            ..Default::default()
+        });
+
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(watchdog.check(None).unwrap_err().kind(), &WatchdogError);
+    }
+
+    #[test]
+    fn shuts_down_in_strict_mode() {
+        let watchdog = watchdog(WatchdogConfig {
+            max_idle_secs: 0,
+            strict: true,
+            ..Default::default()
+        });
+
+        thread::sleep(Duration::from_millis(10));
+        assert!(watchdog.run_check());
+        assert!(watchdog.is_tripped());
+    }
+
+    #[test]
+    fn trips_when_behind_chain() {
+        let watchdog = watchdog(WatchdogConfig {
+            max_idle_secs: 60,
+            max_height_lag: Some(5),
+            ..Default::default()
+        });
+
+        // No signatures yet: only the idle timeout applies
+        assert!(watchdog.check(Some(1042)).is_ok());
+
+        watchdog.record_signature(1037);
+        assert!(watchdog.check(Some(1042)).is_ok());
+        assert!(watchdog.check(Some(1043)).is_err());
+
+        watchdog.record_signature(1043);
+        assert!(watchdog.check(Some(1043)).is_ok());
+    }
+
+    #[test]
+    fn queries_rpc_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let rpc_addr = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let n = socket.read(&mut request).unwrap();
+            assert!(request[..n].starts_with(b"GET /status HTTP/1.1\r\n"));
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                EXAMPLE_STATUS.len(),
+                EXAMPLE_STATUS
+            );
+            socket.write_all(response.as_bytes()).unwrap();
+        });
+
+        let watchdog = watchdog(WatchdogConfig {
+            max_idle_secs: 60,
+            rpc_addr: Some(rpc_addr),
+            ..Default::default()
+        });
+
+        assert_eq!(watchdog.latest_height().unwrap(), Some(1042));
+        server.join().unwrap();
+    }
+}
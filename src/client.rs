@@ -9,10 +9,22 @@ use crate::{
     chain,
     config::ValidatorConfig,
     error::{Error, ErrorKind},
+    notify::{self, EventKind},
     prelude::*,
     session::Session,
+    shutdown, Map,
+};
+use once_cell::sync::Lazy;
+use std::{
+    panic,
+    process::exit,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
-use std::{panic, process::exit, thread, time::Duration};
 
 /// Join handle type used by our clients
 type JoinHandle = thread::JoinHandle<Result<(), Error>>;
@@ -20,6 +32,13 @@ type JoinHandle = thread::JoinHandle<Result<(), Error>>;
 /// How long to wait after a crash before respawning (in seconds)
 pub const RESPAWN_DELAY: u64 = 1;
 
+/// Open validator sessions for each chain (by session ID), along with the
+/// time each of them last handled a request
+static CONNECTIONS: Lazy<Mutex<Map<chain::Id, Map<u64, Instant>>>> = Lazy::new(Default::default);
+
+/// ID of the next validator session
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(0);
+
 /// Client connections: wraps a thread which makes a connection to a particular
 /// validator node and then receives RPCs.
 ///
@@ -66,12 +85,31 @@ impl Client {
 /// Main loop for all clients. Handles reconnecting in the event of an error
 fn main_loop(config: ValidatorConfig) -> Result<(), Error> {
     while let Err(e) = run_client(config.clone()) {
+        // Errors are expected once shutdown closes the connection
+        if shutdown::requested() {
+            break;
+        }
+
         // `PoisonError` is unrecoverable
         if *e.kind() == ErrorKind::PoisonError {
-            error!("[{}@{}] FATAL -- {}", &config.chain_id, &config.addr, e);
+            error!(
+                chain_id = %config.chain_id,
+                validator = %config.addr,
+                "FATAL -- {}",
+                e
+            );
             return Err(e);
         } else {
-            error!("[{}@{}] {}", &config.chain_id, &config.addr, e);
+            error!(chain_id = %config.chain_id, validator = %config.addr, "{}", e);
+        }
+
+        if open_sessions(&config.chain_id) == 0 {
+            notify::send(notify::Event::new(
+                EventKind::ChainDisconnected,
+                &config.chain_id,
+                &config.addr,
+                format!("no connected validators remaining ({})", e),
+            ));
         }
 
         if config.reconnect {
@@ -89,7 +127,7 @@ fn main_loop(config: ValidatorConfig) -> Result<(), Error> {
 pub fn register_chain(chain_id: &chain::Id) {
     let registry = chain::REGISTRY.get();
 
-    debug!("registering chain: {}", chain_id);
+    debug!(chain_id = %chain_id, "registering chain");
     registry.get_chain(chain_id).unwrap_or_else(|| {
         status_err!(
             "unregistered chain: {} (add it to tmkms.toml's [[chain]] section)",
@@ -101,6 +139,80 @@ pub fn register_chain(chain_id: &chain::Id) {
 
 /// Open a new session and run the session loop
 pub fn run_client(config: ValidatorConfig) -> Result<(), Error> {
-    panic::catch_unwind(move || Session::open(config)?.request_loop())
-        .unwrap_or_else(|e| Err(Error::from_panic(e)))
+    panic::catch_unwind(move || {
+        if shutdown::requested() {
+            return Ok(());
+        }
+
+        let chain_id = config.chain_id.clone();
+        let mut session = Session::open(config)?;
+        let connected = ConnectionGuard::new(chain_id);
+        session.request_loop(|| connected.heartbeat())
+    })
+    .unwrap_or_else(|e| Err(Error::from_panic(e)))
+}
+
+/// Get the number of open validator sessions for the given chain
+pub fn open_sessions(chain_id: &chain::Id) -> usize {
+    CONNECTIONS
+        .lock()
+        .unwrap()
+        .get(chain_id)
+        .map(Map::len)
+        .unwrap_or_default()
+}
+
+/// Get the time the least recently active open validator session for the
+/// given chain last handled a request (or was opened), if any are open
+pub fn last_progress(chain_id: &chain::Id) -> Option<Instant> {
+    CONNECTIONS
+        .lock()
+        .unwrap()
+        .get(chain_id)
+        .and_then(|sessions| sessions.values().min().cloned())
+}
+
+/// Tracks an open validator session for the lifetime of this guard
+struct ConnectionGuard {
+    /// Chain the session is for
+    chain_id: chain::Id,
+
+    /// ID of the session
+    session_id: u64,
+}
+
+impl ConnectionGuard {
+    fn new(chain_id: chain::Id) -> Self {
+        let session_id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .entry(chain_id.clone())
+            .or_default()
+            .insert(session_id, Instant::now());
+
+        Self {
+            chain_id,
+            session_id,
+        }
+    }
+
+    /// Record that the session has handled a request
+    fn heartbeat(&self) {
+        if let Some(sessions) = CONNECTIONS.lock().unwrap().get_mut(&self.chain_id) {
+            sessions.insert(self.session_id, Instant::now());
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        // Avoid panicking (i.e. aborting) in the event the lock is poisoned
+        if let Ok(mut connections) = CONNECTIONS.lock() {
+            if let Some(sessions) = connections.get_mut(&self.chain_id) {
+                sessions.remove(&self.session_id);
+            }
+        }
+    }
 }
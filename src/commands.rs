@@ -1,6 +1,8 @@
 //! Subcommands of the `tmkms` command-line application
 
+pub mod bench;
 pub mod init;
+pub mod keys;
 #[cfg(feature = "ledger")]
 pub mod ledger;
 #[cfg(feature = "softsign")]
@@ -17,9 +19,15 @@ pub use self::softsign::SoftsignCommand;
 #[cfg(feature = "yubihsm")]
 pub use self::yubihsm::YubihsmCommand;
 
-pub use self::{init::InitCommand, start::StartCommand, version::VersionCommand};
+pub use self::{
+    bench::BenchCommand, init::InitCommand, keys::KeysCommand, start::StartCommand,
+    version::VersionCommand,
+};
 
-use crate::config::{KmsConfig, CONFIG_ENV_VAR, CONFIG_FILE_NAME};
+use crate::{
+    config::{KmsConfig, CONFIG_ENV_VAR, CONFIG_FILE_NAME},
+    logging::LogFormat,
+};
 use abscissa_core::{Command, Configurable, Help, Options, Runnable};
 use std::{env, path::PathBuf};
 
@@ -30,10 +38,18 @@ pub enum KmsCommand {
     #[options(help = "show help for a command")]
     Help(Help<Self>),
 
+    /// `bench` subcommand
+    #[options(help = "benchmark and verify signing with every configured key")]
+    Bench(BenchCommand),
+
     /// `init` subcommand
     #[options(help = "initialize KMS configuration")]
     Init(InitCommand),
 
+    /// `keys` subcommand
+    #[options(help = "show the keys registered for each chain")]
+    Keys(KeysCommand),
+
     /// `start` subcommand
     #[options(help = "start the KMS application")]
     Start(StartCommand),
@@ -68,6 +84,20 @@ impl KmsCommand {
             _ => false,
         }
     }
+
+    /// Should only warnings and errors be logged? (e.g. so `keys` output
+    /// isn't interleaved with the providers' log messages)
+    pub fn quiet(&self) -> bool {
+        matches!(self, KmsCommand::Bench(_) | KmsCommand::Keys(_))
+    }
+
+    /// Get the configured log output format
+    pub fn log_format(&self) -> LogFormat {
+        match self {
+            KmsCommand::Start(run) => run.log_format.unwrap_or(LogFormat::Text),
+            _ => LogFormat::Text,
+        }
+    }
 }
 
 impl Configurable<KmsConfig> for KmsCommand {
@@ -75,11 +105,13 @@ impl Configurable<KmsConfig> for KmsCommand {
     /// or the default
     fn config_path(&self) -> Option<PathBuf> {
         let config = match self {
+            KmsCommand::Bench(bench) => bench.config.as_ref(),
             KmsCommand::Start(start) => start.config.as_ref(),
+            KmsCommand::Keys(keys) => keys.config_path(),
             #[cfg(feature = "yubihsm")]
             KmsCommand::Yubihsm(yubihsm) => yubihsm.config_path(),
             #[cfg(feature = "ledger")]
-            KmsCommand::Ledger(ledger) => ledger.config_path(),
+            KmsCommand::Ledger(ledger) if ledger.uses_config() => ledger.config_path(),
             _ => return None,
         };
 
@@ -0,0 +1,557 @@
+//! `tmkms bench`: sign realistic votes and proposals with every configured
+//! key, verifying the signatures and reporting how long they took.
+//!
+//! Messages are signed for a chain ID derived from (and never equal to) the
+//! configured one, so benchmark signatures can't be used as conflicting votes
+//! or proposals on the real chain.
+
+use crate::{
+    amino_types::{
+        BlockId, ConsensusMessage, PartsSetHeader, Proposal, SignProposalRequest, SignVoteRequest,
+        SignableMsg, SignedMsgType, TimeMsg, Vote,
+    },
+    chain::{self, Chain},
+    config::{validator::ProtocolVersion, KmsConfig},
+    error::{Error, ErrorKind::*},
+    keyring::{self, ecdsa, ed25519, KeyRing},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use sha2::{Digest, Sha256};
+use signature::Verifier;
+use std::{
+    convert::TryFrom,
+    fmt,
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+use tendermint::{account, TendermintKey, Time};
+
+/// Default number of messages to sign with each key
+const DEFAULT_COUNT: u32 = 100;
+
+/// Default height of the first message
+const DEFAULT_HEIGHT: i64 = 1;
+
+/// Suffix of the chain ID benchmark messages are signed for
+const BENCH_CHAIN_ID_SUFFIX: &str = "-tmkms-bench";
+
+/// Messages signed at each height, in the order a validator signs them
+const MSG_TYPES: [SignedMsgType; 3] = [
+    SignedMsgType::Proposal,
+    SignedMsgType::PreVote,
+    SignedMsgType::PreCommit,
+];
+
+/// The `bench` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct BenchCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Number of messages to sign with each key
+    #[options(
+        short = "n",
+        long = "count",
+        help = "number of messages to sign with each key (default 100)"
+    )]
+    pub count: Option<u32>,
+
+    /// Height of the first message
+    #[options(
+        short = "h",
+        long = "height",
+        help = "height of the first message (default 1)"
+    )]
+    pub height: Option<i64>,
+
+    /// Only benchmark the keys of the given chain
+    #[options(free, help = "chain ID to benchmark the keys of (default all)")]
+    pub chain_id: Option<chain::Id>,
+}
+
+impl Runnable for BenchCommand {
+    /// Sign messages with every key, exiting with an error if any failed
+    fn run(&self) {
+        let config = APP.config();
+        let count = self.count.unwrap_or(DEFAULT_COUNT);
+        let height = self.height.unwrap_or(DEFAULT_HEIGHT);
+
+        if count == 0 {
+            status_err!("count must be at least 1");
+            process::exit(1);
+        }
+
+        // Messages are signed at `height..=(height + count / 3)`
+        let max_height = i64::MAX - i64::from(count / 3);
+
+        if height < 1 || height > max_height {
+            status_err!("height must be between 1 and {}", max_height);
+            process::exit(1);
+        }
+
+        if let Some(chain_id) = &self.chain_id {
+            if !config.chain.iter().any(|chain| &chain.id == chain_id) {
+                status_err!("unknown chain ID: {}", chain_id);
+                process::exit(1);
+            }
+        }
+
+        let registry = load_registry(&config).unwrap_or_else(|e| {
+            status_err!("couldn't load keys: {}", e);
+            process::exit(1);
+        });
+
+        let mut benchmarked = 0;
+        let mut failed = 0;
+
+        for chain_config in &config.chain {
+            if let Some(chain_id) = &self.chain_id {
+                if chain_id != &chain_config.id {
+                    continue;
+                }
+            }
+
+            let chain = registry.get_chain(&chain_config.id).unwrap();
+
+            let params = Params {
+                chain_id: &chain.id,
+                protocol_version: protocol_version(&config, &chain.id),
+                height,
+                count,
+            };
+
+            for (public_key, providers) in chain.keyring.public_keys() {
+                // Benchmark each of the key's signers separately, so redundant
+                // ones (i.e. failover targets) are checked too
+                for (index, provider) in providers.iter().enumerate() {
+                    let description = format!(
+                        "{} {} key {} via {} (signer {} of {})",
+                        chain.id,
+                        key_type(&public_key),
+                        chain.keyring.format().serialize(public_key),
+                        provider,
+                        index + 1,
+                        providers.len()
+                    );
+
+                    if !provider.signs_unattended() {
+                        status_warn!(
+                            "skipping {}: provider can't sign test messages",
+                            description
+                        );
+                        continue;
+                    }
+
+                    benchmarked += 1;
+
+                    match benchmark(&chain.keyring, &public_key, index, &params) {
+                        Ok(report) => status_ok!("Success", "{}: {}", description, report),
+                        Err(e) => {
+                            failed += 1;
+                            status_err!("{}: {}", description, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        if benchmarked == 0 {
+            status_err!("no signers to benchmark");
+            process::exit(1);
+        }
+
+        if failed > 0 {
+            status_err!("{} of {} signers failed", failed, benchmarked);
+            process::exit(1);
+        }
+    }
+}
+
+/// Load the keys for all configured chains, without their consensus state
+/// (so nothing signed here is recorded in the state files)
+fn load_registry(config: &KmsConfig) -> Result<chain::Registry, Error> {
+    let mut registry = chain::Registry::default();
+
+    for chain_config in &config.chain {
+        registry.register_chain(Chain::without_state(chain_config))?;
+    }
+
+    // Background tasks (e.g. draining YubiHSM audit logs) are left to the KMS
+    keyring::load_config(&mut registry, &config.providers)?;
+    Ok(registry)
+}
+
+/// Get the protocol version of the validator configured for the given chain,
+/// or the latest one if there isn't one
+fn protocol_version(config: &KmsConfig, chain_id: &chain::Id) -> ProtocolVersion {
+    config
+        .validator
+        .iter()
+        .find(|validator| &validator.chain_id == chain_id)
+        .map(|validator| validator.protocol_version)
+        .unwrap_or(ProtocolVersion::V0_34)
+}
+
+/// Get a description of the type of the given key
+fn key_type(key: &TendermintKey) -> &'static str {
+    match key {
+        TendermintKey::AccountKey(pk) if pk.ed25519().is_some() => "Ed25519 account",
+        TendermintKey::AccountKey(_) => "secp256k1 account",
+        TendermintKey::ConsensusKey(pk) if pk.ed25519().is_some() => "Ed25519 consensus",
+        TendermintKey::ConsensusKey(_) => "secp256k1 consensus",
+    }
+}
+
+/// Parameters of a benchmark
+#[derive(Copy, Clone, Debug)]
+struct Params<'a> {
+    /// Configured chain (messages are signed for its [`bench_chain_id`])
+    chain_id: &'a chain::Id,
+
+    /// Protocol version to encode messages with
+    protocol_version: ProtocolVersion,
+
+    /// Height of the first message
+    height: i64,
+
+    /// Number of messages to sign
+    count: u32,
+}
+
+/// Results of a benchmark
+#[derive(Clone, Debug)]
+struct Report {
+    /// How long each signature took, fastest first
+    durations: Vec<Duration>,
+}
+
+impl Report {
+    /// Create a report from the durations of the signatures
+    fn new(mut durations: Vec<Duration>) -> Self {
+        durations.sort();
+        Self { durations }
+    }
+
+    /// Get the given percentile (nearest rank, `1..=100`) of the signing
+    /// latency
+    fn percentile(&self, percent: usize) -> Duration {
+        self.durations[(self.durations.len() * percent - 1) / 100]
+    }
+
+    /// Get the number of signatures per second
+    fn throughput(&self) -> f64 {
+        let total = self.durations.iter().sum::<Duration>().as_secs_f64();
+        self.durations.len() as f64 / total.max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "signed and verified {} messages (p50 {:.3} ms, p99 {:.3} ms, {:.1} signatures/s)",
+            self.durations.len(),
+            self.percentile(50).as_secs_f64() * 1000.0,
+            self.percentile(99).as_secs_f64() * 1000.0,
+            self.throughput()
+        )
+    }
+}
+
+/// Sign `count` proposals, prevotes and precommits (in turn) with the given
+/// key's signer at the given index, verifying each signature against the key
+fn benchmark(
+    keyring: &KeyRing,
+    public_key: &TendermintKey,
+    index: usize,
+    params: &Params<'_>,
+) -> Result<Report, Error> {
+    let validator_address = account::Id::from(*public_key.public_key());
+    let mut durations = Vec::with_capacity(params.count as usize);
+
+    for i in 0..i64::from(params.count) {
+        let msg_type = MSG_TYPES[(i % 3) as usize];
+        let height = params.height + i / 3;
+        let sign_bytes = sign_bytes(params, &validator_address, msg_type, height)?;
+
+        let started_at = Instant::now();
+        let signature = keyring.sign_with_provider(public_key, index, &sign_bytes)?;
+        let elapsed = started_at.elapsed();
+
+        verify(public_key.public_key(), &sign_bytes, &signature).map_err(|_| {
+            format_err!(
+                VerificationError,
+                "invalid signature for {:?} at height {}",
+                msg_type,
+                height
+            )
+        })?;
+
+        debug!(
+            "signed {:?} at height {} in {} µs",
+            msg_type,
+            height,
+            elapsed.as_micros()
+        );
+
+        durations.push(elapsed);
+    }
+
+    Ok(Report::new(durations))
+}
+
+/// Get the chain ID benchmark messages for the given chain are signed for
+fn bench_chain_id(chain_id: &chain::Id) -> chain::Id {
+    // Chain IDs are ASCII, and truncated to make room for the suffix
+    let max_len = tendermint::chain::id::MAX_LENGTH - BENCH_CHAIN_ID_SUFFIX.len();
+    let prefix = &chain_id.as_str()[..chain_id.as_str().len().min(max_len)];
+
+    format!("{}{}", prefix, BENCH_CHAIN_ID_SUFFIX)
+        .parse()
+        .unwrap()
+}
+
+/// Compute the sign bytes of a message of the given type at the given height
+/// (round 0), for a block whose hash is derived from the height.
+///
+/// Messages are signed for the [`bench_chain_id`] of the chain.
+fn sign_bytes(
+    params: &Params<'_>,
+    validator_address: &account::Id,
+    msg_type: SignedMsgType,
+    height: i64,
+) -> Result<Vec<u8>, Error> {
+    let block_hash = Sha256::digest(&height.to_be_bytes()).to_vec();
+    let parts_hash = Sha256::digest(&block_hash).to_vec();
+    let block_id = BlockId::new(block_hash, Some(PartsSetHeader::new(1, parts_hash)));
+    let timestamp = Some(TimeMsg::from(Time::now()));
+    let chain_id = bench_chain_id(params.chain_id);
+    let mut sign_bytes = vec![];
+
+    match msg_type {
+        SignedMsgType::Proposal => {
+            let proposal = Proposal {
+                msg_type: msg_type.to_u32(),
+                height,
+                round: 0,
+                pol_round: -1,
+                block_id: Some(block_id),
+                timestamp,
+                signature: vec![],
+            };
+
+            proposal
+                .validate_basic()
+                .map_err(|e| format_err!(InvalidMessageError, "{}", e))?;
+
+            SignProposalRequest {
+                proposal: Some(proposal),
+            }
+            .sign_bytes(chain_id, params.protocol_version, &mut sign_bytes)?;
+        }
+        SignedMsgType::PreVote | SignedMsgType::PreCommit => {
+            let vote = Vote {
+                vote_type: msg_type.to_u32(),
+                height,
+                round: 0,
+                block_id: Some(block_id),
+                timestamp,
+                validator_address: validator_address.as_bytes().to_vec(),
+                validator_index: 0,
+                signature: vec![],
+            };
+
+            vote.validate_basic()
+                .map_err(|e| format_err!(InvalidMessageError, "{}", e))?;
+
+            SignVoteRequest { vote: Some(vote) }.sign_bytes(
+                chain_id,
+                params.protocol_version,
+                &mut sign_bytes,
+            )?;
+        }
+    }
+
+    Ok(sign_bytes)
+}
+
+/// Verify a signature (as serialized in votes and proposals) against the
+/// given public key
+fn verify(
+    public_key: &tendermint::PublicKey,
+    msg: &[u8],
+    signature: &[u8],
+) -> Result<(), signature::Error> {
+    if let Some(public_key) = public_key.ed25519() {
+        public_key.verify(msg, &ed25519::Signature::try_from(signature)?)
+    } else {
+        let public_key = public_key
+            .secp256k1()
+            .ok_or_else(signature::Error::new)
+            .and_then(|pk| {
+                k256::ecdsa::VerifyingKey::from_encoded_point(&pk)
+                    .map_err(|_| signature::Error::new())
+            })?;
+
+        public_key.verify(msg, &ecdsa::Signature::try_from(signature)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::{Format, SigningProvider};
+    use k256::ecdsa::SigningKey;
+
+    fn chain_id() -> chain::Id {
+        "test-chain".parse().unwrap()
+    }
+
+    fn keyring() -> (KeyRing, Vec<TendermintKey>) {
+        let mut keyring = KeyRing::new(Format::Hex);
+
+        let secret = ed25519::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = ed25519::PublicKey::from(&secret);
+        let ed25519_key = TendermintKey::ConsensusKey(public.into());
+
+        keyring
+            .add_ed25519(ed25519::Signer::new(
+                SigningProvider::SoftSign,
+                ed25519_key,
+                Box::new(ed25519::Keypair { secret, public }),
+            ))
+            .unwrap();
+
+        // Redundant signer for the Ed25519 key which signs with the wrong key
+        let secret = ed25519::SecretKey::from_bytes(&[3; 32]).unwrap();
+        let public = ed25519::PublicKey::from(&secret);
+
+        keyring
+            .add_ed25519(ed25519::Signer::new(
+                SigningProvider::SoftSign,
+                ed25519_key,
+                Box::new(ed25519::Keypair { secret, public }),
+            ))
+            .unwrap();
+
+        let signing_key = SigningKey::from_bytes(&[2; 32]).unwrap();
+        let ecdsa_key = TendermintKey::AccountKey(
+            tendermint::PublicKey::from_raw_secp256k1(&signing_key.verify_key().to_bytes())
+                .unwrap(),
+        );
+
+        keyring
+            .add_ecdsa(ecdsa::Signer::new(
+                SigningProvider::SoftSign,
+                ecdsa_key,
+                Box::new(signing_key),
+            ))
+            .unwrap();
+
+        (keyring, vec![ed25519_key, ecdsa_key])
+    }
+
+    #[test]
+    fn benchmarks_keys() {
+        let chain_id = chain_id();
+        let (keyring, keys) = keyring();
+
+        for &protocol_version in &[ProtocolVersion::V0_34, ProtocolVersion::Legacy] {
+            let params = Params {
+                chain_id: &chain_id,
+                protocol_version,
+                height: 1000,
+                count: 10,
+            };
+
+            for key in &keys {
+                let report = benchmark(&keyring, key, 0, &params).unwrap();
+                assert_eq!(report.durations.len(), 10);
+            }
+        }
+    }
+
+    #[test]
+    fn benchmarks_redundant_signers() {
+        let chain_id = chain_id();
+        let (keyring, keys) = keyring();
+        let params = Params {
+            chain_id: &chain_id,
+            protocol_version: ProtocolVersion::V0_34,
+            height: 1,
+            count: 3,
+        };
+
+        // The fallback signer is used rather than the (healthy) primary one
+        let err = benchmark(&keyring, &keys[0], 1, &params).unwrap_err();
+        assert_eq!(*err.kind(), VerificationError);
+    }
+
+    #[test]
+    fn never_signs_for_the_configured_chain() {
+        let long_chain_id = "x"
+            .repeat(tendermint::chain::id::MAX_LENGTH)
+            .parse()
+            .unwrap();
+        let (_, keys) = keyring();
+        let validator_address = account::Id::from(*keys[0].public_key());
+
+        for chain_id in &[chain_id(), long_chain_id] {
+            let bench_chain_id = bench_chain_id(chain_id);
+            assert_ne!(&bench_chain_id, chain_id);
+
+            for &protocol_version in &[ProtocolVersion::V0_34, ProtocolVersion::Legacy] {
+                let params = Params {
+                    chain_id,
+                    protocol_version,
+                    height: 1,
+                    count: 3,
+                };
+
+                for &msg_type in &MSG_TYPES {
+                    let sign_bytes = sign_bytes(&params, &validator_address, msg_type, 1).unwrap();
+
+                    // The bench chain ID may start with the real one, but the
+                    // real one must never appear on its own
+                    let occurrences = |id: &chain::Id| {
+                        (0..sign_bytes.len())
+                            .filter(|&i| sign_bytes[i..].starts_with(id.as_bytes()))
+                            .collect::<Vec<_>>()
+                    };
+
+                    assert_eq!(occurrences(&bench_chain_id).len(), 1);
+                    assert!(occurrences(chain_id)
+                        .iter()
+                        .all(|&i| sign_bytes[i..].starts_with(bench_chain_id.as_bytes())));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let (keyring, keys) = keyring();
+        let msg = b"sign bytes";
+
+        for key in &keys {
+            let mut signature = keyring.sign_with_provider(key, 0, msg).unwrap();
+            assert!(verify(key.public_key(), msg, &signature).is_ok());
+
+            signature[0] ^= 1;
+            assert!(verify(key.public_key(), msg, &signature).is_err());
+        }
+    }
+
+    #[test]
+    fn computes_percentiles() {
+        let report = Report::new((1..=200).rev().map(Duration::from_millis).collect());
+        assert_eq!(report.percentile(50), Duration::from_millis(100));
+        assert_eq!(report.percentile(99), Duration::from_millis(198));
+
+        let report = Report::new(vec![Duration::from_millis(5)]);
+        assert_eq!(report.percentile(99), Duration::from_millis(5));
+    }
+}
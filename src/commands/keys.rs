@@ -0,0 +1,168 @@
+//! `tmkms keys`: show the keys registered for each chain, across all
+//! configured providers
+
+mod list;
+mod show;
+
+pub use self::{list::ListCommand, show::ShowCommand};
+
+use crate::{
+    chain::{self, Chain},
+    config::KmsConfig,
+    error::Error,
+    keyring,
+    prelude::*,
+};
+use abscissa_core::{Command, Help, Options, Runnable};
+use serde::Serialize;
+use std::{path::PathBuf, process};
+use tendermint::{account, TendermintKey};
+
+/// The `keys` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum KeysCommand {
+    /// `keys help`
+    #[options(help = "show help for the 'keys' subcommand")]
+    Help(Help<Self>),
+
+    /// `keys list`
+    #[options(help = "list the keys registered for every chain")]
+    List(ListCommand),
+
+    /// `keys show`
+    #[options(help = "show details of the keys registered for a chain")]
+    Show(ShowCommand),
+}
+
+impl KeysCommand {
+    /// Optional path to the configuration file
+    pub(super) fn config_path(&self) -> Option<&PathBuf> {
+        match self {
+            KeysCommand::List(list) => list.config.as_ref(),
+            KeysCommand::Show(show) => show.config.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Information about a key registered for a chain
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyInfo {
+    /// Chain the key is registered for
+    pub chain_id: chain::Id,
+
+    /// Providers of the key, in the order they're tried
+    pub providers: Vec<String>,
+
+    /// Role of the key: `account` or `consensus`
+    pub role: &'static str,
+
+    /// Key algorithm: `ed25519` or `secp256k1`
+    pub algorithm: &'static str,
+
+    /// Key format configured for the chain
+    pub format: keyring::Format,
+
+    /// Public key serialized in the configured format
+    pub encoded: String,
+
+    /// Public key as hex
+    pub hex: String,
+
+    /// Address derived from the public key (the validator's consensus
+    /// address for consensus keys)
+    pub address: account::Id,
+
+    /// Public key as `tendermint show-validator` JSON
+    pub pubkey: tendermint::PublicKey,
+}
+
+impl KeyInfo {
+    /// Get information about a public key in a chain's keyring
+    fn new(
+        chain: &Chain,
+        public_key: TendermintKey,
+        providers: &[keyring::SigningProvider],
+    ) -> Self {
+        let (role, pubkey) = match public_key {
+            TendermintKey::AccountKey(pk) => ("account", pk),
+            TendermintKey::ConsensusKey(pk) => ("consensus", pk),
+        };
+
+        let algorithm = if pubkey.ed25519().is_some() {
+            "ed25519"
+        } else {
+            "secp256k1"
+        };
+
+        let format = chain.keyring.format().clone();
+
+        Self {
+            chain_id: chain.id.clone(),
+            providers: providers.iter().map(ToString::to_string).collect(),
+            role,
+            algorithm,
+            encoded: format.serialize(public_key),
+            format,
+            hex: public_key.to_hex(),
+            address: account::Id::from(pubkey),
+            pubkey,
+        }
+    }
+
+    /// Serialize the public key as `tendermint show-validator` JSON
+    pub fn pubkey_json(&self) -> String {
+        serde_json::to_string(&self.pubkey).unwrap()
+    }
+}
+
+/// Load the keys registered for the configured chains (or only the given
+/// chain) from all configured providers
+pub fn load_keys(config: &KmsConfig, chain_id: Option<&chain::Id>) -> Result<Vec<KeyInfo>, Error> {
+    let mut registry = chain::Registry::default();
+
+    for chain_config in &config.chain {
+        registry.register_chain(Chain::without_state(chain_config))?;
+    }
+
+    // Background tasks (e.g. draining YubiHSM audit logs) are left to the KMS
+    keyring::load_config(&mut registry, &config.providers)?;
+
+    let mut keys = vec![];
+
+    for chain_config in &config.chain {
+        if chain_id.map(|id| id != &chain_config.id).unwrap_or(false) {
+            continue;
+        }
+
+        let chain = registry.get_chain(&chain_config.id).unwrap();
+
+        for (public_key, providers) in chain.keyring.public_keys() {
+            keys.push(KeyInfo::new(chain, public_key, &providers));
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Load keys, exiting with an error message on failure
+fn load_keys_or_exit(chain_id: Option<&chain::Id>) -> Vec<KeyInfo> {
+    let config = APP.config();
+
+    if let Some(chain_id) = chain_id {
+        if !config.chain.iter().any(|chain| &chain.id == chain_id) {
+            status_err!("unknown chain ID: {}", chain_id);
+            process::exit(1);
+        }
+    }
+
+    load_keys(&config, chain_id).unwrap_or_else(|e| {
+        status_err!("couldn't load keys: {}", e);
+        process::exit(1);
+    })
+}
+
+/// Print keys as JSON
+fn print_json(keys: &[KeyInfo]) {
+    println!("{}", serde_json::to_string_pretty(keys).unwrap());
+}
@@ -0,0 +1,49 @@
+//! List the keys registered for every chain
+
+use super::*;
+
+/// The `keys list` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct ListCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Output JSON
+    #[options(short = "j", long = "json", help = "output JSON")]
+    pub json: bool,
+}
+
+impl Runnable for ListCommand {
+    fn run(&self) {
+        let keys = load_keys_or_exit(None);
+
+        if self.json {
+            print_json(&keys);
+            return;
+        }
+
+        for chain in &APP.config().chain {
+            println!("{}:", chain.id);
+
+            let mut chain_keys = keys
+                .iter()
+                .filter(|key| key.chain_id == chain.id)
+                .peekable();
+
+            if chain_keys.peek().is_none() {
+                println!("  (no keys)");
+            }
+
+            for key in chain_keys {
+                println!(
+                    "- [{}] {} ({}): {}",
+                    key.providers.join(", "),
+                    key.role,
+                    key.algorithm,
+                    key.encoded
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Show details of the keys registered for a chain
+
+use super::*;
+
+/// The `keys show` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct ShowCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Output JSON
+    #[options(short = "j", long = "json", help = "output JSON")]
+    pub json: bool,
+
+    /// Chain to show the keys of
+    #[options(free, help = "chain ID to show the keys of")]
+    pub chain_id: Option<chain::Id>,
+}
+
+impl Runnable for ShowCommand {
+    fn run(&self) {
+        let chain_id = self.chain_id.as_ref().unwrap_or_else(|| {
+            status_err!("no chain ID given");
+            process::exit(1);
+        });
+
+        let keys = load_keys_or_exit(Some(chain_id));
+
+        if self.json {
+            print_json(&keys);
+            return;
+        }
+
+        if keys.is_empty() {
+            status_err!("no keys registered for chain: {}", chain_id);
+            process::exit(1);
+        }
+
+        for (i, key) in keys.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+
+            println!("chain:     {}", key.chain_id);
+            println!("providers: {}", key.providers.join(", "));
+            println!("role:      {}", key.role);
+            println!("algorithm: {}", key.algorithm);
+            println!("format:    {}", key.format);
+            println!("encoded:   {}", key.encoded);
+            println!("hex:       {}", key.hex);
+            println!("address:   {}", key.address);
+            println!("pubkey:    {}", key.pubkey_json());
+        }
+    }
+}
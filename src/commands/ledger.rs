@@ -1,83 +1,183 @@
 //! `tmkms ledger` CLI (sub)commands
 
+mod init;
+mod pubkey;
+mod status;
+mod test;
+
+pub use self::{
+    init::InitCommand, pubkey::PubkeyCommand, status::StatusCommand, test::TestCommand,
+};
+
 use crate::{
     amino_types::{
-        vote::{SignVoteRequest, Vote},
-        SignableMsg, SignedMsgType,
+        ConsensusMessage, Proposal, SignProposalRequest, SignableMsg, SignedMsgType, TimeMsg,
     },
     chain,
-    config::validator::ProtocolVersion,
+    config::{validator::ProtocolVersion, KmsConfig},
+    error::{Error, ErrorKind::*},
+    keyring::{ed25519, providers::ledgertm::client::TendermintValidatorApp},
     prelude::*,
 };
-use abscissa_core::{Command, Options, Runnable};
+use abscissa_core::{Command, Help, Options, Runnable};
+use signature::Verifier;
 use std::{path::PathBuf, process};
+use tendermint::Time;
 
 /// `ledger` subcommand
 #[derive(Command, Debug, Options, Runnable)]
 pub enum LedgerCommand {
+    /// Show help for the `ledger` subcommand
+    #[options(help = "show help for the 'ledger' subcommand")]
+    Help(Help<Self>),
+
     /// Initialize HRS values
     #[options(help = "initialise the height/round/step")]
-    Initialise(InitCommand),
+    Init(InitCommand),
+
+    /// Show the public key
+    #[options(help = "show the validator's public key")]
+    Pubkey(PubkeyCommand),
+
+    /// Show the app version
+    #[options(help = "show the status of the Tendermint Validator app")]
+    Status(StatusCommand),
+
+    /// Perform a signing test
+    #[options(help = "perform a signing benchmark")]
+    Test(TestCommand),
 }
 
 impl LedgerCommand {
     pub(super) fn config_path(&self) -> Option<&PathBuf> {
         match self {
-            LedgerCommand::Initialise(init) => init.config.as_ref(),
+            LedgerCommand::Init(init) => init.config.as_ref(),
+            LedgerCommand::Test(test) => test.config.as_ref(),
+            _ => None,
         }
     }
-}
 
-/// `ledger init` subcommand
-#[derive(Command, Debug, Options)]
-pub struct InitCommand {
-    /// config file path
-    #[options(short = "c", long = "config")]
-    pub config: Option<PathBuf>,
+    /// Does this subcommand need the configuration file? (`status` and
+    /// `pubkey` only talk to the device)
+    pub(super) fn uses_config(&self) -> bool {
+        matches!(self, LedgerCommand::Init(_) | LedgerCommand::Test(_))
+    }
+}
 
-    /// block height
-    #[options(short = "h", long = "height")]
-    pub height: Option<i64>,
+/// Connect to the Tendermint Validator app, exiting on error
+fn connect() -> TendermintValidatorApp {
+    TendermintValidatorApp::connect().unwrap_or_else(|e| {
+        status_err!("couldn't connect to Ledger: {}", e);
+        process::exit(1);
+    })
+}
 
-    /// block round
-    #[options(short = "r", long = "round")]
-    pub round: Option<i64>,
+/// Get the public key of the Tendermint Validator app
+fn public_key(app: &TendermintValidatorApp) -> Result<ed25519::PublicKey, Error> {
+    ed25519::PublicKey::from_bytes(&app.public_key()?)
+        .map_err(|e| format_err!(InvalidKey, "invalid Ed25519 public key: {}", e).into())
 }
 
-impl Runnable for InitCommand {
-    fn run(&self) {
-        let config = APP.config();
-
-        chain::load_config(&config).unwrap_or_else(|e| {
-            status_err!("error loading configuration: {}", e);
-            process::exit(1);
-        });
-
-        let chain_id = config.validator[0].chain_id.clone();
-        let registry = chain::REGISTRY.get();
-        let chain = registry.get_chain(&chain_id).unwrap();
-
-        let mut vote = Vote::default();
-        vote.height = self.height.unwrap();
-        vote.round = self.round.unwrap();
-        vote.vote_type = SignedMsgType::Proposal.to_u32();
-        println!("{:?}", vote);
-        let sign_vote_req = SignVoteRequest { vote: Some(vote) };
-        let mut to_sign = vec![];
-        sign_vote_req
-            .sign_bytes(
-                config.validator[0].chain_id.clone(),
-                ProtocolVersion::Legacy,
-                &mut to_sign,
+/// Get the protocol version of the validator configured for the given chain
+fn protocol_version(config: &KmsConfig, chain_id: &chain::Id) -> Result<ProtocolVersion, Error> {
+    config
+        .validator
+        .iter()
+        .find(|validator| &validator.chain_id == chain_id)
+        .map(|validator| validator.protocol_version)
+        .ok_or_else(|| {
+            format_err!(
+                ConfigError,
+                "no [[validator]] configured for chain: {}",
+                chain_id
             )
-            .unwrap();
+            .into()
+        })
+}
+
+/// Sign a proposal at the given height/round with the app, verifying the
+/// signature against its public key.
+///
+/// The app refuses to sign below the last height/round/step it signed, so
+/// this also sets its HRS.
+fn sign_proposal(
+    app: &TendermintValidatorApp,
+    public_key: &ed25519::PublicKey,
+    chain_id: &chain::Id,
+    protocol_version: ProtocolVersion,
+    height: i64,
+    round: i64,
+) -> Result<ed25519::Signature, Error> {
+    let proposal = Proposal {
+        msg_type: SignedMsgType::Proposal.to_u32(),
+        height,
+        round,
+        pol_round: -1,
+        block_id: None,
+        timestamp: Some(TimeMsg::from(Time::now())),
+        signature: vec![],
+    };
+
+    proposal
+        .validate_basic()
+        .map_err(|e| format_err!(InvalidMessageError, "{}", e))?;
 
-        let _sig = chain.keyring.sign_ed25519(None, &to_sign).unwrap();
+    let request = SignProposalRequest {
+        proposal: Some(proposal),
+    };
 
-        println!(
-            "Successfully called the init command with height {}, and round {}",
-            self.height.unwrap(),
-            self.round.unwrap()
+    let mut sign_bytes = vec![];
+    request.sign_bytes(chain_id.clone(), protocol_version, &mut sign_bytes)?;
+
+    let signature = ed25519::Signature::from(app.sign(&sign_bytes)?);
+
+    public_key.verify(&sign_bytes, &signature).map_err(|_| {
+        format_err!(
+            VerificationError,
+            "Ledger returned an invalid signature for proposal at {}/{}",
+            height,
+            round
+        )
+    })?;
+
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::providers::ledgertm::mock::MockTransport;
+
+    #[test]
+    fn signs_proposals() {
+        let chain_id = "test-chain".parse::<chain::Id>().unwrap();
+        let transport = MockTransport::new(&[0x42; 32]);
+        let expected_key = transport.keypair().public;
+        let app = TendermintValidatorApp::new(transport);
+
+        let public_key = public_key(&app).unwrap();
+        assert_eq!(public_key, expected_key);
+
+        for &protocol_version in &[ProtocolVersion::V0_34, ProtocolVersion::Legacy] {
+            sign_proposal(&app, &public_key, &chain_id, protocol_version, 1000, 0).unwrap();
+        }
+
+        // Negative heights are rejected before anything is sent to the app
+        assert!(
+            sign_proposal(&app, &public_key, &chain_id, ProtocolVersion::V0_34, -1, 0).is_err()
         );
     }
+
+    #[test]
+    fn reports_rejected_proposals() {
+        let chain_id = "test-chain".parse::<chain::Id>().unwrap();
+        let app = TendermintValidatorApp::new(MockTransport::rejecting(&[0x42; 32]));
+        let public_key = public_key(&app).unwrap();
+
+        let err =
+            sign_proposal(&app, &public_key, &chain_id, ProtocolVersion::V0_34, 1, 0).unwrap_err();
+
+        assert_eq!(*err.kind(), LedgerError);
+        assert!(err.to_string().contains("0x6986"));
+    }
 }
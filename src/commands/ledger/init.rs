@@ -0,0 +1,65 @@
+//! Initialize the height/round/step of the Tendermint Validator app
+
+use crate::{chain, prelude::*};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process};
+
+/// The `ledger init` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct InitCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Block height
+    #[options(short = "h", long = "height", help = "block height")]
+    pub height: Option<i64>,
+
+    /// Block round
+    #[options(short = "r", long = "round", help = "block round (default 0)")]
+    pub round: Option<i64>,
+
+    /// Chain to initialize the HRS for
+    #[options(free, help = "chain ID of the validator")]
+    pub chain_id: Option<chain::Id>,
+}
+
+impl Runnable for InitCommand {
+    /// Sign a proposal at the given height/round, which sets the app's HRS
+    fn run(&self) {
+        let (chain_id, height) = match (&self.chain_id, self.height) {
+            (Some(chain_id), Some(height)) => (chain_id, height),
+            _ => {
+                eprintln!("Usage: tmkms ledger init [-c tmkms.toml] -h HEIGHT [-r ROUND] CHAIN_ID");
+                process::exit(1);
+            }
+        };
+
+        let round = self.round.unwrap_or(0);
+
+        let protocol_version =
+            super::protocol_version(&APP.config(), chain_id).unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            });
+
+        let app = super::connect();
+
+        super::public_key(&app)
+            .and_then(|public_key| {
+                super::sign_proposal(&app, &public_key, chain_id, protocol_version, height, round)
+            })
+            .unwrap_or_else(|e| {
+                status_err!("couldn't initialise height/round/step: {}", e);
+                process::exit(1);
+            });
+
+        status_ok!(
+            "Initialised",
+            "signed {} proposal at height {}, round {}",
+            chain_id,
+            height,
+            round
+        );
+    }
+}
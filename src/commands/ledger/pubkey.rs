@@ -0,0 +1,120 @@
+//! Show the public key of the Tendermint Validator app
+
+use crate::prelude::*;
+use abscissa_core::{Command, Options, Runnable};
+use serde::Serialize;
+use std::process;
+use subtle_encoding::base64;
+use tendermint::account;
+
+/// The `ledger pubkey` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct PubkeyCommand {
+    /// Bech32 prefix for consensus public keys
+    #[options(
+        short = "p",
+        long = "prefix",
+        help = "Bech32 prefix for consensus public keys (e.g. cosmosvalconspub)"
+    )]
+    pub prefix: Option<String>,
+
+    /// Output JSON
+    #[options(short = "j", long = "json", help = "output JSON")]
+    pub json: bool,
+}
+
+impl Runnable for PubkeyCommand {
+    /// Show the public key in every supported format
+    fn run(&self) {
+        let app = super::connect();
+
+        let public_key = super::public_key(&app).unwrap_or_else(|e| {
+            status_err!("couldn't get public key: {}", e);
+            process::exit(1);
+        });
+
+        let info = PubkeyInfo::new(public_key.into(), self.prefix.as_deref());
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&info).unwrap());
+            return;
+        }
+
+        println!("hex:     {}", info.hex);
+        println!("base64:  {}", info.base64);
+
+        if let Some(bech32) = &info.bech32 {
+            println!("bech32:  {}", bech32);
+        }
+
+        println!("address: {}", info.address);
+        println!("pubkey:  {}", serde_json::to_string(&info.pubkey).unwrap());
+    }
+}
+
+/// Public key serialized in every supported format
+#[derive(Clone, Debug, Serialize)]
+pub struct PubkeyInfo {
+    /// Public key as hex
+    pub hex: String,
+
+    /// Public key as Base64 (e.g. as used in `genesis.json`)
+    pub base64: String,
+
+    /// Public key as Bech32, if a prefix was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bech32: Option<String>,
+
+    /// Validator's consensus address
+    pub address: account::Id,
+
+    /// Public key as `tendermint show-validator` JSON
+    pub pubkey: tendermint::PublicKey,
+}
+
+impl PubkeyInfo {
+    /// Serialize the given public key
+    pub fn new(public_key: tendermint::PublicKey, bech32_prefix: Option<&str>) -> Self {
+        Self {
+            hex: public_key.to_hex(),
+            base64: String::from_utf8(base64::encode(public_key.as_bytes())).unwrap(),
+            bech32: bech32_prefix.map(|prefix| public_key.to_bech32(prefix)),
+            address: account::Id::from(public_key),
+            pubkey: public_key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::ed25519;
+
+    #[test]
+    fn serializes_public_key() {
+        let public_key = tendermint::PublicKey::from(
+            ed25519::PublicKey::from_bytes(&[
+                0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64,
+                0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68,
+                0xf7, 0x07, 0x51, 0x1a,
+            ])
+            .unwrap(),
+        );
+
+        let info = PubkeyInfo::new(public_key, Some("cosmosvalconspub"));
+        assert_eq!(
+            info.hex,
+            "D75A980182B10AB7D54BFED3C964073A0EE172F3DAA62325AF021A68F707511A"
+        );
+        assert_eq!(info.base64, "11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=");
+        assert!(info
+            .bech32
+            .as_ref()
+            .unwrap()
+            .starts_with("cosmosvalconspub1zcjduepq"));
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["pubkey"]["value"], info.base64.as_str());
+        assert!(PubkeyInfo::new(public_key, None).bech32.is_none());
+    }
+}
@@ -0,0 +1,38 @@
+//! Show the status of the Tendermint Validator app
+
+use crate::prelude::*;
+use abscissa_core::{Command, Options, Runnable};
+use std::process;
+
+/// The `ledger status` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct StatusCommand {}
+
+impl Runnable for StatusCommand {
+    /// Show the app version and public key
+    fn run(&self) {
+        let app = super::connect();
+
+        let version = app.version().unwrap_or_else(|e| {
+            status_err!("couldn't get app version: {}", e);
+            process::exit(1);
+        });
+
+        let public_key = super::public_key(&app).unwrap_or_else(|e| {
+            status_err!("couldn't get public key: {}", e);
+            process::exit(1);
+        });
+
+        status_ok!(
+            "Connected",
+            "Tendermint Validator app v{} (mode 0x{:02X})",
+            version,
+            version.mode
+        );
+
+        println!(
+            "public key: {}",
+            tendermint::PublicKey::from(public_key).to_hex()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Benchmark the Tendermint Validator app by signing successive proposals
+
+use crate::{
+    chain,
+    config::validator::ProtocolVersion,
+    error::Error,
+    keyring::{ed25519, providers::ledgertm::client::TendermintValidatorApp},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+
+/// Default number of proposals to sign
+const DEFAULT_COUNT: u32 = 10;
+
+/// The `ledger test` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct TestCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Height of the first proposal
+    #[options(
+        short = "h",
+        long = "height",
+        help = "height of the first proposal (the app's HRS is advanced past it!)"
+    )]
+    pub height: Option<i64>,
+
+    /// Number of proposals to sign
+    #[options(
+        short = "n",
+        long = "count",
+        help = "number of proposals to sign (default 10)"
+    )]
+    pub count: Option<u32>,
+
+    /// Chain to sign proposals for
+    #[options(free, help = "chain ID of the validator")]
+    pub chain_id: Option<chain::Id>,
+}
+
+impl Runnable for TestCommand {
+    /// Sign proposals at successive heights and report how long they took
+    fn run(&self) {
+        let (chain_id, height) = match (&self.chain_id, self.height) {
+            (Some(chain_id), Some(height)) => (chain_id, height),
+            _ => {
+                eprintln!("Usage: tmkms ledger test [-c tmkms.toml] -h HEIGHT [-n COUNT] CHAIN_ID");
+                process::exit(1);
+            }
+        };
+
+        let count = self.count.unwrap_or(DEFAULT_COUNT);
+
+        if count == 0 {
+            status_err!("count must be at least 1");
+            process::exit(1);
+        }
+
+        let protocol_version =
+            super::protocol_version(&APP.config(), chain_id).unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            });
+
+        let app = super::connect();
+
+        let public_key = super::public_key(&app).unwrap_or_else(|e| {
+            status_err!("couldn't get public key: {}", e);
+            process::exit(1);
+        });
+
+        let durations = benchmark(&app, &public_key, chain_id, protocol_version, height, count)
+            .unwrap_or_else(|e| {
+                status_err!("signature operation failed: {}", e);
+                process::exit(1);
+            });
+
+        let total = durations.iter().sum::<Duration>();
+
+        status_ok!(
+            "Success",
+            "signed {} proposals in {} ms (avg {} ms, min {} ms, max {} ms)",
+            durations.len(),
+            total.as_millis(),
+            (total / count).as_millis(),
+            durations.iter().min().unwrap().as_millis(),
+            durations.iter().max().unwrap().as_millis()
+        );
+    }
+}
+
+/// Sign `count` proposals at successive heights starting at `height`,
+/// returning how long each signature took
+fn benchmark(
+    app: &TendermintValidatorApp,
+    public_key: &ed25519::PublicKey,
+    chain_id: &chain::Id,
+    protocol_version: ProtocolVersion,
+    height: i64,
+    count: u32,
+) -> Result<Vec<Duration>, Error> {
+    let mut durations = Vec::with_capacity(count as usize);
+
+    for proposal_height in (height..).take(count as usize) {
+        let started_at = Instant::now();
+
+        super::sign_proposal(
+            app,
+            public_key,
+            chain_id,
+            protocol_version,
+            proposal_height,
+            0,
+        )?;
+
+        let elapsed = started_at.elapsed();
+        debug!(
+            "signed proposal at height {} in {} ms",
+            proposal_height,
+            elapsed.as_millis()
+        );
+        durations.push(elapsed);
+    }
+
+    Ok(durations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::providers::ledgertm::mock::MockTransport;
+
+    #[test]
+    fn benchmarks_signing() {
+        let chain_id = "test-chain".parse::<chain::Id>().unwrap();
+        let app = TendermintValidatorApp::new(MockTransport::new(&[0x42; 32]));
+        let public_key = super::super::public_key(&app).unwrap();
+
+        let durations =
+            benchmark(&app, &public_key, &chain_id, ProtocolVersion::V0_34, 100, 5).unwrap();
+
+        assert_eq!(durations.len(), 5);
+    }
+}
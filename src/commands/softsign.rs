@@ -1,10 +1,29 @@
 //! `tmkms softsign` CLI (sub)commands
 
+mod convert;
 mod import;
 mod keygen;
+mod recover;
 
-use self::{import::ImportCommand, keygen::KeygenCommand};
+use self::{
+    convert::ConvertCommand, import::ImportCommand, keygen::KeygenCommand, recover::RecoverCommand,
+};
+use crate::{
+    config::provider::{KeyAlgorithm, KeyType},
+    key_utils::{self, hd, PasswordSource},
+    prelude::*,
+};
 use abscissa_core::{Command, Help, Options, Runnable};
+use hkd32::mnemonic;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Default type of key to generate
+pub const DEFAULT_KEY_TYPE: &str = "consensus";
 
 /// The `softsign` subcommand
 #[derive(Command, Debug, Options, Runnable)]
@@ -20,4 +39,134 @@ pub enum SoftsignCommand {
     /// Import an existing key into the softsign Base64 format
     #[options(help = "convert existing private key to base64 format")]
     Import(ImportCommand),
+
+    /// Convert a key between the supported formats
+    #[options(help = "convert a key between base64, json, and Cosmos armor formats")]
+    Convert(ConvertCommand),
+
+    /// Recover a BIP39 seed or a key derived from it from a mnemonic
+    #[options(help = "recover a seed or derived key from a BIP39 mnemonic")]
+    Recover(RecoverCommand),
+}
+
+/// Parse the type of key to generate (default consensus)
+fn parse_key_type(key_type: Option<&str>) -> KeyType {
+    match key_type.unwrap_or(DEFAULT_KEY_TYPE) {
+        "account" => KeyType::Account,
+        "consensus" => KeyType::Consensus,
+        other => {
+            status_err!(
+                "unknown key type: {} (must be 'account' or 'consensus')",
+                other
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Parse the algorithm of key to generate (default according to key type)
+fn parse_algorithm(algorithm: Option<&str>, key_type: &KeyType) -> KeyAlgorithm {
+    match algorithm {
+        None => key_type.default_algorithm(),
+        Some("ed25519") => KeyAlgorithm::Ed25519,
+        Some("secp256k1") => KeyAlgorithm::Secp256k1,
+        Some(other) => {
+            status_err!(
+                "unknown key algorithm: {} (must be 'ed25519' or 'secp256k1')",
+                other
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Read a BIP39 mnemonic phrase from STDIN
+fn read_mnemonic_from_stdin() -> mnemonic::Phrase {
+    print!("Enter mnemonic (separate words with spaces): ");
+    io::stdout().flush().unwrap();
+
+    let mut input_string = String::new();
+    io::stdin()
+        .read_line(&mut input_string)
+        .unwrap_or_else(|e| {
+            status_err!("couldn't read mnemonic: {}", e);
+            process::exit(1);
+        });
+
+    let input_phrase = input_string
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    input_string.zeroize();
+
+    mnemonic::Phrase::new(input_phrase, hd::BIP39_LANGUAGE).unwrap_or_else(|_| {
+        status_err!("couldn't decode mnemonic (invalid word or checksum)");
+        process::exit(1);
+    })
+}
+
+/// Write a key file, either Base64-encoded or (if `encrypt` is set)
+/// encrypted with a password read from `password_file` or prompted for
+fn write_key(
+    output_path: &Path,
+    secret_key: &[u8],
+    encrypt: bool,
+    password_file: Option<&PathBuf>,
+) {
+    let result = if encrypt {
+        let password = new_password(output_path, password_file);
+        key_utils::write_encrypted_secret(output_path, secret_key, password.as_bytes())
+    } else {
+        if password_file.is_some() {
+            status_err!("--password-file requires --encrypt");
+            process::exit(1);
+        }
+
+        key_utils::write_base64_secret(output_path, secret_key)
+    };
+
+    result.unwrap_or_else(|e| {
+        status_err!("{}", e);
+        process::exit(1);
+    });
+}
+
+/// Get the password to encrypt a new key with, either read from
+/// `password_file` or prompted for
+fn new_password(output_path: &Path, password_file: Option<&PathBuf>) -> Zeroizing<String> {
+    match password_file {
+        Some(path) => PasswordSource::File(path.clone())
+            .read(output_path)
+            .unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            }),
+        None => prompt_new_password(),
+    }
+}
+
+/// Prompt for a new password (with confirmation)
+fn prompt_new_password() -> Zeroizing<String> {
+    let read_password = |prompt| {
+        Zeroizing::new(
+            rpassword::read_password_from_tty(Some(prompt)).unwrap_or_else(|e| {
+                status_err!("couldn't read password: {}", e);
+                process::exit(1);
+            }),
+        )
+    };
+
+    let password = read_password("Enter password: ");
+
+    if password.is_empty() {
+        status_err!("password must not be empty");
+        process::exit(1);
+    }
+
+    if read_password("Confirm password: ") != password {
+        status_err!("passwords do not match");
+        process::exit(1);
+    }
+
+    password
 }
@@ -0,0 +1,181 @@
+//! `tmkms softsign convert` subcommand
+
+use crate::{
+    config::provider::{softsign::KeyFormat, KeyAlgorithm, KeyType},
+    key_utils::{self, armor, PasswordSource, SecretKey},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+use zeroize::Zeroizing;
+
+/// `convert` command: convert a key between the raw `base64` format used by
+/// the softsign backend, Tendermint `priv_validator_key.json`, and the Cosmos
+/// SDK armored format (`keys export`/`keys import`)
+#[derive(Command, Debug, Default, Options)]
+pub struct ConvertCommand {
+    #[options(
+        short = "f",
+        long = "from",
+        help = "input format: 'base64', 'json', or 'armor' (default: detected)"
+    )]
+    from: Option<String>,
+
+    #[options(
+        no_short,
+        long = "to",
+        help = "output format: 'base64', 'json', or 'armor'"
+    )]
+    to: Option<String>,
+
+    #[options(
+        short = "a",
+        long = "algorithm",
+        help = "algorithm of base64 input keys: 'ed25519' or 'secp256k1' (default 'ed25519')"
+    )]
+    algorithm: Option<String>,
+
+    #[options(
+        short = "e",
+        long = "encrypt",
+        help = "encrypt base64 output with a password"
+    )]
+    encrypt: bool,
+
+    #[options(
+        long = "password-file",
+        help = "read the output password (or armor passphrase) from a file instead of prompting"
+    )]
+    password_file: Option<PathBuf>,
+
+    #[options(
+        long = "input-password-file",
+        help = "read the password of an encrypted input key from a file instead of prompting"
+    )]
+    input_password_file: Option<PathBuf>,
+
+    #[options(free, help = "[INPUT] and [OUTPUT] paths")]
+    paths: Vec<PathBuf>,
+}
+
+impl Runnable for ConvertCommand {
+    /// Convert a key between formats
+    fn run(&self) {
+        if self.paths.len() != 2 || self.to.is_none() {
+            eprintln!(
+                "Usage: tmkms softsign convert [-f base64,json,armor] --to base64,json,armor \
+                 [-a ed25519,secp256k1] [-e] [--password-file FILE] \
+                 [--input-password-file FILE] INPUT OUTPUT"
+            );
+            process::exit(1);
+        }
+
+        let input_path = &self.paths[0];
+        let output_path = &self.paths[1];
+        let to = parse_format(self.to.as_ref().unwrap());
+
+        if self.encrypt && to != KeyFormat::Base64 {
+            status_err!(
+                "--encrypt is only supported for base64 output (armor is always encrypted)"
+            );
+            process::exit(1);
+        }
+
+        let from = match &self.from {
+            Some(format) => parse_format(format),
+            None => detect_format(input_path),
+        };
+
+        // The algorithm of `json` and `armor` keys is part of the format
+        let algorithm = match self.algorithm.as_deref() {
+            Some(algorithm) => Some(super::parse_algorithm(Some(algorithm), &KeyType::Consensus)),
+            None if from == KeyFormat::Base64 => Some(KeyAlgorithm::Ed25519),
+            None => None,
+        };
+
+        let password = match &self.input_password_file {
+            Some(path) => PasswordSource::File(path.clone()),
+            None => PasswordSource::Prompt,
+        };
+
+        let secret_key = SecretKey::load(input_path, from, algorithm, &password, false)
+            .unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            });
+
+        match to {
+            KeyFormat::Base64 => super::write_key(
+                output_path,
+                &secret_key.to_bytes(),
+                self.encrypt,
+                self.password_file.as_ref(),
+            ),
+            KeyFormat::Json => {
+                if self.password_file.is_some() {
+                    status_err!("--password-file isn't supported for json output");
+                    process::exit(1);
+                }
+
+                write_secret_file(output_path, secret_key.to_json().as_bytes());
+            }
+            KeyFormat::Armor => {
+                let passphrase = super::new_password(output_path, self.password_file.as_ref());
+
+                let armored =
+                    armor::encrypt(&secret_key, passphrase.as_bytes()).unwrap_or_else(|e| {
+                        status_err!("{}", e);
+                        process::exit(1);
+                    });
+
+                write_secret_file(output_path, armored.as_bytes());
+            }
+        }
+
+        status_ok!(
+            "Converted",
+            "{} private key from {} ({}) to {} ({})",
+            secret_key.algorithm(),
+            input_path.display(),
+            from,
+            output_path.display(),
+            to
+        );
+    }
+}
+
+/// Parse a key format
+fn parse_format(format: &str) -> KeyFormat {
+    format.parse().unwrap_or_else(|e| {
+        status_err!("{} (must be 'base64', 'json', or 'armor')", e);
+        process::exit(1);
+    })
+}
+
+/// Detect the format of the key at the given path
+fn detect_format(path: &Path) -> KeyFormat {
+    let data = Zeroizing::new(fs::read_to_string(path).unwrap_or_else(|e| {
+        status_err!("couldn't read {}: {}", path.display(), e);
+        process::exit(1);
+    }));
+
+    if armor::is_armored(&data) {
+        KeyFormat::Armor
+    } else if data.trim_start().starts_with('{') {
+        KeyFormat::Json
+    } else {
+        KeyFormat::Base64
+    }
+}
+
+/// Write a secret file, exiting on error
+fn write_secret_file(path: &Path, data: &[u8]) {
+    key_utils::write_secret_file(path, data).unwrap_or_else(|e| {
+        status_err!("{}", e);
+        process::exit(1);
+    });
+}
@@ -1,9 +1,10 @@
 //! `tmkms softsign import` command
 
-use crate::{config::provider::softsign::KeyFormat, key_utils, prelude::*};
+use crate::{
+    config::provider::softsign::KeyFormat, error::Error, key_utils::SecretKey, prelude::*,
+};
 use abscissa_core::{Command, Options, Runnable};
-use std::{path::PathBuf, process};
-use tendermint::{config::PrivValidatorKey, PrivateKey};
+use std::{fs, path::PathBuf, process};
 
 /// `import` command: import a `priv_validator.json` formatted key and convert
 /// it into the raw format used by the softsign backend (by default)
@@ -15,6 +16,19 @@ pub struct ImportCommand {
     )]
     format: Option<String>,
 
+    #[options(
+        short = "e",
+        long = "encrypt",
+        help = "encrypt the key with a password"
+    )]
+    encrypt: bool,
+
+    #[options(
+        long = "password-file",
+        help = "read the encryption password from a file instead of prompting"
+    )]
+    password_file: Option<PathBuf>,
+
     #[options(free, help = "[INPUT] and [OUTPUT] paths for key generation")]
     paths: Vec<PathBuf>,
 }
@@ -24,7 +38,10 @@ impl Runnable for ImportCommand {
     fn run(&self) {
         if self.paths.len() != 2 {
             status_err!("expected 2 arguments, got {}", self.paths.len());
-            eprintln!("\nUsage: tmkms softsign import [priv_validator.json] [output.key]");
+            eprintln!(
+                "\nUsage: tmkms softsign import [-e [--password-file FILE]] \
+                 [priv_validator.json] [output.key]"
+            );
             process::exit(1);
         }
 
@@ -47,25 +64,25 @@ impl Runnable for ImportCommand {
             process::exit(1);
         }
 
-        let private_key = PrivValidatorKey::load_json_file(input_path)
-            .unwrap_or_else(|e| {
+        let private_key = fs::read_to_string(input_path)
+            .map_err(|e| e.into())
+            .and_then(|json| SecretKey::from_json(&json))
+            .unwrap_or_else(|e: Error| {
                 status_err!("couldn't load {}: {}", input_path.display(), e);
                 process::exit(1);
-            })
-            .priv_key;
+            });
 
-        match private_key {
-            PrivateKey::Ed25519(pk) => {
-                key_utils::write_base64_secret(output_path, pk.secret.as_bytes()).unwrap_or_else(
-                    |e| {
-                        status_err!("{}", e);
-                        process::exit(1);
-                    },
-                );
-            }
-            _ => unreachable!("unsupported priv_validator.json algorithm"),
-        }
+        super::write_key(
+            output_path,
+            &private_key.to_bytes(),
+            self.encrypt,
+            self.password_file.as_ref(),
+        );
 
-        info!("Imported Ed25519 private key to {}", output_path.display());
+        info!(
+            "Imported {} private key to {}",
+            private_key.algorithm(),
+            output_path.display()
+        );
     }
 }
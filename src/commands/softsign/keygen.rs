@@ -1,14 +1,19 @@
 //! `tmkms softsign keygen` subcommand
 
-use crate::{key_utils, prelude::*};
+use crate::{
+    config::provider::{KeyAlgorithm, KeyType},
+    key_utils::hd,
+    prelude::*,
+};
 use abscissa_core::{Command, Options, Runnable};
 use ed25519_dalek as ed25519;
+use hkd32::mnemonic;
 use k256::ecdsa;
 use rand_core::OsRng;
-use std::{path::PathBuf, process};
-
-/// Default type of key to generate
-pub const DEFAULT_KEY_TYPE: &str = "consensus";
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
 
 /// `keygen` command
 #[derive(Command, Debug, Default, Options)]
@@ -20,67 +25,127 @@ pub struct KeygenCommand {
     )]
     key_type: Option<String>,
 
+    #[options(
+        short = "a",
+        long = "algorithm",
+        help = "key algorithm: 'ed25519' or 'secp256k1' (default by type)"
+    )]
+    algorithm: Option<String>,
+
+    #[options(
+        short = "m",
+        long = "mnemonic",
+        help = "generate a BIP39 mnemonic and store its seed for deriving keys"
+    )]
+    mnemonic: bool,
+
+    #[options(
+        short = "e",
+        long = "encrypt",
+        help = "encrypt the key with a password"
+    )]
+    encrypt: bool,
+
+    #[options(
+        long = "password-file",
+        help = "read the encryption password from a file instead of prompting"
+    )]
+    password_file: Option<PathBuf>,
+
     #[options(free, help = "path where generated key should be created")]
     output_paths: Vec<PathBuf>,
 }
 
 impl Runnable for KeygenCommand {
-    /// Generate an Ed25519 secret key for use with a software provider (i.e. ed25519-dalek)
+    /// Generate a secret key for use with a software provider
     fn run(&self) {
         if self.output_paths.len() != 1 {
-            eprintln!("Usage: tmkms softsign keygen [-t account,consensus] PATH");
+            eprintln!(
+                "Usage: tmkms softsign keygen [-t account,consensus] [-a ed25519,secp256k1] [-m] \
+                 [-e [--password-file FILE]] PATH"
+            );
             process::exit(1);
         }
 
         let output_path = &self.output_paths[0];
 
-        match self
-            .key_type
-            .as_ref()
-            .map(AsRef::as_ref)
-            .unwrap_or(DEFAULT_KEY_TYPE)
-        {
-            "account" => generate_secp256k1_key(output_path),
-            "consensus" => generate_ed25519_key(output_path),
-            other => {
-                status_err!(
-                    "unknown key type: {} (must be 'account' or 'consensus')",
-                    other
-                );
+        if self.mnemonic {
+            if self.key_type.is_some() || self.algorithm.is_some() {
+                status_err!("--mnemonic can't be combined with --type or --algorithm");
                 process::exit(1);
             }
+
+            self.generate_seed(output_path);
+            return;
+        }
+
+        let key_type = super::parse_key_type(self.key_type.as_deref());
+        let algorithm = super::parse_algorithm(self.algorithm.as_deref(), &key_type);
+
+        match algorithm {
+            KeyAlgorithm::Secp256k1 => self.generate_secp256k1_key(&key_type, output_path),
+            KeyAlgorithm::Ed25519 => self.generate_ed25519_key(&key_type, output_path),
         }
     }
 }
 
-/// Randomly generate a Base64-encoded secp256k1 key and store it at the given path
-fn generate_secp256k1_key(output_path: &PathBuf) {
-    let signing_key = ecdsa::SigningKey::random(&mut OsRng);
+impl KeygenCommand {
+    /// Randomly generate a secp256k1 key and store it at the given path
+    fn generate_secp256k1_key(&self, key_type: &KeyType, output_path: &Path) {
+        let signing_key = ecdsa::SigningKey::random(&mut OsRng);
 
-    key_utils::write_base64_secret(output_path, &signing_key.to_bytes()).unwrap_or_else(|e| {
-        status_err!("{}", e);
-        process::exit(1);
-    });
+        super::write_key(
+            output_path,
+            &signing_key.to_bytes(),
+            self.encrypt,
+            self.password_file.as_ref(),
+        );
 
-    status_ok!(
-        "Generated",
-        "account (secp256k1) private key at: {}",
-        output_path.display()
-    );
-}
+        status_ok!(
+            "Generated",
+            "{} (secp256k1) private key at: {}",
+            key_type,
+            output_path.display()
+        );
+    }
 
-/// Randomly generate a Base64-encoded Ed25519 key and store it at the given path
-fn generate_ed25519_key(output_path: &PathBuf) {
-    let keypair = ed25519::Keypair::generate(&mut OsRng);
+    /// Randomly generate an Ed25519 key and store it at the given path
+    fn generate_ed25519_key(&self, key_type: &KeyType, output_path: &Path) {
+        let keypair = ed25519::Keypair::generate(&mut OsRng);
 
-    key_utils::write_base64_secret(output_path, keypair.secret.as_ref()).unwrap_or_else(|e| {
-        status_err!("{}", e);
-        process::exit(1);
-    });
+        super::write_key(
+            output_path,
+            keypair.secret.as_ref(),
+            self.encrypt,
+            self.password_file.as_ref(),
+        );
 
-    status_ok!(
-        "Generated",
-        "consensus (Ed25519) private key at: {}",
-        output_path.display()
-    );
+        status_ok!(
+            "Generated",
+            "{} (Ed25519) private key at: {}",
+            key_type,
+            output_path.display()
+        );
+    }
+
+    /// Randomly generate a BIP39 mnemonic phrase, display it, and store the
+    /// corresponding seed at the given path
+    fn generate_seed(&self, output_path: &Path) {
+        let phrase = mnemonic::Phrase::random(OsRng, hd::BIP39_LANGUAGE);
+        let seed = hd::seed_from_mnemonic(&phrase);
+
+        super::write_key(
+            output_path,
+            &seed,
+            self.encrypt,
+            self.password_file.as_ref(),
+        );
+
+        status_ok!("Generated", "BIP39 seed at: {}", output_path.display());
+
+        println!(
+            "\n*** Write down this mnemonic to recover the seed and all keys derived from it:\n"
+        );
+        println!("    {}\n", phrase.phrase());
+    }
 }
@@ -0,0 +1,127 @@
+//! `tmkms softsign recover` subcommand
+
+use crate::{
+    config::provider::KeyAlgorithm,
+    key_utils::hd::{self, DerivationPath},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process};
+use zeroize::Zeroizing;
+
+/// `recover` command: read a BIP39 mnemonic from STDIN and either store the
+/// corresponding seed, or the key derived from it at the given path
+#[derive(Command, Debug, Default, Options)]
+pub struct RecoverCommand {
+    #[options(
+        short = "p",
+        long = "path",
+        help = "derive the key at this BIP32 path (e.g. m/44'/118'/0'/0/0) instead of storing the seed"
+    )]
+    derivation_path: Option<String>,
+
+    #[options(
+        short = "t",
+        long = "type",
+        help = "type of key: 'account' or 'consensus' (default 'consensus')"
+    )]
+    key_type: Option<String>,
+
+    #[options(
+        short = "a",
+        long = "algorithm",
+        help = "key algorithm: 'ed25519' or 'secp256k1' (default by type)"
+    )]
+    algorithm: Option<String>,
+
+    #[options(
+        short = "e",
+        long = "encrypt",
+        help = "encrypt the output with a password"
+    )]
+    encrypt: bool,
+
+    #[options(
+        long = "password-file",
+        help = "read the encryption password from a file instead of prompting"
+    )]
+    password_file: Option<PathBuf>,
+
+    #[options(free, help = "path where the seed or derived key should be stored")]
+    output_paths: Vec<PathBuf>,
+}
+
+impl Runnable for RecoverCommand {
+    /// Recover a seed or derived key from a mnemonic
+    fn run(&self) {
+        if self.output_paths.len() != 1 {
+            eprintln!(
+                "Usage: tmkms softsign recover [-p PATH [-t account,consensus] \
+                 [-a ed25519,secp256k1]] [-e [--password-file FILE]] OUTPUT"
+            );
+            process::exit(1);
+        }
+
+        let output_path = &self.output_paths[0];
+
+        let derivation_path = self.derivation_path.as_ref().map(|path| {
+            path.parse::<DerivationPath>().unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            })
+        });
+
+        if derivation_path.is_none() && (self.key_type.is_some() || self.algorithm.is_some()) {
+            status_err!("--type and --algorithm require --path");
+            process::exit(1);
+        }
+
+        let seed = hd::seed_from_mnemonic(&super::read_mnemonic_from_stdin());
+
+        let derivation_path = match derivation_path {
+            Some(path) => path,
+            None => {
+                super::write_key(
+                    output_path,
+                    &seed,
+                    self.encrypt,
+                    self.password_file.as_ref(),
+                );
+
+                status_ok!("Recovered", "BIP39 seed at: {}", output_path.display());
+                return;
+            }
+        };
+
+        let key_type = super::parse_key_type(self.key_type.as_deref());
+        let algorithm = super::parse_algorithm(self.algorithm.as_deref(), &key_type);
+
+        let result = match algorithm {
+            KeyAlgorithm::Secp256k1 => hd::derive_secp256k1(&seed, &derivation_path)
+                .map(|signing_key| signing_key.to_bytes().to_vec()),
+            KeyAlgorithm::Ed25519 => hd::derive_ed25519(&seed, &derivation_path)
+                .map(|keypair| keypair.secret.as_bytes().to_vec()),
+        };
+
+        let secret_key = Zeroizing::new(result.unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        }));
+
+        super::write_key(
+            output_path,
+            &secret_key,
+            self.encrypt,
+            self.password_file.as_ref(),
+        );
+
+        status_ok!(
+            "Recovered",
+            "{} ({}) private key derived at {} to: {}",
+            key_type,
+            algorithm,
+            derivation_path,
+            output_path.display()
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! Start the KMS
 
-use crate::{chain, client::Client, prelude::*};
+use crate::{
+    chain, client::Client, config::KmsConfig, error::Error, logging::LogFormat, notify, prelude::*,
+    remote::Listener, shutdown, systemd, Map,
+};
 use abscissa_core::{Command, Options};
 use std::{path::PathBuf, process};
 
@@ -17,6 +20,13 @@ pub struct StartCommand {
     /// Print debugging information
     #[options(short = "v", long = "verbose", help = "enable verbose debug logging")]
     pub verbose: bool,
+
+    /// Log output format
+    #[options(
+        long = "log-format",
+        help = "log output format: text (default) or json"
+    )]
+    pub log_format: Option<LogFormat>,
 }
 
 impl Default for StartCommand {
@@ -24,6 +34,7 @@ impl Default for StartCommand {
         Self {
             config: None,
             verbose: false,
+            log_format: None,
         }
     }
 }
@@ -37,39 +48,111 @@ impl Runnable for StartCommand {
             env!("CARGO_PKG_VERSION")
         );
 
-        run_app(self.spawn_clients());
+        // The signals were blocked before any threads were spawned (when
+        // registering the application's components)
+        shutdown::spawn_handler().unwrap_or_else(|e| {
+            status_err!("error installing signal handlers: {}", e);
+            process::exit(1);
+        });
+
+        let (clients, listeners) = self.spawn_clients();
+        run_app(clients, listeners);
     }
 }
 
 impl StartCommand {
-    /// Spawn clients from the app's configuration
-    fn spawn_clients(&self) -> Vec<Client> {
+    /// Spawn clients (and edge KMS listeners) from the app's configuration
+    fn spawn_clients(&self) -> (Vec<Client>, Vec<Listener>) {
         let config = APP.config();
 
-        chain::load_config(&config).unwrap_or_else(|e| {
-            status_err!("error loading configuration: {}", e);
+        notify::init(&config.notify).unwrap_or_else(|e| {
+            status_err!("error initializing notifications: {}", e);
+            process::exit(1);
+        });
+
+        chain::load_config(&config)
+            .unwrap_or_else(|e| {
+                status_err!("error loading configuration: {}", e);
+                process::exit(1);
+            })
+            .spawn();
+
+        spawn_watchdogs(&config).unwrap_or_else(|e| {
+            status_err!("error starting watchdog: {}", e);
             process::exit(1);
         });
 
         // Spawn the validator client threads
-        config
+        let clients = config
             .validator
             .iter()
             .cloned()
             .map(Client::spawn)
-            .collect()
+            .collect();
+
+        // Spawn listeners for edge KMS instances using us as their signer
+        let listeners = config
+            .listen
+            .iter()
+            .cloned()
+            .map(Listener::spawn)
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| {
+                status_err!("error starting listener: {}", e);
+                process::exit(1);
+            });
+
+        spawn_systemd_notifier(&config).unwrap_or_else(|e| {
+            status_err!("error starting systemd notifier: {}", e);
+            process::exit(1);
+        });
+
+        (clients, listeners)
+    }
+}
+
+/// Spawn missed-signature watchdogs for all chains which have one configured
+fn spawn_watchdogs(config: &KmsConfig) -> Result<(), Error> {
+    let registry = chain::REGISTRY.get();
+
+    for chain_config in &config.chain {
+        if let Some(watchdog) = registry
+            .get_chain(&chain_config.id)
+            .and_then(|chain| chain.watchdog.clone())
+        {
+            watchdog.spawn()?;
+        }
     }
+
+    Ok(())
+}
+
+/// Notify systemd of our readiness and status when running under a service
+/// manager which supports it (i.e. `NOTIFY_SOCKET` is set)
+fn spawn_systemd_notifier(config: &KmsConfig) -> Result<(), Error> {
+    let notifier = match systemd::Notifier::from_env() {
+        Some(notifier) => notifier,
+        None => return Ok(()),
+    };
+
+    let mut validators = Map::new();
+
+    for validator in &config.validator {
+        *validators.entry(validator.chain_id.clone()).or_insert(0) += 1;
+    }
+
+    systemd::spawn(notifier, validators)
 }
 
 /// Run the application (non-`tx_signer` version)
 #[cfg(not(feature = "tx-signer"))]
-fn run_app(validator_clients: Vec<Client>) {
-    blocking_wait(validator_clients);
+fn run_app(validator_clients: Vec<Client>, listeners: Vec<Listener>) {
+    blocking_wait(validator_clients, listeners);
 }
 
 /// Run the application, launching the Tokio executor if need be
 #[cfg(feature = "tx-signer")]
-fn run_app(validator_clients: Vec<Client>) {
+fn run_app(validator_clients: Vec<Client>, listeners: Vec<Listener>) {
     let signer_config = {
         let cfg = APP.config();
 
@@ -81,14 +164,15 @@ fn run_app(validator_clients: Vec<Client>) {
     };
 
     if let Some(cfg) = signer_config {
+        // Returns once the TX signer has stopped in response to a shutdown
         run_async_executor(cfg);
-    } else {
-        blocking_wait(validator_clients);
     }
+
+    blocking_wait(validator_clients, listeners);
 }
 
 /// Wait for clients to shut down using synchronous thread joins
-fn blocking_wait(validator_clients: Vec<Client>) {
+fn blocking_wait(validator_clients: Vec<Client>, listeners: Vec<Listener>) {
     // Wait for all of the validator client threads to exit
     debug!("Main thread waiting on clients...");
 
@@ -103,12 +187,27 @@ fn blocking_wait(validator_clients: Vec<Client>) {
         }
     }
 
-    if success {
-        info!("Shutdown completed successfully");
-    } else {
+    for listener in listeners {
+        let name = listener.name().to_owned();
+
+        if let Err(e) = listener.join() {
+            status_err!("listener '{}' exited with error: {}", name, e);
+            success = false;
+        }
+    }
+
+    if !success {
         warn!("Shutdown completed with errors");
         process::exit(1);
     }
+
+    match shutdown::exit_code() {
+        0 => info!("Shutdown completed successfully"),
+        code => {
+            warn!("Shutdown completed; exiting with status {}", code);
+            process::exit(code);
+        }
+    }
 }
 
 /// Launch the Tokio executor and spawn transaction signers
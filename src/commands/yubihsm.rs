@@ -1,17 +1,25 @@
 //! `tmkms yubihsm` CLI (sub)commands
 
+mod audit;
 mod detect;
 mod keys;
 mod setup;
 mod test;
 
-pub use self::{detect::DetectCommand, keys::KeysCommand, setup::SetupCommand, test::TestCommand};
+pub use self::{
+    audit::AuditCommand, detect::DetectCommand, keys::KeysCommand, setup::SetupCommand,
+    test::TestCommand,
+};
 use abscissa_core::{Command, Help, Options, Runnable};
 use std::path::PathBuf;
 
 /// The `yubihsm` subcommand
 #[derive(Command, Debug, Options, Runnable)]
 pub enum YubihsmCommand {
+    /// Audit log subcommands
+    #[options(help = "audit log subcommands")]
+    Audit(AuditCommand),
+
     /// Detected connected YubiHSM2 devices
     #[options(help = "detect all YubiHSM2 devices connected via USB")]
     Detect(DetectCommand),
@@ -39,6 +47,7 @@ impl YubihsmCommand {
         crate::yubihsm::mark_cli_command();
 
         match self {
+            YubihsmCommand::Audit(audit) => audit.config_path(),
             YubihsmCommand::Keys(keys) => keys.config_path(),
             YubihsmCommand::Setup(setup) => setup.config.as_ref(),
             YubihsmCommand::Test(test) => test.config.as_ref(),
@@ -0,0 +1,84 @@
+//! YubiHSM2 audit log commands
+
+mod pull;
+mod status;
+
+use self::{pull::PullCommand, status::StatusCommand};
+use crate::{config::provider::yubihsm::AuthConfig, prelude::*};
+use abscissa_core::{Command, Help, Options, Runnable};
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
+use zeroize::Zeroizing;
+
+/// Auth key ID of the `auditor` role created by `tmkms yubihsm setup`
+pub const DEFAULT_AUDITOR_KEY: u16 = 3;
+
+/// The `yubihsm audit` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum AuditCommand {
+    /// `yubihsm audit help`
+    #[options(help = "show help for the 'yubihsm audit' subcommand")]
+    Help(Help<Self>),
+
+    /// `yubihsm audit pull`
+    #[options(help = "append audit log entries to a file and mark them as consumed")]
+    Pull(PullCommand),
+
+    /// `yubihsm audit status`
+    #[options(help = "show and verify the audit log without consuming it")]
+    Status(StatusCommand),
+}
+
+impl AuditCommand {
+    /// Optional path to the configuration file
+    pub(super) fn config_path(&self) -> Option<&PathBuf> {
+        match self {
+            AuditCommand::Pull(pull) => pull.config.as_ref(),
+            AuditCommand::Status(status) => status.config.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Open a session with the selected YubiHSM using the given auditor auth key,
+/// reading its password from a file or prompting for it
+fn auditor_client(
+    device: Option<&str>,
+    auth_key: Option<u16>,
+    password_file: Option<&Path>,
+) -> yubihsm::Client {
+    let auth_key = auth_key.unwrap_or(DEFAULT_AUDITOR_KEY);
+
+    let credentials = match password_file {
+        Some(path) => AuthConfig::Path {
+            key: auth_key,
+            password_file: path.to_owned(),
+        }
+        .credentials()
+        .unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        }),
+        None => {
+            let prompt = format!("Enter password for YubiHSM2 auth key 0x{:04x}: ", auth_key);
+            let password = Zeroizing::new(
+                rpassword::read_password_from_tty(Some(&prompt)).expect("error reading password"),
+            );
+
+            yubihsm::Credentials::from_password(auth_key, password.as_bytes())
+        }
+    };
+
+    yubihsm::Client::open(crate::yubihsm::connector(device), credentials, false).unwrap_or_else(
+        |e| {
+            status_err!(
+                "couldn't authenticate with auth key 0x{:04x}: {}",
+                auth_key,
+                e
+            );
+            process::exit(1);
+        },
+    )
+}
@@ -0,0 +1,77 @@
+//! Pull entries from the YubiHSM2 audit log
+
+use super::auditor_client;
+use crate::{prelude::*, yubihsm::audit};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process};
+
+/// The `yubihsm audit pull` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct PullCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
+    /// Auth key to use (defaults to the `auditor` role)
+    #[options(short = "k", long = "auth-key", help = "auditor auth key ID")]
+    pub auth_key: Option<u16>,
+
+    /// Read the auth key password from the given file
+    #[options(long = "password-file", help = "file containing the auth key password")]
+    pub password_file: Option<PathBuf>,
+
+    /// Path to the file to append log entries to
+    #[options(free, help = "file to append log entries to (JSON lines)")]
+    pub path: PathBuf,
+}
+
+impl Runnable for PullCommand {
+    fn run(&self) {
+        if self.path.as_os_str().is_empty() {
+            status_err!("no output path given");
+            process::exit(1);
+        }
+
+        // Continue the digest chain from the last entry written to the file
+        let previous = audit::read_last_entry(&self.path).unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        });
+
+        let hsm = auditor_client(
+            self.device.as_deref(),
+            self.auth_key,
+            self.password_file.as_deref(),
+        );
+
+        let appended = audit::append(&hsm, &self.path, previous.as_ref()).unwrap_or_else(|e| {
+            status_err!("couldn't pull audit log: {}", e);
+            process::exit(1);
+        });
+
+        for discontinuity in &appended.discontinuities {
+            status_warn!(
+                "audit log digest chain broken ({}): started a new segment",
+                discontinuity.discontinuity
+            );
+        }
+
+        match appended.last {
+            Some(last) => status_ok!(
+                "Pulled",
+                "audit log entries through #{} to {}",
+                last.item,
+                self.path.display()
+            ),
+            None => status_ok!("Pulled", "no new audit log entries"),
+        }
+    }
+}
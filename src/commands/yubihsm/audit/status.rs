@@ -0,0 +1,60 @@
+//! Show the YubiHSM2 audit log
+
+use super::auditor_client;
+use crate::{prelude::*, yubihsm::audit::Log};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process};
+
+/// The `yubihsm audit status` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct StatusCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
+    /// Auth key to use (defaults to the `auditor` role)
+    #[options(short = "k", long = "auth-key", help = "auditor auth key ID")]
+    pub auth_key: Option<u16>,
+
+    /// Read the auth key password from the given file
+    #[options(long = "password-file", help = "file containing the auth key password")]
+    pub password_file: Option<PathBuf>,
+}
+
+impl Runnable for StatusCommand {
+    fn run(&self) {
+        let hsm = auditor_client(
+            self.device.as_deref(),
+            self.auth_key,
+            self.password_file.as_deref(),
+        );
+
+        let log = Log::retrieve(&hsm).unwrap_or_else(|e| {
+            status_err!("couldn't retrieve audit log: {}", e);
+            process::exit(1);
+        });
+
+        status_ok!(
+            "Verified",
+            "{} audit log entries (unlogged boot events: {}, unlogged auth events: {})",
+            log.entries.len(),
+            log.unlogged_boot_events,
+            log.unlogged_auth_events
+        );
+
+        for entry in &log.entries {
+            println!(
+                "- #{}: command 0x{:02x} by auth key 0x{:04x} on 0x{:04x} (result 0x{:02x})",
+                entry.item, entry.command, entry.session_key, entry.target_key, entry.result
+            );
+        }
+    }
+}
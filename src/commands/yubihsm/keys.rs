@@ -1,12 +1,14 @@
 //! YubiHSM2 key management commands
 
+mod attest;
 mod export;
 mod generate;
 mod import;
 mod list;
 
 use self::{
-    export::ExportCommand, generate::GenerateCommand, import::ImportCommand, list::ListCommand,
+    attest::AttestCommand, export::ExportCommand, generate::GenerateCommand, import::ImportCommand,
+    list::ListCommand,
 };
 use abscissa_core::{Command, Help, Options, Runnable};
 use std::path::PathBuf;
@@ -23,6 +25,10 @@ pub const DEFAULT_WRAP_KEY: yubihsm::object::Id = 1;
 /// The `yubihsm keys` subcommand
 #[derive(Command, Debug, Options, Runnable)]
 pub enum KeysCommand {
+    /// `yubihsm keys attest`
+    #[options(help = "verify an attestation that a key was created inside the HSM device")]
+    Attest(AttestCommand),
+
     /// `yubihsm keys export`
     #[options(help = "export an encrypted backup of a signing key inside the HSM device")]
     Export(ExportCommand),
@@ -48,6 +54,7 @@ impl KeysCommand {
     /// Optional path to the configuration file
     pub(super) fn config_path(&self) -> Option<&PathBuf> {
         match self {
+            KeysCommand::Attest(attest) => attest.config.as_ref(),
             KeysCommand::Export(export) => export.config.as_ref(),
             KeysCommand::Generate(generate) => generate.config.as_ref(),
             KeysCommand::List(list) => list.config.as_ref(),
@@ -56,3 +63,13 @@ impl KeysCommand {
         }
     }
 }
+
+/// Describe where a key came from
+fn describe_origin(origin: yubihsm::object::Origin) -> &'static str {
+    match origin {
+        yubihsm::object::Origin::Generated => "generated",
+        yubihsm::object::Origin::Imported => "imported",
+        yubihsm::object::Origin::WrappedGenerated => "generated (imported under wrap)",
+        yubihsm::object::Origin::WrappedImported => "imported (imported under wrap)",
+    }
+}
@@ -0,0 +1,199 @@
+//! Verify attestations of keys inside the YubiHSM2
+
+use super::*;
+use crate::{prelude::*, yubihsm::attestation};
+use abscissa_core::{Command, Options, Runnable};
+use serde::Serialize;
+use std::{fs, path::PathBuf, process};
+use subtle_encoding::{base64, hex};
+
+/// The `yubihsm keys attest` subcommand: verify a key was created inside the
+/// YubiHSM and show its attested properties
+#[derive(Command, Debug, Default, Options)]
+pub struct AttestCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
+    /// Yubico CA certificate(s) to verify the attestation against
+    #[options(
+        no_short,
+        long = "ca",
+        help = "Yubico CA certificate(s) (overrides `attestation_ca` in tmkms.toml)"
+    )]
+    pub ca: Option<PathBuf>,
+
+    /// Write an attestation report as JSON to the given filename
+    #[options(
+        short = "w",
+        long = "write-report",
+        help = "write report file at given path"
+    )]
+    pub write_report: Option<PathBuf>,
+
+    /// ID of the key to attest
+    #[options(free, help = "ID of the key to attest")]
+    pub key_id: u16,
+}
+
+/// Attestation report
+#[derive(Debug, Serialize)]
+struct Report {
+    /// Serial number of the YubiHSM
+    serial_number: String,
+
+    /// Key ID
+    key_id: u16,
+
+    /// Key label
+    label: String,
+
+    /// Key algorithm
+    algorithm: String,
+
+    /// Public key (hex)
+    public_key: String,
+
+    /// Whether the key was generated inside the YubiHSM or imported
+    origin: String,
+
+    /// Whether the key can be exported under wrap
+    exportable: bool,
+
+    /// Key capabilities
+    capabilities: String,
+
+    /// Key domains
+    domains: String,
+
+    /// Attestation certificate (base64 DER)
+    attestation_certificate: String,
+
+    /// Certificate chain from the key up to the root
+    certificate_chain: Vec<String>,
+}
+
+impl Runnable for AttestCommand {
+    fn run(&self) {
+        let ca_path = self
+            .ca
+            .clone()
+            .or_else(|| crate::yubihsm::config(self.device.as_deref()).attestation_ca)
+            .unwrap_or_else(|| {
+                status_err!("no CA certificate given (use --ca or set `attestation_ca`)");
+                process::exit(1);
+            });
+
+        let ca_certificates = attestation::load_ca_certificates(&ca_path).unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        });
+
+        let hsm = crate::yubihsm::client(self.device.as_deref());
+
+        let serial_number = hsm
+            .device_info()
+            .unwrap_or_else(|e| {
+                status_err!("couldn't get YubiHSM serial number: {}", e);
+                process::exit(1);
+            })
+            .serial_number;
+
+        let key_info = hsm
+            .get_object_info(self.key_id, yubihsm::object::Type::AsymmetricKey)
+            .unwrap_or_else(|e| {
+                status_err!(
+                    "couldn't get object info for asymmetric key 0x{:04x}: {}",
+                    self.key_id,
+                    e
+                );
+                process::exit(1);
+            });
+
+        let public_key = hsm.get_public_key(self.key_id).unwrap_or_else(|e| {
+            status_err!(
+                "couldn't get public key for asymmetric key 0x{:04x}: {}",
+                self.key_id,
+                e
+            );
+            process::exit(1);
+        });
+
+        let attestation = attestation::Attestation::request(
+            &hsm,
+            self.key_id,
+            public_key.as_ref(),
+            &ca_certificates,
+        )
+        .and_then(|attestation| {
+            attestation.check_object_info(serial_number, &key_info)?;
+            Ok(attestation)
+        })
+        .unwrap_or_else(|e| {
+            status_err!("attestation of key 0x{:04x} failed: {}", self.key_id, e);
+            process::exit(1);
+        });
+
+        // Report the properties signed by the device, not the object info
+        let properties = &attestation.properties;
+        let exportable = properties
+            .capabilities
+            .contains(yubihsm::Capability::EXPORTABLE_UNDER_WRAP);
+
+        status_ok!(
+            "Verified",
+            "key 0x{:04x} is in YubiHSM #{}",
+            properties.key_id,
+            properties.serial_number
+        );
+
+        let chain = attestation.describe_chain();
+        println!("   label:        \"{}\"", &properties.label);
+        println!("   algorithm:    {:?}", public_key.algorithm);
+        println!("   origin:       {}", describe_origin(properties.origin));
+        println!("   exportable:   {}", if exportable { "yes" } else { "no" });
+        println!("   capabilities: {:?}", properties.capabilities);
+        println!("   domains:      {:?}", properties.domains);
+        println!("   chain:        {}", chain.join(" <- "));
+
+        if let Some(ref report_path) = self.write_report {
+            let report = Report {
+                serial_number: properties.serial_number.to_string(),
+                key_id: properties.key_id,
+                label: properties.label.clone(),
+                algorithm: format!("{:?}", public_key.algorithm),
+                public_key: String::from_utf8(hex::encode(public_key.as_ref())).unwrap(),
+                origin: describe_origin(properties.origin).to_owned(),
+                exportable,
+                capabilities: format!("{:?}", properties.capabilities),
+                domains: format!("{:?}", properties.domains),
+                attestation_certificate: String::from_utf8(base64::encode(
+                    &attestation.certificate,
+                ))
+                .unwrap(),
+                certificate_chain: chain,
+            };
+
+            fs::write(report_path, serde_json::to_string_pretty(&report).unwrap()).unwrap_or_else(
+                |e| {
+                    status_err!("couldn't write {}: {}", report_path.display(), e);
+                    process::exit(1);
+                },
+            );
+
+            status_ok!(
+                "Writing",
+                "attestation report to: {}",
+                report_path.display()
+            );
+        }
+    }
+}
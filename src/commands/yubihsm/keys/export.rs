@@ -12,6 +12,14 @@ pub struct ExportCommand {
     #[options(short = "c", long = "config", help = "path to tmkms.toml")]
     pub config: Option<PathBuf>,
 
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
     /// ID of the key to export
     #[options(short = "i", long = "id", help = "key to export in encrypted form")]
     pub key_id: u16,
@@ -33,7 +41,7 @@ impl Runnable for ExportCommand {
     fn run(&self) {
         let wrap_key_id = self.wrap_key_id.unwrap_or(DEFAULT_WRAP_KEY);
 
-        let wrapped_bytes = crate::yubihsm::client()
+        let wrapped_bytes = crate::yubihsm::client(self.device.as_deref())
             .export_wrapped(
                 wrap_key_id,
                 yubihsm::object::Type::AsymmetricKey,
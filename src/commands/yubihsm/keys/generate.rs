@@ -17,6 +17,14 @@ pub struct GenerateCommand {
     #[options(short = "c", long = "config", help = "path to tmkms.toml")]
     pub config: Option<PathBuf>,
 
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
     /// Label for generated key(s)
     #[options(short = "l", long = "label", help = "label for generated key")]
     pub label: Option<String>,
@@ -101,7 +109,7 @@ impl Runnable for GenerateCommand {
         let key_id = self.parse_key_id();
         let key_type = self.parse_key_type();
 
-        let hsm = crate::yubihsm::client();
+        let hsm = crate::yubihsm::client(self.device.as_deref());
         let mut capabilities = DEFAULT_CAPABILITIES;
 
         // If the key isn't explicitly marked as non-exportable, allow it to be exported
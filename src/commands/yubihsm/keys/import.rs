@@ -17,6 +17,14 @@ pub struct ImportCommand {
     #[options(short = "c", long = "config", help = "path to tmkms.toml")]
     pub config: Option<PathBuf>,
 
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
     /// ID of the key to import (if applicable)
     #[options(short = "i", long = "id", help = "key ID to import")]
     pub key_id: Option<u16>,
@@ -100,7 +108,7 @@ impl ImportCommand {
                 process::exit(1);
             });
 
-        let hsm = crate::yubihsm::client();
+        let hsm = crate::yubihsm::client(self.device.as_deref());
 
         let obj = hsm
             .import_wrapped(wrap_key_id, wrapped_message)
@@ -179,7 +187,7 @@ impl ImportCommand {
         let label =
             yubihsm::object::Label::from(self.label.as_ref().map(|l| l.as_ref()).unwrap_or(""));
 
-        if let Err(e) = crate::yubihsm::client().put_asymmetric_key(
+        if let Err(e) = crate::yubihsm::client(self.device.as_deref()).put_asymmetric_key(
             key_id,
             label,
             DEFAULT_DOMAINS,
@@ -225,7 +233,7 @@ impl ImportCommand {
         let label =
             yubihsm::object::Label::from(self.label.as_ref().map(|l| l.as_ref()).unwrap_or(""));
 
-        if let Err(e) = crate::yubihsm::client().put_asymmetric_key(
+        if let Err(e) = crate::yubihsm::client(self.device.as_deref()).put_asymmetric_key(
             key_id,
             label,
             DEFAULT_DOMAINS,
@@ -12,13 +12,21 @@ pub struct ListCommand {
     /// Path to configuration file
     #[options(short = "c", long = "config", help = "path to tmkms.toml")]
     pub config: Option<PathBuf>,
+
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
 }
 
 impl Runnable for ListCommand {
     /// List all suitable Ed25519 keys in the HSM
     fn run(&self) {
-        let key_formatters = load_key_formatters();
-        let hsm = crate::yubihsm::client();
+        let key_formatters = load_key_formatters(self.device.as_deref());
+        let hsm = crate::yubihsm::client(self.device.as_deref());
 
         let serial_number = hsm
             .device_info()
@@ -48,15 +56,15 @@ impl Runnable for ListCommand {
         println!("Listing keys in YubiHSM #{}:", serial_number);
 
         for key in &keys {
-            display_key_info(&*hsm, &key, &key_formatters);
+            display_key_info(&hsm, &key, &key_formatters);
         }
     }
 }
 
 /// Load information about configured YubiHSM keys
-fn load_key_formatters() -> Map<u16, keyring::Format> {
+fn load_key_formatters(device: Option<&str>) -> Map<u16, keyring::Format> {
     let chain_formatters = load_chain_formatters();
-    let cfg = crate::yubihsm::config();
+    let cfg = crate::yubihsm::config(device);
     let mut map = Map::new();
 
     for key_config in &cfg.keys {
@@ -155,4 +163,5 @@ fn display_key_info(
 
     status_attr_ok!(key_id, "[{}] {}", key_type, key_serialized);
     println!("   label: \"{}\"", &key_info.label);
+    println!("   origin: {}", super::describe_origin(key_info.origin));
 }
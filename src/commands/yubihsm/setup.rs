@@ -1,5 +1,9 @@
 //! Set up a new YubiHSM2 or restore from backup
 
+mod profile;
+mod shares;
+
+use self::profile::{ProfileConfig, ADMIN_KEY_ID, ADMIN_ROLE_NAME};
 use crate::prelude::*;
 use abscissa_core::{Command, Options, Runnable};
 use chrono::{SecondsFormat, Utc};
@@ -34,12 +38,6 @@ const DERIVATION_VERSION: &[u8] = b"1";
 /// This results in a 24-word BIP39 [`mnemonic::Phrase`].
 const KEY_SIZE: usize = 32;
 
-/// Role names
-const ADMIN_ROLE_NAME: &str = "admin";
-const OPERATOR_ROLE_NAME: &str = "operator";
-const AUDITOR_ROLE_NAME: &str = "auditor";
-const VALIDATOR_ROLE_NAME: &str = "validator";
-
 /// The `yubihsm setup` subcommand: performs initial device provisioning
 /// including creation of initial authentication and wrap keys.
 #[derive(Command, Debug, Default, Options)]
@@ -48,6 +46,14 @@ pub struct SetupCommand {
     #[options(short = "c", long = "config", help = "path to tmkms.toml")]
     pub config: Option<PathBuf>,
 
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
     /// Print debugging information
     #[options(short = "v", long = "verbose", help = "enable verbose debug logging")]
     pub verbose: bool,
@@ -60,6 +66,22 @@ pub struct SetupCommand {
     #[options(short = "r", long = "restore", help = "restore from existing 24-words")]
     pub restore: bool,
 
+    /// Provisioning profile describing the roles and wrap keys to create
+    #[options(
+        short = "P",
+        long = "profile",
+        help = "path to a TOML provisioning profile"
+    )]
+    pub profile: Option<PathBuf>,
+
+    /// Enable forced auditing
+    #[options(
+        short = "a",
+        long = "audit",
+        help = "enable forced auditing (commands fail when the audit log is full)"
+    )]
+    pub audit: bool,
+
     /// Write a provisioning report as JSON to the given filename
     #[options(
         short = "w",
@@ -67,13 +89,40 @@ pub struct SetupCommand {
         help = "write report file at given path"
     )]
     pub write_report: Option<PathBuf>,
+
+    /// Split the admin mnemonic into this many shares
+    #[options(
+        no_short,
+        long = "shares",
+        help = "split the admin mnemonic into this many shares (requires --threshold)"
+    )]
+    pub shares: Option<u8>,
+
+    /// Number of shares required to recover the admin mnemonic
+    #[options(
+        no_short,
+        long = "threshold",
+        help = "number of shares needed to recover the admin mnemonic"
+    )]
+    pub threshold: Option<u8>,
 }
 
 impl Runnable for SetupCommand {
     /// Perform initial YubiHSM dervice provisioning
     fn run(&self) {
-        let hsm_connector = crate::yubihsm::connector();
-        let hsm_serial_number = get_hsm_client(&hsm_connector)
+        if self.restore && self.shares.is_some() {
+            status_err!("--shares can't be used with --restore (use --threshold)");
+            exit(1);
+        }
+
+        if !self.restore && self.shares.is_some() != self.threshold.is_some() {
+            status_err!("--shares and --threshold must be used together");
+            exit(1);
+        }
+
+        let device = self.device.as_deref();
+        let hsm_connector = crate::yubihsm::connector(device);
+        let hsm_serial_number = get_hsm_client(&hsm_connector, device)
             .device_info()
             .expect("error getting device info")
             .serial_number;
@@ -82,31 +131,48 @@ impl Runnable for SetupCommand {
             println!("Restoring and reprovisioning YubiHSM from existing 24-word mnemonic phrase.");
             println!();
 
-            read_mnemonic_from_stdin("*** Enter mnemonic (separate words with spaces): ")
+            match self.threshold {
+                Some(threshold) => read_shares_from_stdin(threshold),
+                None => {
+                    read_mnemonic_from_stdin("*** Enter mnemonic (separate words with spaces): ")
+                }
+            }
         } else {
-            generate_mnemonic_from_hsm_and_os_csprngs(&hsm_connector)
+            generate_mnemonic_from_hsm_and_os_csprngs(&hsm_connector, device)
         };
 
-        let roles = derive_roles_from_mnemonic(&mnemonic);
-        let wrap_key = derive_wrap_key_from_mnemonic(&mnemonic, 1);
+        let mnemonic_shares = match (self.shares, self.threshold) {
+            (Some(n), Some(k)) => Some(shares::split(&mnemonic, k, n).unwrap_or_else(|e| {
+                status_err!("{}", e);
+                exit(1);
+            })),
+            _ => None,
+        };
 
-        // TODO(tarcieri): support for enabling forced auditing
-        let profile = Profile::default()
-            .audit_option(AuditOption::Off)
-            .roles(roles)
-            .wrap_keys(vec![wrap_key]);
+        let profile_config = match &self.profile {
+            Some(path) => ProfileConfig::load(path).unwrap_or_else(|e| {
+                status_err!("couldn't load provisioning profile: {}", e);
+                exit(1);
+            }),
+            None => ProfileConfig::default(),
+        };
 
-        let operator_password = RolePassword::derive_from_mnemonic(&mnemonic, OPERATOR_ROLE_NAME);
-        let auditor_password = RolePassword::derive_from_mnemonic(&mnemonic, AUDITOR_ROLE_NAME);
-        let validator_password = RolePassword::derive_from_mnemonic(&mnemonic, VALIDATOR_ROLE_NAME);
+        let roles = derive_roles_from_mnemonic(&mnemonic, &profile_config);
+        let wrap_keys = derive_wrap_keys_from_mnemonic(&mnemonic, &profile_config);
 
-        // Re-derive wrap key for display
-        // TODO(tarcieri): allow access to the underlying wrap key secret in `yubihsm` crate to avoid this
-        let wrapkey_material =
-            derive_secret_from_mnemonic(&mnemonic, &[b"wrap", serialize_key_id(1).as_bytes()]);
+        let audit_option = if self.audit {
+            AuditOption::On
+        } else {
+            profile_config
+                .audit
+                .map(Into::into)
+                .unwrap_or(AuditOption::Off)
+        };
 
-        let wrapkey_hex =
-            Zeroizing::new(String::from_utf8(hex::encode(wrapkey_material.as_bytes())).unwrap());
+        let profile = Profile::default()
+            .audit_option(audit_option)
+            .roles(roles)
+            .wrap_keys(wrap_keys);
 
         if self.print_only {
             if self.restore {
@@ -125,24 +191,73 @@ impl Runnable for SetupCommand {
         }
 
         println!();
-        println!("- key 0x0001: admin:");
-        println!();
-        print_mnemonic(&mnemonic);
+        if let Some(mnemonic_shares) = &mnemonic_shares {
+            println!(
+                "- key 0x0001: admin (split into {} shares, any {} of which recover it):",
+                mnemonic_shares.len(),
+                self.threshold.unwrap()
+            );
+
+            for share in mnemonic_shares {
+                println!();
+                println!("  share {}:", share.index);
+                print_mnemonic(&share.mnemonic);
+            }
+
+            println!();
+            println!(
+                "  fingerprint (note it down with each share, it's needed to restore): {}",
+                shares::fingerprint(&mnemonic)
+            );
+        } else if let (true, Some(threshold)) = (self.restore, self.threshold) {
+            println!(
+                "- key 0x0001: admin (recombined from {} shares, fingerprint {})",
+                threshold,
+                shares::fingerprint(&mnemonic)
+            );
+        } else {
+            println!("- key 0x0001: admin:");
+            println!();
+            print_mnemonic(&mnemonic);
+        }
         println!();
-        println!(
-            "- authkey 0x0002 [operator]:  {}",
-            operator_password.as_str()
-        );
-        println!(
-            "- authkey 0x0003 [auditor]:   {}",
-            auditor_password.as_str()
-        );
-        println!(
-            "- authkey 0x0004 [validator]: {}",
-            validator_password.as_str()
-        );
 
-        println!("- wrapkey 0x0001 [primary]:   {}", wrapkey_hex.as_str());
+        // Pad labels so the derived secrets line up
+        let width = profile_config
+            .roles
+            .iter()
+            .map(|role| role.name.len())
+            .chain(profile_config.wrap_keys.iter().map(|key| key.label.len()))
+            .max()
+            .unwrap_or(0);
+
+        for role in &profile_config.roles {
+            let password = RolePassword::derive_from_mnemonic(&mnemonic, &role.name);
+            println!(
+                "- authkey 0x{:04x} {:<width$} {}",
+                role.key,
+                format!("[{}]:", role.name),
+                password.as_str(),
+                width = width + 3
+            );
+        }
+
+        for wrap_key in &profile_config.wrap_keys {
+            // Re-derive wrap key for display
+            // TODO(tarcieri): allow access to the underlying wrap key secret in `yubihsm` crate to avoid this
+            let wrapkey_material = derive_wrap_key_secret_from_mnemonic(&mnemonic, wrap_key.key);
+            let wrapkey_hex = Zeroizing::new(
+                String::from_utf8(hex::encode(wrapkey_material.as_bytes())).unwrap(),
+            );
+
+            println!(
+                "- wrapkey 0x{:04x} {:<width$} {}",
+                wrap_key.key,
+                format!("[{}]:", wrap_key.label),
+                wrapkey_hex.as_str(),
+                width = width + 3
+            );
+        }
 
         if self.print_only {
             exit(0);
@@ -152,7 +267,10 @@ impl Runnable for SetupCommand {
 
         let report = yubihsm::setup::erase_device_and_init_with_profile(
             hsm_connector.clone(),
-            crate::yubihsm::config().auth.credentials(),
+            crate::yubihsm::config(device)
+                .auth
+                .credentials()
+                .unwrap_or_else(|e| hsm_error(&e)),
             profile,
         )
         .unwrap_or_else(|e| hsm_error(&e));
@@ -207,6 +325,51 @@ fn read_mnemonic_from_stdin(prompt: &str) -> mnemonic::Phrase {
     result
 }
 
+/// Read `threshold` shares of the mnemonic and its fingerprint from STDIN,
+/// and recombine them
+fn read_shares_from_stdin(threshold: u8) -> mnemonic::Phrase {
+    let mut mnemonic_shares = vec![];
+
+    for n in 1..=threshold {
+        print!("*** Enter number of share {} of {}: ", n, threshold);
+        io::stdout().flush().unwrap();
+
+        let mut index_string = String::new();
+        io::stdin()
+            .read_line(&mut index_string)
+            .expect("error reading share number from STDIN!");
+
+        let index = index_string.trim().parse().unwrap_or_else(|_| {
+            eprintln!("*** ERROR: invalid share number");
+            exit(1);
+        });
+
+        let mnemonic = read_mnemonic_from_stdin(&format!(
+            "*** Enter share {} (separate words with spaces): ",
+            index
+        ));
+
+        mnemonic_shares.push(shares::Share { index, mnemonic });
+    }
+
+    print!("*** Enter the fingerprint noted down with the shares: ");
+    io::stdout().flush().unwrap();
+
+    let mut fingerprint = String::new();
+    io::stdin()
+        .read_line(&mut fingerprint)
+        .expect("error reading fingerprint from STDIN!");
+
+    let mnemonic = shares::combine_checked(&mnemonic_shares, &fingerprint).unwrap_or_else(|e| {
+        eprintln!("*** ERROR: {}", e);
+        exit(1);
+    });
+
+    println!("\nShares recombined successfully (fingerprint matches)!\n");
+
+    mnemonic
+}
+
 /// Display the mnemonic as two groups of 12 words
 fn print_mnemonic(mnemonic: &mnemonic::Phrase) {
     let words: Vec<&str> = mnemonic.phrase().split(' ').collect();
@@ -224,10 +387,13 @@ fn print_mnemonic(mnemonic: &mnemonic::Phrase) {
 /// We need to create our own client here since the global one maintains
 /// a persistent connection, and we need to close this one before we can
 /// reprovision the HSM
-fn get_hsm_client(hsm_connector: &Connector) -> yubihsm::Client {
+fn get_hsm_client(hsm_connector: &Connector, device: Option<&str>) -> yubihsm::Client {
     yubihsm::Client::open(
         hsm_connector.clone(),
-        crate::yubihsm::config().auth.credentials(),
+        crate::yubihsm::config(device)
+            .auth
+            .credentials()
+            .unwrap_or_else(|e| hsm_error(&e)),
         false,
     )
     .unwrap_or_else(|e| hsm_error(&e))
@@ -240,8 +406,11 @@ fn get_hsm_client(hsm_connector: &Connector) -> yubihsm::Client {
 /// function (HKDF) in order to derive the recovery passphrase, which ideally
 /// ensures that the passphrase will be securely random so long as at least
 /// one of the two inputs is secure.
-fn generate_mnemonic_from_hsm_and_os_csprngs(hsm_connector: &Connector) -> mnemonic::Phrase {
-    let hsm_client = get_hsm_client(hsm_connector);
+fn generate_mnemonic_from_hsm_and_os_csprngs(
+    hsm_connector: &Connector,
+    device: Option<&str>,
+) -> mnemonic::Phrase {
+    let hsm_client = get_hsm_client(hsm_connector, device);
 
     // Obtain half of the IKM from the YubiHSM (256-bits)
     let mut ikm = hsm_client
@@ -267,50 +436,22 @@ fn generate_mnemonic_from_hsm_and_os_csprngs(hsm_connector: &Connector) -> mnemo
     result
 }
 
-/// Derive the default roles form the given BIP39 `mnemonic::Phrase`
-fn derive_roles_from_mnemonic(mnemonic: &mnemonic::Phrase) -> Vec<Role> {
-    let admin_role = derive_admin_role_from_mnemonic(mnemonic);
-
-    // operator
-    let operator_role = derive_role_from_mnemonic(mnemonic, 2, OPERATOR_ROLE_NAME)
-        .capabilities(
-            Capability::GENERATE_ASYMMETRIC_KEY
-                | Capability::PUT_ASYMMETRIC_KEY
-                | Capability::GENERATE_HMAC_KEY
-                | Capability::PUT_HMAC_KEY
-                | Capability::IMPORT_WRAPPED
-                | Capability::EXPORT_WRAPPED
-                | Capability::GET_OPAQUE
-                | Capability::GET_OPTION
-                | Capability::GET_LOG_ENTRIES
-                | Capability::SIGN_ATTESTATION_CERTIFICATE,
-        )
-        .delegated_capabilities(Capability::all())
-        .domains(Domain::all());
-
-    // auditor
-    let auditor_role = derive_role_from_mnemonic(mnemonic, 3, AUDITOR_ROLE_NAME)
-        .capabilities(
-            Capability::GET_LOG_ENTRIES
-                | Capability::GET_OPTION
-                | Capability::PUT_OPTION
-                | Capability::GET_OPAQUE,
-        )
-        .delegated_capabilities(Capability::empty())
-        .domains(Domain::all());
-
-    // validator
-    let validator_role = derive_role_from_mnemonic(mnemonic, 4, VALIDATOR_ROLE_NAME)
-        .capabilities(
-            Capability::SIGN_ECDSA
-                | Capability::SIGN_EDDSA
-                | Capability::SIGN_ATTESTATION_CERTIFICATE
-                | Capability::GET_LOG_ENTRIES,
-        )
-        .delegated_capabilities(Capability::empty())
-        .domains(Domain::DOM1);
+/// Derive the admin role and the roles in the given provisioning profile
+/// from the given BIP39 `mnemonic::Phrase`
+fn derive_roles_from_mnemonic(mnemonic: &mnemonic::Phrase, profile: &ProfileConfig) -> Vec<Role> {
+    let mut roles = vec![derive_admin_role_from_mnemonic(mnemonic)];
+
+    // Profiles are validated when they're loaded
+    for role in &profile.roles {
+        roles.push(
+            derive_role_from_mnemonic(mnemonic, role.key, &role.name)
+                .capabilities(role.capabilities().unwrap())
+                .delegated_capabilities(role.delegated_capabilities().unwrap())
+                .domains(role.domains().unwrap()),
+        );
+    }
 
-    vec![admin_role, operator_role, auditor_role, validator_role]
+    roles
 }
 
 /// Derive the admin role from the given mnemonic.
@@ -319,7 +460,7 @@ fn derive_roles_from_mnemonic(mnemonic: &mnemonic::Phrase) -> Vec<Role> {
 /// authority over the HSM device.
 fn derive_admin_role_from_mnemonic(mnemonic: &mnemonic::Phrase) -> Role {
     let admin_credentials = Credentials::new(
-        1,
+        ADMIN_KEY_ID,
         authentication::Key::derive_from_password(mnemonic.phrase().as_bytes()),
     );
 
@@ -389,42 +530,40 @@ impl Drop for RolePassword {
     }
 }
 
-/// Derive a wrap key from the given BIP39 `mnemonic::Phrase`
-fn derive_wrap_key_from_mnemonic(mnemonic: &mnemonic::Phrase, key_id: object::Id) -> wrap::Key {
-    // Capabilities given to the initial wrap key:
-    // wrap/unwrap both data and objects
-    let wrap_key_capabilities = Capability::EXPORT_WRAPPED
-        | Capability::IMPORT_WRAPPED
-        | Capability::WRAP_DATA
-        | Capability::UNWRAP_DATA;
-
-    // Allow the initial wrap key to create new objects with any other
-    // capabilities.
-    //
-    // To prevent escalation of privilege, no roles except administrators
-    // will be given the capability to export other objects.
-    let wrap_key_delegated_capabilities = Capability::all();
-
-    // Make the wrap key accessible from all domains. This allows it to
-    // import and export objects from any domain.
-    let wrap_key_domains = Domain::all();
-
-    // Label to put on the wrap key
-    let wrap_key_label = create_object_label("primary");
-
-    // Includes the key ID in the derivation path, which allows us to derive
-    // other wrap keys from the same seed `mnemonic::Phrase` phrase in the event one
-    // has been compromised.
-    wrap::Key::from_bytes(
-        key_id,
-        derive_secret_from_mnemonic(mnemonic, &[b"wrap", serialize_key_id(key_id).as_bytes()])
-            .as_bytes(),
-    )
-    .unwrap()
-    .label(wrap_key_label)
-    .capabilities(wrap_key_capabilities)
-    .delegated_capabilities(wrap_key_delegated_capabilities)
-    .domains(wrap_key_domains)
+/// Derive the wrap keys in the given provisioning profile from the given
+/// BIP39 `mnemonic::Phrase`
+fn derive_wrap_keys_from_mnemonic(
+    mnemonic: &mnemonic::Phrase,
+    profile: &ProfileConfig,
+) -> Vec<wrap::Key> {
+    // Profiles are validated when they're loaded
+    profile
+        .wrap_keys
+        .iter()
+        .map(|wrap_key| {
+            wrap::Key::from_bytes(
+                wrap_key.key,
+                derive_wrap_key_secret_from_mnemonic(mnemonic, wrap_key.key).as_bytes(),
+            )
+            .unwrap()
+            .label(create_object_label(&wrap_key.label))
+            .capabilities(wrap_key.capabilities().unwrap())
+            .delegated_capabilities(wrap_key.delegated_capabilities().unwrap())
+            .domains(wrap_key.domains().unwrap())
+        })
+        .collect()
+}
+
+/// Derive the secret for a wrap key from the given BIP39 `mnemonic::Phrase`.
+///
+/// Includes the key ID in the derivation path, which allows us to derive
+/// other wrap keys from the same seed `mnemonic::Phrase` phrase in the event one
+/// has been compromised.
+fn derive_wrap_key_secret_from_mnemonic(
+    mnemonic: &mnemonic::Phrase,
+    key_id: object::Id,
+) -> KeyMaterial {
+    derive_secret_from_mnemonic(mnemonic, &[b"wrap", serialize_key_id(key_id).as_bytes()])
 }
 
 /// Serialize a key ID as bytes for use in a derivation path
@@ -0,0 +1,443 @@
+//! Provisioning profiles for `tmkms yubihsm setup`: the roles (i.e.
+//! authentication keys) and wrap keys to create, described in TOML.
+//!
+//! The secrets for each role and wrap key are derived from the admin
+//! mnemonic using the role's name or the wrap key's ID, so the same profile
+//! and mnemonic always produce the same keys.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use serde::Deserialize;
+use std::{collections::BTreeSet, fs, path::Path};
+use yubihsm::{AuditOption, Capability, Domain};
+
+/// Name of the admin role
+pub const ADMIN_ROLE_NAME: &str = "admin";
+
+/// Auth key ID reserved for the admin role (derived from the mnemonic itself)
+pub const ADMIN_KEY_ID: u16 = 1;
+
+/// Maximum length of role names and wrap key labels: object labels are at
+/// most 40 bytes, and include a 20-byte timestamp and a separator
+pub const MAX_NAME_LENGTH: usize = 19;
+
+/// Provisioning profile
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Forced auditing setting (off unless given)
+    pub audit: Option<AuditSetting>,
+
+    /// Roles to create in addition to the admin role
+    #[serde(default, rename = "role")]
+    pub roles: Vec<RoleConfig>,
+
+    /// Wrap keys to create
+    #[serde(default, rename = "wrap_key")]
+    pub wrap_keys: Vec<WrapKeyConfig>,
+}
+
+/// Role (i.e. authentication key) configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleConfig {
+    /// Name of the role: used to derive its password and in its label
+    pub name: String,
+
+    /// Authentication key ID
+    pub key: u16,
+
+    /// Capabilities of the role
+    pub capabilities: Vec<String>,
+
+    /// Capabilities of objects created by the role
+    #[serde(default)]
+    pub delegated_capabilities: Vec<String>,
+
+    /// Domains the role can access (1-16)
+    pub domains: Vec<u8>,
+}
+
+/// Wrap key configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WrapKeyConfig {
+    /// Wrap key ID: used to derive the key
+    pub key: u16,
+
+    /// Label for the wrap key
+    pub label: String,
+
+    /// Capabilities of the wrap key
+    pub capabilities: Vec<String>,
+
+    /// Capabilities of objects imported under the wrap key
+    #[serde(default)]
+    pub delegated_capabilities: Vec<String>,
+
+    /// Domains the wrap key can access (1-16)
+    pub domains: Vec<u8>,
+}
+
+/// Forced auditing setting
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum AuditSetting {
+    /// Forced auditing is disabled
+    #[serde(rename = "off")]
+    Off,
+
+    /// Forced auditing is enabled
+    #[serde(rename = "on")]
+    On,
+
+    /// Forced auditing is enabled and can't be disabled without a reset
+    #[serde(rename = "fix")]
+    Fix,
+}
+
+impl From<AuditSetting> for AuditOption {
+    fn from(setting: AuditSetting) -> AuditOption {
+        match setting {
+            AuditSetting::Off => AuditOption::Off,
+            AuditSetting::On => AuditOption::On,
+            AuditSetting::Fix => AuditOption::Fix,
+        }
+    }
+}
+
+impl Default for ProfileConfig {
+    /// Default profile: `operator`, `auditor`, and `validator` roles, and a
+    /// `primary` wrap key
+    fn default() -> Self {
+        let names =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|&name| name.to_owned()).collect() };
+
+        Self {
+            audit: None,
+            roles: vec![
+                RoleConfig {
+                    name: "operator".to_owned(),
+                    key: 2,
+                    capabilities: names(&[
+                        "generate-asymmetric-key",
+                        "put-asymmetric-key",
+                        "generate-hmac-key",
+                        "put-hmac-key",
+                        "import-wrapped",
+                        "export-wrapped",
+                        "get-opaque",
+                        "get-option",
+                        "get-log-entries",
+                        "sign-attestation-certificate",
+                    ]),
+                    delegated_capabilities: names(&["all"]),
+                    domains: (1..=16).collect(),
+                },
+                RoleConfig {
+                    name: "auditor".to_owned(),
+                    key: 3,
+                    capabilities: names(&[
+                        "get-log-entries",
+                        "get-option",
+                        "put-option",
+                        "get-opaque",
+                    ]),
+                    delegated_capabilities: vec![],
+                    domains: (1..=16).collect(),
+                },
+                RoleConfig {
+                    name: "validator".to_owned(),
+                    key: 4,
+                    capabilities: names(&[
+                        "sign-ecdsa",
+                        "sign-eddsa",
+                        "sign-attestation-certificate",
+                        "get-log-entries",
+                    ]),
+                    delegated_capabilities: vec![],
+                    domains: vec![1],
+                },
+            ],
+            wrap_keys: vec![WrapKeyConfig {
+                key: 1,
+                label: "primary".to_owned(),
+                capabilities: names(&[
+                    "export-wrapped",
+                    "import-wrapped",
+                    "wrap-data",
+                    "unwrap-data",
+                ]),
+                delegated_capabilities: names(&["all"]),
+                domains: (1..=16).collect(),
+            }],
+        }
+    }
+}
+
+impl ProfileConfig {
+    /// Load a profile from a TOML file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let toml_string = fs::read_to_string(path)
+            .map_err(|e| format_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+        Self::parse(&toml_string)
+            .map_err(|e| format_err!(ConfigError, "{}: {}", path.display(), e).into())
+    }
+
+    /// Parse and validate a profile
+    pub fn parse(toml_string: &str) -> Result<Self, Error> {
+        let profile: Self =
+            toml::from_str(toml_string).map_err(|e| format_err!(ConfigError, "{}", e))?;
+
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Validate this profile
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut names = BTreeSet::new();
+        let mut key_ids = BTreeSet::new();
+
+        for role in &self.roles {
+            validate_name(&role.name)?;
+
+            if role.name == ADMIN_ROLE_NAME || role.key == ADMIN_KEY_ID {
+                fail!(
+                    ConfigError,
+                    "role {} (0x{:04x}): name and key ID are reserved for the admin role",
+                    role.name,
+                    role.key
+                );
+            }
+
+            if !names.insert(role.name.as_str()) {
+                fail!(ConfigError, "duplicate role name: {}", role.name);
+            }
+
+            if !key_ids.insert(role.key) {
+                fail!(ConfigError, "duplicate auth key ID: 0x{:04x}", role.key);
+            }
+
+            role.capabilities()?;
+            role.delegated_capabilities()?;
+            role.domains()?;
+        }
+
+        let mut wrap_key_ids = BTreeSet::new();
+
+        for wrap_key in &self.wrap_keys {
+            validate_name(&wrap_key.label)?;
+
+            if !wrap_key_ids.insert(wrap_key.key) {
+                fail!(ConfigError, "duplicate wrap key ID: 0x{:04x}", wrap_key.key);
+            }
+
+            wrap_key.capabilities()?;
+            wrap_key.delegated_capabilities()?;
+            wrap_key.domains()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RoleConfig {
+    /// Parse the capabilities of this role
+    pub fn capabilities(&self) -> Result<Capability, Error> {
+        parse_capabilities(&self.capabilities)
+    }
+
+    /// Parse the delegated capabilities of this role
+    pub fn delegated_capabilities(&self) -> Result<Capability, Error> {
+        parse_capabilities(&self.delegated_capabilities)
+    }
+
+    /// Parse the domains of this role
+    pub fn domains(&self) -> Result<Domain, Error> {
+        parse_domains(&self.domains)
+    }
+}
+
+impl WrapKeyConfig {
+    /// Parse the capabilities of this wrap key
+    pub fn capabilities(&self) -> Result<Capability, Error> {
+        parse_capabilities(&self.capabilities)
+    }
+
+    /// Parse the delegated capabilities of this wrap key
+    pub fn delegated_capabilities(&self) -> Result<Capability, Error> {
+        parse_capabilities(&self.delegated_capabilities)
+    }
+
+    /// Parse the domains of this wrap key
+    pub fn domains(&self) -> Result<Domain, Error> {
+        parse_domains(&self.domains)
+    }
+}
+
+/// Ensure a role name or wrap key label is usable in object labels and
+/// derivation paths
+fn validate_name(name: &str) -> Result<(), Error> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH || !valid_chars {
+        fail!(
+            ConfigError,
+            "invalid name `{}`: must be 1-{} characters of a-z, 0-9 or '-'",
+            name,
+            MAX_NAME_LENGTH
+        );
+    }
+
+    Ok(())
+}
+
+/// Parse a list of capability names (e.g. `sign-eddsa`), or `all`. Other
+/// capabilities can be given as a hex bitmask (e.g. `0x0000000000000100`).
+fn parse_capabilities(names: &[String]) -> Result<Capability, Error> {
+    let mut capabilities = Capability::empty();
+
+    for name in names {
+        capabilities |= match name.as_str() {
+            "all" => Capability::all(),
+            "export-wrapped" => Capability::EXPORT_WRAPPED,
+            "exportable-under-wrap" => Capability::EXPORTABLE_UNDER_WRAP,
+            "generate-asymmetric-key" => Capability::GENERATE_ASYMMETRIC_KEY,
+            "generate-hmac-key" => Capability::GENERATE_HMAC_KEY,
+            "get-log-entries" => Capability::GET_LOG_ENTRIES,
+            "get-opaque" => Capability::GET_OPAQUE,
+            "get-option" => Capability::GET_OPTION,
+            "import-wrapped" => Capability::IMPORT_WRAPPED,
+            "put-asymmetric-key" => Capability::PUT_ASYMMETRIC_KEY,
+            "put-hmac-key" => Capability::PUT_HMAC_KEY,
+            "put-option" => Capability::PUT_OPTION,
+            "sign-attestation-certificate" => Capability::SIGN_ATTESTATION_CERTIFICATE,
+            "sign-ecdsa" => Capability::SIGN_ECDSA,
+            "sign-eddsa" => Capability::SIGN_EDDSA,
+            "unwrap-data" => Capability::UNWRAP_DATA,
+            "wrap-data" => Capability::WRAP_DATA,
+            other => parse_capability_bitmask(other)?,
+        };
+    }
+
+    Ok(capabilities)
+}
+
+/// Parse a capability given as a hex bitmask
+fn parse_capability_bitmask(bitmask: &str) -> Result<Capability, Error> {
+    let bits = match bitmask.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => fail!(ConfigError, "unknown capability: {}", bitmask),
+    };
+
+    bits.and_then(Capability::from_bits)
+        .ok_or_else(|| format_err!(ConfigError, "invalid capability bitmask: {}", bitmask).into())
+}
+
+/// Parse a list of domain numbers (1-16)
+fn parse_domains(domains: &[u8]) -> Result<Domain, Error> {
+    if domains.is_empty() {
+        fail!(ConfigError, "at least one domain must be given");
+    }
+
+    let mut result = Domain::empty();
+
+    for &domain in domains {
+        if !(1..=16).contains(&domain) {
+            fail!(ConfigError, "invalid domain: {} (must be 1-16)", domain);
+        }
+
+        result |= Domain::from_bits(1 << (domain - 1)).unwrap();
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_PROFILE: &str = r#"
+        audit = "on"
+
+        [[role]]
+        name = "operator"
+        key = 0x0002
+        capabilities = ["generate-asymmetric-key", "export-wrapped", "import-wrapped"]
+        delegated_capabilities = ["all"]
+        domains = [1, 2]
+
+        [[role]]
+        name = "validator-cosmos"
+        key = 0x0010
+        capabilities = ["sign-eddsa"]
+        domains = [1]
+
+        [[role]]
+        name = "validator-iris"
+        key = 0x0011
+        capabilities = ["sign-eddsa"]
+        domains = [2]
+
+        [[wrap_key]]
+        key = 1
+        label = "primary"
+        capabilities = ["export-wrapped", "import-wrapped"]
+        delegated_capabilities = ["all"]
+        domains = [1, 2]
+
+        [[wrap_key]]
+        key = 2
+        label = "offsite"
+        capabilities = ["import-wrapped"]
+        delegated_capabilities = ["all"]
+        domains = [1, 2]
+    "#;
+
+    #[test]
+    fn parses_profile() {
+        let profile = ProfileConfig::parse(EXAMPLE_PROFILE).unwrap();
+        assert_eq!(profile.audit, Some(AuditSetting::On));
+        assert_eq!(profile.roles.len(), 3);
+        assert_eq!(profile.wrap_keys.len(), 2);
+
+        let validator = &profile.roles[2];
+        assert_eq!(validator.capabilities().unwrap(), Capability::SIGN_EDDSA);
+        assert_eq!(
+            validator.delegated_capabilities().unwrap(),
+            Capability::empty()
+        );
+        assert_eq!(validator.domains().unwrap(), Domain::DOM2);
+    }
+
+    #[test]
+    fn default_profile_is_valid() {
+        ProfileConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_profiles() {
+        for (from, to) in &[
+            ("0x0011", "0x0010"),        // duplicate auth key ID
+            ("0x0011", "0x0001"),        // admin auth key ID
+            ("validator-iris", "admin"), // reserved role name
+            ("validator-iris", "Validator"),
+            ("domains = [2]", "domains = [17]"),
+            ("domains = [2]", "domains = []"),
+            ("[\"import-wrapped\"]", "[\"frobnicate\"]"),
+            ("key = 2", "key = 1"), // duplicate wrap key ID
+        ] {
+            let profile = EXAMPLE_PROFILE.replacen(from, to, 1);
+            assert!(
+                ProfileConfig::parse(&profile).is_err(),
+                "{} -> {}",
+                from,
+                to
+            );
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Shamir's Secret Sharing of the admin mnemonic.
+//!
+//! The mnemonic's 256-bit entropy is split byte-by-byte over GF(2^8) into
+//! `n` shares, any `k` of which recover it. Each share is the same size as
+//! the entropy, so it's encoded as its own 24-word BIP39 mnemonic (whose
+//! checksum catches typos) along with its index (1-255).
+//!
+//! Recombining fewer than `k` shares (or shares from different splits)
+//! yields a different, valid-looking mnemonic, so a short fingerprint of the
+//! entropy is noted alongside the shares and checked when they're combined.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use getrandom::getrandom;
+use hkd32::mnemonic;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use subtle_encoding::hex;
+use zeroize::Zeroize;
+
+/// Size of the mnemonic entropy (and of each share)
+const SECRET_SIZE: usize = 32;
+
+/// Size of a mnemonic's fingerprint (truncated SHA-256 of its entropy)
+const FINGERPRINT_SIZE: usize = 4;
+
+/// Share of the admin mnemonic
+pub struct Share {
+    /// Index of this share (the x-coordinate it was evaluated at)
+    pub index: u8,
+
+    /// Share data encoded as a mnemonic
+    pub mnemonic: mnemonic::Phrase,
+}
+
+/// Split the given mnemonic into `n` shares, any `k` of which recover it
+pub fn split(mnemonic: &mnemonic::Phrase, k: u8, n: u8) -> Result<Vec<Share>, Error> {
+    if k < 2 || k > n {
+        fail!(
+            ConfigError,
+            "invalid threshold: {} of {} (must be at least 2, and at most the number of shares)",
+            k,
+            n
+        );
+    }
+
+    // Random coefficients of a degree k-1 polynomial for each byte, whose
+    // constant term is the secret byte
+    let mut coefficients = vec![0u8; SECRET_SIZE * (k as usize - 1)];
+    getrandom(&mut coefficients).expect("RNG failure!");
+
+    let shares = (1..=n)
+        .map(|index| {
+            let mut data = [0u8; SECRET_SIZE];
+
+            for (i, byte) in data.iter_mut().enumerate() {
+                let poly = &coefficients[i * (k as usize - 1)..(i + 1) * (k as usize - 1)];
+
+                // Horner's method, from the highest degree coefficient down
+                *byte = poly.iter().rev().fold(0, |acc, &c| gf_mul(acc, index) ^ c);
+                *byte = gf_mul(*byte, index) ^ mnemonic.entropy()[i];
+            }
+
+            let share = Share {
+                index,
+                mnemonic: mnemonic::Phrase::from_entropy(data, mnemonic.language()),
+            };
+
+            data.zeroize();
+            share
+        })
+        .collect();
+
+    coefficients.zeroize();
+    Ok(shares)
+}
+
+/// Compute the fingerprint of a mnemonic (hex-encoded), which is noted when
+/// it's split so the recombined mnemonic can be checked
+pub fn fingerprint(mnemonic: &mnemonic::Phrase) -> String {
+    let digest = Sha256::digest(mnemonic.entropy());
+    String::from_utf8(hex::encode(&digest[..FINGERPRINT_SIZE])).unwrap()
+}
+
+/// Recover the mnemonic from the given shares, checking it has the given
+/// fingerprint
+pub fn combine_checked(shares: &[Share], expected: &str) -> Result<mnemonic::Phrase, Error> {
+    let mnemonic = combine(shares)?;
+    let actual = fingerprint(&mnemonic);
+
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        fail!(
+            ConfigError,
+            "recombined mnemonic has fingerprint {} (expected {}): are there at least as many \
+             shares as the threshold, all from the same setup?",
+            actual,
+            expected.trim()
+        );
+    }
+
+    Ok(mnemonic)
+}
+
+/// Recover the mnemonic from the given shares.
+///
+/// Note that recombining fewer than the threshold number of shares (or
+/// shares from different splits) yields a different, valid-looking mnemonic:
+/// use [`combine_checked`] to detect this.
+pub fn combine(shares: &[Share]) -> Result<mnemonic::Phrase, Error> {
+    if shares.len() < 2 {
+        fail!(ConfigError, "at least 2 shares are required");
+    }
+
+    let mut indexes = BTreeSet::new();
+
+    for share in shares {
+        if share.index == 0 || !indexes.insert(share.index) {
+            fail!(
+                ConfigError,
+                "invalid or duplicate share index: {}",
+                share.index
+            );
+        }
+    }
+
+    let mut secret = [0u8; SECRET_SIZE];
+
+    // Lagrange interpolation at x = 0 (in GF(2^8), subtraction is XOR)
+    for share in shares {
+        let mut basis = 1;
+
+        for other in shares.iter().filter(|other| other.index != share.index) {
+            basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+        }
+
+        for (byte, share_byte) in secret.iter_mut().zip(share.mnemonic.entropy()) {
+            *byte ^= gf_mul(basis, *share_byte);
+        }
+    }
+
+    let result = mnemonic::Phrase::from_entropy(secret, shares[0].mnemonic.language());
+    secret.zeroize();
+    Ok(result)
+}
+
+/// Multiply in GF(2^8) with the AES reduction polynomial
+/// (x^8 + x^4 + x^3 + x + 1).
+///
+/// Constant-time with respect to its inputs.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+
+    product
+}
+
+/// Divide in GF(2^8): multiply by the inverse of `b` (i.e. `b^254`)
+fn gf_div(a: u8, b: u8) -> u8 {
+    debug_assert_ne!(b, 0, "division by zero");
+
+    let mut inverse = 1;
+
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_mnemonic() -> mnemonic::Phrase {
+        let mut entropy = [0u8; SECRET_SIZE];
+        getrandom(&mut entropy).unwrap();
+        mnemonic::Phrase::from_entropy(entropy, mnemonic::Language::English)
+    }
+
+    /// Round-trip the share's phrase, as when it's written down and re-entered
+    fn reenter(share: &Share) -> Share {
+        Share {
+            index: share.index,
+            mnemonic: mnemonic::Phrase::new(share.mnemonic.phrase(), mnemonic::Language::English)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn gf_arithmetic() {
+        // Example from FIPS 197 section 4.2
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+
+        for a in 1..=255 {
+            assert_eq!(gf_mul(gf_div(1, a), a), 1);
+        }
+    }
+
+    #[test]
+    fn round_trips_any_k_shares() {
+        let mnemonic = test_mnemonic();
+        let shares = split(&mnemonic, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for a in 0..5 {
+            for b in (a + 1)..5 {
+                for c in (b + 1)..5 {
+                    let subset = [&shares[c], &shares[a], &shares[b]]
+                        .iter()
+                        .map(|share| reenter(share))
+                        .collect::<Vec<_>>();
+
+                    let recovered = combine(&subset).unwrap();
+                    assert_eq!(recovered.entropy(), mnemonic.entropy());
+                    assert_eq!(recovered.phrase(), mnemonic.phrase());
+                }
+            }
+        }
+
+        // Using more than k shares works too
+        assert_eq!(combine(&shares).unwrap().entropy(), mnemonic.entropy());
+    }
+
+    #[test]
+    fn fewer_than_k_shares_dont_recover() {
+        let mnemonic = test_mnemonic();
+        let shares = split(&mnemonic, 3, 5).unwrap();
+        let recovered = combine(&shares[..2]).unwrap();
+        assert_ne!(recovered.entropy(), mnemonic.entropy());
+    }
+
+    #[test]
+    fn checks_fingerprint() {
+        let mnemonic = test_mnemonic();
+        let fingerprint = fingerprint(&mnemonic);
+        assert_eq!(fingerprint.len(), FINGERPRINT_SIZE * 2);
+
+        let shares = split(&mnemonic, 3, 5).unwrap();
+        let recovered = combine_checked(&shares[1..4], &fingerprint.to_uppercase()).unwrap();
+        assert_eq!(recovered.entropy(), mnemonic.entropy());
+
+        // Fewer than k shares
+        assert!(combine_checked(&shares[..2], &fingerprint).is_err());
+
+        // Shares from different splits of the same mnemonic
+        let other_shares = split(&mnemonic, 3, 5).unwrap();
+        let mixed = [
+            reenter(&shares[0]),
+            reenter(&shares[1]),
+            reenter(&other_shares[2]),
+        ];
+        assert!(combine_checked(&mixed, &fingerprint).is_err());
+
+        // Shares of a different mnemonic
+        let other_shares = split(&test_mnemonic(), 3, 5).unwrap();
+        assert!(combine_checked(&other_shares[..3], &fingerprint).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let mnemonic = test_mnemonic();
+        assert!(split(&mnemonic, 1, 5).is_err());
+        assert!(split(&mnemonic, 6, 5).is_err());
+
+        let shares = split(&mnemonic, 2, 2).unwrap();
+        assert!(combine(&shares[..1]).is_err());
+        assert!(combine(&[reenter(&shares[0]), reenter(&shares[0])]).is_err());
+    }
+}
@@ -18,6 +18,14 @@ pub struct TestCommand {
     #[options(short = "c", long = "config", help = "path to tmkms.toml")]
     pub config: Option<PathBuf>,
 
+    /// Serial number or label of the YubiHSM to use
+    #[options(
+        short = "d",
+        long = "device",
+        help = "serial number or label of the YubiHSM to use"
+    )]
+    pub device: Option<String>,
+
     /// Print debugging information
     #[options(short = "v", long = "verbose", help = "enable verbose debug logging")]
     pub verbose: bool,
@@ -35,7 +43,7 @@ impl Runnable for TestCommand {
             process::exit(1);
         }
 
-        let hsm = crate::yubihsm::client();
+        let hsm = crate::yubihsm::client(self.device.as_deref());
 
         loop {
             let started_at = Instant::now();
@@ -1,7 +1,10 @@
 //! Configuration file structures (with serde-derived parser)
 
 pub mod chain;
+pub mod listen;
+pub mod notify;
 pub mod provider;
+pub mod secrets;
 #[cfg(feature = "tx-signer")]
 pub mod tx_signer;
 pub mod validator;
@@ -11,7 +14,10 @@ pub use self::validator::*;
 #[cfg(feature = "tx-signer")]
 pub use self::tx_signer::TxSignerConfig;
 
-use self::{chain::ChainConfig, provider::ProviderConfig};
+use self::{
+    chain::ChainConfig, listen::ListenConfig, notify::NotifyConfig, provider::ProviderConfig,
+    secrets::SecretsConfig,
+};
 use serde::Deserialize;
 
 /// Environment variable containing path to config file
@@ -35,6 +41,18 @@ pub struct KmsConfig {
     #[serde(default)]
     pub validator: Vec<ValidatorConfig>,
 
+    /// Addresses to accept connections from edge KMS instances on
+    #[serde(default)]
+    pub listen: Vec<ListenConfig>,
+
+    /// Alert notification configuration
+    #[serde(default)]
+    pub notify: NotifyConfig,
+
+    /// Checks applied to secret files
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+
     /// Transaction signer config (for e.g. oracles)
     #[cfg(feature = "tx-signer")]
     #[serde(default)]
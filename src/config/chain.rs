@@ -1,8 +1,9 @@
 //! Chain configuration
 
 mod hook;
+mod watchdog;
 
-pub use self::hook::HookConfig;
+pub use self::{hook::HookConfig, watchdog::WatchdogConfig};
 use crate::{chain, keyring};
 use serde::Deserialize;
 use std::path::PathBuf;
@@ -24,4 +25,7 @@ pub struct ChainConfig {
     /// this chain. This will be executed at launch time to populate the
     /// initial block height if configured
     pub state_hook: Option<HookConfig>,
+
+    /// Missed-signature watchdog for this chain
+    pub watchdog: Option<WatchdogConfig>,
 }
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+/// Missed-signature watchdog configuration
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogConfig {
+    /// Trip the watchdog if no signature has been produced for this many
+    /// seconds
+    pub max_idle_secs: u64,
+
+    /// Tendermint RPC endpoint (e.g. `http://127.0.0.1:26657`) used to
+    /// compare our last signed height against the chain's latest height
+    pub rpc_addr: Option<String>,
+
+    /// Trip the watchdog if the chain's latest height is more than this many
+    /// blocks ahead of our last signed height (default 10)
+    pub max_height_lag: Option<u64>,
+
+    /// How often to check (in seconds, default 10)
+    pub check_interval_secs: Option<u64>,
+
+    /// Shut down the KMS gracefully and exit with a non-zero status (3) when
+    /// the watchdog trips
+    #[serde(default)]
+    pub strict: bool,
+}
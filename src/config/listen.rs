@@ -0,0 +1,31 @@
+//! Listen mode configuration: accept connections from other (edge) KMS
+//! instances which use this one as their signing provider
+
+use super::validator::ProtocolVersion;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tendermint::{chain, net, node};
+
+/// The (optional) `[[listen]]` config section
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenConfig {
+    /// Address to listen on (e.g. `tcp://0.0.0.0:26670`)
+    pub addr: net::Address,
+
+    /// Chains edge KMS instances may request signatures for
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Path to our Ed25519 identity key
+    pub secret_key: PathBuf,
+
+    /// Node IDs of the edge KMS instances allowed to connect
+    pub allowed_peers: Vec<node::Id>,
+
+    /// Protocol version used by the validators of these chains, which
+    /// determines the format of the sign bytes forwarded by edge instances
+    pub protocol_version: ProtocolVersion,
+
+    /// Optional timeout value in seconds
+    pub timeout: Option<u16>,
+}
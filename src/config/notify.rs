@@ -0,0 +1,63 @@
+//! Alert notification configuration
+
+use crate::notify::EventKind;
+use serde::Deserialize;
+
+/// Alert notification (`[notify]`) configuration
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Webhooks to POST JSON-serialized events to
+    #[serde(default)]
+    pub webhook: Vec<WebhookConfig>,
+}
+
+/// Webhook (`[[notify.webhook]]`) configuration
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// URL to POST events to (must be `http://`)
+    pub url: String,
+
+    /// Events to send to this webhook (default: all events)
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+
+    /// Number of times to retry a failed request (default 3)
+    #[serde(default = "max_retries_default")]
+    pub max_retries: u32,
+
+    /// Delay between retries in milliseconds (default 1000)
+    #[serde(default = "retry_delay_ms_default")]
+    pub retry_delay_ms: u64,
+
+    /// Timeout for each request in seconds (default 5)
+    #[serde(default = "timeout_secs_default")]
+    pub timeout_secs: u64,
+
+    /// Minimum number of seconds between two delivered events of the same
+    /// kind for the same chain. Events arriving more frequently are dropped
+    /// (default 60)
+    #[serde(default = "min_interval_secs_default")]
+    pub min_interval_secs: u64,
+}
+
+/// Default value for `WebhookConfig::max_retries`
+fn max_retries_default() -> u32 {
+    3
+}
+
+/// Default value for `WebhookConfig::retry_delay_ms`
+fn retry_delay_ms_default() -> u64 {
+    1000
+}
+
+/// Default value for `WebhookConfig::timeout_secs`
+fn timeout_secs_default() -> u64 {
+    5
+}
+
+/// Default value for `WebhookConfig::min_interval_secs`
+fn min_interval_secs_default() -> u64 {
+    60
+}
@@ -1,18 +1,36 @@
 //! Cryptographic service providers: signing backends
 
+#[cfg(feature = "ledger")]
+pub mod ledgercosmos;
 #[cfg(feature = "ledger")]
 pub mod ledgertm;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(feature = "plugin")]
+pub mod plugin;
+#[cfg(feature = "remote")]
+pub mod remote;
 #[cfg(feature = "softsign")]
 pub mod softsign;
+#[cfg(feature = "vault")]
+pub mod vault;
 #[cfg(feature = "yubihsm")]
 pub mod yubihsm;
 
-#[cfg(feature = "ledger")]
-use self::ledgertm::LedgerTendermintConfig;
+#[cfg(feature = "pkcs11")]
+use self::pkcs11::Pkcs11Config;
+#[cfg(feature = "plugin")]
+use self::plugin::PluginConfig;
+#[cfg(feature = "remote")]
+use self::remote::RemoteConfig;
 #[cfg(feature = "softsign")]
 use self::softsign::SoftsignConfig;
+#[cfg(feature = "vault")]
+use self::vault::VaultConfig;
 #[cfg(feature = "yubihsm")]
 use self::yubihsm::YubihsmConfig;
+#[cfg(feature = "ledger")]
+use self::{ledgercosmos::LedgerCosmosConfig, ledgertm::LedgerTendermintConfig};
 
 use serde::Deserialize;
 use std::fmt;
@@ -35,6 +53,31 @@ pub struct ProviderConfig {
     #[cfg(feature = "ledger")]
     #[serde(default)]
     pub ledgertm: Vec<LedgerTendermintConfig>,
+
+    /// Account keys on a Ledger running the Cosmos app
+    #[cfg(feature = "ledger")]
+    #[serde(default)]
+    pub ledgercosmos: Vec<LedgerCosmosConfig>,
+
+    /// PKCS#11 HSMs
+    #[cfg(feature = "pkcs11")]
+    #[serde(default)]
+    pub pkcs11: Vec<Pkcs11Config>,
+
+    /// HashiCorp Vault Transit secrets engines
+    #[cfg(feature = "vault")]
+    #[serde(default)]
+    pub vault: Vec<VaultConfig>,
+
+    /// External signer plugins
+    #[cfg(feature = "plugin")]
+    #[serde(default)]
+    pub plugin: Vec<PluginConfig>,
+
+    /// Upstream KMS instances (signer chaining)
+    #[cfg(feature = "remote")]
+    #[serde(default)]
+    pub remote: Vec<RemoteConfig>,
 }
 
 /// Types of cryptographic keys
@@ -65,3 +108,35 @@ impl fmt::Display for KeyType {
         }
     }
 }
+
+/// Signature algorithms of cryptographic keys
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum KeyAlgorithm {
+    /// Ed25519
+    #[serde(rename = "ed25519")]
+    Ed25519,
+
+    /// ECDSA/secp256k1
+    #[serde(rename = "secp256k1")]
+    Secp256k1,
+}
+
+impl KeyType {
+    /// Default algorithm for this type of key: secp256k1 for account keys and
+    /// Ed25519 for consensus keys
+    pub fn default_algorithm(&self) -> KeyAlgorithm {
+        match self {
+            KeyType::Account => KeyAlgorithm::Secp256k1,
+            KeyType::Consensus => KeyAlgorithm::Ed25519,
+        }
+    }
+}
+
+impl fmt::Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyAlgorithm::Ed25519 => f.write_str("ed25519"),
+            KeyAlgorithm::Secp256k1 => f.write_str("secp256k1"),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Configuration for account keys on a Ledger running the Cosmos app
+
+use crate::{chain, key_utils::hd::DerivationPath};
+use serde::Deserialize;
+
+/// Ledger Cosmos app signer configuration
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LedgerCosmosConfig {
+    /// Chains this signing key is authorized to be used from
+    pub chain_ids: Vec<chain::Id>,
+
+    /// BIP32 derivation path of the account key
+    #[serde(default = "derivation_path_default")]
+    pub derivation_path: DerivationPath,
+
+    /// Number of seconds to wait for a transaction to be confirmed on the
+    /// device before giving up on signing it (default 60)
+    #[serde(default = "confirmation_timeout_secs_default")]
+    pub confirmation_timeout_secs: u64,
+}
+
+/// Default derivation path: the first Cosmos account key
+fn derivation_path_default() -> DerivationPath {
+    "m/44'/118'/0'/0/0".parse().unwrap()
+}
+
+/// Default value for `LedgerCosmosConfig::confirmation_timeout_secs`
+fn confirmation_timeout_secs_default() -> u64 {
+    60
+}
@@ -0,0 +1,92 @@
+//! Configuration for PKCS#11 HSMs
+
+use super::KeyType;
+use crate::{
+    chain,
+    error::{Error, ErrorKind::*},
+    key_utils,
+    prelude::*,
+};
+use serde::Deserialize;
+use std::{fs, path::PathBuf};
+use subtle_encoding::hex;
+use zeroize::Zeroizing;
+
+/// The (optional) `[[providers.pkcs11]]` config section
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Pkcs11Config {
+    /// Path to the PKCS#11 module (shared library) for this HSM
+    pub module: PathBuf,
+
+    /// Slot containing the token to log in to
+    pub slot: Option<u64>,
+
+    /// Label of the token to log in to (alternative to `slot`)
+    pub token_label: Option<String>,
+
+    /// Path to a file containing the user PIN
+    pub pin_file: PathBuf,
+
+    /// List of signing keys in this token
+    #[serde(default)]
+    pub keys: Vec<SigningKeyConfig>,
+}
+
+impl Pkcs11Config {
+    /// Read the user PIN from `pin_file`
+    pub fn pin(&self) -> Result<Zeroizing<String>, Error> {
+        key_utils::permissions::check(&self.pin_file)?;
+
+        let pin = Zeroizing::new(fs::read_to_string(&self.pin_file).map_err(|e| {
+            format_err!(
+                ConfigError,
+                "couldn't read PIN from {}: {}",
+                self.pin_file.display(),
+                e
+            )
+        })?);
+
+        Ok(Zeroizing::new(pin.trim_end().to_owned()))
+    }
+}
+
+/// Signing key configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningKeyConfig {
+    /// Chains this signing key is authorized to be used from
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Label of the key (`CKA_LABEL`)
+    pub label: Option<String>,
+
+    /// Hex-encoded ID of the key (`CKA_ID`)
+    pub id: Option<String>,
+
+    /// Type of key: consensus keys are Ed25519, account keys are secp256k1
+    #[serde(default, rename = "type")]
+    pub key_type: KeyType,
+}
+
+impl SigningKeyConfig {
+    /// Decode the key's `CKA_ID`
+    pub fn id_bytes(&self) -> Result<Option<Vec<u8>>, Error> {
+        match &self.id {
+            Some(id) => hex::decode(id.to_ascii_lowercase())
+                .map(Some)
+                .map_err(|_| format_err!(ConfigError, "invalid hex PKCS#11 key ID: {}", id).into()),
+            None => Ok(None),
+        }
+    }
+
+    /// Describe how this key is identified (for error messages)
+    pub fn description(&self) -> String {
+        match (&self.label, &self.id) {
+            (Some(label), Some(id)) => format!("label={:?} id={}", label, id),
+            (Some(label), None) => format!("label={:?}", label),
+            (None, Some(id)) => format!("id={}", id),
+            (None, None) => "(no label or id)".to_owned(),
+        }
+    }
+}
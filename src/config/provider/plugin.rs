@@ -0,0 +1,49 @@
+//! Configuration for external signer plugins
+
+use super::KeyType;
+use crate::chain;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// The (optional) `[[providers.plugin]]` config section
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Name of this plugin (used in logs)
+    pub name: String,
+
+    /// Command (and arguments) used to spawn the plugin as a child process
+    pub command: Option<Vec<String>>,
+
+    /// Path to a Unix domain socket the plugin is listening on (alternative
+    /// to `command`)
+    pub socket: Option<PathBuf>,
+
+    /// Timeout for each request to the plugin (in milliseconds)
+    #[serde(default = "timeout_ms_default")]
+    pub timeout_ms: u64,
+
+    /// List of signing keys provided by this plugin
+    #[serde(default)]
+    pub keys: Vec<SigningKeyConfig>,
+}
+
+/// Signing key configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningKeyConfig {
+    /// Chains this signing key is authorized to be used from
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Identifier of the key (passed to the plugin)
+    pub key: String,
+
+    /// Type of key: consensus keys are Ed25519, account keys are secp256k1
+    #[serde(default, rename = "type")]
+    pub key_type: KeyType,
+}
+
+/// Default value for `PluginConfig::timeout_ms`
+fn timeout_ms_default() -> u64 {
+    1000
+}
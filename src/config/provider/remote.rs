@@ -0,0 +1,24 @@
+//! Configuration for using another (upstream) KMS as a signing provider
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use tendermint::{chain, net};
+
+/// The (optional) `[[providers.remote]]` config section
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Address of the upstream KMS, including its node ID
+    /// (i.e. `tcp://<node ID>@host:port`)
+    pub addr: net::Address,
+
+    /// Chains to request signatures for, using the upstream KMS's consensus
+    /// key for each
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Path to our Ed25519 identity key
+    pub secret_key: PathBuf,
+
+    /// Optional timeout value in seconds
+    pub timeout: Option<u16>,
+}
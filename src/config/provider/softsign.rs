@@ -1,13 +1,15 @@
 //! Configuration for software-backed signer (using ed25519-dalek)
 
-use super::KeyType;
+use super::{KeyAlgorithm, KeyType};
 use crate::{
     chain,
     error::{Error, ErrorKind::ConfigError},
+    key_utils::{hd::DerivationPath, PasswordSource},
     prelude::*,
 };
 use serde::Deserialize;
 use std::{
+    fmt,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -23,12 +25,46 @@ pub struct SoftsignConfig {
     #[serde(default)]
     pub key_type: KeyType,
 
-    /// Private key file format
+    /// Signature algorithm of the key (default secp256k1 for account keys
+    /// and Ed25519 for consensus keys)
+    pub key_algorithm: Option<KeyAlgorithm>,
+
+    /// Private key file format: `base64` (default), `json`, or `armor`
     pub key_format: Option<KeyFormat>,
 
     /// Path to a file containing a cryptographic key
     // TODO: use `abscissa_core::Secret` to wrap this `PathBuf`
     pub path: SoftPrivateKey,
+
+    /// Derive the key from the BIP39 seed stored at `path` (e.g. created by
+    /// `tmkms softsign keygen --mnemonic`) using the given BIP32 path
+    pub derivation_path: Option<DerivationPath>,
+
+    /// Path to a file containing the password for an encrypted key
+    pub password_file: Option<PathBuf>,
+
+    /// Environment variable containing the password for an encrypted key
+    pub password_env: Option<String>,
+
+    /// Refuse to load unencrypted keys
+    #[serde(default)]
+    pub strict: bool,
+}
+
+impl SoftsignConfig {
+    /// Get the source of the password for an encrypted key, prompting for
+    /// it interactively if none is configured
+    pub fn password_source(&self) -> Result<PasswordSource, Error> {
+        match (&self.password_file, &self.password_env) {
+            (Some(_), Some(_)) => fail!(
+                ConfigError,
+                "[[providers.softsign]] `password_file` and `password_env` are mutually exclusive"
+            ),
+            (Some(path), None) => Ok(PasswordSource::File(path.clone())),
+            (None, Some(var)) => Ok(PasswordSource::Env(var.clone())),
+            (None, None) => Ok(PasswordSource::Prompt),
+        }
+    }
 }
 
 /// Software-backed private key (stored in a file)
@@ -46,13 +82,17 @@ impl AsRef<Path> for SoftPrivateKey {
 /// Private key format
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub enum KeyFormat {
-    /// Base64-encoded
+    /// Base64-encoded, or password-encrypted
     #[serde(rename = "base64")]
     Base64,
 
-    /// JSON
+    /// Tendermint `priv_validator_key.json`
     #[serde(rename = "json")]
     Json,
+
+    /// Cosmos SDK ASCII-armored, password-encrypted export (`keys export`)
+    #[serde(rename = "armor")]
+    Armor,
 }
 
 impl Default for KeyFormat {
@@ -61,6 +101,16 @@ impl Default for KeyFormat {
     }
 }
 
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeyFormat::Base64 => "base64",
+            KeyFormat::Json => "json",
+            KeyFormat::Armor => "armor",
+        })
+    }
+}
+
 impl FromStr for KeyFormat {
     type Err = Error;
 
@@ -68,6 +118,7 @@ impl FromStr for KeyFormat {
         let format = match s {
             "base64" => KeyFormat::Base64,
             "json" => KeyFormat::Json,
+            "armor" => KeyFormat::Armor,
             other => fail!(ConfigError, "invalid key format: {}", other),
         };
 
@@ -0,0 +1,100 @@
+//! Configuration for the HashiCorp Vault Transit secrets engine
+
+use super::KeyType;
+use crate::chain;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// The (optional) `[[providers.vault]]` config section
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct VaultConfig {
+    /// Address of the Vault server, e.g. `https://127.0.0.1:8200`
+    pub addr: String,
+
+    /// PEM file containing the CA certificate(s) used to verify the Vault
+    /// server (defaults to the Mozilla root certificates)
+    pub ca_cert: Option<PathBuf>,
+
+    /// Vault namespace (Vault Enterprise)
+    pub namespace: Option<String>,
+
+    /// Path where the Transit secrets engine is mounted
+    #[serde(default = "mount_default")]
+    pub mount: String,
+
+    /// Authentication configuration
+    pub auth: AuthConfig,
+
+    /// Timeout for each request to Vault (in milliseconds)
+    #[serde(default = "timeout_ms_default")]
+    pub timeout_ms: u64,
+
+    /// Number of times to retry failed requests
+    #[serde(default = "max_retries_default")]
+    pub max_retries: u32,
+
+    /// List of signing keys in this Transit engine
+    #[serde(default)]
+    pub keys: Vec<SigningKeyConfig>,
+}
+
+/// Vault authentication configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, untagged)]
+pub enum AuthConfig {
+    /// Authenticate with a token read from a file
+    Token {
+        /// Path to a file containing a Vault token
+        token_file: PathBuf,
+    },
+
+    /// Authenticate using AppRole
+    AppRole {
+        /// AppRole role ID
+        role_id: String,
+
+        /// Path to a file containing the AppRole secret ID
+        secret_id_file: PathBuf,
+
+        /// Path where the AppRole auth method is mounted
+        #[serde(default = "approle_mount_default")]
+        approle_mount: String,
+    },
+}
+
+/// Signing key configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningKeyConfig {
+    /// Chains this signing key is authorized to be used from
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Name of the Transit key
+    pub key: String,
+
+    /// Type of key: consensus keys are `ed25519`, account keys are secp256k1
+    /// (via a Transit plugin which supports it)
+    #[serde(default, rename = "type")]
+    pub key_type: KeyType,
+}
+
+/// Default value for `VaultConfig::mount`
+fn mount_default() -> String {
+    "transit".to_owned()
+}
+
+/// Default value for `AuthConfig::AppRole { approle_mount }`
+fn approle_mount_default() -> String {
+    "approle".to_owned()
+}
+
+/// Default value for `VaultConfig::timeout_ms`
+fn timeout_ms_default() -> u64 {
+    2000
+}
+
+/// Default value for `VaultConfig::max_retries`
+fn max_retries_default() -> u32 {
+    2
+}
@@ -1,15 +1,20 @@
 //! Configuration for the `YubiHSM` backend
 
 use super::KeyType;
-use crate::{chain, prelude::*};
+use crate::{
+    chain,
+    error::{Error, ErrorKind::*},
+    key_utils,
+    prelude::*,
+};
 use abscissa_core::secret::{CloneableSecret, DebugSecret, ExposeSecret, Secret};
 use serde::Deserialize;
-use std::{fmt, fs, path::PathBuf, process};
+use std::{fmt, fs, path::PathBuf};
 use tendermint::net;
 use yubihsm::Credentials;
 use zeroize::{Zeroize, Zeroizing};
 
-/// The (optional) `[providers.yubihsm]` config section
+/// The (optional) `[[providers.yubihsm]]` config section (one per device)
 #[derive(Clone, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct YubihsmConfig {
@@ -26,11 +31,39 @@ pub struct YubihsmConfig {
     /// Serial number of the YubiHSM to connect to
     pub serial_number: Option<String>,
 
+    /// Label used to select this YubiHSM (e.g. with `tmkms yubihsm --device`)
+    pub label: Option<String>,
+
+    /// Drain the audit log into a file in the background
+    pub audit_log: Option<AuditLogConfig>,
+
+    /// Yubico CA certificate(s) used to verify key attestations (PEM or DER)
+    pub attestation_ca: Option<PathBuf>,
+
     /// Configuration for `yubihsm-connector` compatible HTTP server.
     #[cfg(feature = "yubihsm-server")]
     pub connector_server: Option<ConnectorServerConfig>,
 }
 
+impl YubihsmConfig {
+    /// Identifier for this YubiHSM: its label, serial number, or `default`
+    /// if neither is configured
+    pub fn device_id(&self) -> &str {
+        self.label
+            .as_ref()
+            .or(self.serial_number.as_ref())
+            .map(AsRef::as_ref)
+            .unwrap_or("default")
+    }
+
+    /// Does the given device selector (serial number or label) match this
+    /// YubiHSM?
+    pub fn matches(&self, device: &str) -> bool {
+        self.label.as_ref().map(AsRef::as_ref) == Some(device)
+            || self.serial_number.as_ref().map(AsRef::as_ref) == Some(device)
+    }
+}
+
 /// Configuration for an individual YubiHSM
 #[derive(Clone, Deserialize, Debug)]
 #[serde(deny_unknown_fields, tag = "type")]
@@ -74,27 +107,51 @@ pub enum AuthConfig {
 }
 
 impl AuthConfig {
-    /// Get the `yubihsm::Credentials` for this `AuthConfig`
-    pub fn credentials(&self) -> Credentials {
+    /// Get the `yubihsm::Credentials` for this `AuthConfig`.
+    ///
+    /// The password file (if any) is read each time, e.g. whenever a session
+    /// is re-authenticated.
+    pub fn credentials(&self) -> Result<Credentials, Error> {
         match self {
             AuthConfig::Path { key, password_file } => {
-                let password =
-                    Zeroizing::new(fs::read_to_string(password_file).unwrap_or_else(|e| {
-                        status_err!("couldn't read key from {}: {}", password_file.display(), e);
-                        process::exit(1);
-                    }));
+                key_utils::permissions::check(password_file)?;
+
+                let password = Zeroizing::new(fs::read_to_string(password_file).map_err(|e| {
+                    format_err!(
+                        IoError,
+                        "couldn't read key from {}: {}",
+                        password_file.display(),
+                        e
+                    )
+                })?);
 
                 // TODO(tarcieri): constant-time string trimming
                 let password_trimmed = password.trim_end();
-                Credentials::from_password(*key, password_trimmed.as_bytes())
-            }
-            AuthConfig::String { key, password } => {
-                Credentials::from_password(*key, password.expose_secret().0.as_bytes())
+                Ok(Credentials::from_password(
+                    *key,
+                    password_trimmed.as_bytes(),
+                ))
             }
+            AuthConfig::String { key, password } => Ok(Credentials::from_password(
+                *key,
+                password.expose_secret().0.as_bytes(),
+            )),
         }
     }
 }
 
+/// Audit log configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditLogConfig {
+    /// Credentials for an auth key which can read the audit log (i.e. the
+    /// `auditor` role created by `tmkms yubihsm setup`)
+    pub auth: AuthConfig,
+
+    /// File to append log entries to (as JSON lines)
+    pub path: PathBuf,
+}
+
 /// Password to the YubiHSM
 #[derive(Clone, Deserialize, Zeroize)]
 #[serde(deny_unknown_fields)]
@@ -150,3 +207,36 @@ pub struct CliConfig {
     /// prompt for a password from the terminal.
     pub auth_key: Option<u16>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_serial_number_or_label() {
+        let config: YubihsmConfig = toml::from_str(
+            r#"
+            adapter = { type = "usb" }
+            auth = { key = 1, password = "password" }
+            serial_number = "0001234567"
+            label = "hsm-a"
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.matches("0001234567"));
+        assert!(config.matches("hsm-a"));
+        assert!(!config.matches("hsm-b"));
+        assert!(!config.matches("default"));
+    }
+
+    #[test]
+    fn reports_unreadable_password_files() {
+        let auth = AuthConfig::Path {
+            key: 1,
+            password_file: "/nonexistent/tmkms-password".into(),
+        };
+
+        assert!(auth.credentials().is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! Secret file checks configuration
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Checks applied to secret files (`[secrets]` section)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretsConfig {
+    /// Refuse secret files which are accessible by group or others, or owned
+    /// by another user (default true). Containers which mount secrets with
+    /// other owners or permissions may need to disable this.
+    #[serde(default = "check_permissions_default")]
+    pub check_permissions: bool,
+
+    /// Directory all secret files must reside in, after resolving symlinks
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            check_permissions: check_permissions_default(),
+            dir: None,
+        }
+    }
+}
+
+/// Default value for `SecretsConfig::check_permissions`
+fn check_permissions_default() -> bool {
+    true
+}
@@ -1,6 +1,7 @@
 //! TCP socket connection to a validator
 
-use std::{net::TcpStream, path::PathBuf, time::Duration};
+use ed25519_dalek as ed25519;
+use std::{io, net::TcpStream, path::PathBuf, time::Duration};
 
 use subtle::ConstantTimeEq;
 use tendermint::node;
@@ -16,7 +17,8 @@ use crate::{
 /// Default timeout in seconds
 const DEFAULT_TIMEOUT: u16 = 10;
 
-/// Open a TCP socket connection encrypted with SecretConnection
+/// Open a TCP socket connection encrypted with SecretConnection, along with a
+/// handle to the underlying socket (e.g. to shut it down from another thread)
 pub fn open_secret_connection(
     host: &str,
     port: u16,
@@ -24,7 +26,7 @@ pub fn open_secret_connection(
     peer_id: &Option<node::Id>,
     timeout: Option<u16>,
     protocol_version: secret_connection::Version,
-) -> Result<SecretConnection<TcpStream>, Error> {
+) -> Result<(SecretConnection<TcpStream>, TcpStream), Error> {
     let identity_key_path = identity_key_path.as_ref().ok_or_else(|| {
         format_err!(
             ConfigError,
@@ -41,16 +43,9 @@ pub fn open_secret_connection(
     let timeout = Duration::from_secs(timeout.unwrap_or(DEFAULT_TIMEOUT).into());
     socket.set_read_timeout(Some(timeout))?;
     socket.set_write_timeout(Some(timeout))?;
+    let handle = socket.try_clone()?;
 
-    let connection = match SecretConnection::new(socket, identity_key, protocol_version) {
-        Ok(conn) => conn,
-        Err(error) => match error.downcast_ref::<TmError>() {
-            Some(TmError::CryptoError) => fail!(CryptoError, format!("{}", error)),
-            Some(TmError::ProtocolError) => fail!(ProtocolError, format!("{}", error)),
-            Some(TmError::InvalidKey) => fail!(InvalidKey, format!("{}", error)),
-            None => fail!(ProtocolError, format!("{}", error)),
-        },
-    };
+    let connection = secret_handshake(socket, identity_key, protocol_version)?;
     let actual_peer_id = connection.remote_pubkey().peer_id();
 
     // TODO(tarcieri): move this into `SecretConnection::new`
@@ -67,5 +62,25 @@ pub fn open_secret_connection(
         }
     }
 
-    Ok(connection)
+    Ok((connection, handle))
+}
+
+/// Perform a SecretConnection handshake over an established socket
+pub fn secret_handshake<IoHandler>(
+    socket: IoHandler,
+    identity_key: ed25519::Keypair,
+    protocol_version: secret_connection::Version,
+) -> Result<SecretConnection<IoHandler>, Error>
+where
+    IoHandler: io::Read + io::Write + Send + Sync,
+{
+    match SecretConnection::new(socket, identity_key, protocol_version) {
+        Ok(conn) => Ok(conn),
+        Err(error) => match error.downcast_ref::<TmError>() {
+            Some(TmError::CryptoError) => fail!(CryptoError, format!("{}", error)),
+            Some(TmError::ProtocolError) => fail!(ProtocolError, format!("{}", error)),
+            Some(TmError::InvalidKey) => fail!(InvalidKey, format!("{}", error)),
+            None => fail!(ProtocolError, format!("{}", error)),
+        },
+    }
 }
@@ -42,7 +42,6 @@ pub enum ErrorKind {
     HookError,
 
     /// Error making an HTTP request
-    #[cfg(feature = "tx-signer")]
     #[error("HTTP error")]
     HttpError,
 
@@ -58,6 +57,11 @@ pub enum ErrorKind {
     #[error("I/O error")]
     IoError,
 
+    /// Ledger-related errors
+    #[cfg(feature = "ledger")]
+    #[error("Ledger error")]
+    LedgerError,
+
     /// KMS internal panic
     #[error("internal crash")]
     PanicError,
@@ -74,6 +78,11 @@ pub enum ErrorKind {
     #[error("protocol error")]
     ProtocolError,
 
+    /// PKCS#11-related errors
+    #[cfg(feature = "pkcs11")]
+    #[error("PKCS#11 error")]
+    Pkcs11Error,
+
     /// Serialization error
     #[error("serialization error")]
     SerializationError,
@@ -95,6 +104,10 @@ pub enum ErrorKind {
     #[error("verification failed")]
     VerificationError,
 
+    /// Watchdog detected missed signatures
+    #[error("watchdog tripped")]
+    WatchdogError,
+
     /// YubiHSM-related errors
     #[cfg(feature = "yubihsm")]
     #[error("YubiHSM error")]
@@ -179,12 +192,26 @@ impl From<io::Error> for Error {
     }
 }
 
+#[cfg(feature = "ledger")]
+impl From<crate::keyring::providers::ledgertm::error::Error> for Error {
+    fn from(other: crate::keyring::providers::ledgertm::error::Error) -> Self {
+        ErrorKind::LedgerError.context(other).into()
+    }
+}
+
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         self.0.source()
     }
 }
 
+#[cfg(feature = "pkcs11")]
+impl From<cryptoki::error::Error> for Error {
+    fn from(other: cryptoki::error::Error) -> Self {
+        ErrorKind::Pkcs11Error.context(other).into()
+    }
+}
+
 impl From<prost::DecodeError> for Error {
     fn from(other: prost::DecodeError) -> Self {
         ErrorKind::ProtocolError.context(other).into()
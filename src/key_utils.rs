@@ -1,5 +1,17 @@
 //! Utilities
 
+#[cfg(feature = "softsign")]
+pub mod armor;
+#[cfg(feature = "softsign")]
+pub mod encrypted;
+pub mod hd;
+pub mod permissions;
+#[cfg(feature = "softsign")]
+pub mod secret_key;
+
+#[cfg(feature = "softsign")]
+pub use self::{encrypted::PasswordSource, secret_key::SecretKey};
+
 use std::{
     fs::{self, OpenOptions},
     io::Write,
@@ -23,25 +35,64 @@ pub const SECRET_FILE_PERMS: u32 = 0o600;
 
 /// Load Base64-encoded secret data (i.e. key) from the given path
 pub fn load_base64_secret(path: impl AsRef<Path>) -> Result<Zeroizing<Vec<u8>>, Error> {
-    // TODO(tarcieri): check file permissions are correct
-    let base64_data = Zeroizing::new(fs::read_to_string(path.as_ref()).map_err(|e| {
-        format_err!(
-            IoError,
-            "couldn't read key from {}: {}",
-            path.as_ref().display(),
-            e
+    let base64_data = read_secret_file(path.as_ref())?;
+    decode_base64_secret(path.as_ref(), &base64_data)
+}
+
+/// Load secret data (i.e. key) from the given path, which may either be
+/// Base64-encoded or password-encrypted.
+///
+/// The password for encrypted keys is obtained from the given source. If
+/// `strict` is set, unencrypted keys are rejected.
+#[cfg(feature = "softsign")]
+pub fn load_secret(
+    path: impl AsRef<Path>,
+    password: &PasswordSource,
+    strict: bool,
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let path = path.as_ref();
+    let data = read_secret_file(path)?;
+
+    if encrypted::is_encrypted(&data) {
+        let password = password.read(path)?;
+
+        encrypted::decrypt(&data, password.as_bytes()).map_err(|e| {
+            format_err!(
+                *e.kind(),
+                "can't decrypt key from `{}`: {}",
+                path.display(),
+                e
+            )
+            .into()
+        })
+    } else if strict {
+        fail!(
+            ConfigError,
+            "refusing to load unencrypted key from `{}` (strict mode)",
+            path.display()
         )
-    })?);
+    } else {
+        decode_base64_secret(path, &data)
+    }
+}
+
+/// Read the contents of a secret key file, checking its permissions first
+fn read_secret_file(path: &Path) -> Result<Zeroizing<String>, Error> {
+    permissions::check(path)?;
 
+    let data = fs::read_to_string(path)
+        .map_err(|e| format_err!(IoError, "couldn't read key from {}: {}", path.display(), e))?;
+
+    Ok(Zeroizing::new(data))
+}
+
+/// Decode Base64-encoded secret data read from the given path
+fn decode_base64_secret(path: &Path, base64_data: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
     // TODO(tarcieri): constant-time string trimming
-    let data = Zeroizing::new(base64::decode(base64_data.trim_end()).map_err(|e| {
-        format_err!(
-            IoError,
-            "can't decode key from `{}`: {}",
-            path.as_ref().display(),
-            e
-        )
-    })?);
+    let data =
+        Zeroizing::new(base64::decode(base64_data.trim_end()).map_err(|e| {
+            format_err!(IoError, "can't decode key from `{}`: {}", path.display(), e)
+        })?);
 
     Ok(data)
 }
@@ -49,8 +100,12 @@ pub fn load_base64_secret(path: impl AsRef<Path>) -> Result<Zeroizing<Vec<u8>>,
 /// Load a Base64-encoded Ed25519 secret key
 pub fn load_base64_ed25519_key(path: impl AsRef<Path>) -> Result<ed25519::Keypair, Error> {
     let key_bytes = load_base64_secret(path)?;
+    ed25519_keypair(&key_bytes)
+}
 
-    let secret = ed25519::SecretKey::from_bytes(&*key_bytes)
+/// Create an Ed25519 keypair from the given secret key bytes
+pub fn ed25519_keypair(secret_key: &[u8]) -> Result<ed25519::Keypair, Error> {
+    let secret = ed25519::SecretKey::from_bytes(secret_key)
         .map_err(|e| format_err!(InvalidKey, "invalid Ed25519 key: {}", e))?;
 
     let public = ed25519::PublicKey::from(&secret);
@@ -60,23 +115,30 @@ pub fn load_base64_ed25519_key(path: impl AsRef<Path>) -> Result<ed25519::Keypai
 /// Store Base64-encoded secret data at the given path
 pub fn write_base64_secret(path: impl AsRef<Path>, data: &[u8]) -> Result<(), Error> {
     let base64_data = Zeroizing::new(base64::encode(data));
+    write_secret_file(path.as_ref(), &base64_data)
+}
 
+/// Store password-encrypted secret data at the given path
+#[cfg(feature = "softsign")]
+pub fn write_encrypted_secret(
+    path: impl AsRef<Path>,
+    data: &[u8],
+    password: &[u8],
+) -> Result<(), Error> {
+    let encrypted_data = encrypted::encrypt(data, password)?;
+    write_secret_file(path.as_ref(), encrypted_data.as_bytes())
+}
+
+/// Write a secret file, readable only by the current user
+pub fn write_secret_file(path: &Path, data: &[u8]) -> Result<(), Error> {
     OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
         .mode(SECRET_FILE_PERMS)
-        .open(path.as_ref())
-        .and_then(|mut file| file.write_all(&*base64_data))
-        .map_err(|e| {
-            format_err!(
-                IoError,
-                "couldn't write `{}`: {}",
-                path.as_ref().display(),
-                e
-            )
-            .into()
-        })
+        .open(path)
+        .and_then(|mut file| file.write_all(data))
+        .map_err(|e| format_err!(IoError, "couldn't write `{}`: {}", path.display(), e).into())
 }
 
 /// Generate a Secret Connection key at the given path
@@ -85,3 +147,38 @@ pub fn generate_key(path: impl AsRef<Path>) -> Result<(), Error> {
     OsRng.fill_bytes(&mut *secret_key);
     write_base64_secret(path, &*secret_key)
 }
+
+#[cfg(all(test, feature = "softsign"))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn loads_encrypted_secrets() {
+        let dir = TempDir::new().unwrap();
+        let secret = [7u8; 32];
+
+        let params = encrypted::KdfParams {
+            log_n: 4,
+            r: 8,
+            p: 1,
+        };
+
+        let encrypted_path = dir.path().join("encrypted.key");
+        let encrypted_data = encrypted::encrypt_with_params(&secret, b"hunter2", params).unwrap();
+        write_secret_file(&encrypted_path, encrypted_data.as_bytes()).unwrap();
+
+        let password_path = dir.path().join("password");
+        write_secret_file(&password_path, b"hunter2\n").unwrap();
+        let password = PasswordSource::File(password_path);
+
+        let loaded = load_secret(&encrypted_path, &password, true).unwrap();
+        assert_eq!(loaded.as_slice(), &secret);
+
+        // Unencrypted keys are only loaded outside of strict mode
+        let plaintext_path = dir.path().join("plaintext.key");
+        write_base64_secret(&plaintext_path, &secret).unwrap();
+        assert!(load_secret(&plaintext_path, &password, false).is_ok());
+        assert!(load_secret(&plaintext_path, &password, true).is_err());
+    }
+}
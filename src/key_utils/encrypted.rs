@@ -0,0 +1,313 @@
+//! Password-encrypted secret key files.
+//!
+//! Secrets are encrypted with ChaCha20Poly1305 using a key derived from a
+//! password with scrypt. Files are Base64-encoded between armor lines:
+//!
+//! ```text
+//! -----BEGIN TMKMS ENCRYPTED KEY-----
+//! Base64(version || log_n || r || p || salt || nonce || ciphertext)
+//! -----END TMKMS ENCRYPTED KEY-----
+//! ```
+//!
+//! The header (everything before the ciphertext) is authenticated as
+//! associated data.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand_core::{OsRng, RngCore};
+use std::{
+    convert::TryInto,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use subtle_encoding::base64;
+use zeroize::Zeroizing;
+
+/// First line of an encrypted key file
+pub const BEGIN_LINE: &str = "-----BEGIN TMKMS ENCRYPTED KEY-----";
+
+/// Last line of an encrypted key file
+pub const END_LINE: &str = "-----END TMKMS ENCRYPTED KEY-----";
+
+/// Current version of the encrypted key format
+const VERSION: u8 = 1;
+
+/// Size of the scrypt salt
+const SALT_SIZE: usize = 16;
+
+/// Size of the ChaCha20Poly1305 nonce
+const NONCE_SIZE: usize = 12;
+
+/// Size of the header: version, scrypt parameters, salt, and nonce
+const HEADER_SIZE: usize = 1 + 1 + 4 + 4 + SALT_SIZE + NONCE_SIZE;
+
+/// Maximum amount of memory scrypt may use when decrypting (1 GiB)
+const MAX_KDF_MEMORY: u128 = 1 << 30;
+
+/// Maximum scrypt parallelization parameter accepted when decrypting
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+/// scrypt parameters
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct KdfParams {
+    /// log2 of the CPU/memory cost parameter `N`
+    pub log_n: u8,
+
+    /// Block size parameter
+    pub r: u32,
+
+    /// Parallelization parameter
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    /// N = 2^15, r = 8, p = 1 (32 MiB)
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Derive an encryption key from the given password and salt
+    fn derive_key(self, password: &[u8], salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, Error> {
+        if self.log_n >= 64
+            || (128 * u128::from(self.r)) << self.log_n > MAX_KDF_MEMORY
+            || self.p > MAX_KDF_PARALLELISM
+        {
+            fail!(CryptoError, "scrypt parameters too expensive: {:?}", self);
+        }
+
+        let params = scrypt::ScryptParams::new(self.log_n, self.r, self.p)
+            .map_err(|_| format_err!(CryptoError, "invalid scrypt parameters: {:?}", self))?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        scrypt::scrypt(password, salt, &params, &mut *key)
+            .map_err(|_| format_err!(CryptoError, "scrypt failed"))?;
+
+        Ok(key)
+    }
+}
+
+/// Source of the password for an encrypted key
+#[derive(Clone, Debug)]
+pub enum PasswordSource {
+    /// Read the password from a file (a trailing newline is ignored)
+    File(PathBuf),
+
+    /// Read the password from an environment variable
+    Env(String),
+
+    /// Prompt for the password interactively
+    Prompt,
+}
+
+impl PasswordSource {
+    /// Read the password for the key at the given path
+    pub fn read(&self, key_path: &Path) -> Result<Zeroizing<String>, Error> {
+        let password = match self {
+            PasswordSource::File(path) => {
+                super::permissions::check(path)?;
+
+                let mut password = Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+                    format_err!(
+                        IoError,
+                        "couldn't read password from {}: {}",
+                        path.display(),
+                        e
+                    )
+                })?);
+
+                let len = password.trim_end_matches(&['\r', '\n'][..]).len();
+                password.truncate(len);
+                password
+            }
+            PasswordSource::Env(var) => Zeroizing::new(env::var(var).map_err(|e| {
+                format_err!(ConfigError, "couldn't read password from ${}: {}", var, e)
+            })?),
+            PasswordSource::Prompt => {
+                let prompt = format!("Password for {}: ", key_path.display());
+                Zeroizing::new(
+                    rpassword::read_password_from_tty(Some(&prompt)).map_err(|e| {
+                        format_err!(
+                            ConfigError,
+                            "no password configured for encrypted key {} and couldn't prompt: {}",
+                            key_path.display(),
+                            e
+                        )
+                    })?,
+                )
+            }
+        };
+
+        if password.is_empty() {
+            fail!(ConfigError, "empty password for {}", key_path.display());
+        }
+
+        Ok(password)
+    }
+}
+
+/// Does the given key file data contain an encrypted key?
+pub fn is_encrypted(data: &str) -> bool {
+    data.trim_start().starts_with(BEGIN_LINE)
+}
+
+/// Encrypt a secret with the given password, using the default scrypt
+/// parameters
+pub fn encrypt(secret: &[u8], password: &[u8]) -> Result<String, Error> {
+    encrypt_with_params(secret, password, KdfParams::default())
+}
+
+/// Encrypt a secret with the given password and scrypt parameters
+pub fn encrypt_with_params(
+    secret: &[u8],
+    password: &[u8],
+    params: KdfParams,
+) -> Result<String, Error> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.push(VERSION);
+    header.push(params.log_n);
+    header.extend_from_slice(&params.r.to_be_bytes());
+    header.extend_from_slice(&params.p.to_be_bytes());
+
+    let mut salt_and_nonce = [0u8; SALT_SIZE + NONCE_SIZE];
+    OsRng.fill_bytes(&mut salt_and_nonce);
+    header.extend_from_slice(&salt_and_nonce);
+
+    let (salt, nonce) = salt_and_nonce.split_at(SALT_SIZE);
+    let key = params.derive_key(password, salt)?;
+
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&*key))
+        .encrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: secret,
+                aad: &header,
+            },
+        )
+        .map_err(|_| format_err!(CryptoError, "encryption failed"))?;
+
+    let mut encoded = header;
+    encoded.extend_from_slice(&ciphertext);
+
+    Ok(format!(
+        "{}\n{}\n{}\n",
+        BEGIN_LINE,
+        String::from_utf8(base64::encode(&encoded)).unwrap(),
+        END_LINE
+    ))
+}
+
+/// Decrypt an encrypted key with the given password
+pub fn decrypt(data: &str, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let body = data
+        .trim()
+        .strip_prefix(BEGIN_LINE)
+        .and_then(|rest| rest.strip_suffix(END_LINE))
+        .ok_or_else(|| format_err!(ParseError, "malformed encrypted key"))?;
+
+    let encoded = base64::decode(body.trim())
+        .map_err(|_| format_err!(ParseError, "malformed encrypted key: invalid Base64"))?;
+
+    if encoded.len() < HEADER_SIZE {
+        fail!(ParseError, "malformed encrypted key: truncated");
+    }
+
+    let (header, ciphertext) = encoded.split_at(HEADER_SIZE);
+
+    if header[0] != VERSION {
+        fail!(
+            ParseError,
+            "unsupported encrypted key version: {}",
+            header[0]
+        );
+    }
+
+    let params = KdfParams {
+        log_n: header[1],
+        r: u32::from_be_bytes(header[2..6].try_into().unwrap()),
+        p: u32::from_be_bytes(header[6..10].try_into().unwrap()),
+    };
+
+    let (salt, nonce) = header[10..].split_at(SALT_SIZE);
+    let key = params.derive_key(password, salt)?;
+
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&*key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| {
+            format_err!(
+                CryptoError,
+                "couldn't decrypt key (wrong password or corrupted file)"
+            )
+        })?;
+
+    Ok(Zeroizing::new(plaintext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters to keep tests fast
+    const TEST_PARAMS: KdfParams = KdfParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn round_trips_secrets() {
+        let secret = [0x42u8; 32];
+        let encrypted = encrypt_with_params(&secret, b"hunter2", TEST_PARAMS).unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(&*decrypt(&encrypted, b"hunter2").unwrap(), &secret);
+        assert!(decrypt(&encrypted, b"hunter3").is_err());
+    }
+
+    #[test]
+    fn authenticates_header() {
+        let encrypted = encrypt_with_params(&[1u8; 32], b"hunter2", TEST_PARAMS).unwrap();
+        let body = encrypted.lines().nth(1).unwrap();
+        let mut encoded = base64::decode(body).unwrap();
+
+        // Tamper with the salt
+        encoded[10] ^= 1;
+
+        let tampered = format!(
+            "{}\n{}\n{}\n",
+            BEGIN_LINE,
+            String::from_utf8(base64::encode(&encoded)).unwrap(),
+            END_LINE
+        );
+
+        assert!(decrypt(&tampered, b"hunter2").is_err());
+    }
+
+    #[test]
+    fn rejects_expensive_params() {
+        let params = KdfParams {
+            log_n: 40,
+            r: 8,
+            p: 1,
+        };
+
+        assert!(encrypt_with_params(&[1u8; 32], b"hunter2", params).is_err());
+    }
+}
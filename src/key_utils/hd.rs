@@ -0,0 +1,295 @@
+//! Hierarchical deterministic derivation of keys from a BIP39 seed.
+//!
+//! secp256k1 keys are derived according to BIP32 (compatible with the Cosmos
+//! `m/44'/118'/0'/0/0` account key path), and Ed25519 keys according to
+//! SLIP-0010, which only supports hardened derivation.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use ed25519_dalek as ed25519;
+use hkd32::mnemonic;
+use hmac::{Hmac, Mac, NewMac};
+use k256::{ecdsa, elliptic_curve::ff::PrimeField, FieldBytes, Scalar};
+use serde::{de, Deserialize};
+use sha2::Sha512;
+use std::{fmt, str::FromStr};
+use zeroize::Zeroizing;
+
+/// Size of a BIP39 seed
+pub const SEED_SIZE: usize = 64;
+
+/// Minimum size of a BIP32 seed
+const MIN_SEED_SIZE: usize = 16;
+
+/// Language of mnemonic phrases
+pub const BIP39_LANGUAGE: mnemonic::Language = mnemonic::Language::English;
+
+/// Flag set on hardened child numbers
+const HARDENED_FLAG: u32 = 1 << 31;
+
+/// HMAC key used to derive the BIP32 master key
+const BIP32_SEED_KEY: &[u8] = b"Bitcoin seed";
+
+/// HMAC key used to derive the SLIP-0010 Ed25519 master key
+const SLIP10_ED25519_SEED_KEY: &[u8] = b"ed25519 seed";
+
+/// Derivation path, e.g. `m/44'/118'/0'/0/0`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Get the path's child numbers (with the high bit set on hardened ones)
+    pub fn child_numbers(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// Are all of the path's components hardened?
+    pub fn is_hardened(&self) -> bool {
+        self.0.iter().all(|&index| index & HARDENED_FLAG != 0)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut components = s.split('/');
+
+        if components.next() != Some("m") {
+            fail!(
+                ParseError,
+                "invalid derivation path (must start with `m/`): {}",
+                s
+            );
+        }
+
+        let indexes = components
+            .map(|component| {
+                let (number, hardened) = match component.strip_suffix(&['\'', 'h'][..]) {
+                    Some(number) => (number, true),
+                    None => (component, false),
+                };
+
+                match number.parse::<u32>() {
+                    Ok(index) if index < HARDENED_FLAG => Ok(if hardened {
+                        index | HARDENED_FLAG
+                    } else {
+                        index
+                    }),
+                    _ => Err(format_err!(
+                        ParseError,
+                        "invalid component in derivation path {}: {}",
+                        s,
+                        component
+                    )
+                    .into()),
+                }
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(DerivationPath(indexes))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+
+        for index in &self.0 {
+            if index & HARDENED_FLAG != 0 {
+                write!(f, "/{}'", index & !HARDENED_FLAG)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for DerivationPath {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Compute the BIP39 seed for the given mnemonic phrase (with an empty
+/// passphrase, as used by the Cosmos SDK)
+pub fn seed_from_mnemonic(phrase: &mnemonic::Phrase) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(phrase.to_seed("").as_bytes().to_vec())
+}
+
+/// Derive a secp256k1 key from the given seed according to BIP32
+pub fn derive_secp256k1(seed: &[u8], path: &DerivationPath) -> Result<ecdsa::SigningKey, Error> {
+    check_seed(seed)?;
+
+    let (mut key, mut chain_code) = split(hmac_sha512(BIP32_SEED_KEY, &[seed]));
+    let mut scalar = parse_scalar(&key)?;
+
+    for &index in &path.0 {
+        let mut data = Zeroizing::new(Vec::with_capacity(37));
+
+        if index & HARDENED_FLAG != 0 {
+            data.push(0);
+            data.extend_from_slice(&*key);
+        } else {
+            let signing_key = ecdsa::SigningKey::from_bytes(&*key)
+                .map_err(|e| format_err!(InvalidKey, "invalid secp256k1 key: {}", e))?;
+
+            data.extend_from_slice(&signing_key.verify_key().to_bytes());
+        }
+
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let (tweak, child_chain_code) = split(hmac_sha512(&*chain_code, &[&data]));
+        scalar = parse_scalar(&tweak)? + scalar;
+
+        if bool::from(scalar.is_zero()) {
+            fail!(InvalidKey, "derived an invalid secp256k1 key at {}", path);
+        }
+
+        key = Zeroizing::new(scalar.to_bytes().into());
+        chain_code = child_chain_code;
+    }
+
+    ecdsa::SigningKey::from_bytes(&*key)
+        .map_err(|e| format_err!(InvalidKey, "invalid secp256k1 key: {}", e).into())
+}
+
+/// Derive an Ed25519 key from the given seed according to SLIP-0010
+pub fn derive_ed25519(seed: &[u8], path: &DerivationPath) -> Result<ed25519::Keypair, Error> {
+    check_seed(seed)?;
+
+    if !path.is_hardened() {
+        fail!(
+            InvalidKey,
+            "Ed25519 keys only support hardened derivation (e.g. `m/44'/118'/0'`): {}",
+            path
+        );
+    }
+
+    let (mut key, mut chain_code) = split(hmac_sha512(SLIP10_ED25519_SEED_KEY, &[seed]));
+
+    for &index in &path.0 {
+        let (child_key, child_chain_code) = split(hmac_sha512(
+            &*chain_code,
+            &[&[0], &*key, &index.to_be_bytes()],
+        ));
+
+        key = child_key;
+        chain_code = child_chain_code;
+    }
+
+    super::ed25519_keypair(&*key)
+}
+
+/// Ensure the seed is within the sizes allowed by BIP32
+fn check_seed(seed: &[u8]) -> Result<(), Error> {
+    if !(MIN_SEED_SIZE..=SEED_SIZE).contains(&seed.len()) {
+        fail!(
+            InvalidKey,
+            "invalid seed length: {} (expected {}-{} bytes)",
+            seed.len(),
+            MIN_SEED_SIZE,
+            SEED_SIZE
+        );
+    }
+
+    Ok(())
+}
+
+/// Compute HMAC-SHA512 over the concatenation of the given inputs
+fn hmac_sha512(key: &[u8], inputs: &[&[u8]]) -> Zeroizing<[u8; 64]> {
+    let mut mac = Hmac::<Sha512>::new_varkey(key).unwrap();
+
+    for input in inputs {
+        mac.update(input);
+    }
+
+    let mut output = Zeroizing::new([0u8; 64]);
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
+
+/// Split HMAC output into a key and chain code
+fn split(output: Zeroizing<[u8; 64]>) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut key = Zeroizing::new([0u8; 32]);
+    let mut chain_code = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&output[..32]);
+    chain_code.copy_from_slice(&output[32..]);
+    (key, chain_code)
+}
+
+/// Parse a secp256k1 scalar, rejecting values which are out of range
+fn parse_scalar(bytes: &[u8; 32]) -> Result<Scalar, Error> {
+    Scalar::from_repr(FieldBytes::clone_from_slice(bytes))
+        .filter(|scalar| !bool::from(scalar.is_zero()))
+        .ok_or_else(|| format_err!(InvalidKey, "derived an invalid secp256k1 key").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subtle_encoding::hex;
+
+    /// Seed from test vector 1 of BIP32 and SLIP-0010
+    const TEST_SEED: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn test_seed() -> Vec<u8> {
+        hex::decode(TEST_SEED).unwrap()
+    }
+
+    fn derive_secp256k1_hex(path: &str) -> String {
+        let key = derive_secp256k1(&test_seed(), &path.parse().unwrap()).unwrap();
+        String::from_utf8(hex::encode(key.to_bytes())).unwrap()
+    }
+
+    fn derive_ed25519_hex(path: &str) -> String {
+        let keypair = derive_ed25519(&test_seed(), &path.parse().unwrap()).unwrap();
+        String::from_utf8(hex::encode(keypair.secret.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn parses_derivation_paths() {
+        let path = "m/44'/118'/0'/0/0".parse::<DerivationPath>().unwrap();
+        assert_eq!(path.to_string(), "m/44'/118'/0'/0/0");
+        assert!(!path.is_hardened());
+
+        let path = "m/44h/118h/0h".parse::<DerivationPath>().unwrap();
+        assert_eq!(path.to_string(), "m/44'/118'/0'");
+        assert!(path.is_hardened());
+
+        assert!("44'/118'".parse::<DerivationPath>().is_err());
+        assert!("m/x".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648".parse::<DerivationPath>().is_err());
+    }
+
+    #[test]
+    fn derives_bip32_secp256k1_keys() {
+        assert_eq!(
+            derive_secp256k1_hex("m"),
+            "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"
+        );
+        assert_eq!(
+            derive_secp256k1_hex("m/0'/1/2'/2/1000000000"),
+            "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"
+        );
+    }
+
+    #[test]
+    fn derives_slip10_ed25519_keys() {
+        assert_eq!(
+            derive_ed25519_hex("m"),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            derive_ed25519_hex("m/0'/1'/2'/2'/1000000000'"),
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"
+        );
+        assert!(derive_ed25519(&test_seed(), &"m/0".parse().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Checks applied to secret files before they're loaded: they must be owned
+//! by the current user, inaccessible to group and others, and (optionally)
+//! reside in a configured directory after resolving symlinks.
+
+use crate::{
+    config::secrets::SecretsConfig,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use once_cell::sync::OnceCell;
+use std::{fs, os::unix::fs::MetadataExt, path::Path};
+
+/// Permission bits which must not be set on secret files
+const GROUP_OTHER_PERMS: u32 = 0o077;
+
+/// Checks configured for this process
+static CONFIG: OnceCell<SecretsConfig> = OnceCell::new();
+
+/// Configure the checks applied to secret files. Until this is called the
+/// defaults apply (i.e. permission checks enabled).
+pub fn init(config: SecretsConfig) {
+    if CONFIG.set(config).is_err() {
+        warn!("secret file checks already configured");
+    }
+}
+
+/// Check that the secret file at the given path is safe to load
+pub fn check(path: impl AsRef<Path>) -> Result<(), Error> {
+    match CONFIG.get() {
+        Some(config) => check_with(config, path.as_ref()),
+        None => check_with(&SecretsConfig::default(), path.as_ref()),
+    }
+}
+
+/// Check a secret file against the given configuration
+fn check_with(config: &SecretsConfig, path: &Path) -> Result<(), Error> {
+    if let Some(dir) = &config.dir {
+        let dir = dir.canonicalize().map_err(|e| {
+            format_err!(
+                ConfigError,
+                "can't resolve secrets directory {}: {}",
+                dir.display(),
+                e
+            )
+        })?;
+
+        let resolved = path
+            .canonicalize()
+            .map_err(|e| format_err!(IoError, "can't resolve {}: {}", path.display(), e))?;
+
+        if !resolved.starts_with(&dir) {
+            fail!(
+                AccessError,
+                "{} resolves to {}, which is outside of the secrets directory {}",
+                path.display(),
+                resolved.display(),
+                dir.display()
+            );
+        }
+    }
+
+    if !config.check_permissions {
+        return Ok(());
+    }
+
+    let metadata = fs::metadata(path)
+        .map_err(|e| format_err!(IoError, "can't stat {}: {}", path.display(), e))?;
+
+    if !metadata.is_file() {
+        fail!(AccessError, "{} is not a regular file", path.display());
+    }
+
+    let uid = nix::unistd::geteuid().as_raw();
+
+    if metadata.uid() != uid {
+        fail!(
+            AccessError,
+            "{} is owned by uid {}, not the current user (uid {})",
+            path.display(),
+            metadata.uid(),
+            uid
+        );
+    }
+
+    if metadata.mode() & GROUP_OTHER_PERMS != 0 {
+        fail!(
+            AccessError,
+            "{} is accessible by group or others (mode {:o}); run `chmod 600 {}`",
+            path.display(),
+            metadata.mode() & 0o777,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+    use tempfile::TempDir;
+
+    fn write_file(path: &Path, mode: u32) {
+        fs::write(path, "secret").unwrap();
+        fs::set_permissions(path, Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn rejects_accessible_files() {
+        let dir = TempDir::new().unwrap();
+        let config = SecretsConfig::default();
+
+        let private = dir.path().join("private.key");
+        write_file(&private, 0o600);
+        assert!(check_with(&config, &private).is_ok());
+
+        for &mode in &[0o640, 0o604, 0o660] {
+            let path = dir.path().join(format!("{:o}.key", mode));
+            write_file(&path, mode);
+            assert!(check_with(&config, &path).is_err());
+
+            // Container override
+            let relaxed = SecretsConfig {
+                check_permissions: false,
+                dir: None,
+            };
+            assert!(check_with(&relaxed, &path).is_ok());
+        }
+
+        assert!(check_with(&config, dir.path()).is_err());
+    }
+
+    #[test]
+    fn rejects_symlinks_escaping_dir() {
+        let secrets_dir = TempDir::new().unwrap();
+        let other_dir = TempDir::new().unwrap();
+
+        let config = SecretsConfig {
+            check_permissions: true,
+            dir: Some(secrets_dir.path().to_owned()),
+        };
+
+        let inside = secrets_dir.path().join("inside.key");
+        write_file(&inside, 0o600);
+        let outside = other_dir.path().join("outside.key");
+        write_file(&outside, 0o600);
+
+        let good_link = secrets_dir.path().join("good.key");
+        std::os::unix::fs::symlink(&inside, &good_link).unwrap();
+        let bad_link = secrets_dir.path().join("bad.key");
+        std::os::unix::fs::symlink(&outside, &bad_link).unwrap();
+
+        assert!(check_with(&config, &inside).is_ok());
+        assert!(check_with(&config, &good_link).is_ok());
+        assert!(check_with(&config, &bad_link).is_err());
+        assert!(check_with(&config, &outside).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Private keys of either algorithm supported by the `softsign` provider, and
+//! the formats they can be stored in:
+//!
+//! - `base64`: raw key bytes, Base64-encoded or password-encrypted
+//! - `json`: Tendermint `priv_validator_key.json`
+//! - `armor`: Cosmos SDK ASCII-armored export (`keys export`)
+
+use super::{armor, hd::DerivationPath, PasswordSource};
+use crate::{
+    config::provider::{softsign::KeyFormat, KeyAlgorithm},
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use ed25519_dalek as ed25519;
+use k256::ecdsa;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use subtle_encoding::base64;
+use tendermint::account;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Amino type name of Ed25519 private keys
+const ED25519_TYPE_NAME: &str = "tendermint/PrivKeyEd25519";
+
+/// Amino type name of secp256k1 private keys
+const SECP256K1_TYPE_NAME: &str = "tendermint/PrivKeySecp256k1";
+
+/// Private key
+pub enum SecretKey {
+    /// Ed25519 key
+    Ed25519(ed25519::Keypair),
+
+    /// secp256k1 (ECDSA) key
+    Secp256k1(ecdsa::SigningKey),
+}
+
+impl SecretKey {
+    /// Load a key stored in the given format. If an algorithm is given, the
+    /// key must be of that algorithm (it's required for raw `base64` keys).
+    ///
+    /// The password for encrypted keys is obtained from the given source. If
+    /// `strict` is set, unencrypted keys are rejected.
+    pub fn load(
+        path: &Path,
+        format: KeyFormat,
+        algorithm: Option<KeyAlgorithm>,
+        password: &PasswordSource,
+        strict: bool,
+    ) -> Result<Self, Error> {
+        let with_path =
+            |e: Error| format_err!(*e.kind(), "couldn't load `{}`: {}", path.display(), e);
+
+        let key = match format {
+            KeyFormat::Base64 => {
+                let algorithm = algorithm.ok_or_else(|| {
+                    format_err!(
+                        ConfigError,
+                        "key algorithm of `{}` must be specified for `base64` keys",
+                        path.display()
+                    )
+                })?;
+
+                let key_bytes = super::load_secret(path, password, strict)?;
+                Self::from_bytes(algorithm, &key_bytes).map_err(with_path)?
+            }
+            KeyFormat::Json => {
+                if strict {
+                    fail!(
+                        ConfigError,
+                        "refusing to load unencrypted key from `{}` (strict mode)",
+                        path.display()
+                    );
+                }
+
+                Self::from_json(&super::read_secret_file(path)?).map_err(with_path)?
+            }
+            KeyFormat::Armor => {
+                let data = super::read_secret_file(path)?;
+                armor::decrypt(&data, password.read(path)?.as_bytes()).map_err(with_path)?
+            }
+        };
+
+        if let Some(algorithm) = algorithm.filter(|&algorithm| algorithm != key.algorithm()) {
+            fail!(
+                ConfigError,
+                "`{}` contains a {} key (expected {})",
+                path.display(),
+                key.algorithm(),
+                algorithm
+            );
+        }
+
+        Ok(key)
+    }
+
+    /// Decode a raw key of the given algorithm
+    pub fn from_bytes(algorithm: KeyAlgorithm, bytes: &[u8]) -> Result<Self, Error> {
+        match algorithm {
+            KeyAlgorithm::Ed25519 => super::ed25519_keypair(bytes).map(SecretKey::Ed25519),
+            KeyAlgorithm::Secp256k1 => ecdsa::SigningKey::from_bytes(bytes)
+                .map(SecretKey::Secp256k1)
+                .map_err(|e| format_err!(InvalidKey, "invalid secp256k1 key: {}", e).into()),
+        }
+    }
+
+    /// Derive a key of the given algorithm from a BIP39 seed
+    pub fn derive(
+        algorithm: KeyAlgorithm,
+        seed: &[u8],
+        path: &DerivationPath,
+    ) -> Result<Self, Error> {
+        match algorithm {
+            KeyAlgorithm::Ed25519 => super::hd::derive_ed25519(seed, path).map(SecretKey::Ed25519),
+            KeyAlgorithm::Secp256k1 => {
+                super::hd::derive_secp256k1(seed, path).map(SecretKey::Secp256k1)
+            }
+        }
+    }
+
+    /// Parse a Tendermint `priv_validator_key.json`
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let priv_validator_key = serde_json::from_str::<PrivValidatorKey>(json)
+            .map_err(|e| format_err!(ParseError, "malformed priv_validator_key.json: {}", e))?;
+
+        let key_bytes = Zeroizing::new(
+            base64::decode(&priv_validator_key.priv_key.value)
+                .map_err(|e| format_err!(ParseError, "malformed private key Base64: {}", e))?,
+        );
+
+        let key = match priv_validator_key.priv_key.type_name.as_str() {
+            // Ed25519 private keys are serialized as the secret key followed
+            // by the public key, which is checked below
+            ED25519_TYPE_NAME if key_bytes.len() == 64 => {
+                Self::from_bytes(KeyAlgorithm::Ed25519, &key_bytes[..32])?
+            }
+            SECP256K1_TYPE_NAME => Self::from_bytes(KeyAlgorithm::Secp256k1, &key_bytes)?,
+            other => fail!(InvalidKey, "unsupported private key type: {}", other),
+        };
+
+        if key.public_key() != priv_validator_key.pub_key {
+            fail!(InvalidKey, "`pub_key` doesn't match `priv_key`");
+        }
+
+        Ok(key)
+    }
+
+    /// Get the algorithm of this key
+    pub fn algorithm(&self) -> KeyAlgorithm {
+        match self {
+            SecretKey::Ed25519(_) => KeyAlgorithm::Ed25519,
+            SecretKey::Secp256k1(_) => KeyAlgorithm::Secp256k1,
+        }
+    }
+
+    /// Get the raw bytes of this key
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(match self {
+            SecretKey::Ed25519(keypair) => keypair.secret.as_bytes().to_vec(),
+            SecretKey::Secp256k1(signing_key) => signing_key.to_bytes().to_vec(),
+        })
+    }
+
+    /// Get the public key which corresponds to this key
+    pub fn public_key(&self) -> tendermint::PublicKey {
+        match self {
+            SecretKey::Ed25519(keypair) => keypair.public.into(),
+            SecretKey::Secp256k1(signing_key) => {
+                tendermint::PublicKey::from_raw_secp256k1(&signing_key.verify_key().to_bytes())
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Serialize this key as a Tendermint `priv_validator_key.json`
+    pub fn to_json(&self) -> Zeroizing<String> {
+        let (type_name, key_bytes) = match self {
+            SecretKey::Ed25519(keypair) => (
+                ED25519_TYPE_NAME,
+                Zeroizing::new(keypair.to_bytes().to_vec()),
+            ),
+            SecretKey::Secp256k1(_) => (SECP256K1_TYPE_NAME, self.to_bytes()),
+        };
+
+        let public_key = self.public_key();
+
+        let priv_validator_key = PrivValidatorKey {
+            address: account::Id::from(public_key),
+            pub_key: public_key,
+            priv_key: JsonPrivateKey {
+                type_name: type_name.to_owned(),
+                value: String::from_utf8(base64::encode(&*key_bytes)).unwrap(),
+            },
+        };
+
+        Zeroizing::new(serde_json::to_string_pretty(&priv_validator_key).unwrap())
+    }
+}
+
+/// Tendermint `priv_validator_key.json`.
+///
+/// This supports secp256k1 keys in addition to the Ed25519 keys supported by
+/// [`tendermint::config::PrivValidatorKey`].
+#[derive(Deserialize, Serialize)]
+struct PrivValidatorKey {
+    /// Address
+    address: account::Id,
+
+    /// Public key
+    pub_key: tendermint::PublicKey,
+
+    /// Private key
+    priv_key: JsonPrivateKey,
+}
+
+/// Private key in `priv_validator_key.json`
+#[derive(Deserialize, Serialize)]
+struct JsonPrivateKey {
+    /// Amino type name
+    #[serde(rename = "type")]
+    type_name: String,
+
+    /// Base64-encoded key
+    value: String,
+}
+
+impl Drop for JsonPrivateKey {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_json() {
+        for &algorithm in &[KeyAlgorithm::Ed25519, KeyAlgorithm::Secp256k1] {
+            let key = SecretKey::from_bytes(algorithm, &[0x42; 32]).unwrap();
+            let json = key.to_json();
+
+            let parsed = SecretKey::from_json(&json).unwrap();
+            assert_eq!(parsed.algorithm(), algorithm);
+            assert_eq!(parsed.to_bytes(), key.to_bytes());
+
+            // Mismatched public keys are rejected
+            let other_key = SecretKey::from_bytes(algorithm, &[0x43; 32]).unwrap();
+            let mut tampered = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+            tampered["pub_key"] = serde_json::to_value(other_key.public_key()).unwrap();
+            assert!(SecretKey::from_json(&tampered.to_string()).is_err());
+        }
+    }
+}
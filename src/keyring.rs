@@ -1,7 +1,9 @@
-//! Signing keyring. Presently specialized for Ed25519.
+//! Signing keyring: Ed25519 and ECDSA (secp256k1) keys, each of which may be
+//! used as either a consensus or an account key.
 
 pub mod ecdsa;
 pub mod ed25519;
+mod failover;
 pub mod format;
 pub mod providers;
 
@@ -40,78 +42,120 @@ impl KeyRing {
         }
     }
 
-    /// Add na ECDSA key to the keyring, returning an error if we already have a
-    /// signer registered for the given public key
+    /// Add an ECDSA key to the keyring. If a signer is already registered
+    /// for the given public key, the new one is added as a fallback for it.
     pub fn add_ecdsa(&mut self, signer: ecdsa::Signer) -> Result<(), Error> {
         let provider = signer.provider();
         let public_key = signer.public_key();
         let public_key_serialized = self.format.serialize(public_key);
-        let key_type = match public_key {
-            TendermintKey::AccountKey(_) => "account",
-            TendermintKey::ConsensusKey(_) => unimplemented!(
-                "ECDSA consensus keys unsupported: {:?}",
-                public_key_serialized
-            ),
-        };
+        let key_type = key_type(&public_key);
 
-        info!(
-            "[keyring:{}] added {} ECDSA key: {}",
-            provider, key_type, public_key_serialized
-        );
+        if let Some(existing) = self.ecdsa_keys.get_mut(&public_key) {
+            existing.add_fallback(&signer);
 
-        if let Some(other) = self.ecdsa_keys.insert(public_key, signer) {
-            fail!(
-                InvalidKey,
-                "[keyring:{}] duplicate key {} already registered as {}",
+            info!(
+                "[keyring:{}] added fallback #{} for {} ECDSA key: {}",
                 provider,
-                public_key_serialized,
-                other.provider(),
-            )
+                existing.num_providers() - 1,
+                key_type,
+                public_key_serialized
+            );
         } else {
-            Ok(())
+            info!(
+                "[keyring:{}] added {} ECDSA key: {}",
+                provider, key_type, public_key_serialized
+            );
+
+            self.ecdsa_keys.insert(public_key, signer);
         }
+
+        Ok(())
     }
 
-    /// Add a key to the keyring, returning an error if we already have a
-    /// signer registered for the given public key
+    /// Add an Ed25519 key to the keyring. If a signer is already registered
+    /// for the given public key, the new one is added as a fallback for it.
     pub fn add_ed25519(&mut self, signer: ed25519::Signer) -> Result<(), Error> {
         let provider = signer.provider();
         let public_key = signer.public_key();
         let public_key_serialized = self.format.serialize(public_key);
-        let key_type = match public_key {
-            TendermintKey::AccountKey(_) => unimplemented!(
-                "Ed25519 account keys unsupported: {:?}",
-                public_key_serialized
-            ),
-            TendermintKey::ConsensusKey(_) => "consensus",
-        };
+        let key_type = key_type(&public_key);
 
-        info!(
-            "[keyring:{}] added {} Ed25519 key: {}",
-            provider, key_type, public_key_serialized
-        );
+        if let Some(existing) = self.ed25519_keys.get_mut(&public_key) {
+            existing.add_fallback(&signer);
 
-        if let Some(other) = self.ed25519_keys.insert(public_key, signer) {
-            fail!(
-                InvalidKey,
-                "[keyring:{}] duplicate key {} already registered as {}",
+            info!(
+                "[keyring:{}] added fallback #{} for {} Ed25519 key: {}",
                 provider,
-                public_key_serialized,
-                other.provider(),
-            )
+                existing.num_providers() - 1,
+                key_type,
+                public_key_serialized
+            );
         } else {
-            Ok(())
+            info!(
+                "[keyring:{}] added {} Ed25519 key: {}",
+                provider, key_type, public_key_serialized
+            );
+
+            self.ed25519_keys.insert(public_key, signer);
+        }
+
+        Ok(())
+    }
+
+    /// Get the formatting configuration for keys in this keyring
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    /// Get the public keys in this keyring along with their providers
+    pub fn public_keys(&self) -> Vec<(TendermintKey, Vec<SigningProvider>)> {
+        let ed25519_keys = self
+            .ed25519_keys
+            .iter()
+            .map(|(key, signer)| (*key, signer.providers()));
+
+        let ecdsa_keys = self
+            .ecdsa_keys
+            .iter()
+            .map(|(key, signer)| (*key, signer.providers()));
+
+        ed25519_keys.chain(ecdsa_keys).collect()
+    }
+
+    /// Get the signer for the consensus key in this keyring, which may be
+    /// either Ed25519 or ECDSA (secp256k1)
+    pub fn consensus_signer(&self) -> Result<ConsensusSigner<'_>, Error> {
+        let ed25519_signers = self
+            .ed25519_keys
+            .iter()
+            .filter(|(key, _)| is_consensus_key(key))
+            .map(|(_, signer)| ConsensusSigner::Ed25519(signer));
+
+        let ecdsa_signers = self
+            .ecdsa_keys
+            .iter()
+            .filter(|(key, _)| is_consensus_key(key))
+            .map(|(_, signer)| ConsensusSigner::Ecdsa(signer));
+
+        let mut signers = ed25519_signers.chain(ecdsa_signers);
+
+        match (signers.next(), signers.next()) {
+            (Some(signer), None) => Ok(signer),
+            (None, _) => fail!(InvalidKey, "no consensus key in keyring"),
+            (Some(_), Some(_)) => fail!(InvalidKey, "expected only one consensus key in keyring"),
         }
     }
 
     /// Get the default Ed25519 (i.e. consensus) public key for this keyring
     pub fn default_ed25519_pubkey(&self) -> Result<TendermintKey, Error> {
-        let mut keys = self.ed25519_keys.keys();
+        let mut keys = self.ed25519_keys.keys().filter(|key| is_consensus_key(key));
 
-        if keys.len() == 1 {
-            Ok(*keys.next().unwrap())
-        } else {
-            fail!(InvalidKey, "expected only one key in keyring");
+        match (keys.next(), keys.next()) {
+            (Some(key), None) => Ok(*key),
+            _ => fail!(
+                InvalidKey,
+                "expected only one Ed25519 consensus key in keyring"
+            ),
         }
     }
 
@@ -149,6 +193,35 @@ impl KeyRing {
         )
     }
 
+    /// Get the Ed25519 signer for the given public key (if it is in our
+    /// keyring), or the only Ed25519 consensus key signer in the keyring if
+    /// none is given
+    pub fn get_ed25519_signer(
+        &self,
+        public_key: Option<&TendermintKey>,
+    ) -> Result<&ed25519::Signer, Error> {
+        match public_key {
+            Some(public_key) => self.ed25519_keys.get(public_key).ok_or_else(|| {
+                format_err!(InvalidKey, "not in keyring: {}", public_key.to_bech32("")).into()
+            }),
+            None => {
+                let mut signers = self
+                    .ed25519_keys
+                    .iter()
+                    .filter(|(key, _)| is_consensus_key(key))
+                    .map(|(_, signer)| signer);
+
+                match (signers.next(), signers.next()) {
+                    (Some(signer), None) => Ok(signer),
+                    (None, _) => fail!(InvalidKey, "no Ed25519 consensus key in keyring"),
+                    (Some(_), Some(_)) => {
+                        fail!(SigningError, "expected only one key in keyring")
+                    }
+                }
+            }
+        }
+    }
+
     /// Sign a message using the secret key associated with the given public key
     /// (if it is in our keyring)
     pub fn sign_ed25519(
@@ -156,36 +229,230 @@ impl KeyRing {
         public_key: Option<&TendermintKey>,
         msg: &[u8],
     ) -> Result<ed25519::Signature, Error> {
-        let signer = match public_key {
-            Some(public_key) => self.ed25519_keys.get(public_key).ok_or_else(|| {
-                format_err!(InvalidKey, "not in keyring: {}", public_key.to_bech32(""))
-            })?,
-            None => {
-                let mut vals = self.ed25519_keys.values();
+        self.get_ed25519_signer(public_key)?.sign(msg)
+    }
 
-                if vals.len() > 1 {
-                    fail!(SigningError, "expected only one key in keyring");
-                } else {
-                    vals.next()
-                        .ok_or_else(|| format_err!(InvalidKey, "keyring is empty"))?
-                }
-            }
-        };
+    /// Sign a message with the given key of either algorithm (if it is in our
+    /// keyring) using only the provider at the given index (in the order of
+    /// `public_keys`), returning the signature as it's serialized in votes
+    /// and proposals
+    pub fn sign_with_provider(
+        &self,
+        public_key: &TendermintKey,
+        index: usize,
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        if let Some(signer) = self.ed25519_keys.get(public_key) {
+            Ok(signer.sign_with_provider(index, msg)?.as_ref().to_vec())
+        } else if let Some(signer) = self.ecdsa_keys.get(public_key) {
+            Ok(signer.sign_with_provider(index, msg)?.as_ref().to_vec())
+        } else {
+            fail!(InvalidKey, "not in keyring: {}", public_key.to_bech32(""))
+        }
+    }
+}
+
+/// Signer for a consensus key of either supported algorithm
+#[derive(Copy, Clone)]
+pub enum ConsensusSigner<'a> {
+    /// Ed25519 consensus key
+    Ed25519(&'a ed25519::Signer),
+
+    /// ECDSA (secp256k1) consensus key
+    Ecdsa(&'a ecdsa::Signer),
+}
+
+impl ConsensusSigner<'_> {
+    /// Get the provider for this signer
+    pub fn provider(&self) -> SigningProvider {
+        match self {
+            ConsensusSigner::Ed25519(signer) => signer.provider(),
+            ConsensusSigner::Ecdsa(signer) => signer.provider(),
+        }
+    }
+
+    /// Get the Tendermint public key for this signer
+    pub fn public_key(&self) -> TendermintKey {
+        match self {
+            ConsensusSigner::Ed25519(signer) => signer.public_key(),
+            ConsensusSigner::Ecdsa(signer) => signer.public_key(),
+        }
+    }
 
-        signer.sign(msg)
+    /// Sign the given message, returning the signature as it's serialized in
+    /// votes and proposals (64 bytes for both algorithms, `r || s` for ECDSA)
+    pub fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            ConsensusSigner::Ed25519(signer) => Ok(signer.sign(msg)?.as_ref().to_vec()),
+            ConsensusSigner::Ecdsa(signer) => Ok(signer.sign(msg)?.as_ref().to_vec()),
+        }
+    }
+}
+
+/// Is the given key a consensus key?
+fn is_consensus_key(key: &TendermintKey) -> bool {
+    matches!(key, TendermintKey::ConsensusKey(_))
+}
+
+/// Get a description of the type of the given key for log messages
+fn key_type(key: &TendermintKey) -> &'static str {
+    match key {
+        TendermintKey::AccountKey(_) => "account",
+        TendermintKey::ConsensusKey(_) => "consensus",
     }
 }
 
 /// Initialize the keyring from the configuration file
-pub fn load_config(registry: &mut chain::Registry, config: &ProviderConfig) -> Result<(), Error> {
+pub fn load_config(
+    registry: &mut chain::Registry,
+    config: &ProviderConfig,
+) -> Result<Tasks, Error> {
     #[cfg(feature = "softsign")]
     providers::softsign::init(registry, &config.softsign)?;
 
     #[cfg(feature = "yubihsm")]
-    providers::yubihsm::init(registry, &config.yubihsm)?;
+    let yubihsm = providers::yubihsm::init(registry, &config.yubihsm)?;
 
     #[cfg(feature = "ledger")]
     providers::ledgertm::init(registry, &config.ledgertm)?;
 
-    Ok(())
+    #[cfg(feature = "ledger")]
+    providers::ledgercosmos::init(registry, &config.ledgercosmos, &config.ledgertm)?;
+
+    #[cfg(feature = "pkcs11")]
+    providers::pkcs11::init(registry, &config.pkcs11)?;
+
+    #[cfg(feature = "vault")]
+    providers::vault::init(registry, &config.vault)?;
+
+    #[cfg(feature = "plugin")]
+    providers::plugin::init(registry, &config.plugin)?;
+
+    #[cfg(feature = "remote")]
+    providers::remote::init(registry, &config.remote)?;
+
+    Ok(Tasks {
+        #[cfg(feature = "yubihsm")]
+        yubihsm,
+    })
+}
+
+/// Background tasks of the signing providers (e.g. YubiHSM liveness probes
+/// and audit log draining), which are only started by `tmkms start`
+pub struct Tasks {
+    /// Tasks of each YubiHSM
+    #[cfg(feature = "yubihsm")]
+    yubihsm: Vec<crate::yubihsm::Tasks>,
+}
+
+impl Tasks {
+    /// Start all background tasks
+    pub fn spawn(self) {
+        #[cfg(feature = "yubihsm")]
+        for tasks in self.yubihsm {
+            tasks.spawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{SigningKey, VerifyingKey};
+    use signature::Verifier;
+    use std::convert::TryFrom;
+
+    fn ed25519_signer(seed: u8, consensus: bool) -> ed25519::Signer {
+        let secret = ed25519::SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = ed25519::PublicKey::from(&secret);
+        let public_key = tendermint::PublicKey::from(public);
+
+        let tendermint_key = if consensus {
+            TendermintKey::ConsensusKey(public_key)
+        } else {
+            TendermintKey::AccountKey(public_key)
+        };
+
+        ed25519::Signer::new(
+            SigningProvider::SoftSign,
+            tendermint_key,
+            Box::new(ed25519::Keypair { secret, public }),
+        )
+    }
+
+    fn ecdsa_signer(seed: u8, consensus: bool) -> (ecdsa::Signer, VerifyingKey) {
+        let signing_key = SigningKey::from_bytes(&[seed; 32]).unwrap();
+        let verify_key = signing_key.verify_key();
+        let public_key = tendermint::PublicKey::from_raw_secp256k1(&verify_key.to_bytes()).unwrap();
+
+        let tendermint_key = if consensus {
+            TendermintKey::ConsensusKey(public_key)
+        } else {
+            TendermintKey::AccountKey(public_key)
+        };
+
+        let signer = ecdsa::Signer::new(
+            SigningProvider::SoftSign,
+            tendermint_key,
+            Box::new(signing_key),
+        );
+
+        (signer, verify_key)
+    }
+
+    #[test]
+    fn supports_secp256k1_consensus_keys() {
+        let mut keyring = KeyRing::new(Format::Hex);
+        let (signer, verify_key) = ecdsa_signer(1, true);
+        keyring.add_ecdsa(signer).unwrap();
+        keyring.add_ed25519(ed25519_signer(2, false)).unwrap();
+
+        let consensus_signer = keyring.consensus_signer().unwrap();
+        assert!(matches!(consensus_signer, ConsensusSigner::Ecdsa(_)));
+
+        let msg = b"sign bytes";
+        let signature = consensus_signer.sign(msg).unwrap();
+        assert_eq!(signature.len(), 64);
+
+        let signature = k256::ecdsa::Signature::try_from(signature.as_slice()).unwrap();
+        verify_key.verify(msg, &signature).unwrap();
+
+        // Ed25519 account keys aren't used for consensus
+        assert!(keyring.get_ed25519_signer(None).is_err());
+    }
+
+    #[test]
+    fn supports_ed25519_account_keys() {
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add_ed25519(ed25519_signer(1, false)).unwrap();
+        keyring.add_ed25519(ed25519_signer(2, true)).unwrap();
+
+        let consensus_key = keyring.consensus_signer().unwrap().public_key();
+        assert_eq!(consensus_key, ed25519_signer(2, true).public_key());
+        assert_eq!(keyring.default_ed25519_pubkey().unwrap(), consensus_key);
+    }
+
+    #[test]
+    fn rejects_multiple_consensus_keys() {
+        let mut keyring = KeyRing::new(Format::Hex);
+        assert!(keyring.consensus_signer().is_err());
+
+        keyring.add_ed25519(ed25519_signer(1, true)).unwrap();
+        keyring.add_ecdsa(ecdsa_signer(2, true).0).unwrap();
+        assert!(keyring.consensus_signer().is_err());
+    }
+
+    #[test]
+    fn accepts_redundant_signers() {
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add_ed25519(ed25519_signer(1, true)).unwrap();
+        keyring.add_ed25519(ed25519_signer(1, true)).unwrap();
+
+        let signer = keyring.get_ed25519_signer(None).unwrap();
+        assert_eq!(signer.num_providers(), 2);
+
+        let signature = signer.sign(b"sign bytes").unwrap();
+        let public_key = signer.public_key().public_key().ed25519().unwrap();
+        public_key.verify(b"sign bytes", &signature).unwrap();
+    }
 }
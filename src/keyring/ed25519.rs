@@ -3,24 +3,21 @@
 pub use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature};
 
 use crate::{
-    error::{Error, ErrorKind::*},
-    keyring::SigningProvider,
-    prelude::*,
+    error::Error,
+    keyring::{failover::Signers, SigningProvider},
 };
-use std::sync::Arc;
+use signature::Verifier;
 use tendermint::TendermintKey;
 
 /// Ed25519 signer
 #[derive(Clone)]
 pub struct Signer {
-    /// Provider for this signer
-    provider: SigningProvider,
-
     /// Tendermint public key
     public_key: TendermintKey,
 
-    /// Signer trait object
-    signer: Arc<Box<dyn signature::Signer<Signature> + Send + Sync>>,
+    /// Signers for this key (more than one if it's available from redundant
+    /// providers)
+    signers: Signers<Signature>,
 }
 
 impl Signer {
@@ -31,27 +28,60 @@ impl Signer {
         signer: Box<dyn signature::Signer<Signature> + Send + Sync>,
     ) -> Self {
         Self {
-            provider,
             public_key,
-            signer: Arc::new(signer),
+            signers: Signers::new(provider, signer),
         }
     }
 
+    /// Add the providers of `other` (which must be for the same key) as
+    /// fallbacks for this signer
+    pub(crate) fn add_fallback(&mut self, other: &Signer) {
+        debug_assert_eq!(self.public_key, other.public_key);
+        self.signers.append(&other.signers);
+    }
+
+    /// Number of providers for this signer
+    pub fn num_providers(&self) -> usize {
+        self.signers.len()
+    }
+
+    /// Get the providers for this signer, in the order they're tried
+    pub fn providers(&self) -> Vec<SigningProvider> {
+        self.signers.providers()
+    }
+
     /// Get the Tendermint public key for this signer
     pub fn public_key(&self) -> TendermintKey {
         self.public_key
     }
 
-    /// Get the provider for this signer
+    /// Get the provider for this signer (i.e. the one which will be tried
+    /// first)
     pub fn provider(&self) -> SigningProvider {
-        self.provider
+        self.signers.provider()
     }
 
-    /// Sign the given message using this signer
+    /// Sign the given message using this signer, failing over to redundant
+    /// providers in the event of an error. Signatures are verified before
+    /// they're returned.
     pub fn sign(&self, msg: &[u8]) -> Result<Signature, Error> {
-        Ok(self
-            .signer
-            .try_sign(msg)
-            .map_err(|e| format_err!(SigningError, "{}", e))?)
+        self.signers
+            .sign(msg, |signature| self.verify(msg, signature))
+    }
+
+    /// Sign the given message using only the provider at the given index (in
+    /// the order of `providers`), without failing over. The signature is
+    /// verified before it's returned.
+    pub fn sign_with_provider(&self, index: usize, msg: &[u8]) -> Result<Signature, Error> {
+        self.signers
+            .sign_with(index, msg, |signature| self.verify(msg, signature))
+    }
+
+    /// Verify a signature against this signer's public key
+    fn verify(&self, msg: &[u8], signature: &Signature) -> Result<(), signature::Error> {
+        match self.public_key.public_key().ed25519() {
+            Some(public_key) => public_key.verify(msg, signature),
+            None => Err(signature::Error::new()),
+        }
     }
 }
@@ -0,0 +1,348 @@
+//! Failover between redundant signers for the same key.
+//!
+//! The same key may be available from more than one provider (e.g. two
+//! YubiHSMs restored from the same wrap key). Signers are tried in the order
+//! they were added to the keyring. When one fails (or produces an invalid
+//! signature) it's marked unhealthy and the next one is used instead.
+//! Unhealthy signers are retried with exponential backoff, and once they
+//! succeed they take precedence again.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    keyring::SigningProvider,
+    prelude::*,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Initial interval after which an unhealthy signer is retried
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Maximum number of times the retry interval is doubled
+const MAX_BACKOFF_EXPONENT: u32 = 6;
+
+/// Redundant signers for a single public key, in order of preference
+pub(crate) struct Signers<S: signature::Signature> {
+    /// Signers for this key
+    signers: Vec<Arc<Signer<S>>>,
+}
+
+// Derived `Clone` would require `S: Clone`
+impl<S: signature::Signature> Clone for Signers<S> {
+    fn clone(&self) -> Self {
+        Self {
+            signers: self.signers.clone(),
+        }
+    }
+}
+
+impl<S: signature::Signature> Signers<S> {
+    /// Create a new set of signers, initially containing the given one
+    pub fn new(
+        provider: SigningProvider,
+        signer: Box<dyn signature::Signer<S> + Send + Sync>,
+    ) -> Self {
+        Self {
+            signers: vec![Arc::new(Signer {
+                provider,
+                signer,
+                health: Mutex::new(Health::default()),
+            })],
+        }
+    }
+
+    /// Add the signers in `other` as fallbacks after the existing ones
+    pub fn append(&mut self, other: &Self) {
+        self.signers.extend(other.signers.iter().cloned());
+    }
+
+    /// Number of signers for this key
+    pub fn len(&self) -> usize {
+        self.signers.len()
+    }
+
+    /// Get the providers of all signers, in the order they were added
+    pub fn providers(&self) -> Vec<SigningProvider> {
+        self.signers.iter().map(|signer| signer.provider).collect()
+    }
+
+    /// Get the provider of the signer which will be tried first
+    pub fn provider(&self) -> SigningProvider {
+        let now = Instant::now();
+
+        self.signers
+            .iter()
+            .find(|signer| signer.is_available(now))
+            .unwrap_or(&self.signers[0])
+            .provider
+    }
+
+    /// Sign the given message, failing over to the next signer in the event
+    /// of an error. Each signature is checked with `verify` before it's
+    /// returned, and an invalid signature counts as a failure.
+    pub fn sign<V>(&self, msg: &[u8], verify: V) -> Result<S, Error>
+    where
+        V: Fn(&S) -> Result<(), signature::Error>,
+    {
+        self.sign_at(Instant::now(), msg, verify)
+    }
+
+    /// Sign the given message as of the given time (which determines which
+    /// signers are still backing off)
+    fn sign_at<V>(&self, now: Instant, msg: &[u8], verify: V) -> Result<S, Error>
+    where
+        V: Fn(&S) -> Result<(), signature::Error>,
+    {
+        // Available signers first, then ones which are still backing off as
+        // a last resort
+        let (available, backing_off): (Vec<_>, Vec<_>) = self
+            .signers
+            .iter()
+            .partition(|signer| signer.is_available(now));
+
+        let mut last_error = None;
+
+        for signer in available.into_iter().chain(backing_off) {
+            match signer.sign(msg, &verify) {
+                Ok(signature) => {
+                    signer.succeeded();
+                    return Ok(signature);
+                }
+                Err(e) => {
+                    signer.failed(&e, self.len() > 1, now);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.expect("no signers"))
+    }
+
+    /// Sign the given message with only the signer at the given index (in
+    /// the order of `providers`), without failing over or affecting its
+    /// health, e.g. to test each of a key's signers individually
+    pub fn sign_with<V>(&self, index: usize, msg: &[u8], verify: V) -> Result<S, Error>
+    where
+        V: Fn(&S) -> Result<(), signature::Error>,
+    {
+        self.signers
+            .get(index)
+            .ok_or_else(|| format_err!(InvalidKey, "no signer #{} for key", index))?
+            .sign(msg, verify)
+    }
+}
+
+/// Individual signer along with its health
+struct Signer<S: signature::Signature> {
+    /// Provider for this signer
+    provider: SigningProvider,
+
+    /// Signer trait object
+    signer: Box<dyn signature::Signer<S> + Send + Sync>,
+
+    /// Health of this signer
+    health: Mutex<Health>,
+}
+
+impl<S: signature::Signature> Signer<S> {
+    /// Sign and verify the given message
+    fn sign<V>(&self, msg: &[u8], verify: V) -> Result<S, Error>
+    where
+        V: Fn(&S) -> Result<(), signature::Error>,
+    {
+        let signature = self
+            .signer
+            .try_sign(msg)
+            .map_err(|e| format_err!(SigningError, "{}", e))?;
+
+        verify(&signature).map_err(|_| {
+            format_err!(
+                VerificationError,
+                "[keyring:{}] produced an invalid signature",
+                self.provider
+            )
+        })?;
+
+        Ok(signature)
+    }
+
+    /// Is this signer healthy, or due to be retried?
+    fn is_available(&self, now: Instant) -> bool {
+        self.health.lock().unwrap().is_available(now)
+    }
+
+    /// Record a successful signature
+    fn succeeded(&self) {
+        let mut health = self.health.lock().unwrap();
+
+        if health.failures > 0 {
+            info!(
+                "[keyring:{}] signer recovered after {} failure(s)",
+                self.provider, health.failures
+            );
+        }
+
+        *health = Health::default();
+    }
+
+    /// Record a failure at the given time
+    fn failed(&self, error: &Error, has_fallbacks: bool, now: Instant) {
+        let mut health = self.health.lock().unwrap();
+
+        if health.failures == 0 && has_fallbacks {
+            warn!(
+                "[keyring:{}] signer failed, failing over: {}",
+                self.provider, error
+            );
+        } else {
+            debug!(
+                "[keyring:{}] signer failed (failures: {}): {}",
+                self.provider,
+                health.failures + 1,
+                error
+            );
+        }
+
+        health.failures = health.failures.saturating_add(1);
+        health.failed_at = Some(now);
+    }
+}
+
+/// Health of an individual signer
+#[derive(Debug, Default)]
+struct Health {
+    /// Number of consecutive failures
+    failures: u32,
+
+    /// Time of the most recent failure
+    failed_at: Option<Instant>,
+}
+
+impl Health {
+    /// Is this signer healthy, or due to be retried?
+    fn is_available(&self, now: Instant) -> bool {
+        match self.failed_at {
+            Some(failed_at) => now.saturating_duration_since(failed_at) >= self.retry_interval(),
+            None => true,
+        }
+    }
+
+    /// Interval after which a failed signer is retried
+    fn retry_interval(&self) -> Duration {
+        let exponent = self.failures.saturating_sub(1).min(MAX_BACKOFF_EXPONENT);
+        RETRY_INTERVAL * 2u32.pow(exponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature};
+    use signature::Verifier;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const MSG: &[u8] = b"sign bytes";
+
+    /// Test signer which fails unless `healthy` is set
+    struct TestSigner {
+        keypair: Keypair,
+        healthy: Arc<AtomicBool>,
+    }
+
+    impl signature::Signer<Signature> for TestSigner {
+        fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+            if self.healthy.load(Ordering::SeqCst) {
+                self.keypair.try_sign(msg)
+            } else {
+                Err(signature::Error::new())
+            }
+        }
+    }
+
+    fn keypair(seed: u8) -> Keypair {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        Keypair { secret, public }
+    }
+
+    fn test_signer(seed: u8) -> (Signers<Signature>, Arc<AtomicBool>) {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let signer = TestSigner {
+            keypair: keypair(seed),
+            healthy: healthy.clone(),
+        };
+
+        (
+            Signers::new(SigningProvider::SoftSign, Box::new(signer)),
+            healthy,
+        )
+    }
+
+    fn verify(signature: &Signature) -> Result<(), signature::Error> {
+        keypair(1).public.verify(MSG, signature)
+    }
+
+    #[test]
+    fn fails_over_and_backs_off() {
+        let (mut signers, primary) = test_signer(1);
+        signers.append(&test_signer(1).0);
+
+        primary.store(false, Ordering::SeqCst);
+        signers.sign(MSG, verify).unwrap();
+        assert_eq!(signers.signers[1].health.lock().unwrap().failures, 0);
+
+        // The primary isn't retried until its backoff elapses
+        let now = Instant::now();
+        let health = signers.signers[0].health.lock().unwrap();
+        assert_eq!(health.failures, 1);
+        assert!(!health.is_available(now));
+        assert!(health.is_available(now + RETRY_INTERVAL));
+    }
+
+    #[test]
+    fn repromotes_recovered_signers() {
+        let (mut signers, primary) = test_signer(1);
+        signers.append(&test_signer(1).0);
+
+        let failed_at = Instant::now();
+        primary.store(false, Ordering::SeqCst);
+        signers.sign_at(failed_at, MSG, verify).unwrap();
+
+        // Sign again once the backoff has elapsed
+        let retry_at = failed_at + RETRY_INTERVAL;
+        primary.store(true, Ordering::SeqCst);
+        assert!(signers.signers[0].is_available(retry_at));
+
+        signers.sign_at(retry_at, MSG, verify).unwrap();
+        assert_eq!(signers.signers[0].health.lock().unwrap().failures, 0);
+    }
+
+    #[test]
+    fn signs_with_individual_signers() {
+        let (mut signers, primary) = test_signer(1);
+        signers.append(&test_signer(1).0);
+
+        primary.store(false, Ordering::SeqCst);
+        assert!(signers.sign_with(0, MSG, verify).is_err());
+        signers.sign_with(1, MSG, verify).unwrap();
+        assert!(signers.sign_with(2, MSG, verify).is_err());
+
+        // Health is unaffected
+        assert!(signers.signers[0].is_available(Instant::now()));
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        // Signer for the wrong key
+        let (mut signers, _) = test_signer(2);
+        let err = signers.sign(MSG, verify).unwrap_err();
+        assert_eq!(*err.kind(), VerificationError);
+
+        signers.append(&test_signer(1).0);
+        signers.signers[0].health.lock().unwrap().failed_at = None;
+        signers.sign(MSG, verify).unwrap();
+        assert_eq!(signers.signers[0].health.lock().unwrap().failures, 2);
+    }
+}
@@ -1,11 +1,12 @@
 //! Chain-specific key configuration
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use subtle_encoding::bech32;
 use tendermint::TendermintKey;
 
 /// Options for how keys for this chain are represented
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "type")]
 pub enum Format {
     /// Use the Bech32 serialization format with the given key prefixes
@@ -40,3 +41,19 @@ impl Format {
         }
     }
 }
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Bech32 {
+                account_key_prefix,
+                consensus_key_prefix,
+            } => write!(
+                f,
+                "bech32 (account: {}, consensus: {})",
+                account_key_prefix, consensus_key_prefix
+            ),
+            Format::Hex => f.write_str("hex"),
+        }
+    }
+}
@@ -1,11 +1,26 @@
 //! Signature providers (i.e. backends/plugins)
 
+#[cfg(feature = "ledger")]
+pub mod ledgercosmos;
+
 #[cfg(feature = "ledger")]
 pub mod ledgertm;
 
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+
+#[cfg(feature = "plugin")]
+pub mod plugin;
+
+#[cfg(feature = "remote")]
+pub mod remote;
+
 #[cfg(feature = "softsign")]
 pub mod softsign;
 
+#[cfg(feature = "vault")]
+pub mod vault;
+
 #[cfg(feature = "yubihsm")]
 pub mod yubihsm;
 
@@ -22,9 +37,59 @@ pub enum SigningProvider {
     #[cfg(feature = "ledger")]
     LedgerTm,
 
+    /// Ledger + Cosmos application
+    #[cfg(feature = "ledger")]
+    LedgerCosmos,
+
+    /// PKCS#11 HSM
+    #[cfg(feature = "pkcs11")]
+    Pkcs11,
+
+    /// External signer plugin
+    #[cfg(feature = "plugin")]
+    Plugin,
+
+    /// Another (upstream) KMS
+    #[cfg(feature = "remote")]
+    Remote,
+
     /// Software signer (not intended for production use)
     #[cfg(feature = "softsign")]
     SoftSign,
+
+    /// HashiCorp Vault Transit secrets engine
+    #[cfg(feature = "vault")]
+    Vault,
+}
+
+impl SigningProvider {
+    /// Can this provider sign arbitrary test messages (e.g. for `tmkms bench`)
+    /// without side effects? Providers which keep their own height/round/step
+    /// state, or which need each signature confirmed by hand, can't.
+    pub fn signs_unattended(self) -> bool {
+        match self {
+            #[cfg(feature = "yubihsm")]
+            SigningProvider::Yubihsm => true,
+
+            #[cfg(feature = "ledger")]
+            SigningProvider::LedgerTm | SigningProvider::LedgerCosmos => false,
+
+            #[cfg(feature = "pkcs11")]
+            SigningProvider::Pkcs11 => true,
+
+            #[cfg(feature = "plugin")]
+            SigningProvider::Plugin => true,
+
+            #[cfg(feature = "remote")]
+            SigningProvider::Remote => false,
+
+            #[cfg(feature = "softsign")]
+            SigningProvider::SoftSign => true,
+
+            #[cfg(feature = "vault")]
+            SigningProvider::Vault => true,
+        }
+    }
 }
 
 impl Display for SigningProvider {
@@ -36,8 +101,23 @@ impl Display for SigningProvider {
             #[cfg(feature = "ledger")]
             SigningProvider::LedgerTm => write!(f, "ledgertm"),
 
+            #[cfg(feature = "ledger")]
+            SigningProvider::LedgerCosmos => write!(f, "ledgercosmos"),
+
+            #[cfg(feature = "pkcs11")]
+            SigningProvider::Pkcs11 => write!(f, "pkcs11"),
+
+            #[cfg(feature = "plugin")]
+            SigningProvider::Plugin => write!(f, "plugin"),
+
+            #[cfg(feature = "remote")]
+            SigningProvider::Remote => write!(f, "remote"),
+
             #[cfg(feature = "softsign")]
             SigningProvider::SoftSign => write!(f, "softsign"),
+
+            #[cfg(feature = "vault")]
+            SigningProvider::Vault => write!(f, "vault"),
         }
     }
 }
@@ -0,0 +1,224 @@
+//! Account keys on a Ledger running the Cosmos app.
+//!
+//! Transactions are signed as Amino JSON (i.e. the `StdSignMsg` sign bytes),
+//! which the app displays for confirmation on the device. The Cosmos app
+//! doesn't support blind signing, so signing blocks until each transaction
+//! is approved (or rejected) by whoever is operating the device, or the
+//! configured confirmation timeout expires.
+
+mod client;
+#[cfg(test)]
+mod mock;
+
+use self::client::CosmosApp;
+use crate::{
+    chain,
+    config::provider::{ledgercosmos::LedgerCosmosConfig, ledgertm::LedgerTendermintConfig},
+    error::{Error, ErrorKind::*},
+    key_utils::hd::DerivationPath,
+    keyring::{self, SigningProvider},
+    prelude::*,
+};
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tendermint::TendermintKey;
+
+/// Create Ledger Cosmos app signer objects from the given configuration
+pub fn init(
+    chain_registry: &mut chain::Registry,
+    configs: &[LedgerCosmosConfig],
+    ledgertm_configs: &[LedgerTendermintConfig],
+) -> Result<(), Error> {
+    if configs.is_empty() {
+        return Ok(());
+    }
+
+    if !ledgertm_configs.is_empty() {
+        fail!(
+            ConfigError,
+            "[[providers.ledgercosmos]] can't be used with [[providers.ledgertm]] \
+             (a Ledger only runs one app at a time)"
+        );
+    }
+
+    let app = Arc::new(Mutex::new(CosmosApp::connect()?));
+
+    for config in configs {
+        let signer = account_key_signer(&app, config)?;
+
+        for chain_id in &config.chain_ids {
+            chain_registry.add_account_key(chain_id, signer.clone())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a signer for the account key configured by the given section
+fn account_key_signer(
+    app: &Arc<Mutex<CosmosApp>>,
+    config: &LedgerCosmosConfig,
+) -> Result<keyring::ecdsa::Signer, Error> {
+    let key = AccountKey::new(
+        app,
+        &config.derivation_path,
+        Duration::from_secs(config.confirmation_timeout_secs),
+    )?;
+
+    Ok(keyring::ecdsa::Signer::new(
+        SigningProvider::LedgerCosmos,
+        TendermintKey::AccountKey(key.public_key),
+        Box::new(key),
+    ))
+}
+
+/// Account key on the device
+struct AccountKey {
+    /// Cosmos app client (shared by all keys on the device)
+    app: Arc<Mutex<CosmosApp>>,
+
+    /// Derivation path of this key
+    derivation_path: DerivationPath,
+
+    /// Public key
+    public_key: tendermint::PublicKey,
+
+    /// How long to wait for transactions to be confirmed on the device
+    confirmation_timeout: Duration,
+}
+
+impl AccountKey {
+    /// Get the key at the given derivation path
+    fn new(
+        app: &Arc<Mutex<CosmosApp>>,
+        derivation_path: &DerivationPath,
+        confirmation_timeout: Duration,
+    ) -> Result<Self, Error> {
+        let public_key = {
+            let app = app.lock().unwrap();
+            let version = app.version()?;
+            let public_key = app.public_key(derivation_path)?;
+
+            info!(
+                "[keyring:ledgercosmos] using account key {} at {} (Cosmos app v{})",
+                public_key.to_hex(),
+                derivation_path,
+                version
+            );
+
+            public_key
+        };
+
+        Ok(Self {
+            app: app.clone(),
+            derivation_path: derivation_path.clone(),
+            public_key,
+            confirmation_timeout,
+        })
+    }
+}
+
+impl signature::Signer<keyring::ecdsa::Signature> for AccountKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ecdsa::Signature, signature::Error> {
+        let (sender, receiver) = mpsc::channel();
+        let app = self.app.clone();
+        let derivation_path = self.derivation_path.clone();
+        let msg = msg.to_vec();
+
+        // Requests can't be cancelled on the device, so wait for confirmation
+        // in another thread which keeps the app locked until it's answered
+        thread::spawn(move || {
+            let result = app.lock().unwrap().sign(&derivation_path, &msg);
+
+            // Errors mean the signer has already given up waiting
+            let _ = sender.send(result);
+        });
+
+        let err = match receiver.recv_timeout(self.confirmation_timeout) {
+            Ok(result) => return result.map_err(signature::Error::from_source),
+            Err(mpsc::RecvTimeoutError::Timeout) => format_err!(
+                LedgerError,
+                "transaction wasn't confirmed on the Ledger within {}s",
+                self.confirmation_timeout.as_secs()
+            ),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                format_err!(LedgerError, "Ledger signing thread crashed")
+            }
+        };
+
+        Err(signature::Error::from_source(Error::from(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock::MockTransport, *};
+
+    fn config(derivation_path: &str) -> LedgerCosmosConfig {
+        LedgerCosmosConfig {
+            chain_ids: vec![],
+            derivation_path: derivation_path.parse().unwrap(),
+            confirmation_timeout_secs: 60,
+        }
+    }
+
+    #[test]
+    fn signs_with_account_keys() {
+        let app = Arc::new(Mutex::new(CosmosApp::new(MockTransport::new())));
+
+        let signers = ["m/44'/118'/0'/0/0", "m/44'/118'/0'/0/1"]
+            .iter()
+            .map(|path| account_key_signer(&app, &config(path)).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_ne!(signers[0].public_key(), signers[1].public_key());
+
+        // `keyring::ecdsa::Signer` verifies signatures against the public key
+        for signer in &signers {
+            assert_eq!(signer.provider(), SigningProvider::LedgerCosmos);
+            signer.sign(br#"{"account_number":"1"}"#).unwrap();
+        }
+    }
+
+    #[test]
+    fn times_out_waiting_for_confirmation() {
+        let transport = MockTransport::confirming_after(Duration::from_millis(500));
+        let app = Arc::new(Mutex::new(CosmosApp::new(transport)));
+        let path = "m/44'/118'/0'/0/0".parse().unwrap();
+        let key = AccountKey::new(&app, &path, Duration::from_millis(50)).unwrap();
+
+        let result: Result<keyring::ecdsa::Signature, _> =
+            signature::Signer::try_sign(&key, br#"{"account_number":"1"}"#);
+        assert!(result.is_err());
+
+        // Signing doesn't fail once requests are confirmed in time
+        let key = AccountKey::new(&app, &path, Duration::from_secs(5)).unwrap();
+        let result: Result<keyring::ecdsa::Signature, _> =
+            signature::Signer::try_sign(&key, br#"{"account_number":"1"}"#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn rejects_unsupported_configs() {
+        let app = Arc::new(Mutex::new(CosmosApp::new(MockTransport::new())));
+        assert!(account_key_signer(&app, &config("m/44'/118'/0'")).is_err());
+
+        let locked = Arc::new(Mutex::new(CosmosApp::new(MockTransport::locked())));
+        let err = account_key_signer(&locked, &config("m/44'/118'/0'/0/0"))
+            .err()
+            .unwrap();
+        assert_eq!(*err.kind(), LedgerError);
+
+        let mut registry = chain::Registry::default();
+        let ledgertm_config = LedgerTendermintConfig { chain_ids: vec![] };
+        assert!(init(
+            &mut registry,
+            &[config("m/44'/118'/0'/0/0")],
+            &[ledgertm_config]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Client for the Ledger Cosmos app
+
+use crate::{
+    key_utils::hd::DerivationPath,
+    keyring::{
+        ecdsa::Signature,
+        providers::ledgertm::{
+            client::{exchange, HidTransport, Transport, Version},
+            error::Error,
+        },
+    },
+};
+use ledger::ApduAnswer;
+
+const CLA: u8 = 0x55;
+const INS_GET_VERSION: u8 = 0x00;
+const INS_SIGN_SECP256K1: u8 = 0x02;
+const INS_GET_ADDR_SECP256K1: u8 = 0x04;
+
+/// `P1` values identifying the chunks of a signing request
+const PAYLOAD_INIT: u8 = 0x00;
+const PAYLOAD_ADD: u8 = 0x01;
+const PAYLOAD_LAST: u8 = 0x02;
+
+/// `P2` value selecting the Amino JSON sign mode
+const SIGN_MODE_AMINO_JSON: u8 = 0x00;
+
+const USER_MESSAGE_CHUNK_SIZE: usize = 250;
+
+/// Oldest major version of the app which uses the chunking protocol above
+const MIN_MAJOR_VERSION: u8 = 2;
+
+/// Address prefix sent when requesting public keys (only the key is used)
+const ADDRESS_HRP: &str = "cosmos";
+
+/// Number of components of the derivation paths supported by the app
+const DERIVATION_PATH_LENGTH: usize = 5;
+
+/// Size of a compressed secp256k1 public key
+const PUBLIC_KEY_SIZE: usize = 33;
+
+/// Flag set on hardened child numbers
+const HARDENED_FLAG: u32 = 1 << 31;
+
+/// Client for the Cosmos app
+pub struct CosmosApp {
+    transport: Box<dyn Transport>,
+}
+
+impl CosmosApp {
+    /// Connect to the app on a Ledger device connected via USB HID
+    pub fn connect() -> Result<Self, Error> {
+        Ok(Self::new(HidTransport::connect()?))
+    }
+
+    /// Create a client which uses the given transport
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        CosmosApp {
+            transport: Box::new(transport),
+        }
+    }
+
+    /// Get the app version, ensuring it's supported and the device is unlocked
+    pub fn version(&self) -> Result<Version, Error> {
+        let response = self.exchange(INS_GET_VERSION, 0x00, 0x00, &[])?;
+
+        if response.data.len() < 5 {
+            return Err(Error::InvalidVersion);
+        }
+
+        let version = Version {
+            mode: response.data[0],
+            major: response.data[1],
+            minor: response.data[2],
+            patch: response.data[3],
+        };
+
+        if response.data[4] != 0 {
+            return Err(Error::DeviceLocked);
+        }
+
+        if version.major < MIN_MAJOR_VERSION {
+            return Err(Error::InvalidVersion);
+        }
+
+        Ok(version)
+    }
+
+    /// Get the public key at the given derivation path
+    pub fn public_key(&self, path: &DerivationPath) -> Result<tendermint::PublicKey, Error> {
+        let mut data = vec![ADDRESS_HRP.len() as u8];
+        data.extend_from_slice(ADDRESS_HRP.as_bytes());
+        data.extend_from_slice(&serialize_path(path)?);
+
+        // `P1 = 0`: don't show the address on the device
+        let response = self.exchange(INS_GET_ADDR_SECP256K1, 0x00, 0x00, &data)?;
+
+        // The public key is followed by the Bech32 address
+        response
+            .data
+            .get(..PUBLIC_KEY_SIZE)
+            .and_then(tendermint::PublicKey::from_raw_secp256k1)
+            .ok_or(Error::InvalidPK)
+    }
+
+    /// Sign an Amino JSON message with the key at the given derivation path.
+    ///
+    /// This blocks until the message is approved (or rejected) on the device.
+    pub fn sign(&self, path: &DerivationPath, message: &[u8]) -> Result<Signature, Error> {
+        if message.is_empty() {
+            return Err(Error::InvalidEmptyMessage);
+        }
+
+        self.exchange(
+            INS_SIGN_SECP256K1,
+            PAYLOAD_INIT,
+            SIGN_MODE_AMINO_JSON,
+            &serialize_path(path)?,
+        )?;
+
+        let chunks = message.chunks(USER_MESSAGE_CHUNK_SIZE).collect::<Vec<_>>();
+        let mut response = None;
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let payload = if i + 1 == chunks.len() {
+                PAYLOAD_LAST
+            } else {
+                PAYLOAD_ADD
+            };
+
+            response =
+                Some(self.exchange(INS_SIGN_SECP256K1, payload, SIGN_MODE_AMINO_JSON, chunk)?);
+        }
+
+        // Last response should contain the (ASN.1 DER) signature
+        let response = response.unwrap();
+
+        if response.data.is_empty() {
+            return Err(Error::NoSignature);
+        }
+
+        let mut signature =
+            Signature::from_asn1(&response.data).map_err(|_| Error::InvalidSignature)?;
+
+        // Tendermint/Cosmos require low-S signatures
+        signature
+            .normalize_s()
+            .map_err(|_| Error::InvalidSignature)?;
+
+        Ok(signature)
+    }
+
+    /// Send a command to the app
+    fn exchange(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<ApduAnswer, Error> {
+        exchange(&*self.transport, CLA, ins, p1, p2, data)
+    }
+}
+
+/// Serialize a derivation path as the app expects: five little endian
+/// child numbers, of which the first three must be hardened
+fn serialize_path(path: &DerivationPath) -> Result<Vec<u8>, Error> {
+    let child_numbers = path.child_numbers();
+
+    if child_numbers.len() != DERIVATION_PATH_LENGTH
+        || child_numbers[..3]
+            .iter()
+            .any(|&child_number| child_number & HARDENED_FLAG == 0)
+    {
+        return Err(Error::InvalidDerivationPath);
+    }
+
+    Ok(child_numbers
+        .iter()
+        .flat_map(|child_number| child_number.to_le_bytes().to_vec())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock::MockTransport;
+    use super::*;
+    use crate::key_utils::hd;
+    use signature::Verifier;
+
+    fn path(path: &str) -> DerivationPath {
+        path.parse().unwrap()
+    }
+
+    #[test]
+    fn serializes_paths() {
+        assert_eq!(
+            serialize_path(&path("m/44'/118'/0'/0/1")).unwrap(),
+            [0x2c, 0, 0, 0x80, 0x76, 0, 0, 0x80, 0, 0, 0, 0x80, 0, 0, 0, 0, 1, 0, 0, 0]
+        );
+
+        assert!(serialize_path(&path("m/44'/118'/0'")).is_err());
+        assert!(serialize_path(&path("m/44'/118'/0/0/0")).is_err());
+    }
+
+    #[test]
+    fn signs_with_derived_key() {
+        let transport = MockTransport::new();
+        let seed = transport.seed().to_vec();
+        let app = CosmosApp::new(transport);
+        let path = path("m/44'/118'/0'/0/0");
+
+        let expected_key = hd::derive_secp256k1(&seed, &path).unwrap().verify_key();
+        assert_eq!(app.version().unwrap().major, 2);
+        assert_eq!(
+            app.public_key(&path).unwrap().as_bytes(),
+            expected_key.to_bytes().as_slice()
+        );
+
+        // Messages longer than one APDU are sent in chunks
+        let message = format!(r#"{{"memo":"{}"}}"#, "x".repeat(600));
+        let signature = app.sign(&path, message.as_bytes()).unwrap();
+        assert!(expected_key.verify(message.as_bytes(), &signature).is_ok());
+    }
+
+    #[test]
+    fn reports_app_errors() {
+        let path = path("m/44'/118'/0'/0/0");
+
+        let err = CosmosApp::new(MockTransport::rejecting())
+            .sign(&path, b"{}")
+            .unwrap_err();
+        assert!(matches!(err, Error::Apdu(0x6986)));
+
+        let err = CosmosApp::new(MockTransport::locked())
+            .version()
+            .unwrap_err();
+        assert!(matches!(err, Error::DeviceLocked));
+    }
+}
@@ -0,0 +1,190 @@
+//! Mock transport which emulates the Cosmos app, for testing
+
+use crate::{
+    key_utils::hd::{self, DerivationPath},
+    keyring::providers::ledgertm::{
+        client::{Transport, APDU_CODE_OK},
+        error::Error,
+    },
+};
+use k256::ecdsa::signature::Signer;
+use ledger::{ApduAnswer, ApduCommand};
+use std::{convert::TryInto, sync::Mutex, thread, time::Duration};
+use subtle_encoding::bech32;
+use tendermint::account;
+
+/// Return code of requests rejected on the device
+const APDU_CODE_COMMAND_NOT_ALLOWED: u16 = 0x6986;
+
+/// Return code of malformed requests (e.g. invalid JSON)
+const APDU_CODE_DATA_INVALID: u16 = 0x6984;
+
+/// BIP39 seed the mock app derives its keys from
+const SEED: [u8; 32] = [0x42; 32];
+
+/// Mock of a Ledger device running the Cosmos app
+#[derive(Default)]
+pub struct MockTransport {
+    /// Is the device locked?
+    locked: bool,
+
+    /// Reject signing requests (as if the user declined them)?
+    reject_signing: bool,
+
+    /// Time it takes the user to confirm signing requests
+    confirmation_delay: Option<Duration>,
+
+    /// Derivation path and chunks of the message currently being signed
+    request: Mutex<Option<(DerivationPath, Vec<u8>)>>,
+}
+
+impl MockTransport {
+    /// Create a mock app which approves every signing request
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a mock app which rejects every signing request
+    pub fn rejecting() -> Self {
+        Self {
+            reject_signing: true,
+            ..Self::new()
+        }
+    }
+
+    /// Create a mock app which approves signing requests after the given
+    /// delay
+    pub fn confirming_after(delay: Duration) -> Self {
+        Self {
+            confirmation_delay: Some(delay),
+            ..Self::new()
+        }
+    }
+
+    /// Create a mock of a locked device
+    pub fn locked() -> Self {
+        Self {
+            locked: true,
+            ..Self::new()
+        }
+    }
+
+    /// Get the seed keys are derived from
+    pub fn seed(&self) -> &[u8] {
+        &SEED
+    }
+}
+
+impl Transport for MockTransport {
+    fn exchange(&self, command: ApduCommand) -> Result<ApduAnswer, Error> {
+        assert_eq!(command.cla, 0x55);
+        assert_eq!(usize::from(command.length), command.data.len());
+
+        let result = match command.ins {
+            // Version: v2.34.12, followed by the lock status and target ID
+            0x00 => Ok(vec![
+                0x00,
+                0x02,
+                0x22,
+                0x0c,
+                self.locked as u8,
+                0x31,
+                0x10,
+                0x00,
+                0x04,
+            ]),
+            0x02 => self.sign(&command),
+            0x04 => {
+                let hrp_len = usize::from(command.data[0]);
+                let hrp = std::str::from_utf8(&command.data[1..=hrp_len]).unwrap();
+                let path = parse_path(&command.data[(hrp_len + 1)..]);
+
+                let public_key = hd::derive_secp256k1(&SEED, &path)
+                    .unwrap()
+                    .verify_key()
+                    .to_bytes();
+
+                let address = bech32::encode(
+                    hrp,
+                    account::Id::from(
+                        tendermint::PublicKey::from_raw_secp256k1(&public_key).unwrap(),
+                    ),
+                );
+
+                let mut data = public_key.to_vec();
+                data.extend_from_slice(address.as_bytes());
+                Ok(data)
+            }
+            ins => panic!("unexpected instruction: 0x{:02X}", ins),
+        };
+
+        Ok(match result {
+            Ok(data) => ApduAnswer {
+                data,
+                retcode: APDU_CODE_OK,
+            },
+            Err(retcode) => ApduAnswer {
+                data: vec![],
+                retcode,
+            },
+        })
+    }
+}
+
+impl MockTransport {
+    /// Handle a chunk of a signing request
+    fn sign(&self, command: &ApduCommand) -> Result<Vec<u8>, u16> {
+        let mut request = self.request.lock().unwrap();
+
+        // Init: the first chunk is the derivation path
+        if command.p1 == 0x00 {
+            *request = Some((parse_path(&command.data), vec![]));
+            return Ok(vec![]);
+        }
+
+        let (_, message) = request.as_mut().ok_or(APDU_CODE_DATA_INVALID)?;
+        message.extend_from_slice(&command.data);
+
+        // Add: wait for more chunks
+        if command.p1 == 0x01 {
+            return Ok(vec![]);
+        }
+
+        let (path, message) = request.take().unwrap();
+
+        if serde_json::from_slice::<serde_json::Value>(&message).is_err() {
+            return Err(APDU_CODE_DATA_INVALID);
+        }
+
+        if let Some(delay) = self.confirmation_delay {
+            thread::sleep(delay);
+        }
+
+        if self.reject_signing {
+            return Err(APDU_CODE_COMMAND_NOT_ALLOWED);
+        }
+
+        let signing_key = hd::derive_secp256k1(&SEED, &path).unwrap();
+        let signature: k256::ecdsa::Signature = signing_key.sign(&message);
+        Ok(signature.to_asn1().as_bytes().to_vec())
+    }
+}
+
+/// Parse a serialized derivation path
+fn parse_path(bytes: &[u8]) -> DerivationPath {
+    assert_eq!(bytes.len(), 20);
+
+    let mut path = String::from("m");
+
+    for chunk in bytes.chunks(4) {
+        let child_number = u32::from_le_bytes(chunk.try_into().unwrap());
+
+        if child_number & (1 << 31) != 0 {
+            path.push_str(&format!("/{}'", child_number & !(1 << 31)));
+        } else {
+            path.push_str(&format!("/{}", child_number));
+        }
+    }
+
+    path.parse().unwrap()
+}
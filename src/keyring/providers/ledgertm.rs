@@ -1,7 +1,9 @@
 //! Ledger Tendermint signer
 
-mod client;
-mod error;
+pub mod client;
+pub mod error;
+#[cfg(test)]
+pub(crate) mod mock;
 mod signer;
 
 use self::signer::Ed25519LedgerTmAppSigner;
@@ -9,10 +11,7 @@ use crate::{
     chain,
     config::provider::ledgertm::LedgerTendermintConfig,
     error::{Error, ErrorKind::*},
-    keyring::{
-        ed25519::{self, Signer},
-        SigningProvider,
-    },
+    keyring::{ed25519::Signer, SigningProvider},
     prelude::*,
 };
 use tendermint::{PublicKey, TendermintKey};
@@ -34,10 +33,8 @@ pub fn init(
         );
     }
 
-    let provider = Ed25519LedgerTmAppSigner::connect().map_err(|_| Error::from(SigningError))?;
-
-    let public_key = PublicKey::from_raw_ed25519(ed25519::PublicKey::from(&provider).as_bytes())
-        .expect("invalid Ed25519 public key");
+    let provider = Ed25519LedgerTmAppSigner::connect()?;
+    let public_key = PublicKey::from(provider.public_key()?);
 
     let signer = Signer::new(
         SigningProvider::LedgerTm,
@@ -18,54 +18,119 @@
 
 use super::error::Error;
 use ledger::{ApduAnswer, ApduCommand};
+use std::fmt;
 
 const CLA: u8 = 0x56;
+const INS_GET_VERSION: u8 = 0x00;
 const INS_PUBLIC_KEY_ED25519: u8 = 0x01;
 const INS_SIGN_ED25519: u8 = 0x02;
 
 const USER_MESSAGE_CHUNK_SIZE: usize = 250;
 
-#[allow(dead_code)]
-const INS_GET_VERSION: u8 = 0x00;
+/// APDU return code indicating success
+pub const APDU_CODE_OK: u16 = 0x9000;
+
+/// Transport used to exchange APDUs with a Ledger device
+pub trait Transport: Send {
+    /// Send a command to the device and receive its answer
+    fn exchange(&self, command: ApduCommand) -> Result<ApduAnswer, Error>;
+}
 
-pub(super) struct TendermintValidatorApp {
+/// Transport for a Ledger device connected via USB HID
+pub struct HidTransport {
     app: ledger::LedgerApp,
 }
 
 // TODO(tarcieri): check this is actually sound?!
 #[allow(unsafe_code)]
-unsafe impl Send for TendermintValidatorApp {}
+unsafe impl Send for HidTransport {}
+
+impl HidTransport {
+    /// Connect to the first Ledger device found
+    pub fn connect() -> Result<Self, Error> {
+        let app = ledger::LedgerApp::new()?;
+        Ok(HidTransport { app })
+    }
+}
+
+impl Transport for HidTransport {
+    fn exchange(&self, command: ApduCommand) -> Result<ApduAnswer, Error> {
+        Ok(self.app.exchange(command)?)
+    }
+}
 
-#[allow(dead_code)]
+/// Send a command to a Ledger app, checking the return code of its answer
+pub fn exchange(
+    transport: &dyn Transport,
+    cla: u8,
+    ins: u8,
+    p1: u8,
+    p2: u8,
+    data: &[u8],
+) -> Result<ApduAnswer, Error> {
+    let command = ApduCommand {
+        cla,
+        ins,
+        p1,
+        p2,
+        length: data.len() as u8,
+        data: data.to_vec(),
+    };
+
+    let response = transport.exchange(command)?;
+
+    if response.retcode != APDU_CODE_OK {
+        return Err(Error::Apdu(response.retcode));
+    }
+
+    Ok(response)
+}
+
+/// Client for the Tendermint Validator app
+pub struct TendermintValidatorApp {
+    transport: Box<dyn Transport>,
+}
+
+/// Version of a Ledger app
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Version {
-    mode: u8,
-    major: u8,
-    minor: u8,
-    patch: u8,
+    /// App mode (`0xFF` in test mode)
+    pub mode: u8,
+
+    /// Major version
+    pub major: u8,
+
+    /// Minor version
+    pub minor: u8,
+
+    /// Patch version
+    pub patch: u8,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
 }
 
 impl TendermintValidatorApp {
+    /// Connect to the app on a Ledger device connected via USB HID
     pub fn connect() -> Result<Self, Error> {
-        let app = ledger::LedgerApp::new()?;
-        Ok(TendermintValidatorApp { app })
+        Ok(Self::new(HidTransport::connect()?))
+    }
+
+    /// Create a client which uses the given transport
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        TendermintValidatorApp {
+            transport: Box::new(transport),
+        }
     }
 
     /// Get version
-    #[allow(dead_code)]
     pub fn version(&self) -> Result<Version, Error> {
-        let command = ApduCommand {
-            cla: CLA,
-            ins: INS_GET_VERSION,
-            p1: 0x00,
-            p2: 0x00,
-            length: 0,
-            data: Vec::new(),
-        };
+        let response = self.exchange(INS_GET_VERSION, 0x00, 0x00, &[])?;
 
-        let response = self.app.exchange(command)?;
-
-        // TODO: this is just temporary, ledger errors should check for 0x9000
-        if response.retcode != 0x9000 {
+        if response.data.len() < 4 {
             return Err(Error::InvalidVersion);
         }
 
@@ -81,34 +146,15 @@ impl TendermintValidatorApp {
 
     /// Get public key
     pub fn public_key(&self) -> Result<[u8; 32], Error> {
-        let command = ApduCommand {
-            cla: CLA,
-            ins: INS_PUBLIC_KEY_ED25519,
-            p1: 0x00,
-            p2: 0x00,
-            length: 0,
-            data: Vec::new(),
-        };
-
-        match self.app.exchange(command) {
-            Ok(response) => {
-                if response.retcode != 0x9000 {
-                    println!("WARNING: retcode={:X?}", response.retcode);
-                }
-
-                if response.data.len() != 32 {
-                    return Err(Error::InvalidPK);
-                }
+        let response = self.exchange(INS_PUBLIC_KEY_ED25519, 0x00, 0x00, &[])?;
 
-                let mut array = [0u8; 32];
-                array.copy_from_slice(&response.data[..32]);
-                Ok(array)
-            }
-            Err(err) => {
-                // TODO: Friendly error
-                Err(Error::Ledger(err))
-            }
+        if response.data.len() != 32 {
+            return Err(Error::InvalidPK);
         }
+
+        let mut array = [0u8; 32];
+        array.copy_from_slice(&response.data[..32]);
+        Ok(array)
     }
 
     /// Sign message
@@ -124,30 +170,25 @@ impl TendermintValidatorApp {
         }
 
         let packet_count = chunks.len() as u8;
-        let mut response: ApduAnswer = ApduAnswer {
-            data: vec![],
-            retcode: 0,
-        };
+        let mut response = None;
 
         // Send message chunks
         for (packet_idx, chunk) in chunks.enumerate() {
-            let _command = ApduCommand {
-                cla: CLA,
-                ins: INS_SIGN_ED25519,
-                p1: (packet_idx + 1) as u8,
-                p2: packet_count,
-                length: chunk.len() as u8,
-                data: chunk.to_vec(),
-            };
-
-            response = self.app.exchange(_command)?;
+            response = Some(self.exchange(
+                INS_SIGN_ED25519,
+                (packet_idx + 1) as u8,
+                packet_count,
+                chunk,
+            )?);
         }
 
-        if response.data.is_empty() && response.retcode == 0x9000 {
+        // Last response should contain the answer
+        let response = response.unwrap();
+
+        if response.data.is_empty() {
             return Err(Error::NoSignature);
         }
 
-        // Last response should contain the answer
         if response.data.len() != 64 {
             return Err(Error::InvalidSignature);
         }
@@ -156,6 +197,11 @@ impl TendermintValidatorApp {
         array.copy_from_slice(&response.data[..64]);
         Ok(array)
     }
+
+    /// Send a command to the app
+    fn exchange(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<ApduAnswer, Error> {
+        exchange(&*self.transport, CLA, ins, p1, p2, data)
+    }
 }
 
 #[cfg(test)]
@@ -2,27 +2,49 @@
 
 use thiserror::Error;
 
+/// Errors communicating with Ledger apps
 #[derive(Debug, Error)]
 pub enum Error {
+    /// App version is unsupported or malformed
     #[error("This version is not supported")]
     InvalidVersion,
 
+    /// Attempted to sign an empty message
     #[error("message cannot be empty")]
     InvalidEmptyMessage,
 
+    /// Message too big to sign
     #[error("message size is invalid (too big)")]
     InvalidMessageSize,
 
+    /// Malformed public key
     #[error("received an invalid PK")]
     InvalidPK,
 
+    /// App didn't return a signature
     #[error("received no signature back")]
     NoSignature,
 
+    /// Malformed signature
     #[error("received an invalid signature")]
     InvalidSignature,
 
-    #[error("ledger error")]
+    /// App returned an error code
+    #[error("device returned error code 0x{0:04X}")]
+    Apdu(u16),
+
+    /// Derivation path unsupported by the app
+    #[error(
+        "derivation path must have 5 components, the first 3 hardened (e.g. m/44'/118'/0'/0/0)"
+    )]
+    InvalidDerivationPath,
+
+    /// Device is locked
+    #[error("device is locked (unlock it with its PIN)")]
+    DeviceLocked,
+
+    /// Error communicating with the device
+    #[error("ledger error: {0}")]
     Ledger(ledger::Error),
 }
 
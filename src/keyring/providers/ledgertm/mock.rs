@@ -0,0 +1,89 @@
+//! Mock transport which emulates the Tendermint Validator app, for testing
+
+use super::{
+    client::{Transport, APDU_CODE_OK},
+    error::Error,
+};
+use crate::key_utils;
+use ed25519_dalek::{Keypair, Signer};
+use ledger::{ApduAnswer, ApduCommand};
+use std::sync::Mutex;
+
+/// Return code of commands the app refuses (e.g. signing below the
+/// last signed height/round/step)
+pub const APDU_CODE_COMMAND_NOT_ALLOWED: u16 = 0x6986;
+
+/// Mock of a Ledger device running the Tendermint Validator app
+pub struct MockTransport {
+    /// Key the app signs with
+    keypair: Keypair,
+
+    /// Reject signing requests?
+    reject_signing: bool,
+
+    /// Chunks of the message currently being signed
+    message: Mutex<Vec<u8>>,
+}
+
+impl MockTransport {
+    /// Create a mock app which signs with a key derived from the given seed
+    pub fn new(seed: &[u8; 32]) -> Self {
+        Self {
+            keypair: key_utils::ed25519_keypair(seed).unwrap(),
+            reject_signing: false,
+            message: Mutex::new(vec![]),
+        }
+    }
+
+    /// Create a mock app which refuses to sign
+    pub fn rejecting(seed: &[u8; 32]) -> Self {
+        Self {
+            reject_signing: true,
+            ..Self::new(seed)
+        }
+    }
+
+    /// Get the Ed25519 key of the app
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+}
+
+impl Transport for MockTransport {
+    fn exchange(&self, command: ApduCommand) -> Result<ApduAnswer, Error> {
+        assert_eq!(command.cla, 0x56);
+        assert_eq!(usize::from(command.length), command.data.len());
+
+        let data = match command.ins {
+            // Version: test mode, v0.9.0
+            0x00 => vec![0xff, 0x00, 0x09, 0x00],
+            0x01 => self.keypair.public.as_bytes().to_vec(),
+            0x02 if self.reject_signing => {
+                return Ok(answer(APDU_CODE_COMMAND_NOT_ALLOWED, vec![]))
+            }
+            0x02 => {
+                let mut message = self.message.lock().unwrap();
+
+                if command.p1 == 1 {
+                    message.clear();
+                }
+
+                message.extend_from_slice(&command.data);
+
+                if command.p1 == command.p2 {
+                    self.keypair.sign(&message).to_bytes().to_vec()
+                } else {
+                    vec![]
+                }
+            }
+            ins => panic!("unexpected instruction: 0x{:02X}", ins),
+        };
+
+        Ok(answer(APDU_CODE_OK, data))
+    }
+}
+
+/// Build an APDU answer
+fn answer(retcode: u16, data: Vec<u8>) -> ApduAnswer {
+    ApduAnswer { data, retcode }
+}
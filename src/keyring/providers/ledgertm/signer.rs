@@ -14,7 +14,7 @@
 *  limitations under the License.
 ********************************************************************************/
 
-use super::client::TendermintValidatorApp;
+use super::{client::TendermintValidatorApp, error::Error as LedgerError};
 use crate::keyring::ed25519::{PublicKey, Signature};
 use signature::{Error, Signer};
 use std::sync::{Arc, Mutex};
@@ -26,18 +26,21 @@ pub(super) struct Ed25519LedgerTmAppSigner {
 
 impl Ed25519LedgerTmAppSigner {
     /// Create a new Ed25519 signer based on Ledger Nano S - Tendermint Validator app
-    pub fn connect() -> Result<Self, Error> {
-        let validator_app = TendermintValidatorApp::connect().map_err(Error::from_source)?;
+    pub fn connect() -> Result<Self, LedgerError> {
+        TendermintValidatorApp::connect().map(Self::new)
+    }
+
+    /// Create a new Ed25519 signer using the given app client
+    pub fn new(validator_app: TendermintValidatorApp) -> Self {
         let app = Arc::new(Mutex::new(validator_app));
-        Ok(Ed25519LedgerTmAppSigner { app })
+        Ed25519LedgerTmAppSigner { app }
     }
-}
 
-impl From<&Ed25519LedgerTmAppSigner> for PublicKey {
-    /// Returns the public key that corresponds to the Tendermint Validator app connected to this signer
-    fn from(signer: &Ed25519LedgerTmAppSigner) -> PublicKey {
-        let app = signer.app.lock().unwrap();
-        PublicKey::from_bytes(&app.public_key().unwrap()).expect("invalid Ed25519 public key")
+    /// Get the public key that corresponds to the Tendermint Validator app
+    /// connected to this signer
+    pub fn public_key(&self) -> Result<PublicKey, LedgerError> {
+        let app = self.app.lock().unwrap();
+        PublicKey::from_bytes(&app.public_key()?).map_err(|_| LedgerError::InvalidPK)
     }
 }
 
@@ -45,21 +48,37 @@ impl Signer<Signature> for Ed25519LedgerTmAppSigner {
     /// c: Compute a compact, fixed-sized signature of the given amino/json vote
     fn try_sign(&self, msg: &[u8]) -> Result<Signature, Error> {
         let app = self.app.lock().unwrap();
-        let sig = app.sign(&msg).map_err(Error::from_source)?;
+        let sig = app.sign(msg).map_err(Error::from_source)?;
         Ok(Signature::from(sig))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Ed25519LedgerTmAppSigner, PublicKey};
-    use signature::Signer;
+    use super::Ed25519LedgerTmAppSigner;
+    use crate::keyring::providers::ledgertm::mock::MockTransport;
+    use signature::{Signer, Verifier};
+
+    #[test]
+    fn sign_with_mock() {
+        let transport = MockTransport::new(&[0x42; 32]);
+        let expected_key = transport.keypair().public;
+        let signer = Ed25519LedgerTmAppSigner::new(super::TendermintValidatorApp::new(transport));
+
+        let public_key = signer.public_key().unwrap();
+        assert_eq!(public_key, expected_key);
+
+        // Messages longer than one APDU are sent in chunks
+        let message = [0x17; 600];
+        let signature = signer.try_sign(&message).unwrap();
+        assert!(public_key.verify(&message, &signature).is_ok());
+    }
 
     #[test]
     #[ignore]
     fn public_key() {
         let signer = Ed25519LedgerTmAppSigner::connect().unwrap();
-        let pk = PublicKey::from(&signer);
+        let pk = signer.public_key().unwrap();
         println!("PK {:0X?}", pk);
     }
 
@@ -126,7 +145,7 @@ mod tests {
         let signer = Ed25519LedgerTmAppSigner::connect().unwrap();
 
         // Get public key to initialize
-        let pk = PublicKey::from(&signer);
+        let pk = signer.public_key().unwrap();
         println!("PK {:0X?}", pk);
 
         for index in 50u8..254u8 {
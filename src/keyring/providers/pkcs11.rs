@@ -0,0 +1,787 @@
+//! PKCS#11 signing provider: supports HSMs which expose a PKCS#11 module
+//! (e.g. Thales, Utimaco, AWS CloudHSM, SoftHSM2).
+//!
+//! Consensus keys are Ed25519 (`CKM_EDDSA`) and account keys are secp256k1
+//! (`CKM_ECDSA`). Keys are located by `CKA_LABEL` and/or `CKA_ID`, and the
+//! public key is read from the matching public key object.
+
+use crate::{
+    chain,
+    config::provider::{
+        pkcs11::{Pkcs11Config, SigningKeyConfig},
+        KeyType,
+    },
+    error::{Error, ErrorKind::*},
+    keyring::{self, SigningProvider},
+    prelude::*,
+};
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    mechanism::Mechanism,
+    object::{self, Attribute, AttributeType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    slot::Slot,
+    types::AuthPin,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+};
+use tendermint::TendermintKey;
+
+/// DER-encoded OID for secp256k1 (1.3.132.0.10)
+const SECP256K1_OID: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+/// DER-encoded OID for Ed25519 (1.3.101.112)
+const ED25519_OID: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+
+/// DER-encoded `PrintableString` curve name used by some tokens for Ed25519
+const EDWARDS25519_NAME: &[u8] = b"\x13\x0cedwards25519";
+
+/// Create PKCS#11 signer objects from the given configuration
+pub fn init(chain_registry: &mut chain::Registry, configs: &[Pkcs11Config]) -> Result<(), Error> {
+    for config in configs {
+        let token = Token::open(config)?;
+
+        for key_config in &config.keys {
+            match key_config.key_type {
+                KeyType::Account => add_account_key(chain_registry, &token, key_config)?,
+                KeyType::Consensus => add_consensus_key(chain_registry, &token, key_config)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Add an account key (ECDSA/secp256k1) to the keychain
+fn add_account_key<S: TokenSession + 'static>(
+    chain_registry: &mut chain::Registry,
+    token: &Token<S>,
+    config: &SigningKeyConfig,
+) -> Result<(), Error> {
+    let (key, ec_point) = token.find_key(config, object::KeyType::EC, SECP256K1_OID, &[])?;
+
+    let public_key = k256::EncodedPoint::from_bytes(parse_ec_point(&ec_point, &[33, 65])?)
+        .map_err(|_| format_err!(InvalidKey, "invalid secp256k1 public key"))?
+        .compress();
+
+    let public_key = tendermint::PublicKey::from_raw_secp256k1(public_key.as_bytes())
+        .ok_or_else(|| format_err!(InvalidKey, "invalid secp256k1 public key"))?;
+
+    let signer = keyring::ecdsa::Signer::new(
+        SigningProvider::Pkcs11,
+        TendermintKey::AccountKey(public_key),
+        Box::new(EcdsaSigner {
+            token: token.clone(),
+            key,
+        }),
+    );
+
+    for chain_id in &config.chain_ids {
+        chain_registry.add_account_key(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Add a consensus key (Ed25519) to the keychain
+fn add_consensus_key<S: TokenSession + 'static>(
+    chain_registry: &mut chain::Registry,
+    token: &Token<S>,
+    config: &SigningKeyConfig,
+) -> Result<(), Error> {
+    let (key, ec_point) = token.find_key(
+        config,
+        object::KeyType::EC_EDWARDS,
+        ED25519_OID,
+        EDWARDS25519_NAME,
+    )?;
+
+    let public_key = tendermint::PublicKey::from_raw_ed25519(parse_ec_point(&ec_point, &[32])?)
+        .ok_or_else(|| format_err!(InvalidKey, "invalid Ed25519 public key"))?;
+
+    let signer = keyring::ed25519::Signer::new(
+        SigningProvider::Pkcs11,
+        TendermintKey::ConsensusKey(public_key),
+        Box::new(Ed25519Signer {
+            token: token.clone(),
+            key,
+        }),
+    );
+
+    for chain_id in &config.chain_ids {
+        chain_registry.add_consensus_key(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Operations on a PKCS#11 session used to find keys and sign with them.
+///
+/// Implemented by [`Session`], and by an in-memory token in tests.
+trait TokenSession: Send {
+    /// Object handle
+    type Object: Copy + Send + Sync + 'static;
+
+    /// Error type
+    type Error: std::fmt::Display;
+
+    /// Find the objects matching the given template
+    fn find_objects(&self, template: &[Attribute]) -> Result<Vec<Self::Object>, Self::Error>;
+
+    /// Get the given attributes of an object
+    fn get_attributes(
+        &self,
+        object: Self::Object,
+        attributes: &[AttributeType],
+    ) -> Result<Vec<Attribute>, Self::Error>;
+
+    /// Sign data with the given key and mechanism
+    fn sign(
+        &self,
+        mechanism: &Mechanism<'_>,
+        key: Self::Object,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Self::Error>;
+}
+
+impl TokenSession for Session {
+    type Object = ObjectHandle;
+    type Error = cryptoki::error::Error;
+
+    fn find_objects(&self, template: &[Attribute]) -> Result<Vec<ObjectHandle>, Self::Error> {
+        Session::find_objects(self, template)
+    }
+
+    fn get_attributes(
+        &self,
+        object: ObjectHandle,
+        attributes: &[AttributeType],
+    ) -> Result<Vec<Attribute>, Self::Error> {
+        Session::get_attributes(self, object, attributes)
+    }
+
+    fn sign(
+        &self,
+        mechanism: &Mechanism<'_>,
+        key: ObjectHandle,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Self::Error> {
+        Session::sign(self, mechanism, key, data)
+    }
+}
+
+/// Logged-in session with a PKCS#11 token.
+///
+/// PKCS#11 sessions can't be used concurrently, so signing operations on the
+/// same token are serialized.
+struct Token<S: TokenSession = Session> {
+    /// Description of the token (for error messages)
+    name: String,
+
+    /// Session with the token
+    session: Arc<Mutex<S>>,
+}
+
+impl<S: TokenSession> Clone for Token<S> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            session: self.session.clone(),
+        }
+    }
+}
+
+impl Token {
+    /// Load the PKCS#11 module and log in to the configured token
+    fn open(config: &Pkcs11Config) -> Result<Self, Error> {
+        let module = config.module.display();
+
+        let pkcs11 = Pkcs11::new(&config.module).map_err(|e| {
+            format_err!(
+                Pkcs11Error,
+                "couldn't load PKCS#11 module {}: {}",
+                module,
+                e
+            )
+        })?;
+
+        pkcs11
+            .initialize(CInitializeArgs::OsThreads)
+            .map_err(|e| format_err!(Pkcs11Error, "couldn't initialize {}: {}", module, e))?;
+
+        let slot = find_slot(&pkcs11, config)?;
+        let name = format!("{} (slot {})", module, slot.id());
+
+        let session = pkcs11
+            .open_ro_session(slot)
+            .map_err(|e| format_err!(Pkcs11Error, "couldn't open session with {}: {}", name, e))?;
+
+        let pin = config.pin()?;
+
+        session
+            .login(UserType::User, Some(&AuthPin::new(pin.as_str().to_owned())))
+            .map_err(|e| format_err!(Pkcs11Error, "couldn't log in to {}: {}", name, e))?;
+
+        info!("[keyring:pkcs11] logged in to {}", name);
+
+        Ok(Self {
+            name,
+            session: Arc::new(Mutex::new(session)),
+        })
+    }
+}
+
+impl<S: TokenSession> Token<S> {
+    /// Find a private key along with the `CKA_EC_POINT` of its corresponding
+    /// public key, checking it uses one of the given curves
+    fn find_key(
+        &self,
+        config: &SigningKeyConfig,
+        key_type: object::KeyType,
+        curve_oid: &[u8],
+        curve_name: &[u8],
+    ) -> Result<(S::Object, Vec<u8>), Error> {
+        if config.label.is_none() && config.id.is_none() {
+            fail!(
+                ConfigError,
+                "PKCS#11 keys must have a `label` and/or `id` ({})",
+                &self.name
+            );
+        }
+
+        let mut template = vec![Attribute::KeyType(key_type)];
+
+        if let Some(label) = &config.label {
+            template.push(Attribute::Label(label.as_bytes().to_vec()));
+        }
+
+        if let Some(id) = config.id_bytes()? {
+            template.push(Attribute::Id(id));
+        }
+
+        let session = self.session.lock().unwrap();
+
+        let private_key =
+            find_object(&*session, ObjectClass::PRIVATE_KEY, &template).map_err(|e| {
+                format_err!(
+                    InvalidKey,
+                    "{} in {}: {}",
+                    config.description(),
+                    &self.name,
+                    e
+                )
+            })?;
+
+        let public_key =
+            find_object(&*session, ObjectClass::PUBLIC_KEY, &template).map_err(|e| {
+                format_err!(
+                    InvalidKey,
+                    "{} in {}: {}",
+                    config.description(),
+                    &self.name,
+                    e
+                )
+            })?;
+
+        let attributes = session
+            .get_attributes(
+                public_key,
+                &[AttributeType::EcParams, AttributeType::EcPoint],
+            )
+            .map_err(|e| format_err!(Pkcs11Error, "couldn't read public key: {}", e))?;
+
+        let mut ec_params = None;
+        let mut ec_point = None;
+
+        for attribute in attributes {
+            match attribute {
+                Attribute::EcParams(params) => ec_params = Some(params),
+                Attribute::EcPoint(point) => ec_point = Some(point),
+                _ => (),
+            }
+        }
+
+        match ec_params {
+            Some(params) if params == curve_oid || params == curve_name => (),
+            _ => fail!(
+                InvalidKey,
+                "{} in {} uses an unsupported curve",
+                config.description(),
+                &self.name
+            ),
+        }
+
+        let ec_point = ec_point.ok_or_else(|| {
+            format_err!(
+                InvalidKey,
+                "{} in {} has no public key",
+                config.description(),
+                &self.name
+            )
+        })?;
+
+        Ok((private_key, ec_point))
+    }
+
+    /// Sign a message using the given key and mechanism
+    fn sign(
+        &self,
+        mechanism: &Mechanism<'_>,
+        key: S::Object,
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let session = self.session.lock().unwrap();
+
+        Ok(session
+            .sign(mechanism, key, msg)
+            .map_err(|e| format_err!(Pkcs11Error, "{} signing failed: {}", &self.name, e))?)
+    }
+}
+
+/// Find the slot configured by either `slot` or `token_label`
+fn find_slot(pkcs11: &Pkcs11, config: &Pkcs11Config) -> Result<Slot, Error> {
+    let slots = pkcs11
+        .get_slots_with_token()
+        .map_err(|e| format_err!(Pkcs11Error, "couldn't list slots: {}", e))?;
+
+    match (config.slot, &config.token_label) {
+        (Some(slot_id), None) => slots
+            .into_iter()
+            .find(|slot| slot.id() == slot_id)
+            .ok_or_else(|| format_err!(ConfigError, "no token in PKCS#11 slot {}", slot_id).into()),
+        (None, Some(label)) => {
+            for slot in slots {
+                let info = pkcs11
+                    .get_token_info(slot)
+                    .map_err(|e| format_err!(Pkcs11Error, "couldn't get token info: {}", e))?;
+
+                // Token labels are padded with spaces
+                if info.label().trim_end() == label {
+                    return Ok(slot);
+                }
+            }
+
+            fail!(ConfigError, "no PKCS#11 token with label {:?}", label)
+        }
+        _ => fail!(
+            ConfigError,
+            "[[providers.pkcs11]] must specify exactly one of `slot` or `token_label`"
+        ),
+    }
+}
+
+/// Find exactly one object of the given class matching the template
+fn find_object<S: TokenSession>(
+    session: &S,
+    class: ObjectClass,
+    template: &[Attribute],
+) -> Result<S::Object, Error> {
+    let mut template = template.to_vec();
+    template.push(Attribute::Class(class));
+
+    let objects = session
+        .find_objects(&template)
+        .map_err(|e| format_err!(Pkcs11Error, "couldn't search for {}: {}", class, e))?;
+
+    match objects.as_slice() {
+        [object] => Ok(*object),
+        [] => fail!(InvalidKey, "no matching {} found", class),
+        _ => fail!(
+            InvalidKey,
+            "{} matching {} objects found (must be unique)",
+            objects.len(),
+            class
+        ),
+    }
+}
+
+/// Parse a `CKA_EC_POINT` value, which the spec says is a DER-encoded
+/// `OCTET STRING` but some tokens return as a bare point
+fn parse_ec_point<'a>(ec_point: &'a [u8], point_lengths: &[usize]) -> Result<&'a [u8], Error> {
+    if ec_point.len() > 2
+        && ec_point[0] == 0x04
+        && usize::from(ec_point[1]) == ec_point.len() - 2
+        && point_lengths.contains(&(ec_point.len() - 2))
+    {
+        Ok(&ec_point[2..])
+    } else if point_lengths.contains(&ec_point.len()) {
+        Ok(ec_point)
+    } else {
+        fail!(
+            InvalidKey,
+            "malformed CKA_EC_POINT ({} bytes)",
+            ec_point.len()
+        )
+    }
+}
+
+/// Ed25519 signer for a key stored in a PKCS#11 token
+struct Ed25519Signer<S: TokenSession = Session> {
+    /// Token containing the key
+    token: Token<S>,
+
+    /// Private key handle
+    key: S::Object,
+}
+
+impl<S: TokenSession> signature::Signer<keyring::ed25519::Signature> for Ed25519Signer<S> {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ed25519::Signature, signature::Error> {
+        let signature = self
+            .token
+            .sign(&Mechanism::Eddsa, self.key, msg)
+            .map_err(signature::Error::from_source)?;
+
+        keyring::ed25519::Signature::try_from(signature.as_slice())
+    }
+}
+
+/// ECDSA/secp256k1 signer for a key stored in a PKCS#11 token
+struct EcdsaSigner<S: TokenSession = Session> {
+    /// Token containing the key
+    token: Token<S>,
+
+    /// Private key handle
+    key: S::Object,
+}
+
+impl<S: TokenSession> signature::Signer<keyring::ecdsa::Signature> for EcdsaSigner<S> {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ecdsa::Signature, signature::Error> {
+        // `CKM_ECDSA` signs a prehashed message
+        let digest = Sha256::digest(msg);
+
+        let signature = self
+            .token
+            .sign(&Mechanism::Ecdsa, self.key, &digest)
+            .map_err(signature::Error::from_source)?;
+
+        let mut signature = keyring::ecdsa::Signature::try_from(signature.as_slice())?;
+
+        // Tendermint/Cosmos require low-S signatures
+        signature.normalize_s()?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_utils;
+    use ecdsa::hazmat::RecoverableSignPrimitive;
+    use k256::{elliptic_curve::Field, FieldBytes, Scalar};
+    use rand_core::OsRng;
+    use signature::{Signer as _, Verifier as _};
+    use std::{convert::Infallible, env, fs};
+    use tempfile::TempDir;
+
+    /// Environment variable with the path to the SoftHSM2 PKCS#11 module
+    const SOFTHSM2_MODULE_ENV_VAR: &str = "TMKMS_SOFTHSM2_MODULE";
+
+    const TEST_PIN: &str = "1234";
+
+    /// Create a SoftHSM2 token containing an Ed25519 and a secp256k1 key,
+    /// returning a provider config for it
+    fn softhsm2_token(dir: &TempDir) -> Pkcs11Config {
+        let module = env::var(SOFTHSM2_MODULE_ENV_VAR)
+            .unwrap_or_else(|_| "/usr/lib/softhsm/libsofthsm2.so".to_owned());
+
+        let tokens_dir = dir.path().join("tokens");
+        let conf_path = dir.path().join("softhsm2.conf");
+        let pin_file = dir.path().join("pin");
+
+        fs::create_dir(&tokens_dir).unwrap();
+        fs::write(
+            &conf_path,
+            format!("directories.tokendir = {}\n", tokens_dir.display()),
+        )
+        .unwrap();
+        key_utils::write_secret_file(&pin_file, format!("{}\n", TEST_PIN).as_bytes()).unwrap();
+        env::set_var("SOFTHSM2_CONF", &conf_path);
+
+        let pkcs11 = Pkcs11::new(&module).unwrap();
+        pkcs11.initialize(CInitializeArgs::OsThreads).unwrap();
+
+        let slot = pkcs11.get_slots_with_token().unwrap()[0];
+        let pin = AuthPin::new(TEST_PIN.to_owned());
+        pkcs11.init_token(slot, &pin, "tmkms-test").unwrap();
+
+        let session = pkcs11.open_rw_session(slot).unwrap();
+        session.login(UserType::So, Some(&pin)).unwrap();
+        session.init_pin(&pin).unwrap();
+        session.logout().unwrap();
+        session.login(UserType::User, Some(&pin)).unwrap();
+
+        let keys = [
+            (Mechanism::EccEdwardsKeyPairGen, ED25519_OID, "consensus"),
+            (Mechanism::EccKeyPairGen, SECP256K1_OID, "account"),
+        ];
+
+        for (mechanism, curve_oid, label) in keys.iter() {
+            let label = Attribute::Label(label.as_bytes().to_vec());
+
+            session
+                .generate_key_pair(
+                    mechanism,
+                    &[
+                        Attribute::Token(true),
+                        Attribute::Verify(true),
+                        Attribute::EcParams(curve_oid.to_vec()),
+                        label.clone(),
+                    ],
+                    &[
+                        Attribute::Token(true),
+                        Attribute::Private(true),
+                        Attribute::Sensitive(true),
+                        Attribute::Sign(true),
+                        label,
+                    ],
+                )
+                .unwrap();
+        }
+
+        // The module is reinitialized by `Token::open`
+        drop(session);
+        pkcs11.finalize();
+
+        Pkcs11Config {
+            module: module.into(),
+            slot: None,
+            token_label: Some("tmkms-test".to_owned()),
+            pin_file,
+            keys: vec![],
+        }
+    }
+
+    /// Software key in a [`MockSession`]
+    enum MockKey {
+        Ed25519(keyring::ed25519::Keypair),
+        Secp256k1(k256::SecretKey),
+    }
+
+    /// In-memory PKCS#11 session with a token containing a private key object
+    /// and a public key object for each key.
+    ///
+    /// The private key of `keys[n]` has handle `2 * n` and its public key
+    /// has handle `2 * n + 1`.
+    struct MockSession {
+        /// Labels and keys of the token
+        keys: Vec<(&'static str, MockKey)>,
+    }
+
+    impl MockSession {
+        /// Does the given object match the attribute of a template?
+        fn matches(&self, object: usize, attribute: &Attribute) -> bool {
+            let (label, key) = &self.keys[object / 2];
+
+            match attribute {
+                Attribute::Class(class) if object % 2 == 0 => *class == ObjectClass::PRIVATE_KEY,
+                Attribute::Class(class) => *class == ObjectClass::PUBLIC_KEY,
+                Attribute::KeyType(key_type) => match key {
+                    MockKey::Ed25519(_) => *key_type == object::KeyType::EC_EDWARDS,
+                    MockKey::Secp256k1(_) => *key_type == object::KeyType::EC,
+                },
+                Attribute::Label(value) => value.as_slice() == label.as_bytes(),
+                Attribute::Id(_) => false,
+                _ => panic!("unsupported template attribute: {:?}", attribute),
+            }
+        }
+    }
+
+    impl TokenSession for MockSession {
+        type Object = usize;
+        type Error = Infallible;
+
+        fn find_objects(&self, template: &[Attribute]) -> Result<Vec<usize>, Infallible> {
+            Ok((0..self.keys.len() * 2)
+                .filter(|&object| {
+                    template
+                        .iter()
+                        .all(|attribute| self.matches(object, attribute))
+                })
+                .collect())
+        }
+
+        fn get_attributes(
+            &self,
+            object: usize,
+            attributes: &[AttributeType],
+        ) -> Result<Vec<Attribute>, Infallible> {
+            assert_eq!(object % 2, 1, "private key attributes can't be read");
+
+            let (ec_params, point) = match &self.keys[object / 2].1 {
+                MockKey::Ed25519(keypair) => (ED25519_OID, keypair.public.as_bytes().to_vec()),
+                MockKey::Secp256k1(secret_key) => (
+                    SECP256K1_OID,
+                    k256::EncodedPoint::from_secret_key(secret_key, true)
+                        .as_bytes()
+                        .to_vec(),
+                ),
+            };
+
+            // DER-encoded `OCTET STRING`
+            let mut ec_point = vec![0x04, point.len() as u8];
+            ec_point.extend_from_slice(&point);
+
+            Ok([
+                Attribute::EcParams(ec_params.to_vec()),
+                Attribute::EcPoint(ec_point),
+            ]
+            .iter()
+            .filter(|attribute| attributes.contains(&attribute.attribute_type()))
+            .cloned()
+            .collect())
+        }
+
+        fn sign(
+            &self,
+            mechanism: &Mechanism<'_>,
+            key: usize,
+            data: &[u8],
+        ) -> Result<Vec<u8>, Infallible> {
+            assert_eq!(key % 2, 0, "public keys can't sign");
+
+            let signature = match (mechanism, &self.keys[key / 2].1) {
+                (Mechanism::Eddsa, MockKey::Ed25519(keypair)) => {
+                    keypair.sign(data).to_bytes().to_vec()
+                }
+                (Mechanism::Ecdsa, MockKey::Secp256k1(secret_key)) => {
+                    let digest = Scalar::from_bytes_reduced(FieldBytes::from_slice(data));
+                    let (signature, _) = secret_key
+                        .secret_scalar()
+                        .try_sign_recoverable_prehashed(&Scalar::random(&mut OsRng), &digest)
+                        .unwrap();
+
+                    signature.as_ref().to_vec()
+                }
+                _ => panic!("key doesn't support {:?}", mechanism),
+            };
+
+            Ok(signature)
+        }
+    }
+
+    /// Create a mock token containing an Ed25519 and a secp256k1 key
+    fn mock_token() -> Token<MockSession> {
+        let keys = vec![
+            (
+                "consensus",
+                MockKey::Ed25519(key_utils::ed25519_keypair(&[0x42; 32]).unwrap()),
+            ),
+            (
+                "account",
+                MockKey::Secp256k1(k256::SecretKey::from_bytes(&[0x42; 32]).unwrap()),
+            ),
+        ];
+
+        Token {
+            name: "mock token".to_owned(),
+            session: Arc::new(Mutex::new(MockSession { keys })),
+        }
+    }
+
+    fn key_config(label: &str, key_type: KeyType) -> SigningKeyConfig {
+        SigningKeyConfig {
+            chain_ids: vec![],
+            label: Some(label.to_owned()),
+            id: None,
+            key_type,
+        }
+    }
+
+    #[test]
+    fn parses_ec_points() {
+        let point = [0x42u8; 32];
+        let mut der = vec![0x04, 0x20];
+        der.extend_from_slice(&point);
+
+        assert_eq!(parse_ec_point(&der, &[32]).unwrap(), &point);
+        assert_eq!(parse_ec_point(&point, &[32]).unwrap(), &point);
+        assert!(parse_ec_point(&point[..31], &[32]).is_err());
+
+        // Bare uncompressed point whose second byte looks like a DER length
+        let mut point = [0x3fu8; 65];
+        point[0] = 0x04;
+        assert_eq!(parse_ec_point(&point, &[33, 65]).unwrap(), &point[..]);
+    }
+
+    /// Find the test keys in the given token and sign with them
+    fn check_signing<S: TokenSession>(token: &Token<S>) {
+        let msg = b"tendermint kms pkcs11 test";
+
+        let (key, ec_point) = token
+            .find_key(
+                &key_config("consensus", KeyType::Consensus),
+                object::KeyType::EC_EDWARDS,
+                ED25519_OID,
+                EDWARDS25519_NAME,
+            )
+            .unwrap();
+
+        let public_key =
+            keyring::ed25519::PublicKey::from_bytes(parse_ec_point(&ec_point, &[32]).unwrap())
+                .unwrap();
+
+        let signer = Ed25519Signer {
+            token: token.clone(),
+            key,
+        };
+
+        public_key.verify(msg, &signer.sign(msg)).unwrap();
+
+        let (key, ec_point) = token
+            .find_key(
+                &key_config("account", KeyType::Account),
+                object::KeyType::EC,
+                SECP256K1_OID,
+                &[],
+            )
+            .unwrap();
+
+        let public_key = k256::ecdsa::VerifyingKey::from_sec1_bytes(
+            parse_ec_point(&ec_point, &[33, 65]).unwrap(),
+        )
+        .unwrap();
+
+        let signer = EcdsaSigner {
+            token: token.clone(),
+            key,
+        };
+
+        public_key.verify(msg, &signer.sign(msg)).unwrap();
+
+        // Keys must have the expected type
+        assert!(token
+            .find_key(
+                &key_config("consensus", KeyType::Account),
+                object::KeyType::EC,
+                SECP256K1_OID,
+                &[],
+            )
+            .is_err());
+
+        // Keys must exist
+        assert!(token
+            .find_key(
+                &key_config("validator", KeyType::Consensus),
+                object::KeyType::EC_EDWARDS,
+                ED25519_OID,
+                EDWARDS25519_NAME,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn signs_with_mock_session() {
+        check_signing(&mock_token());
+    }
+
+    /// Run with: `cargo test --features=pkcs11 -- --ignored softhsm2`
+    #[test]
+    #[ignore]
+    fn signs_with_softhsm2() {
+        let dir = TempDir::new().unwrap();
+        check_signing(&Token::open(&softhsm2_token(&dir)).unwrap());
+    }
+}
@@ -0,0 +1,526 @@
+//! External signer plugins: integrate signing backends which aren't built
+//! into the KMS (e.g. proprietary HSMs) without modifying it.
+//!
+//! A plugin is either a child process which speaks the plugin protocol over
+//! its stdin/stdout, or a service listening on a Unix domain socket. See
+//! `README.plugins.md` for the protocol specification.
+
+pub mod protocol;
+
+use self::protocol::{Algorithm, Method, PublicKeyResult, Request, Response, SignResult};
+use crate::{
+    chain,
+    config::provider::{
+        plugin::{PluginConfig, SigningKeyConfig},
+        KeyType,
+    },
+    error::{Error, ErrorKind::*},
+    keyring::{self, SigningProvider},
+    prelude::*,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    process::{Child, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tendermint::TendermintKey;
+
+/// Create plugin signer objects from the given configuration
+pub fn init(chain_registry: &mut chain::Registry, configs: &[PluginConfig]) -> Result<(), Error> {
+    for config in configs {
+        let plugin = Arc::new(Plugin::new(config)?);
+
+        for key_config in &config.keys {
+            match key_config.key_type {
+                KeyType::Account => add_account_key(chain_registry, &plugin, key_config)?,
+                KeyType::Consensus => add_consensus_key(chain_registry, &plugin, key_config)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Add an account key (ECDSA/secp256k1) to the keychain
+fn add_account_key(
+    chain_registry: &mut chain::Registry,
+    plugin: &Arc<Plugin>,
+    config: &SigningKeyConfig,
+) -> Result<(), Error> {
+    let public_key = plugin.public_key(&config.key, Algorithm::Secp256k1)?;
+
+    let public_key = k256::EncodedPoint::from_bytes(&public_key)
+        .ok()
+        .and_then(|point| tendermint::PublicKey::from_raw_secp256k1(point.compress().as_bytes()))
+        .ok_or_else(|| {
+            format_err!(
+                InvalidKey,
+                "plugin {} returned invalid secp256k1 key for {:?}",
+                &plugin.name,
+                &config.key
+            )
+        })?;
+
+    let signer = keyring::ecdsa::Signer::new(
+        SigningProvider::Plugin,
+        TendermintKey::AccountKey(public_key),
+        Box::new(PluginKey::new(plugin, &config.key)),
+    );
+
+    for chain_id in &config.chain_ids {
+        chain_registry.add_account_key(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Add a consensus key (Ed25519) to the keychain
+fn add_consensus_key(
+    chain_registry: &mut chain::Registry,
+    plugin: &Arc<Plugin>,
+    config: &SigningKeyConfig,
+) -> Result<(), Error> {
+    let public_key = plugin.public_key(&config.key, Algorithm::Ed25519)?;
+
+    let public_key = tendermint::PublicKey::from_raw_ed25519(&public_key).ok_or_else(|| {
+        format_err!(
+            InvalidKey,
+            "plugin {} returned invalid Ed25519 key for {:?}",
+            &plugin.name,
+            &config.key
+        )
+    })?;
+
+    let signer = keyring::ed25519::Signer::new(
+        SigningProvider::Plugin,
+        TendermintKey::ConsensusKey(public_key),
+        Box::new(PluginKey::new(plugin, &config.key)),
+    );
+
+    for chain_id in &config.chain_ids {
+        chain_registry.add_consensus_key(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Where to find a plugin
+#[derive(Clone, Debug)]
+enum Transport {
+    /// Spawn a child process with the given command and arguments
+    Command(Vec<String>),
+
+    /// Connect to a Unix domain socket at the given path
+    Socket(std::path::PathBuf),
+}
+
+/// External signer plugin. Requests are made one at a time; if the plugin
+/// fails to respond it is restarted (or reconnected to) on the next request.
+pub struct Plugin {
+    /// Name of the plugin
+    name: String,
+
+    /// How to connect to the plugin
+    transport: Transport,
+
+    /// Timeout for each request
+    timeout: Duration,
+
+    /// Current connection to the plugin (if any) and the next request ID
+    state: Mutex<(Option<Connection>, u64)>,
+}
+
+impl Plugin {
+    /// Create a new plugin, starting or connecting to it
+    pub fn new(config: &PluginConfig) -> Result<Self, Error> {
+        let transport = match (&config.command, &config.socket) {
+            (Some(command), None) if !command.is_empty() => Transport::Command(command.clone()),
+            (None, Some(socket)) => Transport::Socket(socket.clone()),
+            _ => fail!(
+                ConfigError,
+                "[[providers.plugin]] {:?} must specify exactly one of `command` or `socket`",
+                &config.name
+            ),
+        };
+
+        let connection = Connection::open(&transport)?;
+        info!("[keyring:plugin] started plugin {}", &config.name);
+
+        Ok(Self {
+            name: config.name.clone(),
+            transport,
+            timeout: Duration::from_millis(config.timeout_ms),
+            state: Mutex::new((Some(connection), 0)),
+        })
+    }
+
+    /// Get the public key for the given key ID, checking it's for the
+    /// expected algorithm
+    pub fn public_key(&self, key: &str, algorithm: Algorithm) -> Result<Vec<u8>, Error> {
+        let result: PublicKeyResult = self.call(Method::GetPublicKey {
+            key: key.to_owned(),
+        })?;
+
+        if result.algorithm != algorithm {
+            fail!(
+                InvalidKey,
+                "plugin {} key {:?} is {:?} (expected {:?})",
+                &self.name,
+                key,
+                result.algorithm,
+                algorithm
+            );
+        }
+
+        protocol::decode(&result.public_key)
+    }
+
+    /// Sign a message with the given key ID
+    pub fn sign(&self, key: &str, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let result: SignResult = self.call(Method::sign(key, msg))?;
+        protocol::decode(&result.signature)
+    }
+
+    /// Make a request to the plugin
+    fn call<T: DeserializeOwned>(&self, method: Method) -> Result<T, Error> {
+        let mut state = self.state.lock().unwrap();
+        let (connection, next_id) = &mut *state;
+
+        let id = *next_id;
+        *next_id += 1;
+
+        if connection.is_none() {
+            warn!("[keyring:plugin] restarting plugin {}", &self.name);
+            *connection = Some(Connection::open(&self.transport)?);
+        }
+
+        let result = connection
+            .as_mut()
+            .unwrap()
+            .call(Request { id, method }, self.timeout);
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                // The connection is in an unknown state (e.g. a response may
+                // still arrive), so discard it
+                *connection = None;
+                fail!(SigningError, "plugin {} request failed: {}", &self.name, e);
+            }
+        };
+
+        if let Some(error) = response.error {
+            fail!(SigningError, "plugin {} error: {}", &self.name, error);
+        }
+
+        let result = response.result.ok_or_else(|| {
+            format_err!(ProtocolError, "plugin {} returned no result", &self.name)
+        })?;
+
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+/// Connection to a running plugin
+struct Connection {
+    /// Writer for sending requests
+    writer: Box<dyn Write + Send>,
+
+    /// Responses read by the reader thread
+    responses: Receiver<Result<Response, Error>>,
+
+    /// Child process (if the plugin was spawned by us)
+    child: Option<Child>,
+}
+
+impl Connection {
+    /// Spawn or connect to a plugin
+    fn open(transport: &Transport) -> Result<Self, Error> {
+        let (writer, reader, child): (Box<dyn Write + Send>, Box<dyn Read + Send>, _) =
+            match transport {
+                Transport::Command(command) => {
+                    let mut child = Command::new(&command[0])
+                        .args(&command[1..])
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::inherit())
+                        .spawn()
+                        .map_err(|e| {
+                            format_err!(IoError, "couldn't spawn plugin {:?}: {}", &command[0], e)
+                        })?;
+
+                    let stdin = child.stdin.take().unwrap();
+                    let stdout = child.stdout.take().unwrap();
+                    (Box::new(stdin), Box::new(stdout), Some(child))
+                }
+                Transport::Socket(path) => {
+                    let socket = UnixStream::connect(path).map_err(|e| {
+                        format_err!(
+                            IoError,
+                            "couldn't connect to plugin at {}: {}",
+                            path.display(),
+                            e
+                        )
+                    })?;
+
+                    (Box::new(socket.try_clone()?), Box::new(socket), None)
+                }
+            };
+
+        let (sender, responses) = mpsc::channel();
+
+        // Read responses from a separate thread so requests can time out
+        thread::Builder::new()
+            .name("plugin-reader".to_owned())
+            .spawn(move || {
+                let mut reader = reader;
+
+                loop {
+                    let response = protocol::read_message(&mut reader);
+                    let failed = response.is_err();
+
+                    if sender.send(response).is_err() || failed {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            writer,
+            responses,
+            child,
+        })
+    }
+
+    /// Send a request and wait for the corresponding response
+    fn call(&mut self, request: Request, timeout: Duration) -> Result<Response, Error> {
+        protocol::write_message(&mut self.writer, &request)?;
+
+        let response = match self.responses.recv_timeout(timeout) {
+            Ok(response) => response?,
+            Err(RecvTimeoutError::Timeout) => {
+                fail!(IoError, "timed out after {}ms", timeout.as_millis())
+            }
+            Err(RecvTimeoutError::Disconnected) => fail!(IoError, "plugin exited"),
+        };
+
+        if response.id != request.id {
+            fail!(
+                ProtocolError,
+                "response ID {} doesn't match request ID {}",
+                response.id,
+                request.id
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            // The plugin may have already exited
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Signer for a key provided by a plugin
+struct PluginKey {
+    /// Plugin providing the key
+    plugin: Arc<Plugin>,
+
+    /// Key ID
+    key: String,
+}
+
+impl PluginKey {
+    /// Create a signer for the given key ID
+    fn new(plugin: &Arc<Plugin>, key: &str) -> Self {
+        Self {
+            plugin: plugin.clone(),
+            key: key.to_owned(),
+        }
+    }
+}
+
+impl signature::Signer<keyring::ed25519::Signature> for PluginKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ed25519::Signature, signature::Error> {
+        let signature = self
+            .plugin
+            .sign(&self.key, msg)
+            .map_err(signature::Error::from_source)?;
+
+        keyring::ed25519::Signature::try_from(signature.as_slice())
+    }
+}
+
+impl signature::Signer<keyring::ecdsa::Signature> for PluginKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ecdsa::Signature, signature::Error> {
+        let signature = self
+            .plugin
+            .sign(&self.key, msg)
+            .map_err(signature::Error::from_source)?;
+
+        let mut signature = keyring::ecdsa::Signature::try_from(signature.as_slice())?;
+
+        // Tendermint/Cosmos require low-S signatures
+        signature.normalize_s()?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use signature::{Signer as _, Verifier as _};
+    use std::os::unix::net::UnixListener;
+    use tempfile::TempDir;
+
+    /// Run a plugin service on a Unix socket which signs with a fixed key
+    fn spawn_plugin_service(path: &std::path::Path) {
+        let listener = UnixListener::bind(path).unwrap();
+
+        thread::spawn(move || {
+            for socket in listener.incoming() {
+                let socket = socket.unwrap();
+                thread::spawn(move || serve_plugin_connection(socket));
+            }
+        });
+    }
+
+    /// Answer requests on a single plugin connection
+    fn serve_plugin_connection(mut socket: UnixStream) {
+        let secret = keyring::ed25519::SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let keypair = keyring::ed25519::Keypair {
+            public: (&secret).into(),
+            secret,
+        };
+
+        while let Ok(request) = protocol::read_message::<Request>(&mut socket) {
+            let (result, error) = match request.method {
+                Method::GetPublicKey { key } if key == "consensus" => (
+                    Some(json!({
+                        "algorithm": "ed25519",
+                        "public_key": protocol::encode(keypair.public.as_bytes())
+                    })),
+                    None,
+                ),
+                Method::Sign { key, msg } if key == "consensus" => {
+                    let msg = protocol::decode(&msg).unwrap();
+
+                    // Simulate a hung plugin
+                    if msg == b"hang" {
+                        thread::sleep(Duration::from_millis(500));
+                    }
+
+                    let signature = keypair.sign(&msg).to_bytes();
+                    (
+                        Some(json!({ "signature": protocol::encode(&signature) })),
+                        None,
+                    )
+                }
+                _ => (None, Some("unknown key".to_owned())),
+            };
+
+            let response = Response {
+                id: request.id,
+                result,
+                error,
+            };
+
+            if protocol::write_message(&mut socket, &response).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn socket_config(path: &std::path::Path) -> PluginConfig {
+        PluginConfig {
+            name: "test".to_owned(),
+            command: None,
+            socket: Some(path.to_owned()),
+            timeout_ms: 100,
+            keys: vec![],
+        }
+    }
+
+    #[test]
+    fn signs_via_socket() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("plugin.sock");
+        spawn_plugin_service(&path);
+
+        let plugin = Arc::new(Plugin::new(&socket_config(&path)).unwrap());
+        let public_key = plugin.public_key("consensus", Algorithm::Ed25519).unwrap();
+        let public_key = keyring::ed25519::PublicKey::from_bytes(&public_key).unwrap();
+
+        let signer = PluginKey::new(&plugin, "consensus");
+        let msg = b"tendermint kms plugin test";
+        let signature: keyring::ed25519::Signature = signer.try_sign(msg).unwrap();
+        public_key.verify(msg, &signature).unwrap();
+
+        // Errors reported by the plugin
+        assert!(plugin.public_key("missing", Algorithm::Ed25519).is_err());
+        assert!(plugin
+            .public_key("consensus", Algorithm::Secp256k1)
+            .is_err());
+
+        // Timeouts discard the connection, which is reestablished on the next
+        // request
+        assert!(plugin.sign("consensus", b"hang").is_err());
+        assert!(plugin.state.lock().unwrap().0.is_none());
+        let signature: keyring::ed25519::Signature = signer.try_sign(msg).unwrap();
+        public_key.verify(msg, &signature).unwrap();
+    }
+
+    #[test]
+    fn spawns_child_process() {
+        // Plugin which responds to the first request with a fixed public key
+        let response = serde_json::to_vec(&json!({
+            "id": 0,
+            "result": { "algorithm": "ed25519", "public_key": protocol::encode(&[1u8; 32]) }
+        }))
+        .unwrap();
+
+        let mut framed = (response.len() as u32).to_be_bytes().to_vec();
+        framed.extend_from_slice(&response);
+
+        let escaped = framed
+            .iter()
+            .map(|byte| format!("\\{:03o}", byte))
+            .collect::<String>();
+
+        let config = PluginConfig {
+            name: "test".to_owned(),
+            command: Some(vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                format!("printf '{}'; cat > /dev/null", escaped),
+            ]),
+            socket: None,
+            timeout_ms: 1000,
+            keys: vec![],
+        };
+
+        let plugin = Plugin::new(&config).unwrap();
+        assert_eq!(
+            plugin.public_key("consensus", Algorithm::Ed25519).unwrap(),
+            vec![1u8; 32]
+        );
+
+        // The plugin doesn't answer subsequent requests
+        assert!(plugin.sign("consensus", b"hello").is_err());
+    }
+}
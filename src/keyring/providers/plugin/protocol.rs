@@ -0,0 +1,172 @@
+//! Plugin wire protocol.
+//!
+//! Messages are JSON objects, each prefixed with its length as a 32-bit
+//! big endian integer. See `README.plugins.md` for the full specification.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+};
+use subtle_encoding::base64;
+
+/// Maximum size of a message (excluding the length prefix)
+pub const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Request sent to a plugin
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Request {
+    /// Request ID, echoed back in the response
+    pub id: u64,
+
+    /// Method being invoked and its parameters
+    #[serde(flatten)]
+    pub method: Method,
+}
+
+/// Methods supported by plugins
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Method {
+    /// Get the public key for the given key ID
+    GetPublicKey {
+        /// Key ID
+        key: String,
+    },
+
+    /// Sign a message with the given key ID
+    Sign {
+        /// Key ID
+        key: String,
+
+        /// Base64-encoded message to be signed
+        msg: String,
+    },
+}
+
+impl Method {
+    /// Create a `sign` request for the given message
+    pub fn sign(key: &str, msg: &[u8]) -> Self {
+        Method::Sign {
+            key: key.to_owned(),
+            msg: encode(msg),
+        }
+    }
+}
+
+/// Response from a plugin
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Response {
+    /// ID of the request this is a response to
+    pub id: u64,
+
+    /// Result of a successful request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+
+    /// Error message for an unsuccessful request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of a `get_public_key` request
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PublicKeyResult {
+    /// Signature algorithm for this key
+    pub algorithm: Algorithm,
+
+    /// Base64-encoded public key: 32-byte Ed25519 key or SEC1-encoded
+    /// (compressed or uncompressed) secp256k1 point
+    pub public_key: String,
+}
+
+/// Result of a `sign` request
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SignResult {
+    /// Base64-encoded signature: 64-byte Ed25519 signature or raw `r || s`
+    /// ECDSA signature over the SHA-256 digest of the message
+    pub signature: String,
+}
+
+/// Signature algorithms
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// Ed25519 (consensus keys)
+    Ed25519,
+
+    /// ECDSA/secp256k1 (account keys)
+    Secp256k1,
+}
+
+/// Write a length-prefixed message
+pub fn write_message(writer: &mut impl Write, msg: &impl Serialize) -> Result<(), Error> {
+    let json = serde_json::to_vec(msg)?;
+
+    if json.len() > MAX_MESSAGE_SIZE {
+        fail!(ProtocolError, "message too large: {} bytes", json.len());
+    }
+
+    let len = u32::try_from(json.len()).unwrap();
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&json)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a length-prefixed message
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T, Error> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+
+    let len = u32::from_be_bytes(len) as usize;
+
+    if len > MAX_MESSAGE_SIZE {
+        fail!(ProtocolError, "message too large: {} bytes", len);
+    }
+
+    let mut json = vec![0u8; len];
+    reader.read_exact(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Encode bytes as Base64
+pub fn encode(bytes: &[u8]) -> String {
+    String::from_utf8(base64::encode(bytes)).unwrap()
+}
+
+/// Decode Base64 bytes
+pub fn decode(encoded: &str) -> Result<Vec<u8>, Error> {
+    base64::decode(encoded).map_err(|_| format_err!(ProtocolError, "invalid Base64").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_messages() {
+        let request = Request {
+            id: 7,
+            method: Method::sign("consensus", b"hello"),
+        };
+
+        let mut buf = vec![];
+        write_message(&mut buf, &request).unwrap();
+
+        let json = br#"{"id":7,"method":"sign","params":{"key":"consensus","msg":"aGVsbG8="}}"#;
+        assert_eq!(&buf[..4], &(json.len() as u32).to_be_bytes());
+        assert_eq!(&buf[4..], &json[..]);
+
+        let decoded: Request = read_message(&mut buf.as_slice()).unwrap();
+        assert_eq!(decoded.id, 7);
+
+        // Oversized messages are rejected without being read
+        let oversized = ((MAX_MESSAGE_SIZE + 1) as u32).to_be_bytes();
+        assert!(read_message::<Request>(&mut &oversized[..]).is_err());
+    }
+}
@@ -0,0 +1,529 @@
+//! Remote KMS signing provider: forwards signing requests to an upstream KMS
+//! running in listen mode (i.e. signer chaining).
+//!
+//! The upstream KMS holds the keys and enforces its own double signing
+//! protection. Chain state is still tracked locally, so this (edge) KMS
+//! refuses to double sign even if the upstream KMS were to allow it.
+
+use crate::{
+    chain,
+    config::provider::remote::RemoteConfig,
+    connection::tcp,
+    error::{Error, ErrorKind::*},
+    keyring::{self, SigningProvider},
+    prelude::*,
+    remote::protocol::{self, Request, Response},
+};
+use signature::Verifier;
+use std::{
+    convert::TryFrom,
+    net::TcpStream,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tendermint::{net, node, TendermintKey};
+use tendermint_p2p::secret_connection::{self, SecretConnection};
+
+/// Create remote KMS signer objects from the given configuration
+pub fn init(chain_registry: &mut chain::Registry, configs: &[RemoteConfig]) -> Result<(), Error> {
+    for config in configs {
+        let upstream = Arc::new(Upstream::new(config)?);
+
+        for chain_id in &config.chain_ids {
+            let public_key = upstream.public_key(chain_id)?;
+
+            let signer = keyring::ed25519::Signer::new(
+                SigningProvider::Remote,
+                TendermintKey::ConsensusKey(public_key.into()),
+                Box::new(RemoteKey {
+                    upstream: upstream.clone(),
+                    chain_id: chain_id.clone(),
+                    public_key,
+                }),
+            );
+
+            chain_registry.add_consensus_key(chain_id, signer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Upstream KMS
+pub struct Upstream {
+    /// Address of the upstream KMS
+    addr: net::Address,
+
+    /// Host of the upstream KMS
+    host: String,
+
+    /// Port of the upstream KMS
+    port: u16,
+
+    /// Expected node ID of the upstream KMS
+    peer_id: node::Id,
+
+    /// Path to our identity key
+    secret_key: PathBuf,
+
+    /// Socket timeout in seconds
+    timeout: Option<u16>,
+
+    /// Connection to the upstream KMS (opened on demand)
+    connection: Mutex<Option<SecretConnection<TcpStream>>>,
+}
+
+impl Upstream {
+    /// Create a new upstream KMS from the given configuration, connecting to
+    /// it on the first request
+    pub fn new(config: &RemoteConfig) -> Result<Self, Error> {
+        let (peer_id, host, port) = match &config.addr {
+            net::Address::Tcp {
+                peer_id: Some(peer_id),
+                host,
+                port,
+            } => (*peer_id, host.clone(), *port),
+            _ => fail!(
+                ConfigError,
+                "[[providers.remote]] addr must include the upstream KMS node ID \
+                 (i.e. `tcp://<node ID>@host:port`): {}",
+                &config.addr
+            ),
+        };
+
+        Ok(Self {
+            addr: config.addr.clone(),
+            host,
+            port,
+            peer_id,
+            secret_key: config.secret_key.clone(),
+            timeout: config.timeout,
+            connection: Mutex::new(None),
+        })
+    }
+
+    /// Get the upstream KMS's consensus public key for the given chain
+    pub fn public_key(&self, chain_id: &chain::Id) -> Result<keyring::ed25519::PublicKey, Error> {
+        let response = self.call(&Request {
+            chain_id: chain_id.to_string(),
+            sign_bytes: vec![],
+        })?;
+
+        keyring::ed25519::PublicKey::from_bytes(&response.public_key).map_err(|_| {
+            format_err!(
+                InvalidKey,
+                "upstream KMS {} returned invalid public key for {}",
+                &self.addr,
+                chain_id
+            )
+            .into()
+        })
+    }
+
+    /// Request a signature over the given sign bytes
+    pub fn sign(
+        &self,
+        chain_id: &chain::Id,
+        sign_bytes: &[u8],
+    ) -> Result<keyring::ed25519::Signature, Error> {
+        let response = self.call(&Request {
+            chain_id: chain_id.to_string(),
+            sign_bytes: sign_bytes.to_vec(),
+        })?;
+
+        keyring::ed25519::Signature::try_from(response.signature.as_slice()).map_err(|_| {
+            format_err!(
+                SigningError,
+                "upstream KMS {} returned malformed signature",
+                &self.addr
+            )
+            .into()
+        })
+    }
+
+    /// Send a request to the upstream KMS
+    fn call(&self, request: &Request) -> Result<Response, Error> {
+        let mut connection = self.connection.lock().unwrap();
+        let reconnect = connection.is_some();
+
+        let response = match self.try_call(&mut connection, request) {
+            // Retry once if an existing connection failed (e.g. the upstream
+            // KMS restarted). Repeating a request is safe, as signing the same
+            // message again is never a double sign.
+            Err(e) if reconnect => {
+                warn!(upstream = %self.addr, "reconnecting to upstream KMS: {}", e);
+                self.try_call(&mut connection, request)?
+            }
+            result => result?,
+        };
+
+        if !response.error.is_empty() {
+            fail!(
+                SigningError,
+                "upstream KMS {} error: {}",
+                &self.addr,
+                &response.error
+            );
+        }
+
+        Ok(response)
+    }
+
+    /// Send a request, connecting first if need be. Discards the connection
+    /// on error.
+    fn try_call(
+        &self,
+        connection: &mut Option<SecretConnection<TcpStream>>,
+        request: &Request,
+    ) -> Result<Response, Error> {
+        if connection.is_none() {
+            *connection = Some(self.connect()?);
+        }
+
+        let conn = connection.as_mut().unwrap();
+        let result =
+            protocol::write_message(conn, request).and_then(|()| protocol::read_message(conn));
+
+        if result.is_err() {
+            *connection = None;
+        }
+
+        result
+    }
+
+    /// Connect to the upstream KMS, verifying its node ID
+    fn connect(&self) -> Result<SecretConnection<TcpStream>, Error> {
+        debug!(upstream = %self.addr, "connecting to upstream KMS...");
+
+        let (connection, _) = tcp::open_secret_connection(
+            &self.host,
+            self.port,
+            &Some(self.secret_key.clone()),
+            &Some(self.peer_id),
+            self.timeout,
+            secret_connection::Version::V0_34,
+        )?;
+
+        info!(upstream = %self.addr, "connected to upstream KMS successfully");
+        Ok(connection)
+    }
+}
+
+/// Consensus key held by an upstream KMS
+struct RemoteKey {
+    /// Upstream KMS holding the key
+    upstream: Arc<Upstream>,
+
+    /// Chain the key is for
+    chain_id: chain::Id,
+
+    /// Public key, used to verify signatures returned by the upstream KMS
+    public_key: keyring::ed25519::PublicKey,
+}
+
+impl signature::Signer<keyring::ed25519::Signature> for RemoteKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ed25519::Signature, signature::Error> {
+        let signature = self
+            .upstream
+            .sign(&self.chain_id, msg)
+            .map_err(signature::Error::from_source)?;
+
+        self.public_key.verify(msg, &signature)?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        amino_types::{
+            BlockId, PartsSetHeader, SignVoteRequest, SignableMsg, SignedMsgType, TimeMsg, Vote,
+        },
+        chain::{Chain, GlobalRegistry},
+        config::{chain::ChainConfig, listen::ListenConfig, validator::ProtocolVersion},
+        key_utils,
+        remote::Listener,
+    };
+    use signature::Signer as _;
+    use std::{net::TcpListener, thread};
+    use tempfile::TempDir;
+    use tendermint_p2p::secret_connection::PublicKey;
+
+    /// Run a mock upstream KMS which signs with a fixed key, returning its
+    /// address. If `corrupt` is set, signatures are corrupted.
+    fn spawn_upstream(corrupt: bool) -> (node::Id, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let identity_key = keypair(1);
+        let peer_id = PublicKey::from(&identity_key).peer_id();
+
+        thread::spawn(move || {
+            for socket in listener.incoming() {
+                let identity_key = keypair(1);
+                let mut conn = match tcp::secret_handshake(
+                    socket.unwrap(),
+                    identity_key,
+                    secret_connection::Version::V0_34,
+                ) {
+                    Ok(conn) => conn,
+                    Err(_) => continue,
+                };
+
+                let signing_key = keypair(2);
+
+                while let Ok(request) = protocol::read_message::<Request>(&mut conn) {
+                    let mut response = Response::default();
+
+                    if request.sign_bytes.is_empty() {
+                        response.public_key = signing_key.public.as_bytes().to_vec();
+                    } else {
+                        response.signature =
+                            signing_key.sign(&request.sign_bytes).to_bytes().to_vec();
+
+                        if corrupt {
+                            response.signature[0] ^= 1;
+                        }
+                    }
+
+                    if protocol::write_message(&mut conn, &response).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        (peer_id, port)
+    }
+
+    /// Run a real listener holding a consensus key (`keypair(2)`) for
+    /// `test-chain` and `other-chain`, permitting only `test-chain` and only
+    /// the given edge KMS
+    fn spawn_listener(dir: &TempDir, allowed_peer: node::Id) -> (node::Id, u16) {
+        let registry: &'static GlobalRegistry = Box::leak(Box::default());
+
+        for chain_id in &["test-chain", "other-chain"] {
+            let mut chain = Chain::from_config(&ChainConfig {
+                id: chain_id.parse().unwrap(),
+                key_format: keyring::Format::Hex,
+                state_file: Some(dir.path().join(format!("{}_state.json", chain_id))),
+                state_hook: None,
+                watchdog: None,
+            })
+            .unwrap();
+
+            let signing_key = keypair(2);
+            let public_key = TendermintKey::ConsensusKey(signing_key.public.into());
+
+            chain
+                .keyring
+                .add_ed25519(keyring::ed25519::Signer::new(
+                    SigningProvider::Remote,
+                    public_key,
+                    Box::new(signing_key),
+                ))
+                .unwrap();
+
+            registry.register(chain).unwrap();
+        }
+
+        let secret_key = dir.path().join("listener.key");
+        key_utils::write_base64_secret(&secret_key, &[1u8; 32]).unwrap();
+
+        let listener = Listener::spawn_with_registry(
+            ListenConfig {
+                addr: "tcp://127.0.0.1:0".parse().unwrap(),
+                chain_ids: vec!["test-chain".parse().unwrap()],
+                secret_key,
+                allowed_peers: vec![allowed_peer],
+                protocol_version: ProtocolVersion::V0_34,
+                timeout: Some(1),
+            },
+            registry,
+        )
+        .unwrap();
+
+        let peer_id = PublicKey::from(&keypair(1)).peer_id();
+        (peer_id, listener.local_addr().port())
+    }
+
+    /// Sign bytes for a precommit for the given block at height 1, round 0
+    fn precommit(block_hash: u8) -> Vec<u8> {
+        let request = SignVoteRequest {
+            vote: Some(Vote {
+                vote_type: SignedMsgType::PreCommit.to_u32(),
+                height: 1,
+                round: 0,
+                block_id: Some(BlockId::new(
+                    vec![block_hash; 32],
+                    Some(PartsSetHeader {
+                        total: 1,
+                        hash: vec![block_hash; 32],
+                    }),
+                )),
+                timestamp: Some(TimeMsg {
+                    seconds: 1_500_000_000,
+                    nanos: 0,
+                }),
+                validator_address: vec![0xa3; 20],
+                validator_index: 0,
+                signature: vec![],
+            }),
+        };
+
+        let mut sign_bytes = vec![];
+        request
+            .sign_bytes(
+                "test-chain".parse().unwrap(),
+                ProtocolVersion::V0_34,
+                &mut sign_bytes,
+            )
+            .unwrap();
+        sign_bytes
+    }
+
+    /// Node ID of the edge KMS identity key written by `config`
+    fn edge_peer_id() -> node::Id {
+        PublicKey::from(&keypair(3)).peer_id()
+    }
+
+    fn keypair(seed: u8) -> keyring::ed25519::Keypair {
+        let secret = keyring::ed25519::SecretKey::from_bytes(&[seed; 32]).unwrap();
+        keyring::ed25519::Keypair {
+            public: (&secret).into(),
+            secret,
+        }
+    }
+
+    fn config(dir: &TempDir, peer_id: node::Id, port: u16) -> RemoteConfig {
+        let secret_key = dir.path().join("identity.key");
+        key_utils::write_base64_secret(&secret_key, &[3u8; 32]).unwrap();
+
+        RemoteConfig {
+            addr: format!("tcp://{}@127.0.0.1:{}", peer_id, port)
+                .parse()
+                .unwrap(),
+            chain_ids: vec!["test-chain".parse().unwrap()],
+            secret_key,
+            timeout: Some(1),
+        }
+    }
+
+    #[test]
+    fn signs_via_upstream() {
+        let dir = TempDir::new().unwrap();
+        let (peer_id, port) = spawn_upstream(false);
+        let config = config(&dir, peer_id, port);
+
+        let upstream = Arc::new(Upstream::new(&config).unwrap());
+        let public_key = upstream.public_key(&config.chain_ids[0]).unwrap();
+        assert_eq!(public_key, keypair(2).public);
+
+        let key = RemoteKey {
+            upstream,
+            chain_id: config.chain_ids[0].clone(),
+            public_key,
+        };
+
+        let msg = b"sign bytes";
+        let signature: keyring::ed25519::Signature = key.try_sign(msg).unwrap();
+        public_key.verify(msg, &signature).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let dir = TempDir::new().unwrap();
+        let (peer_id, port) = spawn_upstream(true);
+        let config = config(&dir, peer_id, port);
+
+        let upstream = Arc::new(Upstream::new(&config).unwrap());
+        let public_key = upstream.public_key(&config.chain_ids[0]).unwrap();
+
+        let key = RemoteKey {
+            upstream,
+            chain_id: config.chain_ids[0].clone(),
+            public_key,
+        };
+
+        let result: Result<keyring::ed25519::Signature, _> = key.try_sign(b"sign bytes");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn requires_pinned_peer_id() {
+        let dir = TempDir::new().unwrap();
+        let (_, port) = spawn_upstream(false);
+
+        let wrong_peer_id = PublicKey::from(&keypair(9)).peer_id();
+        let mut config = config(&dir, wrong_peer_id, port);
+
+        // Pinned to the wrong node ID
+        let upstream = Upstream::new(&config).unwrap();
+        assert!(upstream.public_key(&config.chain_ids[0]).is_err());
+
+        // Unpinned
+        config.addr = format!("tcp://127.0.0.1:{}", port).parse().unwrap();
+        assert!(Upstream::new(&config).is_err());
+    }
+
+    #[test]
+    fn signs_via_listener() {
+        let dir = TempDir::new().unwrap();
+        let (peer_id, port) = spawn_listener(&dir, edge_peer_id());
+        let config = config(&dir, peer_id, port);
+
+        let upstream = Upstream::new(&config).unwrap();
+        let public_key = upstream.public_key(&config.chain_ids[0]).unwrap();
+        assert_eq!(public_key, keypair(2).public);
+
+        let sign_bytes = precommit(0xab);
+        let signature = upstream.sign(&config.chain_ids[0], &sign_bytes).unwrap();
+        public_key.verify(&sign_bytes, &signature).unwrap();
+    }
+
+    #[test]
+    fn listener_rejects_unauthorized_peers() {
+        let dir = TempDir::new().unwrap();
+        let other_peer_id = PublicKey::from(&keypair(9)).peer_id();
+        let (peer_id, port) = spawn_listener(&dir, other_peer_id);
+        let config = config(&dir, peer_id, port);
+
+        let upstream = Upstream::new(&config).unwrap();
+        assert!(upstream.public_key(&config.chain_ids[0]).is_err());
+    }
+
+    #[test]
+    fn listener_rejects_chains_not_permitted() {
+        let dir = TempDir::new().unwrap();
+        let (peer_id, port) = spawn_listener(&dir, edge_peer_id());
+        let config = config(&dir, peer_id, port);
+
+        // `other-chain` is registered with a key, but not listed in `chain_ids`
+        let upstream = Upstream::new(&config).unwrap();
+        let err = upstream
+            .public_key(&"other-chain".parse().unwrap())
+            .unwrap_err();
+        assert!(err.to_string().contains("chain not permitted"), "{}", err);
+
+        // The connection is still usable for permitted chains
+        assert!(upstream.public_key(&config.chain_ids[0]).is_ok());
+    }
+
+    #[test]
+    fn listener_refuses_to_double_sign() {
+        let dir = TempDir::new().unwrap();
+        let (peer_id, port) = spawn_listener(&dir, edge_peer_id());
+        let config = config(&dir, peer_id, port);
+        let chain_id = &config.chain_ids[0];
+
+        let upstream = Upstream::new(&config).unwrap();
+        upstream.sign(chain_id, &precommit(0xab)).unwrap();
+
+        // Signing the same block again is fine
+        upstream.sign(chain_id, &precommit(0xab)).unwrap();
+
+        // A different block at the same height/round/step is a double sign
+        let err = upstream.sign(chain_id, &precommit(0xcd)).unwrap_err();
+        assert!(err.to_string().contains("double sign"), "{}", err);
+    }
+}
@@ -6,18 +6,16 @@ use crate::{
     chain,
     config::provider::{
         softsign::{KeyFormat, SoftsignConfig},
-        KeyType,
+        KeyAlgorithm, KeyType,
     },
     error::{Error, ErrorKind::*},
-    key_utils,
+    key_utils::{self, SecretKey},
     keyring::{self, SigningProvider},
     prelude::*,
 };
-use ed25519_dalek as ed25519;
-use k256::ecdsa;
-use tendermint::{config::PrivValidatorKey, PrivateKey, TendermintKey};
+use tendermint::TendermintKey;
 
-/// Create software-backed Ed25519 signer objects from the given configuration
+/// Create software-backed signer objects from the given configuration
 pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) -> Result<(), Error> {
     if configs.is_empty() {
         return Ok(());
@@ -26,46 +24,45 @@ pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) ->
     let mut loaded_consensus_key = false;
 
     for config in configs {
-        match config.key_type {
-            KeyType::Account => {
-                let signer = load_secp256k1_key(&config)?;
-                let public_key =
-                    tendermint::PublicKey::from_raw_secp256k1(&signer.verify_key().to_bytes())
-                        .unwrap();
+        if let KeyType::Consensus = config.key_type {
+            if loaded_consensus_key {
+                fail!(
+                    ConfigError,
+                    "only one [[providers.softsign]] consensus key allowed"
+                );
+            }
+
+            loaded_consensus_key = true;
+        }
 
-                let account_pubkey = TendermintKey::AccountKey(public_key);
+        let algorithm = config
+            .key_algorithm
+            .unwrap_or_else(|| config.key_type.default_algorithm());
 
+        let secret_key = load_key(config, algorithm)?;
+        let public_key = tendermint_key(&config.key_type, secret_key.public_key());
+
+        match secret_key {
+            SecretKey::Secp256k1(signing_key) => {
                 let signer = keyring::ecdsa::Signer::new(
                     SigningProvider::SoftSign,
-                    account_pubkey,
-                    Box::new(signer),
+                    public_key,
+                    Box::new(signing_key),
                 );
 
                 for chain_id in &config.chain_ids {
-                    chain_registry.add_account_key(chain_id, signer.clone())?;
+                    chain_registry.add_ecdsa_key(chain_id, signer.clone())?;
                 }
             }
-            KeyType::Consensus => {
-                if loaded_consensus_key {
-                    fail!(
-                        ConfigError,
-                        "only one [[providers.softsign]] consensus key allowed"
-                    );
-                }
-
-                loaded_consensus_key = true;
-
-                let signing_key = load_ed25519_key(&config)?;
-                let consensus_pubkey = TendermintKey::ConsensusKey(signing_key.public.into());
-
+            SecretKey::Ed25519(signing_key) => {
                 let signer = keyring::ed25519::Signer::new(
                     SigningProvider::SoftSign,
-                    consensus_pubkey,
+                    public_key,
                     Box::new(signing_key),
                 );
 
                 for chain_id in &config.chain_ids {
-                    chain_registry.add_consensus_key(chain_id, signer.clone())?;
+                    chain_registry.add_ed25519_key(chain_id, signer.clone())?;
                 }
             }
         }
@@ -74,52 +71,36 @@ pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) ->
     Ok(())
 }
 
-/// Load an Ed25519 key according to the provided configuration
-fn load_ed25519_key(config: &SoftsignConfig) -> Result<ed25519::Keypair, Error> {
-    let key_format = config.key_format.as_ref().cloned().unwrap_or_default();
-
-    match key_format {
-        KeyFormat::Base64 => key_utils::load_base64_ed25519_key(&config.path),
-        KeyFormat::Json => {
-            let private_key = PrivValidatorKey::load_json_file(&config.path)
-                .map_err(|e| {
-                    format_err!(
-                        ConfigError,
-                        "couldn't load `{}`: {}",
-                        config.path.as_ref().display(),
-                        e
-                    )
-                })?
-                .priv_key;
-
-            if let PrivateKey::Ed25519(pk) = private_key {
-                Ok(pk)
-            } else {
-                unreachable!("unsupported priv_validator.json algorithm");
-            }
-        }
+/// Get the Tendermint key of the given type for a public key
+fn tendermint_key(key_type: &KeyType, public_key: tendermint::PublicKey) -> TendermintKey {
+    match key_type {
+        KeyType::Account => TendermintKey::AccountKey(public_key),
+        KeyType::Consensus => TendermintKey::ConsensusKey(public_key),
     }
 }
 
-/// Load a secp256k1 (ECDSA) key according to the provided configuration
-fn load_secp256k1_key(config: &SoftsignConfig) -> Result<ecdsa::SigningKey, Error> {
-    if config.key_format.unwrap_or_default() != KeyFormat::Base64 {
-        fail!(
-            ConfigError,
-            "[[providers.softsign]] account keys must be `base64` encoded"
-        );
-    }
+/// Load a key of the given algorithm according to the provided configuration
+fn load_key(config: &SoftsignConfig, algorithm: KeyAlgorithm) -> Result<SecretKey, Error> {
+    let key_format = config.key_format.unwrap_or_default();
+
+    if let Some(derivation_path) = &config.derivation_path {
+        if key_format != KeyFormat::Base64 {
+            fail!(
+                ConfigError,
+                "[[providers.softsign]] seeds for `derivation_path` must be `base64` encoded (or encrypted)"
+            );
+        }
 
-    let key_bytes = key_utils::load_base64_secret(&config.path)?;
+        let seed = key_utils::load_secret(&config.path, &config.password_source()?, config.strict)?;
 
-    let secret_key = ecdsa::SigningKey::from_bytes(key_bytes.as_slice()).map_err(|e| {
-        format_err!(
-            ConfigError,
-            "can't decode account key base64 from {}: {}",
-            config.path.as_ref().display(),
-            e
-        )
-    })?;
+        return SecretKey::derive(algorithm, &seed, derivation_path);
+    }
 
-    Ok(secret_key)
+    SecretKey::load(
+        config.path.as_ref(),
+        key_format,
+        Some(algorithm),
+        &config.password_source()?,
+        config.strict,
+    )
 }
@@ -0,0 +1,391 @@
+//! HashiCorp Vault Transit signing provider
+//!
+//! Consensus keys are `ed25519` Transit keys. Account keys are secp256k1,
+//! which requires a Transit plugin that supports it (e.g. `ecdsa-secp256k1`).
+//! The key version is pinned at startup so rotating a key in Vault can't
+//! silently change the validator's public key.
+
+mod client;
+#[cfg(test)]
+mod mock;
+
+use self::client::{field, Client};
+use crate::{
+    chain,
+    config::provider::{
+        vault::{SigningKeyConfig, VaultConfig},
+        KeyType,
+    },
+    error::{Error, ErrorKind::*},
+    keyring::{self, SigningProvider},
+    prelude::*,
+};
+use serde_json::{json, Value};
+use std::{convert::TryFrom, sync::Arc};
+use subtle_encoding::base64;
+use tendermint::TendermintKey;
+
+/// Create Vault Transit signer objects from the given configuration
+pub fn init(chain_registry: &mut chain::Registry, configs: &[VaultConfig]) -> Result<(), Error> {
+    for config in configs {
+        let client = Arc::new(Client::new(config)?);
+
+        for key_config in &config.keys {
+            let key = TransitKey::fetch(&client, &config.mount, key_config)?;
+
+            match key_config.key_type {
+                KeyType::Account => add_account_key(chain_registry, key, key_config)?,
+                KeyType::Consensus => add_consensus_key(chain_registry, key, key_config)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Add an account key (ECDSA/secp256k1) to the keychain
+fn add_account_key(
+    chain_registry: &mut chain::Registry,
+    key: TransitKey,
+    config: &SigningKeyConfig,
+) -> Result<(), Error> {
+    if !key.key_type.contains("secp256k1") {
+        fail!(
+            ConfigError,
+            "Vault key {:?} has type {:?} (account keys must be secp256k1)",
+            &config.key,
+            &key.key_type
+        );
+    }
+
+    let public_key = parse_secp256k1_public_key(&key.public_key)?;
+
+    let signer = keyring::ecdsa::Signer::new(
+        SigningProvider::Vault,
+        TendermintKey::AccountKey(public_key),
+        Box::new(key),
+    );
+
+    for chain_id in &config.chain_ids {
+        chain_registry.add_account_key(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Add a consensus key (Ed25519) to the keychain
+fn add_consensus_key(
+    chain_registry: &mut chain::Registry,
+    key: TransitKey,
+    config: &SigningKeyConfig,
+) -> Result<(), Error> {
+    if key.key_type != "ed25519" {
+        fail!(
+            ConfigError,
+            "Vault key {:?} has type {:?} (consensus keys must be ed25519)",
+            &config.key,
+            &key.key_type
+        );
+    }
+
+    let public_key = decode_base64(&key.public_key)
+        .ok()
+        .and_then(|bytes| tendermint::PublicKey::from_raw_ed25519(&bytes))
+        .ok_or_else(|| format_err!(InvalidKey, "invalid Ed25519 public key in Vault"))?;
+
+    let signer = keyring::ed25519::Signer::new(
+        SigningProvider::Vault,
+        TendermintKey::ConsensusKey(public_key),
+        Box::new(key),
+    );
+
+    for chain_id in &config.chain_ids {
+        chain_registry.add_consensus_key(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Transit key (pinned to a particular version)
+struct TransitKey {
+    /// Vault API client
+    client: Arc<Client>,
+
+    /// API path used to sign with this key (relative to `/v1/`)
+    sign_path: String,
+
+    /// Transit key type, e.g. `ed25519`
+    key_type: String,
+
+    /// Key version used to sign
+    version: u64,
+
+    /// Public key as returned by Vault
+    public_key: String,
+}
+
+impl TransitKey {
+    /// Fetch information about the latest version of a Transit key
+    fn fetch(client: &Arc<Client>, mount: &str, config: &SigningKeyConfig) -> Result<Self, Error> {
+        let response = client.get(&format!("{}/keys/{}", mount, &config.key))?;
+
+        let key_type = as_str(field(&response, "/data/type")?)?.to_owned();
+        let version = field(&response, "/data/latest_version")?
+            .as_u64()
+            .ok_or_else(|| format_err!(ParseError, "invalid Vault key version"))?;
+
+        let public_key = as_str(field(
+            &response,
+            &format!("/data/keys/{}/public_key", version),
+        )?)?
+        .to_owned();
+
+        let mut sign_path = format!("{}/sign/{}", mount, &config.key);
+
+        // ECDSA signs a SHA-256 digest of the message
+        if key_type != "ed25519" {
+            sign_path.push_str("/sha2-256");
+        }
+
+        info!(
+            "[keyring:vault] using {} key {:?} version {}",
+            &key_type, &config.key, version
+        );
+
+        Ok(Self {
+            client: client.clone(),
+            sign_path,
+            key_type,
+            version,
+            public_key,
+        })
+    }
+
+    /// Sign a message, returning the raw signature bytes
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let input = String::from_utf8(base64::encode(msg)).unwrap();
+
+        let mut request = json!({ "input": input, "key_version": self.version });
+
+        // Request raw `r || s` ECDSA signatures rather than ASN.1 DER
+        if self.key_type != "ed25519" {
+            request["marshaling_algorithm"] = Value::from("jws");
+        }
+
+        let response = self.client.post(&self.sign_path, &request)?;
+        parse_signature(as_str(field(&response, "/data/signature")?)?)
+    }
+}
+
+impl signature::Signer<keyring::ed25519::Signature> for TransitKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ed25519::Signature, signature::Error> {
+        let signature = self.sign(msg).map_err(signature::Error::from_source)?;
+        keyring::ed25519::Signature::try_from(signature.as_slice())
+    }
+}
+
+impl signature::Signer<keyring::ecdsa::Signature> for TransitKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<keyring::ecdsa::Signature, signature::Error> {
+        let signature = self.sign(msg).map_err(signature::Error::from_source)?;
+
+        let mut signature = if signature.len() == 64 {
+            keyring::ecdsa::Signature::try_from(signature.as_slice())?
+        } else {
+            keyring::ecdsa::Signature::from_asn1(&signature)?
+        };
+
+        // Tendermint/Cosmos require low-S signatures
+        signature.normalize_s()?;
+        Ok(signature)
+    }
+}
+
+/// Get a JSON value as a string
+fn as_str(value: &Value) -> Result<&str, Error> {
+    value
+        .as_str()
+        .ok_or_else(|| format_err!(ParseError, "expected string in Vault response").into())
+}
+
+/// Parse a Transit signature of the form `vault:v<version>:<base64>`
+fn parse_signature(signature: &str) -> Result<Vec<u8>, Error> {
+    let encoded = signature
+        .strip_prefix("vault:v")
+        .and_then(|rest| rest.find(':').map(|pos| &rest[(pos + 1)..]))
+        .ok_or_else(|| format_err!(ParseError, "malformed Vault signature"))?;
+
+    decode_base64(encoded)
+}
+
+/// Parse a secp256k1 public key, which may be either a PEM-encoded
+/// `SubjectPublicKeyInfo` or a Base64-encoded SEC1 point
+fn parse_secp256k1_public_key(public_key: &str) -> Result<tendermint::PublicKey, Error> {
+    let bytes = if public_key.trim_start().starts_with("-----BEGIN") {
+        let encoded = public_key
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+
+        let der = decode_base64(&encoded)?;
+
+        // The point is the contents of the trailing `BIT STRING`
+        spki_point(&der)
+            .ok_or_else(|| format_err!(InvalidKey, "malformed secp256k1 public key"))?
+            .to_vec()
+    } else {
+        decode_base64(public_key)?
+    };
+
+    let point = k256::EncodedPoint::from_bytes(&bytes)
+        .map_err(|_| format_err!(InvalidKey, "invalid secp256k1 public key in Vault"))?
+        .compress();
+
+    tendermint::PublicKey::from_raw_secp256k1(point.as_bytes())
+        .ok_or_else(|| format_err!(InvalidKey, "invalid secp256k1 public key in Vault").into())
+}
+
+/// Extract the (uncompressed or compressed) point from a DER-encoded
+/// `SubjectPublicKeyInfo`
+fn spki_point(der: &[u8]) -> Option<&[u8]> {
+    for &(header, len) in &[([0x03, 0x42, 0x00], 65), ([0x03, 0x22, 0x00], 33)] {
+        if der.len() >= len + 3 && der[(der.len() - len - 3)..(der.len() - len)] == header {
+            return Some(&der[(der.len() - len)..]);
+        }
+    }
+
+    None
+}
+
+/// Decode standard or URL-safe Base64, with or without padding
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, Error> {
+    let mut encoded = encoded.trim().replace('-', "+").replace('_', "/");
+
+    let padding = (4 - encoded.len() % 4) % 4;
+    encoded.push_str(&"=".repeat(padding));
+
+    base64::decode(&encoded).map_err(|_| format_err!(ParseError, "invalid Base64").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock::MockVault, *};
+    use signature::{Signer as _, Verifier as _};
+    use tempfile::TempDir;
+
+    /// Secret key used by the mock Transit engine
+    const SECRET_KEY: [u8; 32] = [7u8; 32];
+
+    fn key_config(key: &str, key_type: KeyType) -> SigningKeyConfig {
+        SigningKeyConfig {
+            chain_ids: vec![],
+            key: key.to_owned(),
+            key_type,
+        }
+    }
+
+    fn spawn_transit() -> MockVault {
+        let secret = keyring::ed25519::SecretKey::from_bytes(&SECRET_KEY).unwrap();
+        let keypair = keyring::ed25519::Keypair {
+            public: (&secret).into(),
+            secret,
+        };
+
+        MockVault::spawn(move |request| match request.path.as_str() {
+            "/v1/auth/token/lookup-self" => (200, json!({ "data": { "ttl": 0 } })),
+            "/v1/transit/keys/consensus" => (
+                200,
+                json!({
+                    "data": {
+                        "type": "ed25519",
+                        "latest_version": 2,
+                        "keys": {
+                            "1": { "public_key": "" },
+                            "2": { "public_key": String::from_utf8(base64::encode(keypair.public.as_bytes())).unwrap() }
+                        }
+                    }
+                }),
+            ),
+            "/v1/transit/sign/consensus" => {
+                assert_eq!(request.method, "POST");
+                assert_eq!(request.token.as_deref(), Some("test-token"));
+                assert_eq!(request.body["key_version"], 2);
+
+                // Vault is temporarily unavailable for the first request
+                if request.count(&request.path) == 0 {
+                    return (503, json!({ "errors": ["Vault is sealed"] }));
+                }
+
+                let msg = decode_base64(request.body["input"].as_str().unwrap()).unwrap();
+                let signature = keypair.sign(&msg);
+                let encoded = String::from_utf8(base64::encode(&signature.to_bytes()[..])).unwrap();
+
+                (
+                    200,
+                    json!({ "data": { "signature": format!("vault:v2:{}", encoded) } }),
+                )
+            }
+            _ => (404, json!({ "errors": [] })),
+        })
+    }
+
+    #[test]
+    fn signs_with_ed25519_key() {
+        let vault = spawn_transit();
+        let dir = TempDir::new().unwrap();
+        let client = Arc::new(Client::new(&vault.token_config(&dir)).unwrap());
+
+        let key = TransitKey::fetch(
+            &client,
+            "transit",
+            &key_config("consensus", KeyType::Consensus),
+        )
+        .unwrap();
+
+        let public_key =
+            keyring::ed25519::PublicKey::from_bytes(&decode_base64(&key.public_key).unwrap())
+                .unwrap();
+
+        let msg = b"tendermint kms vault test";
+        let signature: keyring::ed25519::Signature = key.try_sign(msg).unwrap();
+        public_key.verify(msg, &signature).unwrap();
+
+        // The first sign request was retried
+        assert_eq!(vault.requests("/v1/transit/sign/consensus"), 2);
+    }
+
+    #[test]
+    fn parses_signatures() {
+        assert_eq!(parse_signature("vault:v1:AAEC").unwrap(), vec![0, 1, 2]);
+        assert_eq!(parse_signature("vault:v12:-_8").unwrap(), vec![0xfb, 0xff]);
+        assert!(parse_signature("AAEC").is_err());
+    }
+
+    #[test]
+    fn parses_secp256k1_public_keys() {
+        let signing_key = k256::ecdsa::SigningKey::from_bytes(&SECRET_KEY).unwrap();
+        let point = signing_key.verify_key().to_bytes();
+        let expected = tendermint::PublicKey::from_raw_secp256k1(&point).unwrap();
+
+        let uncompressed = k256::EncodedPoint::from_bytes(&point)
+            .unwrap()
+            .decompress()
+            .unwrap();
+
+        // SubjectPublicKeyInfo header for an uncompressed secp256k1 point
+        let mut der = vec![
+            0x30, 0x56, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06,
+            0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a, 0x03, 0x42, 0x00,
+        ];
+        der.extend_from_slice(uncompressed.as_bytes());
+
+        let pem = format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+            String::from_utf8(base64::encode(&der)).unwrap()
+        );
+
+        assert_eq!(parse_secp256k1_public_key(&pem).unwrap(), expected);
+
+        let encoded = String::from_utf8(base64::encode(&point[..])).unwrap();
+        assert_eq!(parse_secp256k1_public_key(&encoded).unwrap(), expected);
+    }
+}
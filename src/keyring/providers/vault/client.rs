@@ -0,0 +1,487 @@
+//! Blocking client for the HashiCorp Vault HTTP API
+
+use crate::{
+    config::provider::vault::{AuthConfig, VaultConfig},
+    error::{Error, ErrorKind::*},
+    http, key_utils,
+    prelude::*,
+};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use zeroize::Zeroizing;
+
+/// Delay before the first retry (doubled for each subsequent retry)
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Interval at which to retry renewing a token after renewal fails
+const RENEWAL_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Vault API client which keeps its token renewed
+pub struct Client {
+    /// Base URL of the Vault server
+    addr: http::Url,
+
+    /// HTTP client
+    http: http::Client,
+
+    /// Vault namespace (if any)
+    namespace: Option<String>,
+
+    /// Authentication configuration
+    auth: AuthConfig,
+
+    /// Number of times to retry failed requests
+    max_retries: u32,
+
+    /// Current token
+    token: Mutex<Token>,
+}
+
+/// Vault token and its renewal state
+struct Token {
+    /// Token used for requests
+    secret: Zeroizing<String>,
+
+    /// Can this token be renewed?
+    renewable: bool,
+
+    /// When the token should next be renewed (`None` if it never expires)
+    renew_at: Option<Instant>,
+}
+
+impl Token {
+    /// Create a token with the given TTL (in seconds), scheduling renewal at
+    /// half its lifetime
+    fn new(secret: String, ttl: u64, renewable: bool) -> Self {
+        Self {
+            secret: Zeroizing::new(secret),
+            renewable,
+            renew_at: renew_at(ttl),
+        }
+    }
+}
+
+impl Client {
+    /// Create a new client, authenticating to Vault
+    pub fn new(config: &VaultConfig) -> Result<Self, Error> {
+        let addr = config.addr.parse::<http::Url>()?;
+        let timeout = Duration::from_millis(config.timeout_ms);
+
+        let http = match &config.ca_cert {
+            Some(ca_cert) => http::Client::with_ca_cert(timeout, ca_cert)?,
+            None => http::Client::new(timeout),
+        };
+
+        let client = Self {
+            addr,
+            http,
+            namespace: config.namespace.clone(),
+            auth: config.auth.clone(),
+            max_retries: config.max_retries,
+            token: Mutex::new(Token::new(String::new(), 0, false)),
+        };
+
+        client.login()?;
+        Ok(client)
+    }
+
+    /// Make an authenticated `GET` request to the given API path (relative
+    /// to `/v1/`), returning the response JSON
+    pub fn get(&self, path: &str) -> Result<Value, Error> {
+        self.request("GET", path, None)
+    }
+
+    /// Make an authenticated `POST` request to the given API path (relative
+    /// to `/v1/`), returning the response JSON
+    pub fn post(&self, path: &str, body: &Value) -> Result<Value, Error> {
+        self.request("POST", path, Some(body))
+    }
+
+    /// Make an authenticated request, renewing the token if it's due and
+    /// logging in again if it has been revoked or has expired (for token
+    /// authentication this rereads the token file, e.g. as updated by Vault
+    /// Agent)
+    fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, Error> {
+        self.renew_token_if_due();
+
+        let token = self.token();
+
+        match self.call(method, path, body, Some(token.as_str())) {
+            Err(e) if *e.kind() == AccessError => {
+                warn!("[keyring:vault] {}; logging in again", e);
+                self.login()?;
+                self.call(method, path, body, Some(self.token().as_str()))
+            }
+            result => result,
+        }
+    }
+
+    /// Get the current token
+    fn token(&self) -> Zeroizing<String> {
+        self.token.lock().unwrap().secret.clone()
+    }
+
+    /// Obtain a token according to the authentication configuration
+    fn login(&self) -> Result<(), Error> {
+        let token = match &self.auth {
+            AuthConfig::Token { token_file } => {
+                let secret = read_secret(token_file)?;
+
+                // Look up the token to validate it and find out its TTL
+                let response =
+                    self.call("GET", "auth/token/lookup-self", None, Some(secret.as_str()))?;
+                let ttl = field(&response, "/data/ttl")?.as_u64().unwrap_or(0);
+                let renewable = response.pointer("/data/renewable") == Some(&Value::Bool(true));
+
+                Token::new(secret.as_str().to_owned(), ttl, renewable)
+            }
+            AuthConfig::AppRole {
+                role_id,
+                secret_id_file,
+                approle_mount,
+            } => {
+                let secret_id = read_secret(secret_id_file)?;
+
+                let response = self.call(
+                    "POST",
+                    &format!("auth/{}/login", approle_mount),
+                    Some(&json!({ "role_id": role_id, "secret_id": secret_id.as_str() })),
+                    None,
+                )?;
+
+                parse_auth(&response)?
+            }
+        };
+
+        debug!("[keyring:vault] authenticated to {}", &self.addr);
+        *self.token.lock().unwrap() = token;
+        Ok(())
+    }
+
+    /// Renew the token if it has reached half of its TTL. Errors are logged
+    /// rather than returned, as the token may still be valid.
+    fn renew_token_if_due(&self) {
+        let (due, renewable) = {
+            let token = self.token.lock().unwrap();
+            let due = match token.renew_at {
+                Some(renew_at) => Instant::now() >= renew_at,
+                None => false,
+            };
+            (due, token.renewable)
+        };
+
+        if !due {
+            return;
+        }
+
+        let result = if renewable {
+            self.renew_token().or_else(|e| {
+                warn!(
+                    "[keyring:vault] token renewal failed ({}); logging in again",
+                    e
+                );
+                self.login()
+            })
+        } else {
+            self.login()
+        };
+
+        if let Err(e) = result {
+            error!("[keyring:vault] couldn't renew token: {}", e);
+
+            // Avoid retrying on every request
+            self.token.lock().unwrap().renew_at = Some(Instant::now() + RENEWAL_RETRY_INTERVAL);
+        }
+    }
+
+    /// Renew the current token
+    fn renew_token(&self) -> Result<(), Error> {
+        let secret = self.token();
+        let response = self.call(
+            "POST",
+            "auth/token/renew-self",
+            Some(&json!({})),
+            Some(secret.as_str()),
+        )?;
+
+        let renewed = parse_auth(&response)?;
+        let mut token = self.token.lock().unwrap();
+        token.renewable = renewed.renewable;
+        token.renew_at = renewed.renew_at;
+
+        debug!("[keyring:vault] renewed token");
+        Ok(())
+    }
+
+    /// Make a request to the Vault API, retrying transport errors, rate
+    /// limiting, and server errors
+    fn call(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+        token: Option<&str>,
+    ) -> Result<Value, Error> {
+        let url = self.addr.with_path(&format!("/v1/{}", path));
+        let body = body.map(|body| body.to_string());
+
+        let mut headers = vec![("X-Vault-Request", "true")];
+
+        if let Some(token) = token {
+            headers.push(("X-Vault-Token", token));
+        }
+
+        if let Some(namespace) = &self.namespace {
+            headers.push(("X-Vault-Namespace", namespace.as_str()));
+        }
+
+        let mut attempt = 0;
+
+        loop {
+            let result =
+                self.http
+                    .request(method, &url, &headers, body.as_ref().map(String::as_bytes));
+
+            // Retry transport errors, rate limiting, and server errors (e.g.
+            // Vault is sealed or in standby), but not other client errors
+            let failure = match &result {
+                Ok(response) if response.status == 429 || response.status >= 500 => {
+                    format!("HTTP {}", response.status)
+                }
+                Ok(_) => break result.and_then(|response| parse_response(method, path, response)),
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= self.max_retries {
+                break result.and_then(|response| parse_response(method, path, response));
+            }
+
+            let delay = RETRY_DELAY * 2u32.pow(attempt);
+            attempt += 1;
+
+            warn!(
+                "[keyring:vault] {} {} failed ({}); retrying in {}ms",
+                method,
+                path,
+                failure,
+                delay.as_millis()
+            );
+
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// Parse a Vault API response, returning its JSON body on success
+fn parse_response(method: &str, path: &str, response: http::Response) -> Result<Value, Error> {
+    if response.is_success() {
+        if response.body.is_empty() {
+            return Ok(Value::Null);
+        }
+
+        return Ok(serde_json::from_slice(&response.body)?);
+    }
+
+    // Vault returns errors as `{"errors": ["..."]}`
+    let errors = serde_json::from_slice::<Value>(&response.body)
+        .ok()
+        .and_then(|json| json.get("errors").map(Value::to_string))
+        .unwrap_or_default();
+
+    let kind = match response.status {
+        401 | 403 => AccessError,
+        _ => HttpError,
+    };
+
+    fail!(
+        kind,
+        "{} {} returned HTTP {} {}",
+        method,
+        path,
+        response.status,
+        errors
+    )
+}
+
+/// Parse the `auth` section of a login or renewal response
+fn parse_auth(response: &Value) -> Result<Token, Error> {
+    let secret = field(response, "/auth/client_token")?
+        .as_str()
+        .ok_or_else(|| format_err!(ParseError, "Vault client_token is not a string"))?;
+
+    let ttl = field(response, "/auth/lease_duration")?
+        .as_u64()
+        .unwrap_or(0);
+
+    let renewable = response.pointer("/auth/renewable") == Some(&Value::Bool(true));
+
+    Ok(Token::new(secret.to_owned(), ttl, renewable))
+}
+
+/// Get a field of a Vault API response by JSON pointer
+pub(super) fn field<'a>(response: &'a Value, pointer: &str) -> Result<&'a Value, Error> {
+    response.pointer(pointer).ok_or_else(|| {
+        format_err!(
+            ParseError,
+            "missing {} in Vault API response",
+            pointer.trim_start_matches('/').replace('/', ".")
+        )
+        .into()
+    })
+}
+
+/// Compute when to renew a token with the given TTL (in seconds)
+fn renew_at(ttl: u64) -> Option<Instant> {
+    if ttl == 0 {
+        None
+    } else {
+        Some(Instant::now() + Duration::from_secs(ttl) / 2)
+    }
+}
+
+/// Read a secret (token or secret ID) from a file
+fn read_secret(path: &Path) -> Result<Zeroizing<String>, Error> {
+    key_utils::permissions::check(path)?;
+
+    let secret = Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+        format_err!(
+            ConfigError,
+            "couldn't read Vault credentials from {}: {}",
+            path.display(),
+            e
+        )
+    })?);
+
+    Ok(Zeroizing::new(secret.trim_end().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{key_utils::write_secret_file, keyring::providers::vault::mock::MockVault};
+    use tempfile::TempDir;
+
+    fn approle_config(vault: &MockVault, dir: &TempDir) -> VaultConfig {
+        let secret_id_file = dir.path().join("secret-id");
+        write_secret_file(&secret_id_file, b"test-secret-id\n").unwrap();
+
+        vault.config(AuthConfig::AppRole {
+            role_id: "test-role-id".to_owned(),
+            secret_id_file,
+            approle_mount: "approle".to_owned(),
+        })
+    }
+
+    fn login_response(token: &str) -> (u16, Value) {
+        (
+            200,
+            json!({
+                "auth": {
+                    "client_token": token,
+                    "lease_duration": 3600,
+                    "renewable": true
+                }
+            }),
+        )
+    }
+
+    #[test]
+    fn logs_in_with_approle() {
+        let vault = MockVault::spawn(|request| match request.path.as_str() {
+            "/v1/auth/approle/login" => {
+                assert_eq!(request.body["role_id"], "test-role-id");
+                assert_eq!(request.body["secret_id"], "test-secret-id");
+                login_response("token-1")
+            }
+            "/v1/transit/keys/test" => {
+                assert_eq!(request.token.as_deref(), Some("token-1"));
+                (200, json!({ "data": { "name": "test" } }))
+            }
+            _ => (404, json!({ "errors": [] })),
+        });
+
+        let dir = TempDir::new().unwrap();
+        let client = Client::new(&approle_config(&vault, &dir)).unwrap();
+        let response = client.get("transit/keys/test").unwrap();
+        assert_eq!(response["data"]["name"], "test");
+    }
+
+    #[test]
+    fn renews_token() {
+        let vault = MockVault::spawn(|request| match request.path.as_str() {
+            "/v1/auth/approle/login" => login_response("token-1"),
+            "/v1/auth/token/renew-self" => {
+                assert_eq!(request.token.as_deref(), Some("token-1"));
+                login_response("token-1")
+            }
+            _ => (200, json!({})),
+        });
+
+        let dir = TempDir::new().unwrap();
+        let client = Client::new(&approle_config(&vault, &dir)).unwrap();
+        let renew_at = client.token.lock().unwrap().renew_at.unwrap();
+        assert!(renew_at > Instant::now() + Duration::from_secs(1700));
+
+        // Pretend half of the TTL has elapsed
+        client.token.lock().unwrap().renew_at = Some(Instant::now());
+        client.get("transit/keys/test").unwrap();
+
+        assert_eq!(vault.requests("/v1/auth/token/renew-self"), 1);
+        assert!(client.token.lock().unwrap().renew_at.unwrap() > Instant::now());
+    }
+
+    #[test]
+    fn logs_in_again_when_token_is_revoked() {
+        let vault = MockVault::spawn(|request| {
+            let logins = request.count("/v1/auth/approle/login");
+
+            match request.path.as_str() {
+                "/v1/auth/approle/login" => login_response(&format!("token-{}", logins + 1)),
+                _ if request.token.as_deref() == Some("token-1") => {
+                    (403, json!({ "errors": ["permission denied"] }))
+                }
+                _ => (200, json!({ "data": {} })),
+            }
+        });
+
+        let dir = TempDir::new().unwrap();
+        let client = Client::new(&approle_config(&vault, &dir)).unwrap();
+        client.get("transit/keys/test").unwrap();
+        assert_eq!(vault.requests("/v1/auth/approle/login"), 2);
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let vault = MockVault::spawn(|request| match request.path.as_str() {
+            "/v1/auth/token/lookup-self" => (200, json!({ "data": { "ttl": 0 } })),
+            _ if request.count(&request.path) < 2 => (503, json!({ "errors": ["sealed"] })),
+            _ => (200, json!({ "data": {} })),
+        });
+
+        let dir = TempDir::new().unwrap();
+        let client = Client::new(&vault.token_config(&dir)).unwrap();
+        client.get("transit/keys/test").unwrap();
+
+        // Two failures followed by a successful retry
+        assert_eq!(vault.requests("/v1/transit/keys/test"), 3);
+
+        // Client errors aren't retried
+        let vault = MockVault::spawn(|request| match request.path.as_str() {
+            "/v1/auth/token/lookup-self" => (200, json!({ "data": { "ttl": 0 } })),
+            _ => (400, json!({ "errors": ["bad request"] })),
+        });
+
+        let client = Client::new(&vault.token_config(&dir)).unwrap();
+        assert_eq!(
+            client.get("transit/keys/test").unwrap_err().kind(),
+            &HttpError
+        );
+        assert_eq!(vault.requests("/v1/transit/keys/test"), 1);
+    }
+}
@@ -0,0 +1,157 @@
+//! Mock Vault server for testing
+
+use crate::{
+    config::provider::vault::{AuthConfig, VaultConfig},
+    key_utils,
+};
+use serde_json::Value;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+use tempfile::TempDir;
+
+/// Request received by the mock server
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// HTTP method
+    pub method: String,
+
+    /// Request path
+    pub path: String,
+
+    /// Value of the `X-Vault-Token` header
+    pub token: Option<String>,
+
+    /// JSON request body (`null` if none)
+    pub body: Value,
+
+    /// All requests received prior to this one
+    history: Vec<Request>,
+}
+
+impl Request {
+    /// Number of prior requests for the given path
+    pub fn count(&self, path: &str) -> usize {
+        self.history.iter().filter(|req| req.path == path).count()
+    }
+}
+
+/// Mock Vault server which responds to requests using a handler function
+pub struct MockVault {
+    /// Address of the server
+    addr: String,
+
+    /// Requests received so far
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockVault {
+    /// Spawn a mock server on a random local port
+    pub fn spawn<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> (u16, Value) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::<Request>::new()));
+        let history = requests.clone();
+
+        thread::spawn(move || {
+            for socket in listener.incoming() {
+                let mut socket = socket.unwrap();
+                let mut request = read_request(&mut socket);
+                request.history = history.lock().unwrap().clone();
+
+                let (status, body) = handler(&request);
+                request.history.clear();
+                history.lock().unwrap().push(request);
+
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    /// Number of requests received for the given path
+    pub fn requests(&self, path: &str) -> usize {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().filter(|req| req.path == path).count()
+    }
+
+    /// Get a provider configuration for this server
+    pub fn config(&self, auth: AuthConfig) -> VaultConfig {
+        VaultConfig {
+            addr: self.addr.clone(),
+            ca_cert: None,
+            namespace: None,
+            mount: "transit".to_owned(),
+            auth,
+            timeout_ms: 1000,
+            max_retries: 2,
+            keys: vec![],
+        }
+    }
+
+    /// Get a provider configuration which uses token authentication
+    pub fn token_config(&self, dir: &TempDir) -> VaultConfig {
+        let token_file = dir.path().join("token");
+        key_utils::write_secret_file(&token_file, b"test-token\n").unwrap();
+        self.config(AuthConfig::Token { token_file })
+    }
+}
+
+/// Read a request from the given socket
+fn read_request(socket: &mut TcpStream) -> Request {
+    let mut reader = BufReader::new(socket);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap().to_owned();
+    let path = parts.next().unwrap().to_owned();
+
+    let mut token = None;
+    let mut content_length = 0;
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        let (name, value) = line.split_at(line.find(':').unwrap());
+        let value = value[1..].trim();
+
+        match name.to_ascii_lowercase().as_str() {
+            "x-vault-token" => token = Some(value.to_owned()),
+            "content-length" => content_length = value.parse().unwrap(),
+            _ => (),
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    Request {
+        method,
+        path,
+        token,
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+        history: vec![],
+    }
+}
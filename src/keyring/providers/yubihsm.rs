@@ -9,54 +9,69 @@ use crate::{
     error::{Error, ErrorKind::*},
     keyring::{self, SigningProvider},
     prelude::*,
+    yubihsm::{Device, Tasks},
 };
+use std::sync::Arc;
 use tendermint::TendermintKey;
 
-/// Create hardware-backed YubiHSM signer objects from the given configuration
+/// Create hardware-backed YubiHSM signer objects from the given configuration,
+/// returning the background tasks of each device (which aren't started)
 pub fn init(
     chain_registry: &mut chain::Registry,
     yubihsm_configs: &[YubihsmConfig],
-) -> Result<(), Error> {
-    if yubihsm_configs.is_empty() {
-        return Ok(());
-    }
+) -> Result<Vec<Tasks>, Error> {
+    crate::yubihsm::check_configs(yubihsm_configs)?;
 
-    // TODO(tarcieri): support for multiple YubiHSMs per host?
-    if yubihsm_configs.len() != 1 {
-        fail!(
-            ConfigError,
-            "expected one [yubihsm.provider] in config, found: {}",
-            yubihsm_configs.len()
-        );
-    }
+    let mut tasks = vec![];
+
+    for hsm_config in yubihsm_configs {
+        // Route each key to the device it's configured for
+        let device = if yubihsm_configs.len() > 1 {
+            Some(hsm_config.device_id())
+        } else {
+            None
+        };
+
+        let device = crate::yubihsm::device(device);
 
-    for config in &yubihsm_configs[0].keys {
-        match config.key_type {
-            KeyType::Account => add_account_key(chain_registry, config)?,
-            KeyType::Consensus => add_consensus_key(chain_registry, config)?,
+        for config in &hsm_config.keys {
+            match config.key_type {
+                KeyType::Account => add_account_key(chain_registry, &device, config)?,
+                KeyType::Consensus => add_consensus_key(chain_registry, &device, config)?,
+            }
         }
+
+        tasks.push(Tasks::new(device, hsm_config.audit_log.clone()));
     }
 
-    Ok(())
+    Ok(tasks)
 }
 
 /// Add an account key (ECDSA/secp256k1) to the keychain
 fn add_account_key(
     chain_registry: &mut chain::Registry,
+    device: &Arc<Device>,
     config: &SigningKeyConfig,
 ) -> Result<(), Error> {
-    let signer = yubihsm::ecdsa::Signer::create(crate::yubihsm::client().clone(), config.key)
-        .map_err(|_| {
+    let key_id = config.key;
+
+    // Recreated with a new client if the session with the YubiHSM is lost
+    let signer = crate::yubihsm::Signer::new(device.clone(), move |client| {
+        yubihsm::ecdsa::Signer::<k256::Secp256k1>::create(client, key_id).map_err(|_| {
             format_err!(
                 InvalidKey,
                 "YubiHSM key ID 0x{:04x} is not a valid ECDSA signing key",
-                config.key
+                key_id
             )
-        })?;
+            .into()
+        })
+    })?;
 
-    let public_key =
-        tendermint::PublicKey::from_raw_secp256k1(signer.public_key().compress().as_bytes())
-            .expect("invalid secp256k1 key");
+    let public_key = signer
+        .with_inner(|signer| {
+            tendermint::PublicKey::from_raw_secp256k1(signer.public_key().compress().as_bytes())
+        })
+        .expect("invalid secp256k1 key");
 
     let signer = keyring::ecdsa::Signer::new(
         SigningProvider::Yubihsm,
@@ -74,18 +89,27 @@ fn add_account_key(
 /// Add a consensus key (Ed25519) to the keychain
 fn add_consensus_key(
     chain_registry: &mut chain::Registry,
+    device: &Arc<Device>,
     config: &SigningKeyConfig,
 ) -> Result<(), Error> {
-    let signer = yubihsm::ed25519::Signer::create(crate::yubihsm::client().clone(), config.key)
-        .map_err(|_| {
+    let key_id = config.key;
+
+    // Recreated with a new client if the session with the YubiHSM is lost
+    let signer = crate::yubihsm::Signer::new(device.clone(), move |client| {
+        yubihsm::ed25519::Signer::create(client, key_id).map_err(|_| {
             format_err!(
                 InvalidKey,
                 "YubiHSM key ID 0x{:04x} is not a valid Ed25519 signing key",
-                config.key
+                key_id
             )
-        })?;
+            .into()
+        })
+    })?;
 
-    let public_key = tendermint::PublicKey::from_raw_ed25519(signer.public_key().as_bytes())
+    let public_key = signer
+        .with_inner(|signer| {
+            tendermint::PublicKey::from_raw_ed25519(signer.public_key().as_bytes())
+        })
         .expect("invalid Ed25519 key");
 
     let signer = keyring::ed25519::Signer::new(
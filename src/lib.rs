@@ -17,11 +17,17 @@ pub mod commands;
 pub mod config;
 pub mod connection;
 pub mod error;
+pub mod http;
 pub mod key_utils;
 pub mod keyring;
+pub mod logging;
+pub mod notify;
 pub mod prelude;
+pub mod remote;
 pub mod rpc;
 pub mod session;
+pub mod shutdown;
+pub mod systemd;
 
 #[cfg(feature = "tx-signer")]
 pub mod tx_signer;
@@ -0,0 +1,203 @@
+//! Log output formats.
+//!
+//! By default the KMS emits human-readable text logs. When started with
+//! `--log-format json` each log line is instead a JSON object containing the
+//! event's structured fields (e.g. `chain_id`, `height`, `latency_ms`),
+//! which is easier to consume from log pipelines.
+
+use abscissa_core::{FrameworkError, FrameworkErrorKind::ComponentError};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::{
+    fmt::{self, Debug},
+    str::FromStr,
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_log::{LogTracer, NormalizeEvent};
+use tracing_subscriber::{
+    fmt::{FmtContext, FormatEvent, FormatFields},
+    registry::LookupSpan,
+    FmtSubscriber,
+};
+
+/// Log output formats
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LogFormat {
+    /// Human-readable text (default)
+    Text,
+
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!(
+                "invalid log format: {} (expected `text` or `json`)",
+                other
+            )),
+        }
+    }
+}
+
+/// Install a global `tracing` subscriber which emits JSON log lines, using
+/// the given filter (e.g. `info` or `debug`)
+pub fn init_json(filter: &str) -> Result<(), FrameworkError> {
+    LogTracer::init().map_err(|e| ComponentError.context(e))?;
+
+    let subscriber = FmtSubscriber::builder()
+        .with_env_filter(filter)
+        .event_format(JsonFormat)
+        .finish();
+
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| ComponentError.context(e).into())
+}
+
+/// Formats events as single-line JSON objects
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        writer: &mut dyn fmt::Write,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        // Events forwarded from the `log` crate carry their real metadata
+        // in `log.*` fields
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut fields = Map::new();
+        fields.insert(
+            "timestamp".to_owned(),
+            Utc::now()
+                .to_rfc3339_opts(SecondsFormat::Micros, true)
+                .into(),
+        );
+        fields.insert("level".to_owned(), metadata.level().to_string().into());
+        fields.insert("target".to_owned(), metadata.target().into());
+
+        event.record(&mut JsonVisitor(&mut fields));
+
+        let json = serde_json::to_string(&fields).map_err(|_| fmt::Error)?;
+        writeln!(writer, "{}", json)
+    }
+}
+
+/// Collects event fields into a JSON object
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl<'a> JsonVisitor<'a> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if !field.name().starts_with("log.") {
+            self.0.insert(field.name().to_owned(), value);
+        }
+    }
+}
+
+impl<'a> Visit for JsonVisitor<'a> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tracing_subscriber::fmt::MakeWriter;
+
+    /// Writer which captures log output in memory
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MakeWriter for Capture {
+        type Writer = Self;
+
+        fn make_writer(&self) -> Self {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn formats_events_as_json() {
+        let capture = Capture::default();
+        let subscriber = FmtSubscriber::builder()
+            .with_writer(capture.clone())
+            .event_format(JsonFormat)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                chain_id = "test_chain_id",
+                height = 42u64,
+                round = 0i64,
+                block_id = %"26C0A41F32",
+                "signed {}",
+                "Proposal"
+            );
+        });
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 1);
+
+        let json: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["message"], "signed Proposal");
+        assert_eq!(json["chain_id"], "test_chain_id");
+        assert_eq!(json["height"], 42);
+        assert_eq!(json["round"], 0);
+        assert_eq!(json["block_id"], "26C0A41F32");
+    }
+
+    #[test]
+    fn parses_log_formats() {
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Alert notifications: push safety-related events (e.g. attempted double
+//! signing) to operator-configured webhooks.
+//!
+//! Events are queued and delivered from a background thread so sending a
+//! notification never blocks the signing path.
+
+mod webhook;
+
+pub use self::webhook::Webhook;
+
+use crate::{
+    chain,
+    config::notify::NotifyConfig,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use chrono::{SecondsFormat, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+/// Maximum number of events which can be queued for delivery. Events sent
+/// while the queue is full are dropped.
+pub const QUEUE_SIZE: usize = 64;
+
+/// Global notifier (only initialized if notifications are configured)
+static NOTIFIER: OnceCell<Notifier> = OnceCell::new();
+
+/// Kinds of events we send notifications for
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum EventKind {
+    /// Refused to sign a conflicting message (attempted double sign)
+    #[serde(rename = "double_sign")]
+    DoubleSign,
+
+    /// The validator requested a signature below the last signed height
+    #[serde(rename = "height_regression")]
+    HeightRegression,
+
+    /// All connections to validators for a chain have been lost
+    #[serde(rename = "chain_disconnected")]
+    ChainDisconnected,
+
+    /// Signing provider (e.g. HSM) failed to produce a signature
+    #[serde(rename = "signing_error")]
+    SigningError,
+
+    /// Transaction signer failed to broadcast a transaction
+    #[serde(rename = "tx_broadcast_failure")]
+    TxBroadcastFailure,
+
+    /// Watchdog detected the KMS is no longer signing for a chain
+    #[serde(rename = "missed_signatures")]
+    MissedSignatures,
+
+    /// Digest chain of a YubiHSM audit log is broken
+    #[serde(rename = "audit_log_discontinuity")]
+    AuditLogDiscontinuity,
+}
+
+impl EventKind {
+    /// Get a string identifying this kind of event
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::DoubleSign => "double_sign",
+            EventKind::HeightRegression => "height_regression",
+            EventKind::ChainDisconnected => "chain_disconnected",
+            EventKind::SigningError => "signing_error",
+            EventKind::TxBroadcastFailure => "tx_broadcast_failure",
+            EventKind::MissedSignatures => "missed_signatures",
+            EventKind::AuditLogDiscontinuity => "audit_log_discontinuity",
+        }
+    }
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Events sent to webhooks (serialized as JSON)
+#[derive(Clone, Debug, Serialize)]
+pub struct Event {
+    /// Kind of event
+    pub event: EventKind,
+
+    /// Chain the event pertains to
+    pub chain_id: chain::Id,
+
+    /// Component which raised the event (e.g. validator address)
+    pub source: String,
+
+    /// Block height (if applicable)
+    pub height: Option<u64>,
+
+    /// Human-readable description of the event
+    pub message: String,
+
+    /// Time at which the event occurred (RFC 3339)
+    pub timestamp: String,
+}
+
+impl Event {
+    /// Create a new event
+    pub fn new(
+        event: EventKind,
+        chain_id: &chain::Id,
+        source: impl ToString,
+        message: impl ToString,
+    ) -> Self {
+        Self {
+            event,
+            chain_id: chain_id.clone(),
+            source: source.to_string(),
+            height: None,
+            message: message.to_string(),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+
+    /// Set the block height this event occurred at
+    pub fn height(mut self, height: impl Into<u64>) -> Self {
+        self.height = Some(height.into());
+        self
+    }
+}
+
+/// Notifier: queues events for delivery by a background thread
+pub struct Notifier {
+    /// Sender for the event queue
+    sender: Mutex<mpsc::SyncSender<Event>>,
+}
+
+impl Notifier {
+    /// Create a new notifier from the given configuration, spawning the
+    /// background thread which delivers events
+    pub fn new(config: &NotifyConfig) -> Result<Self, Error> {
+        let webhooks = config
+            .webhook
+            .iter()
+            .map(Webhook::new)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+
+        thread::Builder::new()
+            .name("notify".to_owned())
+            .spawn(move || deliver_events(receiver, webhooks))?;
+
+        Ok(Self {
+            sender: Mutex::new(sender),
+        })
+    }
+
+    /// Queue an event for delivery, dropping it if the queue is full
+    pub fn send(&self, event: Event) {
+        match self.sender.lock().unwrap().try_send(event) {
+            Ok(()) => (),
+            Err(mpsc::TrySendError::Full(event)) => warn!(
+                "[{}] notification queue full; dropping {} event",
+                event.chain_id, event.event
+            ),
+            Err(mpsc::TrySendError::Disconnected(event)) => error!(
+                "[{}] notification thread exited; dropping {} event",
+                event.chain_id, event.event
+            ),
+        }
+    }
+}
+
+/// Initialize the global notifier from the configuration file
+pub fn init(config: &NotifyConfig) -> Result<(), Error> {
+    if config.webhook.is_empty() {
+        return Ok(());
+    }
+
+    NOTIFIER
+        .set(Notifier::new(config)?)
+        .map_err(|_| format_err!(ConfigError, "notifications already initialized").into())
+}
+
+/// Send an event to all configured webhooks (no-op if none are configured)
+pub fn send(event: Event) {
+    if let Some(notifier) = NOTIFIER.get() {
+        notifier.send(event);
+    }
+}
+
+/// Deliver queued events to webhooks until the queue is closed
+fn deliver_events(receiver: mpsc::Receiver<Event>, mut webhooks: Vec<Webhook>) {
+    for event in receiver {
+        for webhook in &mut webhooks {
+            webhook.notify(&event);
+        }
+    }
+}
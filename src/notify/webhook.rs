@@ -0,0 +1,268 @@
+//! Webhooks: POST events as JSON to an HTTP endpoint
+
+use super::{Event, EventKind};
+use crate::{
+    chain,
+    config::notify::WebhookConfig,
+    error::{Error, ErrorKind::*},
+    http,
+    prelude::*,
+    Map,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Webhook which receives events
+pub struct Webhook {
+    /// URL of the webhook
+    url: http::Url,
+
+    /// Events this webhook is interested in (empty means all)
+    events: Vec<EventKind>,
+
+    /// Number of times to retry failed requests
+    max_retries: u32,
+
+    /// Delay between retries
+    retry_delay: Duration,
+
+    /// Timeout for each request
+    timeout: Duration,
+
+    /// Minimum interval between events of the same kind for the same chain
+    min_interval: Duration,
+
+    /// Time at which an event of a given kind was last sent for a given chain
+    last_sent: Map<(EventKind, chain::Id), Instant>,
+}
+
+impl Webhook {
+    /// Create a new webhook from the given configuration
+    pub fn new(config: &WebhookConfig) -> Result<Self, Error> {
+        Ok(Self {
+            url: config.url.parse()?,
+            events: config.events.clone(),
+            max_retries: config.max_retries,
+            retry_delay: Duration::from_millis(config.retry_delay_ms),
+            timeout: Duration::from_secs(config.timeout_secs),
+            min_interval: Duration::from_secs(config.min_interval_secs),
+            last_sent: Map::new(),
+        })
+    }
+
+    /// Deliver an event to this webhook (subject to filtering, rate limiting
+    /// and retries)
+    pub fn notify(&mut self, event: &Event) {
+        if !self.events.is_empty() && !self.events.contains(&event.event) {
+            return;
+        }
+
+        if self.is_rate_limited(event) {
+            debug!(
+                "[{}] rate limited {} event for {}",
+                &event.chain_id, event.event, &self.url
+            );
+            return;
+        }
+
+        let body = serde_json::to_vec(event).expect("JSON serialization error");
+
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                thread::sleep(self.retry_delay);
+            }
+
+            match self.post(&body) {
+                Ok(()) => {
+                    self.last_sent
+                        .insert((event.event, event.chain_id.clone()), Instant::now());
+
+                    debug!(
+                        "[{}] sent {} event to {}",
+                        &event.chain_id, event.event, &self.url
+                    );
+                    return;
+                }
+                Err(e) => warn!(
+                    "[{}] error sending {} event to {} (attempt {}/{}): {}",
+                    &event.chain_id,
+                    event.event,
+                    &self.url,
+                    attempt + 1,
+                    self.max_retries + 1,
+                    e
+                ),
+            }
+        }
+
+        error!(
+            "[{}] giving up sending {} event to {}",
+            &event.chain_id, event.event, &self.url
+        );
+    }
+
+    /// Check if an event should be dropped due to rate limiting, i.e. an
+    /// event of the same kind for the same chain was recently delivered
+    fn is_rate_limited(&self, event: &Event) -> bool {
+        let key = (event.event, event.chain_id.clone());
+
+        self.last_sent
+            .get(&key)
+            .map(|last_sent| last_sent.elapsed() < self.min_interval)
+            .unwrap_or(false)
+    }
+
+    /// POST the given JSON body to this webhook
+    fn post(&self, body: &[u8]) -> Result<(), Error> {
+        let response = http::post_json(&self.url, body, self.timeout)?;
+
+        if response.is_success() {
+            Ok(())
+        } else {
+            fail!(HttpError, "unexpected HTTP status: {}", response.status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::{self, JoinHandle},
+    };
+
+    /// Spawn a local HTTP server which answers requests with the given status
+    /// codes (in order), returning the request bodies it received
+    fn spawn_server(statuses: &'static [u16]) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            statuses
+                .iter()
+                .map(|status| {
+                    let (socket, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(socket);
+                    let mut content_length = 0;
+
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+
+                        if line == "\r\n" {
+                            break;
+                        }
+
+                        if let Some(len) = line.strip_prefix("Content-Length: ") {
+                            content_length = len.trim().parse().unwrap();
+                        }
+                    }
+
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    let response =
+                        format!("HTTP/1.1 {} Whatever\r\nContent-Length: 0\r\n\r\n", status);
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+                    String::from_utf8(body).unwrap()
+                })
+                .collect()
+        });
+
+        (url, handle)
+    }
+
+    fn webhook_config(url: String) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            events: vec![],
+            max_retries: 3,
+            retry_delay_ms: 10,
+            timeout_secs: 5,
+            min_interval_secs: 60,
+        }
+    }
+
+    fn example_event(kind: EventKind) -> Event {
+        Event::new(
+            kind,
+            &"test_chain_id".parse().unwrap(),
+            "tcp://127.0.0.1:26658",
+            "example event",
+        )
+        .height(42u64)
+    }
+
+    #[test]
+    fn posts_event_json() {
+        let (url, server) = spawn_server(&[200]);
+        let mut webhook = Webhook::new(&webhook_config(url)).unwrap();
+        webhook.notify(&example_event(EventKind::DoubleSign));
+
+        let bodies = server.join().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(json["event"], "double_sign");
+        assert_eq!(json["chain_id"], "test_chain_id");
+        assert_eq!(json["height"], 42);
+    }
+
+    #[test]
+    fn retries_failed_requests() {
+        let (url, server) = spawn_server(&[500, 503, 200]);
+        let mut webhook = Webhook::new(&webhook_config(url)).unwrap();
+        webhook.notify(&example_event(EventKind::SigningError));
+
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn rate_limits_repeated_events() {
+        let (url, server) = spawn_server(&[200, 200]);
+        let mut webhook = Webhook::new(&webhook_config(url)).unwrap();
+
+        // The second double sign event should be dropped, so the server
+        // should next receive the height regression event
+        webhook.notify(&example_event(EventKind::DoubleSign));
+        webhook.notify(&example_event(EventKind::DoubleSign));
+        webhook.notify(&example_event(EventKind::HeightRegression));
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].contains("double_sign"));
+        assert!(bodies[1].contains("height_regression"));
+    }
+
+    #[test]
+    fn does_not_rate_limit_after_failures() {
+        let (url, server) = spawn_server(&[500, 200]);
+        let mut config = webhook_config(url);
+        config.max_retries = 0;
+
+        // The first double sign event couldn't be delivered, so the second
+        // one shouldn't be dropped
+        let mut webhook = Webhook::new(&config).unwrap();
+        webhook.notify(&example_event(EventKind::DoubleSign));
+        webhook.notify(&example_event(EventKind::DoubleSign));
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[1].contains("double_sign"));
+    }
+
+    #[test]
+    fn filters_events() {
+        let (url, server) = spawn_server(&[200]);
+        let mut config = webhook_config(url);
+        config.events = vec![EventKind::TxBroadcastFailure];
+
+        let mut webhook = Webhook::new(&config).unwrap();
+        webhook.notify(&example_event(EventKind::DoubleSign));
+        webhook.notify(&example_event(EventKind::TxBroadcastFailure));
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].contains("tx_broadcast_failure"));
+    }
+}
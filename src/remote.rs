@@ -0,0 +1,11 @@
+//! Signer chaining: an edge KMS forwards signing requests over Secret
+//! Connection to an upstream KMS running in listen mode, which holds the keys.
+//!
+//! The sign bytes of each request are parsed by the upstream KMS so it can
+//! enforce its own double signing protection, in addition to that of the
+//! edge KMS (which updates its chain state before forwarding requests).
+
+pub mod listener;
+pub mod protocol;
+
+pub use self::listener::Listener;
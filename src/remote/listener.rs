@@ -0,0 +1,337 @@
+//! Listen mode: accept Secret Connections from edge KMS instances and sign
+//! on their behalf, enforcing our own double signing protection
+
+use super::protocol::{self, Request, Response, SignedMsg};
+use crate::{
+    chain::{self, state::StateErrorKind, Chain, GlobalRegistry},
+    config::listen::ListenConfig,
+    connection::tcp,
+    error::{Error, ErrorKind::*},
+    key_utils,
+    notify::{self, EventKind},
+    prelude::*,
+    shutdown,
+};
+use ed25519_dalek as ed25519;
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use subtle::ConstantTimeEq;
+use tendermint::{net, node};
+use tendermint_p2p::secret_connection::{self, PublicKey};
+
+/// Join handle type used by listeners
+type JoinHandle = thread::JoinHandle<Result<(), Error>>;
+
+/// Default timeout in seconds
+const DEFAULT_TIMEOUT: u16 = 10;
+
+/// How often to check for shutdown while waiting for connections
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Listener for connections from edge KMS instances
+pub struct Listener {
+    /// Name of the listener thread
+    name: String,
+
+    /// Address the listener is bound to
+    local_addr: SocketAddr,
+
+    /// Handle to the listener thread
+    handle: JoinHandle,
+}
+
+impl Listener {
+    /// Bind the configured address and spawn a thread accepting connections
+    pub fn spawn(config: ListenConfig) -> Result<Self, Error> {
+        Self::spawn_with_registry(config, &chain::REGISTRY)
+    }
+
+    /// Bind the configured address and spawn a thread accepting connections,
+    /// signing for the chains in the given registry
+    pub(crate) fn spawn_with_registry(
+        config: ListenConfig,
+        registry: &'static GlobalRegistry,
+    ) -> Result<Self, Error> {
+        let (host, port) = match &config.addr {
+            net::Address::Tcp {
+                peer_id: None,
+                host,
+                port,
+            } => (host.clone(), *port),
+            _ => fail!(
+                ConfigError,
+                "[[listen]] addr must be of the form `tcp://host:port`: {}",
+                &config.addr
+            ),
+        };
+
+        if config.allowed_peers.is_empty() {
+            fail!(
+                ConfigError,
+                "[[listen]] {}: `allowed_peers` must list the node IDs of edge KMS instances",
+                &config.addr
+            );
+        }
+
+        for chain_id in &config.chain_ids {
+            if registry.get().get_chain(chain_id).is_none() {
+                fail!(
+                    ConfigError,
+                    "[[listen]] {}: unregistered chain: {} (add it to tmkms.toml's [[chain]] section)",
+                    &config.addr,
+                    chain_id
+                );
+            }
+        }
+
+        let identity_key = key_utils::load_base64_ed25519_key(&config.secret_key)?;
+        info!("KMS node ID: {}", PublicKey::from(&identity_key));
+
+        let listener = TcpListener::bind((host.as_str(), port))?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        info!(listener = %config.addr, "listening for edge KMS connections");
+
+        let name = format!("listen@{}", &config.addr);
+        let identity_key = Arc::new(identity_key);
+        let config = Arc::new(config);
+
+        let handle = thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || accept_loop(listener, config, identity_key, registry))?;
+
+        Ok(Self {
+            name,
+            local_addr,
+            handle,
+        })
+    }
+
+    /// Get the name of this listener
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the address this listener is bound to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait for a running listener to finish
+    pub fn join(self) -> Result<(), Error> {
+        self.handle.join().unwrap()
+    }
+}
+
+/// Accept connections until shutdown is requested
+fn accept_loop(
+    listener: TcpListener,
+    config: Arc<ListenConfig>,
+    identity_key: Arc<ed25519::Keypair>,
+    registry: &'static GlobalRegistry,
+) -> Result<(), Error> {
+    while !shutdown::requested() {
+        let (socket, peer_addr) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::WouldBlock {
+                    error!(listener = %config.addr, "error accepting connection: {}", e);
+                }
+
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+                continue;
+            }
+        };
+
+        let config = config.clone();
+        let identity_key = identity_key.clone();
+
+        thread::Builder::new()
+            .name(format!("edge@{}", peer_addr))
+            .spawn(move || {
+                if let Err(e) = serve(socket, &config, &identity_key, registry) {
+                    // Errors are expected once shutdown closes the connection
+                    if !shutdown::requested() {
+                        warn!(
+                            listener = %config.addr,
+                            peer_addr = %peer_addr,
+                            "edge KMS connection closed: {}",
+                            e
+                        );
+                    }
+                }
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Authenticate an edge KMS and serve its requests
+fn serve(
+    socket: TcpStream,
+    config: &ListenConfig,
+    identity_key: &ed25519::Keypair,
+    registry: &GlobalRegistry,
+) -> Result<(), Error> {
+    // Accepted sockets may inherit the listener's non-blocking mode
+    socket.set_nonblocking(false)?;
+
+    let timeout = Duration::from_secs(config.timeout.unwrap_or(DEFAULT_TIMEOUT).into());
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    let _shutdown = shutdown::register(shutdown::Socket::Tcp(socket.try_clone()?));
+
+    let identity_key = ed25519::Keypair::from_bytes(&identity_key.to_bytes()).unwrap();
+    let mut connection = tcp::secret_handshake(
+        socket.try_clone()?,
+        identity_key,
+        secret_connection::Version::V0_34,
+    )?;
+
+    let peer_id = connection.remote_pubkey().peer_id();
+
+    if !config
+        .allowed_peers
+        .iter()
+        .any(|allowed| allowed.ct_eq(&peer_id).unwrap_u8() == 1)
+    {
+        fail!(VerificationError, "unauthorized edge KMS: {}", peer_id);
+    }
+
+    // Connections are idle between blocks
+    socket.set_read_timeout(None)?;
+
+    info!(listener = %config.addr, peer_id = %peer_id, "edge KMS connected");
+
+    loop {
+        let request: Request = protocol::read_message(&mut connection)?;
+
+        // Don't begin processing new requests once shutdown has been requested
+        let _in_flight = match shutdown::begin_request() {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+
+        let response = handle_request(registry, config, &peer_id, &request).unwrap_or_else(|e| {
+            error!(
+                listener = %config.addr,
+                peer_id = %peer_id,
+                chain_id = %request.chain_id,
+                "request failed: {}",
+                e
+            );
+
+            Response::error(e)
+        });
+
+        protocol::write_message(&mut connection, &response)?;
+    }
+}
+
+/// Handle a request from an edge KMS
+fn handle_request(
+    registry: &GlobalRegistry,
+    config: &ListenConfig,
+    peer_id: &node::Id,
+    request: &Request,
+) -> Result<Response, Error> {
+    let chain_id: chain::Id = request.chain_id.parse()?;
+
+    if !config.chain_ids.contains(&chain_id) {
+        fail!(AccessError, "chain not permitted: {}", chain_id);
+    }
+
+    let registry = registry.get();
+
+    let chain = registry.get_chain(&chain_id).unwrap_or_else(|| {
+        panic!("chain '{}' missing from registry!", chain_id);
+    });
+
+    if request.sign_bytes.is_empty() {
+        let public_key = chain.keyring.default_ed25519_pubkey()?;
+
+        return Ok(Response {
+            public_key: public_key.public_key().as_bytes().to_vec(),
+            ..Response::default()
+        });
+    }
+
+    let msg = protocol::parse_sign_bytes(&request.sign_bytes, config.protocol_version)?;
+
+    if msg.chain_id != chain_id {
+        fail!(
+            ChainIdError,
+            "sign bytes are for chain {} (expected {})",
+            msg.chain_id,
+            chain_id
+        );
+    }
+
+    update_consensus_state(chain, peer_id, &msg)?;
+
+    let signer = chain.keyring.get_ed25519_signer(None)?;
+    let signature = signer.sign(&request.sign_bytes)?;
+
+    if let Some(watchdog) = &chain.watchdog {
+        watchdog.record_signature(msg.state.height.value());
+    }
+
+    info!(
+        chain_id = %chain_id,
+        peer_id = %peer_id,
+        msg_type = ?msg.msg_type,
+        height = msg.state.height.value(),
+        round = msg.state.round.value(),
+        step = msg.state.step,
+        block_id = %msg.state.block_id_prefix(),
+        provider = %signer.provider(),
+        "signed {:?}:{} at h/r/s {} for edge KMS",
+        msg.msg_type,
+        msg.state.block_id_prefix(),
+        msg.state,
+    );
+
+    Ok(Response {
+        signature: signature.to_bytes().to_vec(),
+        ..Response::default()
+    })
+}
+
+/// Check the message being signed against our own record of the chain's
+/// consensus state, refusing to double sign
+fn update_consensus_state(chain: &Chain, peer_id: &node::Id, msg: &SignedMsg) -> Result<(), Error> {
+    let mut chain_state = chain.state.lock().unwrap();
+
+    match chain_state.update_consensus_state(msg.state.clone()) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == StateErrorKind::DoubleSign => {
+            let original_block_id = chain_state.consensus_state().block_id_prefix();
+
+            let message = format!(
+                "edge KMS {} attempted double sign {:?} at h/r/s: {} ({} != {})",
+                peer_id,
+                msg.msg_type,
+                msg.state,
+                original_block_id,
+                msg.state.block_id_prefix()
+            );
+
+            error!(chain_id = %msg.chain_id, peer_id = %peer_id, "{}", &message);
+
+            notify::send(
+                notify::Event::new(EventKind::DoubleSign, &msg.chain_id, peer_id, &message)
+                    .height(msg.state.height.value()),
+            );
+
+            fail!(DoubleSign, "{}", message)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
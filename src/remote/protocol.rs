@@ -0,0 +1,368 @@
+//! Protocol spoken between an edge KMS and the upstream KMS it forwards
+//! signing requests to.
+//!
+//! Each request and response is a length-delimited Protocol Buffers message
+//! sent in a single Secret Connection frame.
+
+use crate::{
+    amino_types::{
+        block_id::ParseId, proposal::CanonicalProposal, vote::CanonicalVote, CanonicalBlockId,
+        CanonicalPartSetHeader, SignedMsgType,
+    },
+    config::validator::ProtocolVersion,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use bytes_v0_5::BytesMut as BytesMutV05;
+use prost::Message as _;
+use prost_amino::Message as _;
+use prost_derive::Message;
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+use tendermint::{block, chain, consensus};
+use tendermint_p2p::secret_connection::DATA_MAX_SIZE;
+use tendermint_proto as proto;
+
+/// Request sent by an edge KMS
+#[derive(Clone, PartialEq, Message)]
+pub struct Request {
+    /// Chain whose consensus key is requested
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+
+    /// Canonical sign bytes of the vote or proposal to be signed, or empty
+    /// to request the public key
+    #[prost(bytes, tag = "2")]
+    pub sign_bytes: Vec<u8>,
+}
+
+/// Response from the upstream KMS
+#[derive(Clone, PartialEq, Message)]
+pub struct Response {
+    /// Ed25519 public key (in response to a public key request)
+    #[prost(bytes, tag = "1")]
+    pub public_key: Vec<u8>,
+
+    /// Ed25519 signature over the requested sign bytes
+    #[prost(bytes, tag = "2")]
+    pub signature: Vec<u8>,
+
+    /// Error message if the request failed
+    #[prost(string, tag = "3")]
+    pub error: String,
+}
+
+impl Response {
+    /// Create an error response
+    pub fn error(error: impl ToString) -> Self {
+        Self {
+            error: error.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Write a message as a single Secret Connection frame
+pub fn write_message(conn: &mut impl Write, msg: &impl prost::Message) -> Result<(), Error> {
+    let mut buf = vec![];
+    msg.encode_length_delimited(&mut buf)?;
+
+    if buf.len() > DATA_MAX_SIZE {
+        fail!(ProtocolError, "message too large: {} bytes", buf.len());
+    }
+
+    conn.write_all(&buf)?;
+    Ok(())
+}
+
+/// Read a message sent as a single Secret Connection frame
+pub fn read_message<M>(conn: &mut impl Read) -> Result<M, Error>
+where
+    M: prost::Message + Default,
+{
+    let mut buf = vec![0; DATA_MAX_SIZE];
+    let len = conn.read(&mut buf)?;
+
+    if len == 0 {
+        return Err(Error::from(io::Error::from(io::ErrorKind::UnexpectedEof)));
+    }
+
+    Ok(M::decode_length_delimited(&buf[..len])?)
+}
+
+/// Consensus message parsed from the sign bytes of a request
+#[derive(Clone, Debug)]
+pub struct SignedMsg {
+    /// Chain ID the message is for
+    pub chain_id: chain::Id,
+
+    /// Type of the message
+    pub msg_type: SignedMsgType,
+
+    /// Consensus state (height/round/step/block ID) being signed
+    pub state: consensus::State,
+}
+
+/// Parse the canonical sign bytes of a vote or proposal, so the upstream KMS
+/// can check for double signing based on exactly what it's asked to sign
+pub fn parse_sign_bytes(
+    sign_bytes: &[u8],
+    protocol_version: ProtocolVersion,
+) -> Result<SignedMsg, Error> {
+    let (msg, encoded) = if protocol_version.is_protobuf() {
+        parse_protobuf(sign_bytes)?
+    } else {
+        parse_amino(sign_bytes)?
+    };
+
+    // Reject anything other than the canonical encoding of the parsed message
+    // (e.g. trailing data or unknown fields)
+    if encoded != sign_bytes {
+        fail!(ProtocolError, "sign bytes aren't canonically encoded");
+    }
+
+    Ok(msg)
+}
+
+/// Parse Protocol Buffers-encoded sign bytes (Tendermint v0.34+)
+fn parse_protobuf(sign_bytes: &[u8]) -> Result<(SignedMsg, Vec<u8>), Error> {
+    let mut encoded = vec![];
+
+    let vote = proto::types::CanonicalVote::decode_length_delimited(sign_bytes)
+        .ok()
+        .filter(|vote| vote.r#type as u32 != SignedMsgType::Proposal.to_u32());
+
+    let (msg_type, height, round, block_id, chain_id) = match vote {
+        Some(vote) => {
+            vote.encode_length_delimited(&mut encoded)?;
+            let msg_type = vote_type(vote.r#type as u32)?;
+            (
+                msg_type,
+                vote.height,
+                vote.round,
+                vote.block_id,
+                vote.chain_id,
+            )
+        }
+        None => {
+            let proposal = proto::types::CanonicalProposal::decode_length_delimited(sign_bytes)?;
+            proposal.encode_length_delimited(&mut encoded)?;
+
+            if proposal.r#type as u32 != SignedMsgType::Proposal.to_u32() {
+                fail!(ProtocolError, "invalid message type: {}", proposal.r#type);
+            }
+
+            (
+                SignedMsgType::Proposal,
+                proposal.height,
+                proposal.round,
+                proposal.block_id,
+                proposal.chain_id,
+            )
+        }
+    };
+
+    let block_id = block_id.map(|block_id| CanonicalBlockId {
+        hash: block_id.hash,
+        parts_header: block_id
+            .part_set_header
+            .map(|parts| CanonicalPartSetHeader {
+                hash: parts.hash,
+                total: parts.total.into(),
+            }),
+    });
+
+    let msg = signed_msg(msg_type, height, round, block_id.as_ref(), &chain_id)?;
+    Ok((msg, encoded))
+}
+
+/// Parse Amino-encoded sign bytes (pre-Tendermint v0.34)
+fn parse_amino(sign_bytes: &[u8]) -> Result<(SignedMsg, Vec<u8>), Error> {
+    let mut encoded = BytesMutV05::new();
+
+    let vote = CanonicalVote::decode_length_delimited(sign_bytes)
+        .ok()
+        .filter(|vote| vote.vote_type != SignedMsgType::Proposal.to_u32());
+
+    let msg = match vote {
+        Some(vote) => {
+            vote.encode_length_delimited(&mut encoded)?;
+            signed_msg(
+                vote_type(vote.vote_type)?,
+                vote.height,
+                vote.round,
+                vote.block_id.as_ref(),
+                &vote.chain_id,
+            )?
+        }
+        None => {
+            let proposal = CanonicalProposal::decode_length_delimited(sign_bytes)?;
+            proposal.encode_length_delimited(&mut encoded)?;
+
+            if proposal.msg_type != SignedMsgType::Proposal.to_u32() {
+                fail!(ProtocolError, "invalid message type: {}", proposal.msg_type);
+            }
+
+            signed_msg(
+                SignedMsgType::Proposal,
+                proposal.height,
+                proposal.round,
+                proposal.block_id.as_ref(),
+                &proposal.chain_id,
+            )?
+        }
+    };
+
+    Ok((msg, encoded.to_vec()))
+}
+
+/// Parse the type of a vote
+fn vote_type(msg_type: u32) -> Result<SignedMsgType, Error> {
+    if msg_type == SignedMsgType::PreVote.to_u32() {
+        Ok(SignedMsgType::PreVote)
+    } else if msg_type == SignedMsgType::PreCommit.to_u32() {
+        Ok(SignedMsgType::PreCommit)
+    } else {
+        fail!(ProtocolError, "invalid message type: {}", msg_type)
+    }
+}
+
+/// Build a `SignedMsg` from the fields of a canonical vote or proposal
+fn signed_msg(
+    msg_type: SignedMsgType,
+    height: i64,
+    round: i64,
+    block_id: Option<&CanonicalBlockId>,
+    chain_id: &str,
+) -> Result<SignedMsg, Error> {
+    let round =
+        u16::try_from(round).map_err(|_| format_err!(ProtocolError, "invalid round: {}", round))?;
+
+    let state = consensus::State {
+        height: block::Height::try_from(height)?,
+        round: block::Round::from(round),
+        step: match msg_type {
+            SignedMsgType::Proposal => 0,
+            SignedMsgType::PreVote => 1,
+            SignedMsgType::PreCommit => 2,
+        },
+        // Votes for `<nil>` have no (valid) block ID
+        block_id: block_id.and_then(|block_id| block_id.parse_block_id().ok()),
+    };
+
+    Ok(SignedMsg {
+        chain_id: chain_id.parse()?,
+        msg_type,
+        state,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amino_types::{
+        BlockId, PartsSetHeader, Proposal, SignProposalRequest, SignVoteRequest, SignableMsg,
+        TimeMsg, Vote,
+    };
+
+    const CHAIN_ID: &str = "test_chain_id";
+
+    fn block_id() -> BlockId {
+        BlockId::new(
+            vec![0xab; 32],
+            Some(PartsSetHeader {
+                total: 1_000_000,
+                hash: vec![0xcd; 32],
+            }),
+        )
+    }
+
+    fn timestamp() -> Option<TimeMsg> {
+        Some(TimeMsg {
+            seconds: 1_500_000_000,
+            nanos: 0,
+        })
+    }
+
+    fn sign_bytes(request: &impl SignableMsg, protocol_version: ProtocolVersion) -> Vec<u8> {
+        let mut sign_bytes = vec![];
+        request
+            .sign_bytes(CHAIN_ID.parse().unwrap(), protocol_version, &mut sign_bytes)
+            .unwrap();
+        sign_bytes
+    }
+
+    #[test]
+    fn parses_sign_bytes() {
+        let vote = SignVoteRequest {
+            vote: Some(Vote {
+                vote_type: SignedMsgType::PreCommit.to_u32(),
+                height: 12345,
+                round: 2,
+                block_id: Some(block_id()),
+                timestamp: timestamp(),
+                validator_address: vec![0xa3; 20],
+                validator_index: 56789,
+                signature: vec![],
+            }),
+        };
+
+        let proposal = SignProposalRequest {
+            proposal: Some(Proposal {
+                msg_type: SignedMsgType::Proposal.to_u32(),
+                height: 12345,
+                round: 23456,
+                pol_round: -1,
+                block_id: Some(block_id()),
+                timestamp: timestamp(),
+                signature: vec![],
+            }),
+        };
+
+        for &protocol_version in &[ProtocolVersion::V0_34, ProtocolVersion::Legacy] {
+            let vote_bytes = sign_bytes(&vote, protocol_version);
+            let msg = parse_sign_bytes(&vote_bytes, protocol_version).unwrap();
+            assert_eq!(msg.chain_id.as_str(), CHAIN_ID);
+            assert!(matches!(msg.msg_type, SignedMsgType::PreCommit));
+            assert_eq!(msg.state.height.value(), 12345);
+            assert_eq!(msg.state.round.value(), 2);
+            assert_eq!(msg.state.step, 2);
+            assert!(msg.state.block_id.is_some());
+
+            let proposal_bytes = sign_bytes(&proposal, protocol_version);
+            let msg = parse_sign_bytes(&proposal_bytes, protocol_version).unwrap();
+            assert!(matches!(msg.msg_type, SignedMsgType::Proposal));
+            assert_eq!(msg.state.round.value(), 23456);
+            assert_eq!(msg.state.step, 0);
+
+            // Trailing data isn't signed
+            let mut tampered = vote_bytes.clone();
+            tampered.push(0);
+            assert!(parse_sign_bytes(&tampered, protocol_version).is_err());
+        }
+    }
+
+    #[test]
+    fn round_trips_messages() {
+        let request = Request {
+            chain_id: CHAIN_ID.to_owned(),
+            sign_bytes: vec![1, 2, 3],
+        };
+
+        let mut buf = vec![];
+        write_message(&mut buf, &request).unwrap();
+        assert_eq!(
+            read_message::<Request>(&mut buf.as_slice()).unwrap(),
+            request
+        );
+
+        // Messages must fit in a single Secret Connection frame
+        let oversized = Response {
+            signature: vec![0; DATA_MAX_SIZE],
+            ..Response::default()
+        };
+        assert!(write_message(&mut vec![], &oversized).is_err());
+    }
+}
@@ -3,7 +3,7 @@
 // TODO: docs for everything
 #![allow(missing_docs)]
 
-use std::io::Read;
+use std::{convert::TryFrom, io::Read};
 
 use bytes_v0_5::Bytes;
 use prost::Message as _;
@@ -115,7 +115,7 @@ pub enum Response {
     SignedVote(amino_types::SignedVoteResponse),
     SignedProposal(amino_types::SignedProposalResponse),
     Ping(amino_types::PingResponse),
-    PublicKey(amino_types::PubKeyResponse),
+    PublicKey(tendermint::PublicKey),
 }
 
 impl Response {
@@ -160,12 +160,8 @@ impl Response {
                     proto::privval::message::Sum::PingResponse(proto::privval::PingResponse {})
                 }
                 Response::PublicKey(pk) => {
-                    let pk = proto::crypto::PublicKey {
-                        sum: Some(proto::crypto::public_key::Sum::Ed25519(pk.pub_key_ed25519)),
-                    };
-
                     proto::privval::message::Sum::PubKeyResponse(proto::privval::PubKeyResponse {
-                        pub_key: Some(pk),
+                        pub_key: Some(pk.into()),
                         error: None,
                     })
                 }
@@ -180,7 +176,9 @@ impl Response {
                 Response::SignedProposal(sp) => sp.encode(&mut buf)?,
                 Response::SignedVote(sv) => sv.encode(&mut buf)?,
                 Response::Ping(ping) => ping.encode(&mut buf)?,
-                Response::PublicKey(pk) => pk.encode(&mut buf)?,
+                Response::PublicKey(pk) => {
+                    amino_types::PubKeyResponse::try_from(pk)?.encode(&mut buf)?
+                }
             }
 
             Ok(buf)
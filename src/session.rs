@@ -1,15 +1,16 @@
 //! A session with a validator node
 
 use crate::{
-    amino_types::{
-        PingResponse, PubKeyRequest, PubKeyResponse, RemoteError, SignedMsgType, TendermintRequest,
-    },
+    amino_types::{PingResponse, PubKeyRequest, RemoteError, SignedMsgType, TendermintRequest},
     chain::{self, state::StateErrorKind, Chain},
     config::ValidatorConfig,
     connection::{tcp, unix::UnixConnection, Connection},
     error::{Error, ErrorKind::*},
+    keyring::SigningProvider,
+    notify::{self, EventKind},
     prelude::*,
     rpc::{Request, Response},
+    shutdown,
 };
 use std::{fmt::Debug, os::unix::net::UnixStream, time::Instant};
 use tendermint::{consensus, net};
@@ -21,23 +22,27 @@ pub struct Session {
 
     /// TCP connection to a validator node
     connection: Box<dyn Connection>,
+
+    /// Closes the connection on shutdown while registered
+    _shutdown: shutdown::Registration,
 }
 
 impl Session {
     /// Open a session using the given validator configuration
     pub fn open(config: ValidatorConfig) -> Result<Self, Error> {
-        let connection: Box<dyn Connection> = match &config.addr {
+        let (connection, socket): (Box<dyn Connection>, _) = match &config.addr {
             net::Address::Tcp {
                 peer_id,
                 host,
                 port,
             } => {
                 debug!(
-                    "[{}@{}] connecting to validator...",
-                    &config.chain_id, &config.addr
+                    chain_id = %config.chain_id,
+                    validator = %config.addr,
+                    "connecting to validator..."
                 );
 
-                let conn = tcp::open_secret_connection(
+                let (conn, socket) = tcp::open_secret_connection(
                     host,
                     *port,
                     &config.secret_key,
@@ -47,59 +52,84 @@ impl Session {
                 )?;
 
                 info!(
-                    "[{}@{}] connected to validator successfully",
-                    &config.chain_id, &config.addr
+                    chain_id = %config.chain_id,
+                    validator = %config.addr,
+                    "connected to validator successfully"
                 );
 
                 if peer_id.is_none() {
                     // TODO(tarcieri): make peer verification mandatory
                     warn!(
-                        "[{}@{}]: unverified validator peer ID! ({})",
-                        &config.chain_id,
-                        &config.addr,
-                        conn.remote_pubkey().peer_id()
+                        chain_id = %config.chain_id,
+                        validator = %config.addr,
+                        peer_id = %conn.remote_pubkey().peer_id(),
+                        "unverified validator peer ID!"
                     );
                 }
 
-                Box::new(conn)
+                (Box::new(conn), shutdown::Socket::Tcp(socket))
             }
             net::Address::Unix { path } => {
                 if let Some(timeout) = config.timeout {
-                    warn!("timeouts not supported with Unix sockets: {}", timeout);
+                    warn!(
+                        chain_id = %config.chain_id,
+                        validator = %config.addr,
+                        "timeouts not supported with Unix sockets: {}",
+                        timeout
+                    );
                 }
 
                 debug!(
-                    "{}: Connecting to socket at {}...",
-                    &config.chain_id, &config.addr
+                    chain_id = %config.chain_id,
+                    validator = %config.addr,
+                    "connecting to socket..."
                 );
 
                 let socket = UnixStream::connect(path)?;
+                let handle = socket.try_clone()?;
                 let conn = UnixConnection::new(socket);
 
                 info!(
-                    "[{}@{}] connected to validator successfully",
-                    &config.chain_id, &config.addr
+                    chain_id = %config.chain_id,
+                    validator = %config.addr,
+                    "connected to validator successfully"
                 );
 
-                Box::new(conn)
+                (Box::new(conn), shutdown::Socket::Unix(handle))
             }
         };
 
-        Ok(Self { config, connection })
+        Ok(Self {
+            config,
+            connection,
+            _shutdown: shutdown::register(socket),
+        })
     }
 
-    /// Main request loop
-    pub fn request_loop(&mut self) -> Result<(), Error> {
-        while self.handle_request()? {}
+    /// Main request loop, calling `heartbeat` after each handled request
+    pub fn request_loop(&mut self, mut heartbeat: impl FnMut()) -> Result<(), Error> {
+        while self.handle_request()? {
+            heartbeat();
+        }
+
         Ok(())
     }
 
     /// Handle an incoming request from the validator
     fn handle_request(&mut self) -> Result<bool, Error> {
         let request = Request::read(&mut self.connection, self.config.protocol_version)?;
+
+        // Don't begin processing new requests once shutdown has been requested
+        let _in_flight = match shutdown::begin_request() {
+            Some(guard) => guard,
+            None => return Ok(false),
+        };
+
         debug!(
-            "[{}@{}] received request: {:?}",
-            &self.config.chain_id, &self.config.addr, &request
+            chain_id = %self.config.chain_id,
+            validator = %self.config.addr,
+            "received request: {:?}",
+            &request
         );
 
         let response = match request {
@@ -111,8 +141,10 @@ impl Session {
         };
 
         debug!(
-            "[{}@{}] sending response: {:?}",
-            &self.config.chain_id, &self.config.addr, &response
+            chain_id = %self.config.chain_id,
+            validator = %self.config.addr,
+            "sending response: {:?}",
+            &response
         );
 
         let response_bytes = response.encode(self.config.protocol_version)?;
@@ -152,12 +184,32 @@ impl Session {
             &mut to_sign,
         )?;
 
+        // TODO(ismail): figure out which key to use here instead of taking the only key
+        let signer = chain.keyring.consensus_signer()?;
         let started_at = Instant::now();
 
-        // TODO(ismail): figure out which key to use here instead of taking the only key
-        let signature = chain.keyring.sign_ed25519(None, &to_sign)?;
+        let signature = match signer.sign(&to_sign) {
+            Ok(signature) => signature,
+            Err(e) => {
+                error!(
+                    chain_id = %self.config.chain_id,
+                    validator = %self.config.addr,
+                    provider = %signer.provider(),
+                    "signing failed: {}",
+                    e
+                );
+
+                self.notify(EventKind::SigningError, request.height(), &e);
+                return Err(e);
+            }
+        };
+
+        if let (Some(watchdog), Some(height)) = (&chain.watchdog, request.height()) {
+            watchdog.record_signature(height as u64);
+        }
 
-        self.log_signing_request(&request, started_at).unwrap();
+        self.log_signing_request(&request, started_at, signer.provider())
+            .unwrap();
         request.set_signature(&signature);
 
         Ok(request.build_response(None))
@@ -206,23 +258,47 @@ impl Session {
                 let original_block_id = chain_state.consensus_state().block_id_prefix();
 
                 error!(
-                    "[{}@{}] attempted double sign {:?} at h/r/s: {} ({} != {})",
-                    &self.config.chain_id,
-                    &self.config.addr,
+                    chain_id = %self.config.chain_id,
+                    validator = %self.config.addr,
+                    msg_type = ?msg_type,
+                    height = request_state.height.value(),
+                    round = request_state.round.value(),
+                    step = request_state.step,
+                    block_id = %request_state.block_id_prefix(),
+                    original_block_id = %original_block_id,
+                    "attempted double sign {:?} at h/r/s: {} ({} != {})",
                     msg_type,
                     request_state,
                     original_block_id,
                     request_state.block_id_prefix()
                 );
 
+                self.notify(
+                    EventKind::DoubleSign,
+                    request.height(),
+                    format!(
+                        "attempted double sign {:?} at h/r/s: {} ({} != {})",
+                        msg_type,
+                        request_state,
+                        original_block_id,
+                        request_state.block_id_prefix()
+                    ),
+                );
+
                 let remote_err = RemoteError::double_sign(request_state.height.into());
                 Ok(Some(remote_err))
             }
-            Err(e) => Err(e.into()),
+            Err(e) => {
+                if e.kind() == StateErrorKind::HeightRegression {
+                    self.notify(EventKind::HeightRegression, request.height(), &e);
+                }
+
+                Err(e.into())
+            }
         }
     }
 
-    /// Get the public key for (the only) public key in the keyring
+    /// Get the public key for (the only) consensus key in the keyring
     fn get_public_key(&mut self, _request: &PubKeyRequest) -> Result<Response, Error> {
         let registry = chain::REGISTRY.get();
 
@@ -232,26 +308,49 @@ impl Session {
                 panic!("chain '{}' missing from registry!", &self.config.chain_id);
             });
 
-        Ok(Response::PublicKey(PubKeyResponse::from(
-            *chain.keyring.default_ed25519_pubkey()?,
-        )))
+        let public_key = chain.keyring.consensus_signer()?.public_key();
+        Ok(Response::PublicKey(*public_key.public_key()))
+    }
+
+    /// Send a notification about an event which occurred in this session
+    fn notify(&self, kind: EventKind, height: Option<i64>, message: impl ToString) {
+        let mut event = notify::Event::new(kind, &self.config.chain_id, &self.config.addr, message);
+
+        if let Some(height) = height {
+            event = event.height(height as u64);
+        }
+
+        notify::send(event);
     }
 
     /// Write an INFO logline about a signing request
-    fn log_signing_request<R>(&self, request: &R, started_at: Instant) -> Result<(), Error>
+    fn log_signing_request<R>(
+        &self,
+        request: &R,
+        started_at: Instant,
+        provider: SigningProvider,
+    ) -> Result<(), Error>
     where
         R: TendermintRequest + Debug,
     {
         let (msg_type, request_state) = parse_request(request)?;
+        let latency_ms = started_at.elapsed().as_millis() as u64;
 
         info!(
-            "[{}@{}] signed {:?}:{} at h/r/s {} ({} ms)",
-            &self.config.chain_id,
-            &self.config.addr,
+            chain_id = %self.config.chain_id,
+            validator = %self.config.addr,
+            msg_type = ?msg_type,
+            height = request_state.height.value(),
+            round = request_state.round.value(),
+            step = request_state.step,
+            block_id = %request_state.block_id_prefix(),
+            latency_ms,
+            provider = %provider,
+            "signed {:?}:{} at h/r/s {} ({} ms)",
             msg_type,
             request_state.block_id_prefix(),
             request_state,
-            started_at.elapsed().as_millis(),
+            latency_ms,
         );
 
         Ok(())
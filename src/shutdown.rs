@@ -0,0 +1,296 @@
+//! Graceful shutdown on `SIGTERM`/`SIGINT`.
+//!
+//! When a signal is received the KMS stops accepting new requests, waits for
+//! any in-flight signing requests to finish (consensus state is persisted
+//! before a signature is produced), then closes all validator connections so
+//! client threads can exit. A second signal exits immediately.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use nix::sys::signal::{SigSet, Signal};
+use once_cell::sync::Lazy;
+use std::{
+    net::{self, TcpStream},
+    os::unix::net::UnixStream,
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+        Mutex, RwLock, RwLockReadGuard,
+    },
+    thread,
+};
+
+/// Exit status used when a second signal forces an immediate exit
+pub const FORCED_EXIT_CODE: i32 = 130;
+
+/// Shutdown state of the process
+static STATE: Lazy<State> = Lazy::new(Default::default);
+
+/// Sockets which can be closed from another thread
+#[derive(Debug)]
+pub enum Socket {
+    /// TCP socket
+    Tcp(TcpStream),
+
+    /// Unix domain socket
+    Unix(UnixStream),
+}
+
+impl Socket {
+    /// Shut down both halves of this socket
+    fn close(&self) {
+        // Errors (e.g. the peer already hung up) are not interesting here
+        let _ = match self {
+            Socket::Tcp(socket) => socket.shutdown(net::Shutdown::Both),
+            Socket::Unix(socket) => socket.shutdown(net::Shutdown::Both),
+        };
+    }
+}
+
+/// Block `SIGTERM` and `SIGINT` in the calling thread.
+///
+/// This must be called before any other threads are spawned (i.e. before the
+/// application's components are registered): new threads inherit the signal
+/// mask, so the signals are only ever delivered to the thread started by
+/// [`spawn_handler`].
+pub fn block_signals() -> Result<(), Error> {
+    signals()
+        .thread_block()
+        .map_err(|e| format_err!(IoError, "couldn't block signals: {}", e).into())
+}
+
+/// Handle `SIGTERM` and `SIGINT` (which must have been blocked with
+/// [`block_signals`]) in a background thread
+pub fn spawn_handler() -> Result<(), Error> {
+    thread::Builder::new()
+        .name("signals".to_owned())
+        .spawn(|| handle_signals(signals()))?;
+
+    Ok(())
+}
+
+/// Signals which begin a graceful shutdown
+fn signals() -> SigSet {
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGTERM);
+    signals.add(Signal::SIGINT);
+    signals
+}
+
+/// Has a shutdown been requested?
+pub fn requested() -> bool {
+    STATE.requested()
+}
+
+/// Begin a request. Returns `None` if the KMS is shutting down, in which case
+/// the request should not be processed. Shutdown waits until the returned
+/// guard is dropped.
+pub fn begin_request() -> Option<RwLockReadGuard<'static, ()>> {
+    STATE.begin_request()
+}
+
+/// Register a socket to be closed on shutdown. The socket is deregistered
+/// when the returned handle is dropped.
+pub fn register(socket: Socket) -> Registration {
+    Registration(STATE.register(socket))
+}
+
+/// Handle to a registered socket
+#[derive(Debug)]
+pub struct Registration(usize);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        STATE.deregister(self.0);
+    }
+}
+
+/// Request a graceful shutdown: stop accepting requests, wait for in-flight
+/// requests to complete, then close all registered sockets
+pub fn request() {
+    STATE.request();
+}
+
+/// Request a graceful shutdown (as with `request`) after which the process
+/// should exit with the given status
+pub fn request_exit(code: i32) {
+    STATE.exit_code.store(code, Ordering::SeqCst);
+    STATE.request();
+}
+
+/// Get the status the process should exit with once shutdown has completed
+pub fn exit_code() -> i32 {
+    STATE.exit_code.load(Ordering::SeqCst)
+}
+
+/// Shutdown state, of which the functions in this module use a single
+/// process-wide instance
+#[derive(Debug, Default)]
+struct State {
+    /// Has a shutdown been requested?
+    shutdown: AtomicBool,
+
+    /// Exit status once shutdown has completed (non-zero if it was requested
+    /// because of an error)
+    exit_code: AtomicI32,
+
+    /// Held (shared) while a request is being processed, and exclusively
+    /// while shutting down
+    in_flight: RwLock<()>,
+
+    /// Sockets to close on shutdown
+    sockets: Mutex<Vec<(usize, Socket)>>,
+
+    /// Counter used to identify registered sockets
+    next_socket_id: AtomicUsize,
+}
+
+impl State {
+    /// Has a shutdown been requested?
+    fn requested(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Begin a request, unless shutting down
+    fn begin_request(&self) -> Option<RwLockReadGuard<'_, ()>> {
+        // Acquire the lock before checking the flag: shutdown sets the flag
+        // before acquiring the lock exclusively, so either we observe the flag
+        // or shutdown waits for us
+        let guard = self.in_flight.read().unwrap_or_else(|e| e.into_inner());
+
+        if self.requested() {
+            None
+        } else {
+            Some(guard)
+        }
+    }
+
+    /// Register a socket to be closed on shutdown, returning its ID
+    fn register(&self, socket: Socket) -> usize {
+        let id = self.next_socket_id.fetch_add(1, Ordering::SeqCst);
+        let mut sockets = self.sockets.lock().unwrap();
+
+        // Close immediately if we're already shutting down
+        if self.requested() {
+            socket.close();
+        }
+
+        sockets.push((id, socket));
+        id
+    }
+
+    /// Deregister the socket with the given ID
+    fn deregister(&self, id: usize) {
+        // Avoid panicking (i.e. aborting) in the event the lock is poisoned
+        if let Ok(mut sockets) = self.sockets.lock() {
+            sockets.retain(|(socket_id, _)| *socket_id != id);
+        }
+    }
+
+    /// Stop accepting requests, wait for in-flight requests to complete, then
+    /// close all registered sockets
+    fn request(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+
+        // Wait for in-flight requests to finish
+        drop(self.in_flight.write().unwrap_or_else(|e| e.into_inner()));
+
+        for (_, socket) in self
+            .sockets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            socket.close();
+        }
+    }
+}
+
+/// Wait for signals, beginning a graceful shutdown on the first and exiting
+/// immediately on the second
+fn handle_signals(signals: SigSet) {
+    loop {
+        let signal = match signals.wait() {
+            Ok(signal) => signal,
+            Err(e) => {
+                error!("error waiting for signals: {}", e);
+                return;
+            }
+        };
+
+        if requested() {
+            warn!("received {} during shutdown; exiting immediately", signal);
+            process::exit(FORCED_EXIT_CODE);
+        }
+
+        info!("received {}; shutting down gracefully...", signal);
+
+        // Shut down from a separate thread so a second signal is still handled
+        // if an in-flight request hangs
+        thread::Builder::new()
+            .name("shutdown".to_owned())
+            .spawn(request)
+            .unwrap_or_else(|e| {
+                error!("error spawning shutdown thread: {}", e);
+                process::exit(1);
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{self, Read},
+        sync::Arc,
+    };
+
+    #[test]
+    fn shutdown_closes_sockets_after_in_flight_requests() {
+        let state = Arc::new(State::default());
+        let (socket, mut peer) = UnixStream::pair().unwrap();
+        peer.set_nonblocking(true).unwrap();
+        state.register(Socket::Unix(socket));
+
+        let in_flight = state.begin_request().unwrap();
+        let shutdown = thread::spawn({
+            let state = Arc::clone(&state);
+            move || state.request()
+        });
+
+        while !state.requested() {
+            thread::yield_now();
+        }
+
+        // The socket stays open until the in-flight request completes
+        let mut buf = [0u8; 1];
+        assert_eq!(
+            peer.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        drop(in_flight);
+        shutdown.join().unwrap();
+
+        // The socket was closed, so the peer sees EOF
+        assert_eq!(peer.read(&mut buf).unwrap(), 0);
+
+        // New requests are refused once shutdown has begun
+        assert!(state.begin_request().is_none());
+    }
+
+    #[test]
+    fn closes_sockets_registered_during_shutdown() {
+        let state = State::default();
+        state.request();
+
+        let (socket, mut peer) = UnixStream::pair().unwrap();
+        let id = state.register(Socket::Unix(socket));
+        assert_eq!(peer.read(&mut [0u8; 1]).unwrap(), 0);
+
+        state.deregister(id);
+        assert!(state.sockets.lock().unwrap().is_empty());
+    }
+}
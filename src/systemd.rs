@@ -0,0 +1,468 @@
+//! systemd service notifications (`Type=notify`) using the `NOTIFY_SOCKET`
+//! datagram protocol (see `sd_notify(3)`).
+//!
+//! Once the chain registry and keyring providers are loaded, a background
+//! thread monitors validator connections, sending `READY=1` once every chain
+//! has at least one connected validator session, `STATUS=` updates whenever
+//! the connection state changes, and `WATCHDOG=1` keepalives if the service
+//! has `WatchdogSec=` configured.
+//!
+//! Keepalives are only sent while every open validator session is making
+//! progress, i.e. handling requests (validators ping the KMS every few
+//! seconds), so the service manager restarts the KMS if a session hangs.
+
+use crate::{
+    chain, client,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+    shutdown, Map,
+};
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use nix::{
+    sys::socket::{self, AddressFamily, MsgFlags, SockAddr, SockFlag, SockType, UnixAddr},
+    unistd,
+};
+use std::{
+    env, io,
+    os::unix::net::UnixDatagram,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+/// Environment variable containing the notification socket address
+pub const NOTIFY_SOCKET_ENV_VAR: &str = "NOTIFY_SOCKET";
+
+/// Environment variable containing the watchdog interval in microseconds
+pub const WATCHDOG_USEC_ENV_VAR: &str = "WATCHDOG_USEC";
+
+/// Environment variable containing the PID the watchdog applies to
+pub const WATCHDOG_PID_ENV_VAR: &str = "WATCHDOG_PID";
+
+/// Maximum interval at which connection state is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Open validator sessions which haven't handled a request for this long are
+/// considered stuck, and watchdog keepalives stop
+pub const PROGRESS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Sends notifications to the service manager
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    /// Address of the notification socket
+    addr: String,
+}
+
+impl Notifier {
+    /// Create a notifier for the given socket address. Addresses beginning
+    /// with `@` are in the Linux abstract socket namespace.
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into() }
+    }
+
+    /// Create a notifier from `NOTIFY_SOCKET`, if we're running under a
+    /// service manager which supports notifications
+    pub fn from_env() -> Option<Self> {
+        env::var(NOTIFY_SOCKET_ENV_VAR)
+            .ok()
+            .filter(|addr| !addr.is_empty())
+            .map(Self::new)
+    }
+
+    /// Send the given newline-separated `KEY=VALUE` assignments
+    pub fn notify(&self, state: &str) -> Result<(), Error> {
+        let result = match self.addr.strip_prefix('@') {
+            Some(name) => send_abstract(name, state.as_bytes()),
+            None => UnixDatagram::unbound()
+                .and_then(|socket| socket.send_to(state.as_bytes(), &self.addr))
+                .map(|_| ()),
+        };
+
+        result.map_err(|e| format_err!(IoError, "error notifying {}: {}", &self.addr, e))?;
+        Ok(())
+    }
+
+    /// Notify the service manager that startup has completed
+    pub fn ready(&self, status: &str) -> Result<(), Error> {
+        self.notify(&format!("READY=1\nSTATUS={}", status))
+    }
+
+    /// Update the service's status string
+    pub fn status(&self, status: &str) -> Result<(), Error> {
+        self.notify(&format!("STATUS={}", status))
+    }
+
+    /// Send a watchdog keepalive
+    pub fn watchdog(&self) -> Result<(), Error> {
+        self.notify("WATCHDOG=1")
+    }
+
+    /// Notify the service manager that we're shutting down
+    pub fn stopping(&self) -> Result<(), Error> {
+        self.notify("STOPPING=1")
+    }
+}
+
+/// Send a datagram to a socket in the abstract namespace
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn send_abstract(name: &str, msg: &[u8]) -> io::Result<()> {
+    // Errors without an errno are due to an invalid address
+    let to_io_error = |e: nix::Error| match e.as_errno() {
+        Some(errno) => io::Error::from(errno),
+        None => io::Error::from(io::ErrorKind::InvalidInput),
+    };
+
+    let addr = SockAddr::Unix(UnixAddr::new_abstract(name.as_bytes()).map_err(to_io_error)?);
+
+    let fd = socket::socket(
+        AddressFamily::Unix,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        None,
+    )
+    .map_err(to_io_error)?;
+
+    let result = socket::sendto(fd, msg, &addr, MsgFlags::empty());
+
+    // Errors closing the socket aren't actionable
+    let _ = unistd::close(fd);
+
+    result.map(|_| ()).map_err(to_io_error)
+}
+
+/// Abstract sockets are Linux-specific
+#[cfg(not(any(target_os = "android", target_os = "linux")))]
+fn send_abstract(_name: &str, _msg: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "abstract sockets unsupported on this platform",
+    ))
+}
+
+/// Get the interval at which watchdog keepalives should be sent (half of the
+/// configured timeout), if the watchdog is enabled for this process
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog_interval(
+        env::var(WATCHDOG_USEC_ENV_VAR).ok().as_deref(),
+        env::var(WATCHDOG_PID_ENV_VAR).ok().as_deref(),
+        process::id(),
+    )
+}
+
+/// Parse `WATCHDOG_USEC` and `WATCHDOG_PID`
+fn parse_watchdog_interval(
+    usec: Option<&str>,
+    pid: Option<&str>,
+    our_pid: u32,
+) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok() != Some(our_pid) {
+            return None;
+        }
+    }
+
+    match usec?.parse::<u64>() {
+        Ok(usec) if usec > 0 => Some(Duration::from_micros(usec / 2)),
+        _ => None,
+    }
+}
+
+/// Is the KMS healthy enough to send watchdog keepalives? Lock poisoning is
+/// unrecoverable, so stop sending keepalives and let the service manager
+/// restart us.
+fn is_healthy(validators: &Map<chain::Id, usize>) -> bool {
+    let registry = chain::REGISTRY.get();
+
+    validators.keys().all(|chain_id| {
+        registry
+            .get_chain(chain_id)
+            .map(|chain| !chain.state.is_poisoned())
+            .unwrap_or(false)
+    })
+}
+
+/// Spawn a thread which sends readiness, status, and watchdog notifications
+/// for the given validators (number of configured validators for each chain)
+pub fn spawn(notifier: Notifier, validators: Map<chain::Id, usize>) -> Result<(), Error> {
+    let mut monitor = Monitor::new(notifier, validators, watchdog_interval());
+
+    thread::Builder::new()
+        .name("systemd".to_owned())
+        .spawn(move || monitor.run())?;
+
+    Ok(())
+}
+
+/// Monitors validator connections and reports them to the service manager
+struct Monitor {
+    /// Notifier for the service manager
+    notifier: Notifier,
+
+    /// Number of configured validators for each chain
+    validators: Map<chain::Id, usize>,
+
+    /// Interval at which to send watchdog keepalives (if enabled)
+    watchdog: Option<Duration>,
+
+    /// Function which counts open sessions for a chain
+    open_sessions: fn(&chain::Id) -> usize,
+
+    /// Function which gets the time the least recently active open session
+    /// for a chain last handled a request
+    last_progress: fn(&chain::Id) -> Option<Instant>,
+
+    /// Time after which sessions which haven't handled a request are stuck
+    progress_timeout: Duration,
+
+    /// Is a session currently stuck?
+    stuck: bool,
+
+    /// Have we sent `READY=1`?
+    ready: bool,
+
+    /// Last status sent
+    last_status: String,
+
+    /// Time the last watchdog keepalive was sent
+    last_keepalive: Option<Instant>,
+}
+
+impl Monitor {
+    /// Create a new monitor
+    fn new(
+        notifier: Notifier,
+        validators: Map<chain::Id, usize>,
+        watchdog: Option<Duration>,
+    ) -> Self {
+        Self {
+            notifier,
+            validators,
+            watchdog,
+            open_sessions: client::open_sessions,
+            last_progress: client::last_progress,
+            progress_timeout: PROGRESS_TIMEOUT,
+            stuck: false,
+            ready: false,
+            last_status: String::new(),
+            last_keepalive: None,
+        }
+    }
+
+    /// Poll until shutdown is requested
+    fn run(&mut self) {
+        let poll_interval = self
+            .watchdog
+            .map_or(POLL_INTERVAL, |interval| interval.min(POLL_INTERVAL));
+
+        while !shutdown::requested() {
+            self.poll();
+            thread::sleep(poll_interval);
+        }
+
+        if let Err(e) = self.notifier.stopping() {
+            warn!("{}", e);
+        }
+    }
+
+    /// Send any notifications which are due
+    fn poll(&mut self) {
+        let (all_connected, status) = self.connection_status();
+
+        let result = if !self.ready && all_connected {
+            self.ready = true;
+            info!("notifying service manager we're ready ({})", &status);
+            self.notifier.ready(&status)
+        } else if status != self.last_status {
+            self.notifier.status(&status)
+        } else {
+            Ok(())
+        };
+
+        if let Err(e) = result {
+            warn!("{}", e);
+        }
+
+        self.last_status = status;
+
+        if let Some(interval) = self.watchdog {
+            let due = match self.last_keepalive {
+                Some(sent) => sent.elapsed() >= interval,
+                None => true,
+            };
+
+            if due && self.making_progress() && is_healthy(&self.validators) {
+                if let Err(e) = self.notifier.watchdog() {
+                    warn!("{}", e);
+                }
+
+                self.last_keepalive = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Are all open validator sessions making progress, i.e. have they
+    /// handled a request recently? Logs when sessions get stuck or recover.
+    fn making_progress(&mut self) -> bool {
+        let (last_progress, timeout) = (self.last_progress, self.progress_timeout);
+
+        let stuck_chain = self
+            .validators
+            .keys()
+            .find(|chain_id| match last_progress(chain_id) {
+                Some(last) => last.elapsed() >= timeout,
+                None => false,
+            });
+
+        match (stuck_chain, self.stuck) {
+            (Some(chain_id), false) => error!(
+                chain_id = %chain_id,
+                "validator session hasn't handled a request in {}s: stopping watchdog keepalives",
+                self.progress_timeout.as_secs()
+            ),
+            (None, true) => info!("validator sessions are making progress again"),
+            _ => (),
+        }
+
+        self.stuck = stuck_chain.is_some();
+        !self.stuck
+    }
+
+    /// Describe the state of validator connections, returning whether all
+    /// chains have at least one connected session
+    fn connection_status(&self) -> (bool, String) {
+        let mut all_connected = true;
+        let mut chains = Vec::with_capacity(self.validators.len());
+
+        for (chain_id, total) in &self.validators {
+            let connected = (self.open_sessions)(chain_id);
+            all_connected &= connected > 0;
+            chains.push(format!("{} {}/{}", chain_id, connected, total));
+        }
+
+        (
+            all_connected,
+            format!("connected validators: {}", chains.join(", ")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn recv(socket: &UnixDatagram) -> String {
+        let mut buf = [0u8; 1024];
+        let len = socket.recv(&mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn sends_notifications_to_socket() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notify.sock");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        let notifier = Notifier::new(path.to_str().unwrap());
+
+        notifier
+            .ready("connected validators: test_chain_id 1/1")
+            .unwrap();
+        assert_eq!(
+            recv(&socket),
+            "READY=1\nSTATUS=connected validators: test_chain_id 1/1"
+        );
+
+        notifier.watchdog().unwrap();
+        assert_eq!(recv(&socket), "WATCHDOG=1");
+
+        notifier.stopping().unwrap();
+        assert_eq!(recv(&socket), "STOPPING=1");
+    }
+
+    #[test]
+    fn reports_connection_status() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notify.sock");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket.set_nonblocking(true).unwrap();
+
+        let mut validators = Map::new();
+        validators.insert("chain-a".parse().unwrap(), 2);
+        validators.insert("chain-b".parse().unwrap(), 1);
+
+        let mut monitor = Monitor::new(Notifier::new(path.to_str().unwrap()), validators, None);
+
+        // Only one chain has a connected validator: not ready yet
+        monitor.open_sessions = |chain_id| (chain_id.as_str() == "chain-a") as usize;
+        monitor.poll();
+        assert_eq!(
+            recv(&socket),
+            "STATUS=connected validators: chain-a 1/2, chain-b 0/1"
+        );
+
+        // Status is only sent when it changes
+        monitor.poll();
+        assert!(socket.recv(&mut [0u8; 1024]).is_err());
+
+        // Every chain has a connected validator: ready
+        monitor.open_sessions = |_| 1;
+        monitor.poll();
+        assert_eq!(
+            recv(&socket),
+            "READY=1\nSTATUS=connected validators: chain-a 1/2, chain-b 1/1"
+        );
+
+        // Readiness is only sent once
+        monitor.open_sessions = |_| 0;
+        monitor.poll();
+        assert_eq!(
+            recv(&socket),
+            "STATUS=connected validators: chain-a 0/2, chain-b 0/1"
+        );
+    }
+
+    #[test]
+    fn checks_session_progress() {
+        let mut validators = Map::new();
+        validators.insert("chain-a".parse().unwrap(), 1);
+
+        let dir = TempDir::new().unwrap();
+        let notifier = Notifier::new(dir.path().join("notify.sock").to_str().unwrap());
+        let mut monitor = Monitor::new(notifier, validators, Some(Duration::from_secs(1)));
+
+        monitor.last_progress = |_| Some(Instant::now());
+        assert!(monitor.making_progress());
+
+        // The session hasn't handled a request within the timeout
+        monitor.progress_timeout = Duration::from_secs(0);
+        assert!(!monitor.making_progress());
+
+        // Chains without open sessions don't hold back keepalives
+        monitor.last_progress = |_| None;
+        assert!(monitor.making_progress());
+    }
+
+    #[test]
+    fn errors_without_listener() {
+        let dir = TempDir::new().unwrap();
+        let notifier = Notifier::new(dir.path().join("missing.sock").to_str().unwrap());
+        assert!(notifier.watchdog().is_err());
+    }
+
+    #[test]
+    fn parses_watchdog_interval() {
+        assert_eq!(
+            parse_watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            parse_watchdog_interval(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            parse_watchdog_interval(Some("30000000"), Some("7"), 42),
+            None
+        );
+        assert_eq!(parse_watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(parse_watchdog_interval(None, None, 42), None);
+    }
+}
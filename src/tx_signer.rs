@@ -16,11 +16,13 @@ use crate::{
     chain,
     config::tx_signer::{PollInterval, TxAcl, TxSignerConfig, TxSource},
     error::{Error, ErrorKind},
+    notify::{self, EventKind},
     prelude::*,
+    shutdown,
 };
 use abscissa_tokio::tokio;
 use sequence_file::SequenceFile;
-use std::process;
+use std::{process, time::Instant};
 use stdtx::amino;
 use subtle_encoding::hex;
 use tendermint_rpc::{endpoint::status, Client};
@@ -105,37 +107,49 @@ impl TxSigner {
         })
     }
 
-    /// Run the transaction signer
+    /// Run the transaction signer until shutdown is requested.
+    ///
+    /// A transaction which is already being signed/broadcast when shutdown is
+    /// requested is allowed to complete; no new transactions are requested.
     pub async fn run(&mut self) {
         // Fetch the block height via RPC and use that to synchronize the
         // block interval to the block height count
         let mut next_block = loop {
+            if shutdown::requested() {
+                return;
+            }
+
             match self.rpc_client.status().await {
                 Ok(status) => {
                     break self.next_block_after(status.sync_info.latest_block_height.value())
                 }
                 Err(e) => {
                     warn!(
-                        "[{}] error getting initial block height: {}",
-                        self.chain_id, e
+                        chain_id = %self.chain_id,
+                        "error getting initial block height: {}",
+                        e
                     );
                     time::sleep(RETRY_DELAY).await
                 }
             }
         };
 
-        loop {
+        while !shutdown::requested() {
             info!(
-                "[{}] waiting until block height: {}",
-                &self.chain_id, next_block
+                chain_id = %self.chain_id,
+                height = next_block,
+                "waiting until block height: {}",
+                next_block
             );
 
             let status = match self.wait_until_block_height(next_block).await {
-                Ok(status) => status,
+                Ok(Some(status)) => status,
+                Ok(None) => break,
                 Err(e) => {
                     error!(
-                        "[{}] couldn't get current block height via RPC: {}",
-                        &self.chain_id, e
+                        chain_id = %self.chain_id,
+                        "couldn't get current block height via RPC: {}",
+                        e
                     );
                     time::sleep(RETRY_DELAY).await;
                     continue;
@@ -146,16 +160,24 @@ impl TxSigner {
             next_block = self.next_block_after(current_block_height);
 
             if let Err(e) = self.request_and_sign_tx(status).await {
-                error!("[{}] {} - {}", &self.chain_id, self.source.uri(), e);
+                error!(
+                    chain_id = %self.chain_id,
+                    source = %self.source.uri(),
+                    "{}",
+                    e
+                );
             }
         }
+
+        info!(chain_id = %self.chain_id, "TX signer stopped");
     }
 
-    /// Wait until the chain is at the given block height
+    /// Wait until the chain is at the given block height, returning `None` if
+    /// shutdown is requested while waiting
     async fn wait_until_block_height(
         &mut self,
         target_height: u64,
-    ) -> Result<status::Response, Error> {
+    ) -> Result<Option<status::Response>, Error> {
         let (block_interval, min_secs) = match self.poll_interval {
             PollInterval::Block { blocks, min_secs } => (blocks, min_secs),
         };
@@ -163,34 +185,53 @@ impl TxSigner {
         let min_deadline = time::Instant::now() + time::Duration::from_secs(min_secs);
 
         loop {
+            if shutdown::requested() {
+                return Ok(None);
+            }
+
             let status = self.rpc_client.status().await?;
             let current_height = status.sync_info.latest_block_height.value();
 
             debug!(
-                "[{}] current block height is: {}",
-                &self.chain_id, current_height
+                chain_id = %self.chain_id,
+                height = current_height,
+                "current block height is: {}",
+                current_height
             );
 
             if current_height >= target_height {
                 if time::Instant::now() < min_deadline {
                     warn!(
-                        "[{}] target height {} reached before min_secs deadline ({}s)! \
+                        chain_id = %self.chain_id,
+                        height = current_height,
+                        "target height {} reached before min_secs deadline ({}s)! \
                         sleeping... (is node catching up?)",
-                        &self.chain_id, target_height, min_secs
+                        target_height,
+                        min_secs
                     );
 
-                    time::sleep_until(min_deadline).await;
+                    while time::Instant::now() < min_deadline {
+                        if shutdown::requested() {
+                            return Ok(None);
+                        }
+
+                        time::sleep(RPC_POLL_INTERVAL).await;
+                    }
                 }
 
-                return Ok(status);
+                return Ok(Some(status));
             } else if target_height.checked_sub(current_height).unwrap() > block_interval {
                 warn!(
+                    chain_id = %self.chain_id,
+                    height = current_height,
                     "block wait sanity check failed: current={} target={} interval={}",
-                    current_height, target_height, block_interval
+                    current_height,
+                    target_height,
+                    block_interval
                 );
 
                 // Hopefully returning the current status will sync us back up if this ever happens
-                return Ok(status);
+                return Ok(Some(status));
             }
 
             time::sleep(RPC_POLL_INTERVAL).await
@@ -243,7 +284,13 @@ impl TxSigner {
         let retry_on_failure = !self.last_tx.is_response();
 
         if let Err(e) = self.broadcast_tx(sign_msg, seq).await {
-            error!("[{}] {} - {}", &self.chain_id, self.source.uri(), e);
+            error!(
+                chain_id = %self.chain_id,
+                source = %self.source.uri(),
+                sequence = seq,
+                "{}",
+                e
+            );
 
             // If the last transaction errored, speculatively try the next
             // sequence number, as the previous transaction may have been
@@ -253,15 +300,22 @@ impl TxSigner {
                 let seq = seq.checked_add(1).unwrap();
 
                 warn!(
-                    "[{}] {} - retrying transaction at sequence {}",
-                    &self.chain_id,
-                    self.source.uri(),
+                    chain_id = %self.chain_id,
+                    source = %self.source.uri(),
+                    sequence = seq,
+                    "retrying transaction at sequence {}",
                     seq
                 );
 
                 let sign_msg = SignMsg::new(&tx_req, &self.tx_builder, seq)?;
                 if let Err(e) = self.broadcast_tx(sign_msg, seq).await {
-                    error!("[{}] {} - {}", &self.chain_id, self.source.uri(), e);
+                    error!(
+                        chain_id = %self.chain_id,
+                        source = %self.source.uri(),
+                        sequence = seq,
+                        "{}",
+                        e
+                    );
 
                     // Try a third time for good measure
                     // If we wanted to generalize this, it could use a loop,
@@ -271,9 +325,10 @@ impl TxSigner {
                     let seq = seq.checked_add(1).unwrap();
 
                     warn!(
-                        "[{}] {} - retrying transaction at sequence {}",
-                        &self.chain_id,
-                        self.source.uri(),
+                        chain_id = %self.chain_id,
+                        source = %self.source.uri(),
+                        sequence = seq,
+                        "retrying transaction at sequence {}",
                         seq
                     );
 
@@ -286,8 +341,29 @@ impl TxSigner {
         Ok(())
     }
 
-    /// Broadcast signed transaction to the Tendermint P2P network via RPC
+    /// Broadcast signed transaction to the Tendermint P2P network via RPC,
+    /// sending a notification in the event it fails
     async fn broadcast_tx(&mut self, sign_msg: SignMsg, sequence: u64) -> Result<(), Error> {
+        let result = self.try_broadcast_tx(sign_msg, sequence).await;
+
+        if let Err(e) = &result {
+            let address = self
+                .address
+                .to_bech32(self.tx_builder.schema().acc_prefix());
+
+            notify::send(notify::Event::new(
+                EventKind::TxBroadcastFailure,
+                &self.chain_id,
+                address,
+                format!("sequence {}: {}", sequence, e),
+            ));
+        }
+
+        result
+    }
+
+    /// Broadcast signed transaction to the Tendermint P2P network via RPC
+    async fn try_broadcast_tx(&mut self, sign_msg: SignMsg, sequence: u64) -> Result<(), Error> {
         let tx = self.sign_tx(&sign_msg)?;
 
         let amino_tx = tendermint::abci::Transaction::from(
@@ -298,8 +374,9 @@ impl TxSigner {
             String::from_utf8(hex::encode(amino_tx.as_ref())).expect("hex should always be UTF-8");
 
         info!(
-            "[{}] broadcasting TX: {}",
-            self.chain_id,
+            chain_id = %self.chain_id,
+            sequence,
+            "broadcasting TX: {}",
             amino_tx_hex.to_ascii_uppercase()
         );
 
@@ -338,8 +415,11 @@ impl TxSigner {
         }
 
         info!(
-            "[{}] successfully broadcast TX {} (shash={})",
-            self.chain_id,
+            chain_id = %self.chain_id,
+            sequence,
+            height = response.height.value(),
+            tx_hash = %response.hash,
+            "successfully broadcast TX {} (shash={})",
             self.seq_file.sequence(),
             response.hash
         );
@@ -356,9 +436,10 @@ impl TxSigner {
             panic!("chain '{}' missing from registry!", &self.chain_id);
         });
 
-        debug!("[{}] performing signature", &self.chain_id);
+        debug!(chain_id = %self.chain_id, "performing signature");
 
         let account_id = tendermint::account::Id::new(self.address.0);
+        let started_at = Instant::now();
 
         let mut signature = amino::StdSignature::from(
             chain
@@ -385,8 +466,12 @@ impl TxSigner {
             .to_bech32(self.tx_builder.schema().acc_prefix());
 
         info!(
-            "[{}] signed TX {} for {} ({} msgs total; types: {})",
-            self.chain_id,
+            chain_id = %self.chain_id,
+            account = %address,
+            sequence = self.seq_file.sequence(),
+            msg_type = %msg_type_info,
+            latency_ms = started_at.elapsed().as_millis() as u64,
+            "signed TX {} for {} ({} msgs total; types: {})",
             self.seq_file.sequence(),
             address,
             sign_msg.msgs().len(),
@@ -90,6 +90,7 @@ impl SequenceFile {
 
         let mut state_file = NamedTempFile::new_in(parent_dir)?;
         state_file.write_all(json.as_bytes())?;
+        state_file.as_file().sync_all()?;
         state_file.persist(&self.path)?;
 
         Ok(())
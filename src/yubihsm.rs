@@ -1,9 +1,16 @@
 //! Application-local YubiHSM configuration and initialization
 
+pub mod attestation;
+pub mod audit;
+mod device;
+
+pub use self::device::{Device, Signer, Status};
+
 use crate::{
-    config::provider::yubihsm::YubihsmConfig,
+    config::provider::yubihsm::{AuditLogConfig, YubihsmConfig},
     error::{Error, ErrorKind},
     prelude::*,
+    Map,
 };
 use once_cell::sync::Lazy;
 #[cfg(all(feature = "yubihsm-server", not(feature = "yubihsm-mock")))]
@@ -12,7 +19,7 @@ use std::{
     process,
     sync::{
         atomic::{self, AtomicBool},
-        Mutex, MutexGuard,
+        Arc, Mutex,
     },
 };
 use yubihsm::{Client, Connector};
@@ -27,13 +34,8 @@ use {
     yubihsm::{device::SerialNumber, HttpConfig, UsbConfig},
 };
 
-/// Connection to the YubiHSM device
-// TODO(tarcieri): refactor with a straightforward `once_cell::sync::OnceCell`
-static HSM_CONNECTOR: Lazy<Connector> = Lazy::new(init_connector);
-
-/// Authenticated client connection to the YubiHSM device
-// TODO(tarcieri): refactor with a straightforward `once_cell::sync::OnceCell`
-static HSM_CLIENT: Lazy<Mutex<Client>> = Lazy::new(|| Mutex::new(init_client()));
+/// YubiHSM devices used by this process, keyed by device ID
+static DEVICES: Lazy<Mutex<Map<String, Arc<Device>>>> = Lazy::new(Default::default);
 
 /// Flag indicating we're inside of a `tmkms yubihsm` command
 // TODO(tarcieri): refactor with a straightforward `once_cell::sync::OnceCell`
@@ -50,20 +52,69 @@ fn is_cli_command() -> bool {
     CLI_COMMAND.load(atomic::Ordering::SeqCst)
 }
 
-/// Get the global HSM connector configured from global settings
-pub fn connector() -> &'static Connector {
-    &HSM_CONNECTOR
+/// Get the selected YubiHSM device (by serial number or label), or the only
+/// configured device if none is selected
+pub fn device(device: Option<&str>) -> Arc<Device> {
+    let config = config(device);
+
+    DEVICES
+        .lock()
+        .unwrap()
+        .entry(config.device_id().to_owned())
+        .or_insert_with(|| {
+            let connector = init_connector(&config);
+            Arc::new(Device::new(config, connector))
+        })
+        .clone()
 }
 
-/// Get an HSM client configured from global settings
-pub fn client() -> MutexGuard<'static, Client> {
-    HSM_CLIENT.lock().unwrap()
+/// Background tasks of a YubiHSM device, which are only started by
+/// `tmkms start` (so other commands never e.g. consume the audit log)
+pub struct Tasks {
+    /// Device to run the tasks for
+    device: Arc<Device>,
+
+    /// Where to drain the device's audit log to (if anywhere)
+    audit_log: Option<AuditLogConfig>,
+}
+
+impl Tasks {
+    /// Create the background tasks of the given device
+    pub fn new(device: Arc<Device>, audit_log: Option<AuditLogConfig>) -> Self {
+        Self { device, audit_log }
+    }
+
+    /// Start probing the device's liveness and draining its audit log in
+    /// background threads
+    pub fn spawn(self) {
+        self.device.spawn_liveness_probe();
+
+        if let Some(audit_log) = self.audit_log {
+            audit::spawn_drain(self.device, audit_log);
+        }
+    }
+}
+
+/// Get the connector for the selected YubiHSM device (by serial number or
+/// label), or the only configured device if none is selected
+pub fn connector(device: Option<&str>) -> Connector {
+    self::device(device).connector()
+}
+
+/// Get an authenticated client for the selected YubiHSM device (by serial
+/// number or label), or the only configured device if none is selected
+pub fn client(device: Option<&str>) -> Client {
+    let device = self::device(device);
+
+    device.client().unwrap_or_else(|e| {
+        status_err!("error connecting to YubiHSM2 ({}): {}", device.id(), e);
+        process::exit(1);
+    })
 }
 
-/// Open a session with the YubiHSM2 using settings from the global config
+/// Open a session with the YubiHSM2 using the given device configuration
 #[cfg(not(feature = "yubihsm-mock"))]
-fn init_connector() -> Connector {
-    let cfg = config();
+fn init_connector(cfg: &YubihsmConfig) -> Connector {
     let serial_number = cfg
         .serial_number
         .as_ref()
@@ -136,47 +187,68 @@ fn connect_http(addr: &net::Address) -> Connector {
     Connector::http(&http_config_for_address(addr))
 }
 
+/// Create a new connector for the given device (re-enumerating USB devices)
+/// when reconnecting to it, without starting another connector server
+#[cfg(not(feature = "yubihsm-mock"))]
+fn reconnect_connector(cfg: &YubihsmConfig, current: &Connector) -> Connector {
+    // CLI commands which use the connector server keep using it
+    #[cfg(feature = "yubihsm-server")]
+    {
+        if let Some(ref connector_server) = cfg.connector_server {
+            if connector_server.cli.is_some() && is_cli_command() {
+                return current.clone();
+            }
+        }
+    }
+
+    #[cfg(not(feature = "yubihsm-server"))]
+    let _ = current;
+
+    let serial_number = cfg
+        .serial_number
+        .as_ref()
+        .map(|serial| serial.parse::<SerialNumber>().unwrap());
+
+    init_connector_adapter(&cfg.adapter, serial_number)
+}
+
 #[cfg(feature = "yubihsm-mock")]
-fn init_connector() -> Connector {
+fn init_connector(_cfg: &YubihsmConfig) -> Connector {
     Connector::mockhsm()
 }
 
-/// Get a `yubihsm::Client` configured from the global configuration
-fn init_client() -> Client {
-    let (credentials, reconnect) = client_config();
-
-    Client::open(connector().clone(), credentials, reconnect).unwrap_or_else(|e| {
-        status_err!("error connecting to YubiHSM2: {}", e);
-        process::exit(1);
-    })
+/// The `MockHsm` only exists in memory, so reconnecting reuses it
+#[cfg(feature = "yubihsm-mock")]
+fn reconnect_connector(_cfg: &YubihsmConfig, current: &Connector) -> Connector {
+    current.clone()
 }
 
 /// Get client configuration settings
 #[cfg(not(feature = "yubihsm-server"))]
-fn client_config() -> (yubihsm::Credentials, bool) {
-    (config().auth.credentials(), true)
+fn client_config(cfg: &YubihsmConfig) -> Result<(yubihsm::Credentials, bool), Error> {
+    Ok((cfg.auth.credentials()?, true))
 }
 
 /// Get client configuration settings, accounting for `yubihsm-server` server
 /// overrides (i.e. local loopback for `tmkms yubihsm` commands)
 #[cfg(feature = "yubihsm-server")]
-fn client_config() -> (yubihsm::Credentials, bool) {
-    let cfg = config();
-
-    cfg.connector_server
-        .as_ref()
-        .and_then(|connector_server| {
-            connector_server.cli.as_ref().and_then(|cli| {
-                cli.auth_key.and_then(|auth_key_id| {
-                    if is_cli_command() {
-                        Some((prompt_for_auth_key_password(auth_key_id), false))
-                    } else {
-                        None
-                    }
-                })
+fn client_config(cfg: &YubihsmConfig) -> Result<(yubihsm::Credentials, bool), Error> {
+    let cli_credentials = cfg.connector_server.as_ref().and_then(|connector_server| {
+        connector_server.cli.as_ref().and_then(|cli| {
+            cli.auth_key.and_then(|auth_key_id| {
+                if is_cli_command() {
+                    Some((prompt_for_auth_key_password(auth_key_id), false))
+                } else {
+                    None
+                }
             })
         })
-        .unwrap_or_else(|| (cfg.auth.credentials(), true))
+    });
+
+    match cli_credentials {
+        Some(credentials) => Ok(credentials),
+        None => Ok((cfg.auth.credentials()?, true)),
+    }
 }
 
 /// Prompt for the password for the given auth key and generate `yubihsm::Credentials`
@@ -194,20 +266,80 @@ fn prompt_for_auth_key_password(auth_key_id: u16) -> yubihsm::Credentials {
     yubihsm::Credentials::from_password(auth_key_id, password.as_bytes())
 }
 
-/// Get the YubiHSM-related configuration
-pub fn config() -> YubihsmConfig {
+/// Get the configuration for the selected YubiHSM device (by serial number
+/// or label), or the only configured device if none is selected
+pub fn config(device: Option<&str>) -> YubihsmConfig {
     let kms_config = APP.config();
-    let yubihsm_configs = &kms_config.providers.yubihsm;
 
-    if yubihsm_configs.len() != 1 {
-        status_err!(
-            "expected one [yubihsm.provider] in config, found: {}",
-            yubihsm_configs.len()
-        );
-        process::exit(1);
+    select_config(&kms_config.providers.yubihsm, device)
+        .cloned()
+        .unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        })
+}
+
+/// Select a YubiHSM device configuration by serial number or label, or the
+/// only configured device if none is selected
+pub fn select_config<'a>(
+    configs: &'a [YubihsmConfig],
+    device: Option<&str>,
+) -> Result<&'a YubihsmConfig, Error> {
+    check_configs(configs)?;
+
+    let mut matching = configs.iter().filter(|config| match device {
+        Some(device) => config.matches(device),
+        None => true,
+    });
+
+    match (matching.next(), matching.next(), device) {
+        (Some(config), None, _) => Ok(config),
+        (None, _, None) => fail!(ErrorKind::ConfigError, "no [[providers.yubihsm]] in config"),
+        (None, _, Some(device)) => fail!(
+            ErrorKind::ConfigError,
+            "no [[providers.yubihsm]] with serial number or label: {}",
+            device
+        ),
+        (Some(_), Some(_), None) => fail!(
+            ErrorKind::ConfigError,
+            "multiple [[providers.yubihsm]] in config: select a device by serial number or label"
+        ),
+        (Some(_), Some(_), Some(device)) => fail!(
+            ErrorKind::ConfigError,
+            "multiple [[providers.yubihsm]] match: {}",
+            device
+        ),
+    }
+}
+
+/// Ensure every configured YubiHSM can be selected unambiguously: when more
+/// than one is configured, each needs a serial number or label, and none of
+/// them may match more than one device
+pub fn check_configs(configs: &[YubihsmConfig]) -> Result<(), Error> {
+    if configs.len() < 2 {
+        return Ok(());
+    }
+
+    for config in configs {
+        if config.serial_number.is_none() && config.label.is_none() {
+            fail!(
+                ErrorKind::ConfigError,
+                "multiple [[providers.yubihsm]] in config: each needs a `serial_number` or `label`"
+            );
+        }
+
+        for device in config.serial_number.iter().chain(config.label.iter()) {
+            if configs.iter().filter(|c| c.matches(device)).count() > 1 {
+                fail!(
+                    ErrorKind::ConfigError,
+                    "ambiguous [[providers.yubihsm]] serial number or label: {} matches more than one device",
+                    device
+                );
+            }
+        }
     }
 
-    yubihsm_configs[0].clone()
+    Ok(())
 }
 
 /// Run a `yubihsm-connector` service in a background thread
@@ -228,3 +360,75 @@ impl From<yubihsm::client::Error> for Error {
         ErrorKind::YubihsmError.context(other).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a `[[providers.yubihsm]]` config with the given extra fields
+    fn yubihsm_config(fields: &str) -> YubihsmConfig {
+        toml::from_str(&format!(
+            "adapter = {{ type = \"usb\" }}\nauth = {{ key = 1, password = \"password\" }}\n{}",
+            fields
+        ))
+        .unwrap()
+    }
+
+    fn configs() -> Vec<YubihsmConfig> {
+        vec![
+            yubihsm_config("serial_number = \"0001234567\"\nlabel = \"hsm-a\""),
+            yubihsm_config("serial_number = \"0007654321\""),
+        ]
+    }
+
+    #[test]
+    fn selects_by_serial_number() {
+        let configs = configs();
+        let config = select_config(&configs, Some("0007654321")).unwrap();
+        assert_eq!(config.device_id(), "0007654321");
+    }
+
+    #[test]
+    fn selects_by_label() {
+        let configs = configs();
+        let config = select_config(&configs, Some("hsm-a")).unwrap();
+        assert_eq!(config.serial_number.as_deref(), Some("0001234567"));
+    }
+
+    #[test]
+    fn selects_only_device() {
+        let configs = vec![yubihsm_config("")];
+        assert_eq!(
+            select_config(&configs, None).unwrap().device_id(),
+            "default"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_device() {
+        let configs = configs();
+        assert!(select_config(&configs, Some("hsm-b")).is_err());
+        assert!(select_config(&[], None).is_err());
+    }
+
+    #[test]
+    fn rejects_unselected_device() {
+        let configs = configs();
+        assert!(select_config(&configs, None).is_err());
+    }
+
+    #[test]
+    fn rejects_ambiguous_devices() {
+        // The second device's label is the first device's serial number
+        let configs = vec![
+            yubihsm_config("serial_number = \"0001234567\""),
+            yubihsm_config("serial_number = \"0007654321\"\nlabel = \"0001234567\""),
+        ];
+
+        assert!(check_configs(&configs).is_err());
+        assert!(select_config(&configs, Some("0007654321")).is_err());
+
+        let configs = vec![yubihsm_config("label = \"hsm-a\""), yubihsm_config("")];
+        assert!(check_configs(&configs).is_err());
+    }
+}
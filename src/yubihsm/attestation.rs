@@ -0,0 +1,870 @@
+//! YubiHSM key attestation: verifying that a key was created inside a
+//! particular YubiHSM.
+//!
+//! The YubiHSM signs an attestation certificate for a key using its
+//! attestation key, whose own certificate (stored as opaque object 0) is
+//! signed by Yubico. The chain is verified up to a self-signed root from a
+//! configured CA file, and the attested public key is checked against the
+//! key's actual public key.
+//!
+//! Only certificate signatures are checked: the device attestation
+//! certificate isn't a CA certificate, so generic path validation would
+//! reject it, and validity periods aren't meaningful for attestations.
+//!
+//! The properties of the key (serial number of the device, origin, domains,
+//! capabilities, and label) are taken from the Yubico extensions of the key
+//! attestation certificate (OID 1.3.6.1.4.1.41482.4.x), as they're signed by
+//! the device, unlike the object info returned by the YubiHSM.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use ring::signature;
+use std::{fs, path::Path};
+use subtle_encoding::base64;
+use yubihsm::{device::SerialNumber, object, Capability, Client, Domain};
+
+/// ID of the opaque object containing the device attestation certificate
+pub const DEVICE_CERTIFICATE_ID: object::Id = 0;
+
+/// Maximum number of CA certificates between the device and the root
+const MAX_CHAIN_LENGTH: usize = 8;
+
+/// DER tags used in certificates
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_VERSION: u8 = 0xa0;
+const TAG_EXTENSIONS: u8 = 0xa3;
+
+/// Signature algorithm OIDs (DER-encoded contents)
+const OID_SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+const OID_SHA384_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+const OID_SHA512_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const OID_ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+
+/// Public key algorithm and curve OIDs (DER-encoded contents)
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_P384: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+
+/// Prefix of the Yubico attestation extension OIDs (1.3.6.1.4.1.41482.4),
+/// followed by one of the `EXT_*` arcs below
+const OID_YUBICO_ATTESTATION: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0xc4, 0x0a, 0x04];
+
+/// Yubico attestation extensions
+const EXT_SERIAL_NUMBER: u8 = 2;
+const EXT_ORIGIN: u8 = 3;
+const EXT_DOMAINS: u8 = 4;
+const EXT_CAPABILITIES: u8 = 5;
+const EXT_OBJECT_ID: u8 = 6;
+const EXT_LABEL: u8 = 9;
+
+/// Verified attestation of a key
+#[derive(Clone, Debug)]
+pub struct Attestation {
+    /// Key attestation certificate (DER)
+    pub certificate: Vec<u8>,
+
+    /// Subjects of the certificates from the key up to the root (DER)
+    pub chain: Vec<Vec<u8>>,
+
+    /// Attested properties of the key
+    pub properties: Properties,
+}
+
+/// Properties of a key attested by the YubiHSM
+#[derive(Clone, Debug)]
+pub struct Properties {
+    /// Serial number of the YubiHSM the key resides in
+    pub serial_number: SerialNumber,
+
+    /// Object ID of the key
+    pub key_id: object::Id,
+
+    /// Whether the key was generated inside the YubiHSM or imported
+    pub origin: object::Origin,
+
+    /// Key domains
+    pub domains: Domain,
+
+    /// Key capabilities
+    pub capabilities: Capability,
+
+    /// Key label
+    pub label: String,
+}
+
+impl Attestation {
+    /// Request an attestation certificate for the given key from the
+    /// YubiHSM, and verify it was issued by a device chaining up to one of
+    /// the given CA certificates, for the given public key.
+    pub fn request(
+        client: &Client,
+        key_id: object::Id,
+        public_key: &[u8],
+        ca_certificates: &[Vec<u8>],
+    ) -> Result<Self, Error> {
+        let certificate = client.sign_attestation_certificate(key_id, None)?;
+        let device_certificate = client.get_opaque(DEVICE_CERTIFICATE_ID)?;
+
+        Self::verify(
+            certificate.as_ref(),
+            &device_certificate,
+            public_key,
+            ca_certificates,
+        )
+    }
+
+    /// Verify a key attestation certificate
+    pub fn verify<'a>(
+        certificate: &'a [u8],
+        device_certificate: &'a [u8],
+        public_key: &[u8],
+        ca_certificates: &'a [Vec<u8>],
+    ) -> Result<Self, Error> {
+        let key_cert = Certificate::parse(certificate)?;
+        let properties = key_cert.properties()?;
+        let device_cert = Certificate::parse(device_certificate)?;
+        let ca_certs = ca_certificates
+            .iter()
+            .map(|der| Certificate::parse(der))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if key_cert.public_key_bits()? != public_key {
+            fail!(
+                VerificationError,
+                "attested public key doesn't match the key's public key"
+            );
+        }
+
+        key_cert.verify_signed_by(&device_cert)?;
+
+        let mut chain = vec![key_cert.subject.to_vec(), device_cert.subject.to_vec()];
+        let mut current = device_cert;
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            let issuer = ca_certs
+                .iter()
+                .find(|ca| ca.subject == current.issuer && current.verify_signed_by(ca).is_ok())
+                .ok_or_else(|| {
+                    format_err!(
+                        VerificationError,
+                        "no CA certificate found for issuer: {}",
+                        describe_name(current.issuer)
+                    )
+                })?;
+
+            chain.push(issuer.subject.to_vec());
+
+            if issuer.subject == issuer.issuer {
+                // Reached a self-signed root
+                issuer.verify_signed_by(issuer)?;
+
+                return Ok(Self {
+                    certificate: certificate.to_vec(),
+                    chain,
+                    properties,
+                });
+            }
+
+            current = issuer.clone();
+        }
+
+        fail!(VerificationError, "certificate chain is too long")
+    }
+
+    /// Describe the subjects of the certificate chain
+    pub fn describe_chain(&self) -> Vec<String> {
+        self.chain.iter().map(|name| describe_name(name)).collect()
+    }
+
+    /// Ensure the attested properties of the key match the serial number
+    /// reported by the device and the (unauthenticated) object info of the key
+    pub fn check_object_info(
+        &self,
+        serial_number: SerialNumber,
+        info: &object::Info,
+    ) -> Result<(), Error> {
+        let properties = &self.properties;
+
+        let mismatch = if properties.serial_number != serial_number {
+            "serial number"
+        } else if properties.key_id != info.object_id {
+            "object ID"
+        } else if properties.origin != info.origin {
+            "origin"
+        } else if properties.domains != info.domains {
+            "domains"
+        } else if properties.capabilities != info.capabilities {
+            "capabilities"
+        } else if properties.label != info.label.to_string() {
+            "label"
+        } else {
+            return Ok(());
+        };
+
+        fail!(
+            VerificationError,
+            "attested {} doesn't match the one reported by the YubiHSM",
+            mismatch
+        )
+    }
+}
+
+/// Load CA certificates from a file containing PEM or DER certificates
+pub fn load_ca_certificates(path: &Path) -> Result<Vec<Vec<u8>>, Error> {
+    let bytes = fs::read(path)
+        .map_err(|e| format_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+    let pem = match std::str::from_utf8(&bytes) {
+        Ok(pem) if pem.contains("-----BEGIN CERTIFICATE-----") => pem,
+        _ => return Ok(vec![bytes]),
+    };
+
+    let mut certificates = vec![];
+
+    for block in pem.split("-----BEGIN CERTIFICATE-----").skip(1) {
+        let body = block
+            .split("-----END CERTIFICATE-----")
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .collect::<String>();
+
+        let der = base64::decode(&body).map_err(|e| {
+            format_err!(
+                ParseError,
+                "malformed certificate in {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+        certificates.push(der);
+    }
+
+    Ok(certificates)
+}
+
+/// Parts of an X.509 certificate needed to verify attestations
+#[derive(Clone, Debug)]
+struct Certificate<'a> {
+    /// DER-encoded `TBSCertificate`
+    tbs: &'a [u8],
+
+    /// Signature algorithm OID
+    signature_algorithm: &'a [u8],
+
+    /// Signature (contents of the BIT STRING)
+    signature: &'a [u8],
+
+    /// DER-encoded issuer name
+    issuer: &'a [u8],
+
+    /// DER-encoded subject name
+    subject: &'a [u8],
+
+    /// Public key algorithm OID
+    public_key_algorithm: &'a [u8],
+
+    /// Public key algorithm parameters (e.g. curve OID), if any
+    public_key_parameters: Option<&'a [u8]>,
+
+    /// Public key (contents of the BIT STRING)
+    public_key: &'a [u8],
+
+    /// Extensions: OIDs and values (contents of the OCTET STRING)
+    extensions: Vec<(&'a [u8], &'a [u8])>,
+}
+
+impl<'a> Certificate<'a> {
+    /// Parse a DER-encoded certificate
+    fn parse(der: &'a [u8]) -> Result<Self, Error> {
+        let mut outer = Reader::new(der);
+        let mut certificate = Reader::new(outer.read(TAG_SEQUENCE)?.contents);
+        outer.finish()?;
+
+        let tbs_tlv = certificate.read(TAG_SEQUENCE)?;
+        let signature_algorithm = read_algorithm(&mut certificate)?.0;
+        let signature = read_bit_string(&mut certificate)?;
+        certificate.finish()?;
+
+        let mut tbs = Reader::new(tbs_tlv.contents);
+
+        if tbs.peek() == Some(TAG_VERSION) {
+            tbs.read(TAG_VERSION)?;
+        }
+
+        tbs.read_any()?; // serial number
+        tbs.read(TAG_SEQUENCE)?; // signature algorithm
+        let issuer = tbs.read(TAG_SEQUENCE)?.der;
+        tbs.read(TAG_SEQUENCE)?; // validity
+        let subject = tbs.read(TAG_SEQUENCE)?.der;
+
+        let mut spki = Reader::new(tbs.read(TAG_SEQUENCE)?.contents);
+        let (public_key_algorithm, public_key_parameters) = read_algorithm(&mut spki)?;
+        let public_key = read_bit_string(&mut spki)?;
+        spki.finish()?;
+
+        let mut extensions = vec![];
+
+        // Skip the issuer and subject unique IDs, if any
+        while let Some(tag) = tbs.peek() {
+            if tag != TAG_EXTENSIONS {
+                tbs.read_any()?;
+                continue;
+            }
+
+            let mut list = Reader::new(tbs.read(TAG_EXTENSIONS)?.contents);
+            let mut sequence = Reader::new(list.read(TAG_SEQUENCE)?.contents);
+            list.finish()?;
+
+            while sequence.peek().is_some() {
+                let mut extension = Reader::new(sequence.read(TAG_SEQUENCE)?.contents);
+                let oid = extension.read(TAG_OID)?.contents;
+
+                // Skip the `critical` flag, if present
+                if extension.peek() != Some(TAG_OCTET_STRING) {
+                    extension.read_any()?;
+                }
+
+                extensions.push((oid, extension.read(TAG_OCTET_STRING)?.contents));
+                extension.finish()?;
+            }
+        }
+
+        Ok(Self {
+            tbs: tbs_tlv.der,
+            signature_algorithm,
+            signature,
+            issuer,
+            subject,
+            public_key_algorithm,
+            public_key_parameters,
+            public_key,
+            extensions,
+        })
+    }
+
+    /// Get the key properties from the Yubico attestation extensions
+    fn properties(&self) -> Result<Properties, Error> {
+        let serial_number = read_uint(self.extension(EXT_SERIAL_NUMBER, TAG_INTEGER)?, 4)?;
+        let key_id = read_uint(self.extension(EXT_OBJECT_ID, TAG_INTEGER)?, 2)?;
+        let origin = read_flags(self.extension(EXT_ORIGIN, TAG_BIT_STRING)?, 1)?;
+        let domains = read_flags(self.extension(EXT_DOMAINS, TAG_BIT_STRING)?, 2)?;
+        let capabilities = read_flags(self.extension(EXT_CAPABILITIES, TAG_BIT_STRING)?, 8)?;
+        let label = self.extension(EXT_LABEL, TAG_UTF8_STRING)?;
+
+        let malformed = |name| format_err!(ParseError, "malformed attested {}", name);
+
+        Ok(Properties {
+            serial_number: format!("{:010}", serial_number)
+                .parse()
+                .map_err(|_| malformed("serial number"))?,
+            key_id: key_id as object::Id,
+            origin: object::Origin::from_u8(origin as u8).map_err(|_| malformed("origin"))?,
+            domains: Domain::from_bits(domains as u16).ok_or_else(|| malformed("domains"))?,
+            capabilities: Capability::from_bits(capabilities)
+                .ok_or_else(|| malformed("capabilities"))?,
+            label: String::from_utf8(label.to_vec()).map_err(|_| malformed("label"))?,
+        })
+    }
+
+    /// Get the contents of the value of the Yubico attestation extension with
+    /// the given arc, which must have the given tag
+    fn extension(&self, arc: u8, tag: u8) -> Result<&'a [u8], Error> {
+        let value = self
+            .extensions
+            .iter()
+            .find(|(oid, _)| {
+                oid.len() == OID_YUBICO_ATTESTATION.len() + 1
+                    && oid.starts_with(OID_YUBICO_ATTESTATION)
+                    && oid.last() == Some(&arc)
+            })
+            .map(|(_, value)| *value)
+            .ok_or_else(|| {
+                format_err!(
+                    VerificationError,
+                    "attestation certificate is missing extension 1.3.6.1.4.1.41482.4.{}",
+                    arc
+                )
+            })?;
+
+        let mut reader = Reader::new(value);
+        let contents = reader.read(tag)?.contents;
+        reader.finish()?;
+        Ok(contents)
+    }
+
+    /// Get the public key in the format returned by the YubiHSM: raw
+    /// Ed25519 keys, or untagged (i.e. `x || y`) EC points
+    fn public_key_bits(&self) -> Result<&'a [u8], Error> {
+        match self.public_key.split_first() {
+            Some((0x04, point)) if self.public_key_algorithm == OID_EC_PUBLIC_KEY => Ok(point),
+            Some(_) => Ok(self.public_key),
+            None => fail!(ParseError, "empty public key in certificate"),
+        }
+    }
+
+    /// Verify this certificate's signature with the issuer's public key
+    fn verify_signed_by(&self, issuer: &Certificate<'_>) -> Result<(), Error> {
+        let algorithm: &dyn signature::VerificationAlgorithm = match (
+            self.signature_algorithm,
+            issuer.public_key_algorithm,
+            issuer.public_key_parameters,
+        ) {
+            (OID_SHA256_WITH_RSA, OID_RSA_ENCRYPTION, _) => &signature::RSA_PKCS1_2048_8192_SHA256,
+            (OID_SHA384_WITH_RSA, OID_RSA_ENCRYPTION, _) => &signature::RSA_PKCS1_2048_8192_SHA384,
+            (OID_SHA512_WITH_RSA, OID_RSA_ENCRYPTION, _) => &signature::RSA_PKCS1_2048_8192_SHA512,
+            (OID_ECDSA_WITH_SHA256, OID_EC_PUBLIC_KEY, Some(OID_P256)) => {
+                &signature::ECDSA_P256_SHA256_ASN1
+            }
+            (OID_ECDSA_WITH_SHA384, OID_EC_PUBLIC_KEY, Some(OID_P256)) => {
+                &signature::ECDSA_P256_SHA384_ASN1
+            }
+            (OID_ECDSA_WITH_SHA256, OID_EC_PUBLIC_KEY, Some(OID_P384)) => {
+                &signature::ECDSA_P384_SHA256_ASN1
+            }
+            (OID_ECDSA_WITH_SHA384, OID_EC_PUBLIC_KEY, Some(OID_P384)) => {
+                &signature::ECDSA_P384_SHA384_ASN1
+            }
+            _ => fail!(
+                VerificationError,
+                "unsupported signature algorithm in certificate for {}",
+                describe_name(self.subject)
+            ),
+        };
+
+        signature::UnparsedPublicKey::new(algorithm, issuer.public_key)
+            .verify(self.tbs, self.signature)
+            .map_err(|_| {
+                format_err!(
+                    VerificationError,
+                    "invalid signature on certificate for {} by {}",
+                    describe_name(self.subject),
+                    describe_name(issuer.subject)
+                )
+                .into()
+            })
+    }
+}
+
+/// Read an `AlgorithmIdentifier`, returning its OID and the contents of its
+/// parameters if they're an OID
+fn read_algorithm<'a>(reader: &mut Reader<'a>) -> Result<(&'a [u8], Option<&'a [u8]>), Error> {
+    let mut algorithm = Reader::new(reader.read(TAG_SEQUENCE)?.contents);
+    let oid = algorithm.read(TAG_OID)?.contents;
+
+    let parameters = match algorithm.peek() {
+        Some(TAG_OID) => Some(algorithm.read(TAG_OID)?.contents),
+        Some(_) => {
+            algorithm.read_any()?;
+            None
+        }
+        None => None,
+    };
+
+    algorithm.finish()?;
+    Ok((oid, parameters))
+}
+
+/// Read a BIT STRING without unused bits
+fn read_bit_string<'a>(reader: &mut Reader<'a>) -> Result<&'a [u8], Error> {
+    match reader.read(TAG_BIT_STRING)?.contents.split_first() {
+        Some((0, bits)) => Ok(bits),
+        _ => fail!(ParseError, "malformed BIT STRING in certificate"),
+    }
+}
+
+/// Read the contents of an unsigned INTEGER of at most `size` bytes
+fn read_uint(contents: &[u8], size: usize) -> Result<u64, Error> {
+    let bytes = match contents.split_first() {
+        Some((0, rest)) if !rest.is_empty() => rest,
+        Some((first, _)) if first & 0x80 == 0 => contents,
+        _ => fail!(ParseError, "malformed INTEGER in certificate"),
+    };
+
+    if bytes.len() > size {
+        fail!(ParseError, "INTEGER in certificate is too large");
+    }
+
+    Ok(bytes.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b)))
+}
+
+/// Read the contents of a BIT STRING of (at most) `size` bytes containing
+/// big-endian flags. Trailing zero bytes may have been omitted.
+fn read_flags(contents: &[u8], size: usize) -> Result<u64, Error> {
+    let bytes = match contents.split_first() {
+        Some((unused_bits, bytes)) if *unused_bits < 8 && bytes.len() <= size => bytes,
+        _ => fail!(ParseError, "malformed BIT STRING in certificate"),
+    };
+
+    let flags = bytes.iter().fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+    Ok(flags
+        .checked_shl(8 * (size - bytes.len()) as u32)
+        .unwrap_or(0))
+}
+
+/// Describe a DER-encoded name by its printable attribute values
+/// (e.g. the common name)
+fn describe_name(name: &[u8]) -> String {
+    let mut values = vec![];
+    let mut rdns = Reader::new(name);
+
+    if let Ok(sequence) = rdns.read(TAG_SEQUENCE) {
+        let mut rdns = Reader::new(sequence.contents);
+
+        while let Ok(rdn) = rdns.read_any() {
+            let mut attributes = Reader::new(rdn.contents);
+
+            while let Ok(attribute) = attributes.read(TAG_SEQUENCE) {
+                let mut attribute = Reader::new(attribute.contents);
+
+                if attribute.read(TAG_OID).is_ok() {
+                    if let Ok(value) = attribute.read_any() {
+                        values.push(String::from_utf8_lossy(value.contents).into_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    if values.is_empty() {
+        "(unnamed)".to_owned()
+    } else {
+        format!("\"{}\"", values.join(", "))
+    }
+}
+
+/// DER tag-length-value
+struct Tlv<'a> {
+    /// Tag byte
+    tag: u8,
+
+    /// Contents
+    contents: &'a [u8],
+
+    /// Entire encoding including the tag and length
+    der: &'a [u8],
+}
+
+/// Minimal DER reader
+struct Reader<'a> {
+    /// Remaining input
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Peek at the next tag
+    fn peek(&self) -> Option<u8> {
+        self.bytes.first().cloned()
+    }
+
+    /// Read a TLV with the given tag
+    fn read(&mut self, tag: u8) -> Result<Tlv<'a>, Error> {
+        let tlv = self.read_any()?;
+
+        if tlv.tag != tag {
+            fail!(
+                ParseError,
+                "malformed certificate: expected tag 0x{:02x}, got 0x{:02x}",
+                tag,
+                tlv.tag
+            );
+        }
+
+        Ok(tlv)
+    }
+
+    /// Read a TLV with any tag
+    fn read_any(&mut self) -> Result<Tlv<'a>, Error> {
+        let malformed = || format_err!(ParseError, "malformed certificate: truncated DER");
+
+        let (&tag, rest) = self.bytes.split_first().ok_or_else(malformed)?;
+        let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+
+        let (length, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let n = (first & 0x7f) as usize;
+
+            if n == 0 || n > 3 {
+                fail!(ParseError, "malformed certificate: unsupported DER length");
+            }
+
+            if rest.len() < n {
+                return Err(malformed().into());
+            }
+
+            let length = rest[..n]
+                .iter()
+                .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+
+            // DER requires the shortest possible length encoding
+            if length < 0x80 || rest[0] == 0 {
+                fail!(ParseError, "malformed certificate: non-minimal DER length");
+            }
+
+            (length, &rest[n..])
+        };
+
+        if rest.len() < length {
+            return Err(malformed().into());
+        }
+
+        let header_len = self.bytes.len() - rest.len();
+        let der = &self.bytes[..header_len + length];
+        self.bytes = &rest[length..];
+
+        Ok(Tlv {
+            tag,
+            contents: &rest[..length],
+            der,
+        })
+    }
+
+    /// Ensure all input has been consumed
+    fn finish(&self) -> Result<(), Error> {
+        if !self.bytes.is_empty() {
+            fail!(ParseError, "malformed certificate: trailing data");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Ed25519 public key in the test key attestation certificate
+    const TEST_PUBLIC_KEY: &str =
+        "52f84993030b92304986440804a19b131c0cff1049e67fc4879f192124c8b742";
+
+    fn load(name: &str) -> Vec<Vec<u8>> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/support/attestation")
+            .join(name);
+
+        load_ca_certificates(&path).unwrap()
+    }
+
+    fn public_key() -> Vec<u8> {
+        subtle_encoding::hex::decode(TEST_PUBLIC_KEY).unwrap()
+    }
+
+    #[test]
+    fn verifies_attestation_chain() {
+        let key_cert = load("key.pem").remove(0);
+        let device_cert = load("device.pem").remove(0);
+
+        let attestation =
+            Attestation::verify(&key_cert, &device_cert, &public_key(), &load("root.pem")).unwrap();
+
+        assert_eq!(
+            attestation.describe_chain(),
+            &[
+                "\"Test Key 0x0001\"",
+                "\"Test YubiHSM Attestation\"",
+                "\"Test YubiHSM Root CA\""
+            ]
+        );
+    }
+
+    /// Object info the YubiHSM reports for the test key
+    fn object_info() -> object::Info {
+        object::Info {
+            capabilities: Capability::SIGN_EDDSA | Capability::EXPORTABLE_UNDER_WRAP,
+            object_id: 1,
+            length: 32,
+            domains: Domain::DOM1,
+            object_type: object::Type::AsymmetricKey,
+            algorithm: yubihsm::asymmetric::Algorithm::Ed25519.into(),
+            sequence: 0,
+            origin: object::Origin::Generated,
+            label: "test key".into(),
+            delegated_capabilities: Capability::empty(),
+        }
+    }
+
+    fn serial_number() -> SerialNumber {
+        "0012345678".parse().unwrap()
+    }
+
+    #[test]
+    fn parses_attested_properties() {
+        let key_cert = load("key.pem").remove(0);
+        let device_cert = load("device.pem").remove(0);
+
+        let attestation =
+            Attestation::verify(&key_cert, &device_cert, &public_key(), &load("root.pem")).unwrap();
+
+        let properties = &attestation.properties;
+        assert_eq!(properties.serial_number, serial_number());
+        assert_eq!(properties.key_id, 1);
+        assert_eq!(properties.origin, object::Origin::Generated);
+        assert_eq!(properties.domains, Domain::DOM1);
+        assert_eq!(
+            properties.capabilities,
+            Capability::SIGN_EDDSA | Capability::EXPORTABLE_UNDER_WRAP
+        );
+        assert_eq!(properties.label, "test key");
+
+        attestation
+            .check_object_info(serial_number(), &object_info())
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_mismatched_object_info() {
+        let key_cert = load("key.pem").remove(0);
+        let device_cert = load("device.pem").remove(0);
+
+        let attestation =
+            Attestation::verify(&key_cert, &device_cert, &public_key(), &load("root.pem")).unwrap();
+
+        let other_serial = "0087654321".parse().unwrap();
+        assert!(attestation
+            .check_object_info(other_serial, &object_info())
+            .is_err());
+
+        let mut info = object_info();
+        info.label = "other key".into();
+        assert!(attestation
+            .check_object_info(serial_number(), &info)
+            .is_err());
+
+        let mut info = object_info();
+        info.origin = object::Origin::Imported;
+        assert!(attestation
+            .check_object_info(serial_number(), &info)
+            .is_err());
+
+        let mut info = object_info();
+        info.capabilities = Capability::SIGN_EDDSA;
+        assert!(attestation
+            .check_object_info(serial_number(), &info)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_missing_extensions() {
+        let key_cert = load("key-without-extensions.pem").remove(0);
+        let device_cert = load("device.pem").remove(0);
+
+        let err = Attestation::verify(&key_cert, &device_cert, &public_key(), &load("root.pem"))
+            .unwrap_err();
+        assert_eq!(*err.kind(), VerificationError);
+    }
+
+    #[test]
+    fn rejects_wrong_public_key() {
+        let key_cert = load("key.pem").remove(0);
+        let device_cert = load("device.pem").remove(0);
+        let mut public_key = public_key();
+        public_key[0] ^= 1;
+
+        let err = Attestation::verify(&key_cert, &device_cert, &public_key, &load("root.pem"))
+            .unwrap_err();
+        assert_eq!(*err.kind(), VerificationError);
+    }
+
+    #[test]
+    fn rejects_untrusted_chain() {
+        let key_cert = load("key.pem").remove(0);
+        let device_cert = load("device.pem").remove(0);
+
+        // The device certificate isn't a trusted root
+        let err = Attestation::verify(
+            &key_cert,
+            &device_cert,
+            &public_key(),
+            std::slice::from_ref(&device_cert),
+        )
+        .unwrap_err();
+        assert_eq!(*err.kind(), VerificationError);
+
+        // Tampered key certificate
+        let mut tampered = key_cert.clone();
+        let index = tampered.len() - 300;
+        tampered[index] ^= 1;
+        assert!(
+            Attestation::verify(&tampered, &device_cert, &public_key(), &load("root.pem")).is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_der() {
+        let key_cert = load("key.pem").remove(0);
+
+        for len in 0..key_cert.len() {
+            assert!(
+                Certificate::parse(&key_cert[..len]).is_err(),
+                "parsed certificate truncated to {} bytes",
+                len
+            );
+        }
+
+        // Contents shorter than the length
+        assert!(Reader::new(&[TAG_SEQUENCE, 0x03, 0x02, 0x01])
+            .read_any()
+            .is_err());
+
+        // Long-form length missing its length bytes
+        assert!(Reader::new(&[TAG_SEQUENCE, 0x82, 0x01]).read_any().is_err());
+    }
+
+    #[test]
+    fn rejects_overlong_der_lengths() {
+        for der in &[
+            // More length bytes than any certificate needs
+            &[TAG_SEQUENCE, 0x84, 0x00, 0x00, 0x00, 0x01, 0x00][..],
+            // Length far beyond the end of the input
+            &[TAG_SEQUENCE, 0x83, 0xff, 0xff, 0xff][..],
+            // Indefinite length (BER only)
+            &[TAG_SEQUENCE, 0x80, 0x00, 0x00][..],
+            // Non-minimal length encodings
+            &[TAG_INTEGER, 0x81, 0x01, 0x00][..],
+            &[TAG_INTEGER, 0x82, 0x00, 0x01, 0x00][..],
+        ] {
+            let err = Reader::new(der).read_any().err().unwrap();
+            assert_eq!(*err.kind(), ParseError, "{:02x?}", der);
+        }
+
+        // Trailing data after the certificate
+        let mut key_cert = load("key.pem").remove(0);
+        key_cert.push(0);
+        assert!(Certificate::parse(&key_cert).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_der_tags() {
+        let err = Reader::new(&[TAG_INTEGER, 0x01, 0x00])
+            .read(TAG_SEQUENCE)
+            .err()
+            .unwrap();
+        assert_eq!(*err.kind(), ParseError);
+
+        let key_cert = load("key.pem").remove(0);
+
+        // Outer certificate and `tbsCertificate` must both be SEQUENCEs
+        for &index in &[0, 4] {
+            let mut cert = key_cert.clone();
+            cert[index] = TAG_OCTET_STRING;
+            let err = Certificate::parse(&cert).err().unwrap();
+            assert!(err.to_string().contains("expected tag 0x30"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! YubiHSM audit log: retrieving entries, verifying their digest chain, and
+//! marking them as consumed.
+//!
+//! Each log entry contains a 16-byte digest, which is the first 16 bytes of
+//! the SHA-256 digest of the entry's fields concatenated with the digest of
+//! the previous entry.
+//!
+//! If the chain is broken (e.g. because entries were lost or tampered with),
+//! a [`Discontinuity`] marker is written to the drained log file, and a new
+//! segment of the chain starts with the device's current entries.
+
+use crate::{
+    config::provider::yubihsm::AuditLogConfig,
+    error::{Error, ErrorKind::*},
+    notify::{self, EventKind},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use subtle_encoding::hex;
+use yubihsm::Client;
+
+/// Size of the digest in each log entry
+pub const DIGEST_SIZE: usize = 16;
+
+/// Interval at which the running KMS drains the audit log
+pub const DRAIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Audit log entry
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Entry {
+    /// Sequence number of this entry
+    pub item: u16,
+
+    /// Command which was executed
+    pub command: u8,
+
+    /// Length of the command
+    pub length: u16,
+
+    /// ID of the authentication key used for the session
+    pub session_key: u16,
+
+    /// ID of the object the command operated on
+    pub target_key: u16,
+
+    /// ID of the second object the command operated on
+    pub second_key: u16,
+
+    /// Result code of the command
+    pub result: u8,
+
+    /// Device tick count when the command was executed
+    pub tick: u32,
+
+    /// Digest of this entry and the previous one (hex)
+    pub digest: String,
+}
+
+impl Entry {
+    /// Serialize the fields covered by the digest
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&self.item.to_be_bytes());
+        bytes.push(self.command);
+        bytes.extend_from_slice(&self.length.to_be_bytes());
+        bytes.extend_from_slice(&self.session_key.to_be_bytes());
+        bytes.extend_from_slice(&self.target_key.to_be_bytes());
+        bytes.extend_from_slice(&self.second_key.to_be_bytes());
+        bytes.push(self.result);
+        bytes.extend_from_slice(&self.tick.to_be_bytes());
+        bytes
+    }
+
+    /// Decode the digest of this entry
+    fn digest_bytes(&self) -> Result<Vec<u8>, Error> {
+        hex::decode(&self.digest)
+            .ok()
+            .filter(|digest| digest.len() == DIGEST_SIZE)
+            .ok_or_else(|| {
+                format_err!(ParseError, "malformed digest in entry {}", self.item).into()
+            })
+    }
+
+    /// Compute the expected digest of this entry given the previous digest
+    fn compute_digest(&self, previous_digest: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.to_bytes());
+        hasher.update(previous_digest);
+        hasher.finalize()[..DIGEST_SIZE].to_vec()
+    }
+
+    /// Check that this entry follows the given one in the digest chain,
+    /// describing the break if it doesn't
+    fn check_follows(&self, previous: &Entry) -> Result<(), String> {
+        if self.item != previous.item.wrapping_add(1) {
+            return Err(format!(
+                "audit log entry {} doesn't follow entry {}",
+                self.item, previous.item
+            ));
+        }
+
+        let previous_digest = previous.digest_bytes().map_err(|e| e.to_string())?;
+
+        if self.digest_bytes().map_err(|e| e.to_string())? != self.compute_digest(&previous_digest)
+        {
+            return Err(format!("digest mismatch in audit log entry {}", self.item));
+        }
+
+        Ok(())
+    }
+}
+
+/// Marker for a break in the digest chain, written to the drained log file
+/// before the entries starting a new segment of the chain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Discontinuity {
+    /// Description of the break
+    pub discontinuity: String,
+}
+
+/// Entries appended to a drained log file
+#[derive(Clone, Debug, Default)]
+pub struct Appended {
+    /// Last entry which was appended (if any)
+    pub last: Option<Entry>,
+
+    /// Breaks in the digest chain which were marked in the file
+    pub discontinuities: Vec<Discontinuity>,
+}
+
+/// Audit log entries retrieved from a YubiHSM
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Log {
+    /// Number of boot events which weren't logged (because the log was full)
+    pub unlogged_boot_events: u16,
+
+    /// Number of authentication events which weren't logged
+    pub unlogged_auth_events: u16,
+
+    /// Log entries
+    pub entries: Vec<Entry>,
+}
+
+impl Log {
+    /// Retrieve the log entries from the YubiHSM, verifying their digest
+    /// chain
+    pub fn retrieve(client: &Client) -> Result<Self, Error> {
+        let log = Self::fetch(client)?;
+        log.verify(None)?;
+        Ok(log)
+    }
+
+    /// Retrieve the log entries from the YubiHSM without verifying them
+    fn fetch(client: &Client) -> Result<Self, Error> {
+        let log = client.get_log_entries()?;
+
+        Ok(Self {
+            unlogged_boot_events: log.unlogged_boot_events,
+            unlogged_auth_events: log.unlogged_auth_events,
+            entries: log
+                .entries
+                .iter()
+                .map(|entry| Entry {
+                    item: entry.item,
+                    command: entry.cmd.to_u8(),
+                    length: entry.length,
+                    session_key: entry.session_key,
+                    target_key: entry.target_key,
+                    second_key: entry.second_key,
+                    result: entry.result.to_u8(),
+                    tick: entry.tick,
+                    digest: String::from_utf8(hex::encode(entry.digest.0)).unwrap(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Verify the digest chain of these entries, optionally starting from the
+    /// last entry of a previous retrieval
+    pub fn verify(&self, previous: Option<&Entry>) -> Result<(), Error> {
+        match find_discontinuity(&self.entries, previous) {
+            Some((_, d)) => fail!(VerificationError, "{}", d.discontinuity),
+            None => Ok(()),
+        }
+    }
+
+    /// Mark these entries as consumed, allowing the YubiHSM to reuse the
+    /// space they occupy
+    pub fn consume(&self, client: &Client) -> Result<(), Error> {
+        if let Some(last) = self.entries.last() {
+            client.set_log_index(last.item)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Find the first break in the digest chain of the given entries, optionally
+/// starting from the last entry of a previous retrieval. Returns the index of
+/// the first entry after the break along with its description.
+fn find_discontinuity(
+    entries: &[Entry],
+    previous: Option<&Entry>,
+) -> Option<(usize, Discontinuity)> {
+    let (mut previous, start) = match previous {
+        Some(previous) => (previous, 0),
+        None => (entries.first()?, 1),
+    };
+
+    for (index, entry) in entries.iter().enumerate().skip(start) {
+        // The device may return the last consumed entry again
+        if entry == previous {
+            continue;
+        }
+
+        if let Err(discontinuity) = entry.check_follows(previous) {
+            return Some((index, Discontinuity { discontinuity }));
+        }
+
+        previous = entry;
+    }
+
+    None
+}
+
+/// Drain the audit log of the given device into the configured file (as
+/// JSON lines) in a background thread, so it never fills up
+pub fn spawn_drain(device: Arc<super::Device>, config: AuditLogConfig) {
+    thread::spawn(move || {
+        let mut client: Option<Client> = None;
+
+        let mut previous = read_last_entry(&config.path).unwrap_or_else(|e| {
+            error!("[yubihsm:{}] {}", device.id(), e);
+            None
+        });
+
+        loop {
+            if client.is_none() {
+                let opened = config.auth.credentials().and_then(|credentials| {
+                    Ok(Client::open(device.connector(), credentials, true)?)
+                });
+
+                match opened {
+                    Ok(c) => client = Some(c),
+                    Err(e) => error!(
+                        "[yubihsm:{}] couldn't open audit session: {}",
+                        device.id(),
+                        e
+                    ),
+                }
+            }
+
+            if let Some(c) = &client {
+                match append(c, &config.path, previous.as_ref()) {
+                    Ok(appended) => {
+                        for discontinuity in &appended.discontinuities {
+                            notify_discontinuity(&device, discontinuity);
+                        }
+
+                        if appended.last.is_some() {
+                            previous = appended.last;
+                        }
+                    }
+                    Err(e) => {
+                        error!("[yubihsm:{}] error draining audit log: {}", device.id(), e);
+                        client = None;
+                    }
+                }
+            }
+
+            thread::sleep(DRAIN_INTERVAL);
+        }
+    });
+}
+
+/// Report a break in the digest chain of the given device's audit log
+fn notify_discontinuity(device: &super::Device, discontinuity: &Discontinuity) {
+    error!(
+        "[yubihsm:{}] audit log digest chain broken ({}): starting a new segment",
+        device.id(),
+        discontinuity.discontinuity
+    );
+
+    let message = format!(
+        "YubiHSM audit log digest chain broken: {}",
+        discontinuity.discontinuity
+    );
+
+    let mut chain_ids = device
+        .config()
+        .keys
+        .iter()
+        .flat_map(|key| key.chain_ids.iter())
+        .collect::<Vec<_>>();
+
+    chain_ids.sort();
+    chain_ids.dedup();
+
+    for chain_id in chain_ids {
+        notify::send(notify::Event::new(
+            EventKind::AuditLogDiscontinuity,
+            chain_id,
+            format!("yubihsm:{}", device.id()),
+            &message,
+        ));
+    }
+}
+
+/// Append new log entries to the given file (as JSON lines) and mark them as
+/// consumed.
+///
+/// If the entries don't follow the given previous entry (or each other), a
+/// [`Discontinuity`] marker is written before the entries after the break,
+/// which start a new segment of the digest chain.
+pub fn append(client: &Client, path: &Path, previous: Option<&Entry>) -> Result<Appended, Error> {
+    let log = Log::fetch(client)?;
+
+    if log.unlogged_boot_events > 0 || log.unlogged_auth_events > 0 {
+        warn!(
+            "YubiHSM audit log overflowed: {} boot and {} authentication events weren't logged",
+            log.unlogged_boot_events, log.unlogged_auth_events
+        );
+    }
+
+    let appended = write_entries(path, &log.entries, previous)?;
+
+    log.consume(client)?;
+    Ok(appended)
+}
+
+/// Write the given entries to a file (as JSON lines), marking breaks in their
+/// digest chain. The last consumed entry is omitted if it's returned again.
+fn write_entries(
+    path: &Path,
+    mut entries: &[Entry],
+    mut previous: Option<&Entry>,
+) -> Result<Appended, Error> {
+    while let (Some(first), Some(prev)) = (entries.first(), previous) {
+        if first != prev {
+            break;
+        }
+
+        entries = &entries[1..];
+    }
+
+    let mut appended = Appended::default();
+
+    if entries.is_empty() {
+        return Ok(appended);
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format_err!(IoError, "couldn't open {}: {}", path.display(), e))?;
+
+    let mut write_line = |line: String| {
+        writeln!(file, "{}", line)
+            .map_err(|e| format_err!(IoError, "couldn't write {}: {}", path.display(), e))
+    };
+
+    let mut segment = entries;
+
+    loop {
+        let discontinuity = find_discontinuity(segment, previous);
+        let end = discontinuity
+            .as_ref()
+            .map(|(index, _)| *index)
+            .unwrap_or_else(|| segment.len());
+
+        for entry in &segment[..end] {
+            write_line(serde_json::to_string(entry).unwrap())?;
+        }
+
+        match discontinuity {
+            Some((index, discontinuity)) => {
+                write_line(serde_json::to_string(&discontinuity).unwrap())?;
+                appended.discontinuities.push(discontinuity);
+                segment = &segment[index..];
+                previous = None;
+            }
+            None => break,
+        }
+    }
+
+    file.sync_all()?;
+
+    debug!(
+        "appended {} YubiHSM audit log entries to {}",
+        entries.len(),
+        path.display()
+    );
+
+    appended.last = entries.last().cloned();
+    Ok(appended)
+}
+
+/// Read the last entry from a JSON lines audit log file (if it exists)
+pub fn read_last_entry(path: &Path) -> Result<Option<Entry>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+    let line = match contents.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+
+    match serde_json::from_str(line) {
+        Ok(entry) => Ok(Some(entry)),
+        // A new segment of the digest chain starts after a discontinuity
+        Err(_) if serde_json::from_str::<Discontinuity>(line).is_ok() => Ok(None),
+        Err(e) => fail!(ParseError, "malformed entry in {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Create a chain of `n` entries
+    fn entries(n: u16) -> Vec<Entry> {
+        let mut previous_digest = vec![0u8; DIGEST_SIZE];
+
+        (1..=n)
+            .map(|item| {
+                let mut entry = Entry {
+                    item,
+                    command: 0x56,
+                    length: 42,
+                    session_key: 4,
+                    target_key: 1,
+                    second_key: 0xffff,
+                    result: 0xd6,
+                    tick: u32::from(item) * 100,
+                    digest: String::new(),
+                };
+
+                previous_digest = entry.compute_digest(&previous_digest);
+                entry.digest = String::from_utf8(hex::encode(&previous_digest)).unwrap();
+                entry
+            })
+            .collect()
+    }
+
+    fn log(entries: Vec<Entry>) -> Log {
+        Log {
+            unlogged_boot_events: 0,
+            unlogged_auth_events: 0,
+            entries,
+        }
+    }
+
+    #[test]
+    fn verifies_digest_chain() {
+        let entries = entries(5);
+        log(entries.clone()).verify(None).unwrap();
+        log(entries[2..].to_vec())
+            .verify(Some(&entries[1]))
+            .unwrap();
+        log(entries[1..].to_vec())
+            .verify(Some(&entries[1]))
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_tampered_entries() {
+        let mut entries = entries(5);
+        entries[3].target_key = 2;
+        assert!(log(entries).verify(None).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_entries() {
+        let mut entries = entries(5);
+        entries.remove(2);
+        assert!(log(entries.clone()).verify(None).is_err());
+        assert!(log(entries[2..].to_vec())
+            .verify(Some(&entries[0]))
+            .is_err());
+    }
+
+    #[test]
+    fn marks_broken_digest_chain() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let entries = entries(5);
+
+        let appended = write_entries(&path, &entries[..3], None).unwrap();
+        assert!(appended.discontinuities.is_empty());
+
+        // The device's log restarted (e.g. after a reset), so its entries
+        // don't follow the last one which was drained
+        let previous = read_last_entry(&path).unwrap();
+        let appended = write_entries(&path, &entries[..2], previous.as_ref()).unwrap();
+        assert_eq!(appended.discontinuities.len(), 1);
+        assert_eq!(appended.last.as_ref(), Some(&entries[1]));
+
+        // Draining continues with the new segment of the chain
+        let previous = read_last_entry(&path).unwrap();
+        assert_eq!(previous.as_ref(), Some(&entries[1]));
+        let appended = write_entries(&path, &entries[1..], previous.as_ref()).unwrap();
+        assert!(appended.discontinuities.is_empty());
+        assert_eq!(appended.last.as_ref(), Some(&entries[4]));
+
+        let lines = fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 3 + 1 + 5);
+        assert!(lines.lines().nth(3).unwrap().contains("discontinuity"));
+    }
+
+    #[test]
+    fn marks_tampered_entries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut entries = entries(5);
+        entries[3].target_key = 2;
+
+        let appended = write_entries(&path, &entries, None).unwrap();
+        assert_eq!(
+            appended.discontinuities,
+            [Discontinuity {
+                discontinuity: "digest mismatch in audit log entry 4".to_owned()
+            }]
+        );
+        assert_eq!(appended.last.as_ref(), Some(&entries[4]));
+    }
+}
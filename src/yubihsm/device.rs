@@ -0,0 +1,555 @@
+//! YubiHSM device supervision: session liveness probing, transparent
+//! re-authentication, and reconnecting (i.e. re-enumerating USB devices)
+//! with bounded retries.
+
+use super::{client_config, reconnect_connector};
+use crate::{
+    config::provider::yubihsm::YubihsmConfig,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+#[cfg(feature = "yubihsm-mock")]
+use std::sync::atomic::AtomicU32;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+use yubihsm::{Client, Connector};
+
+/// Interval at which sessions are probed. The YubiHSM 2 closes sessions after
+/// 30 seconds of inactivity, so this also keeps idle sessions alive.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of attempts to reconnect to a device after
+/// re-authenticating with it failed
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Delay before the first reconnect attempt (increases linearly)
+const RECONNECT_DELAY: Duration = Duration::from_millis(250);
+
+/// Message sent to the YubiHSM when probing the session
+const PROBE_MESSAGE: &[u8] = b"tmkms liveness probe";
+
+/// Connection status of a YubiHSM device
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Status {
+    /// No session has been opened yet
+    Disconnected,
+
+    /// Authenticated session is open
+    Connected,
+
+    /// Opening a new session using the configured credentials
+    Reauthenticating,
+
+    /// Reconnecting to the device (i.e. re-enumerating USB devices)
+    Reconnecting,
+
+    /// Reconnecting failed after the maximum number of attempts
+    Failed,
+}
+
+impl Status {
+    /// Is the session currently being recovered?
+    pub fn is_recovering(self) -> bool {
+        matches!(self, Status::Reauthenticating | Status::Reconnecting)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Disconnected => "disconnected",
+            Status::Connected => "connected",
+            Status::Reauthenticating => "reauthenticating",
+            Status::Reconnecting => "reconnecting",
+            Status::Failed => "failed",
+        })
+    }
+}
+
+/// YubiHSM device, along with its connection state
+pub struct Device {
+    /// Configuration for this device
+    config: YubihsmConfig,
+
+    /// Connection state
+    state: Mutex<State>,
+
+    /// Notified when recovering the session finishes
+    recovered: Condvar,
+
+    /// Has the liveness probe been started?
+    probing: AtomicBool,
+
+    /// Number of faults to inject into subsequent operations
+    #[cfg(feature = "yubihsm-mock")]
+    faults: AtomicU32,
+}
+
+/// Connection state of a device
+struct State {
+    /// Connector for the device
+    connector: Connector,
+
+    /// Authenticated client
+    client: Option<Client>,
+
+    /// Incremented every time a new client is opened
+    generation: u64,
+
+    /// Current status
+    status: Status,
+}
+
+impl Device {
+    /// Create a new device from its configuration and connector
+    pub fn new(config: YubihsmConfig, connector: Connector) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State {
+                connector,
+                client: None,
+                generation: 0,
+                status: Status::Disconnected,
+            }),
+            recovered: Condvar::new(),
+            probing: AtomicBool::new(false),
+            #[cfg(feature = "yubihsm-mock")]
+            faults: AtomicU32::new(0),
+        }
+    }
+
+    /// Get the configuration for this device
+    pub fn config(&self) -> &YubihsmConfig {
+        &self.config
+    }
+
+    /// Get the identifier for this device (label or serial number)
+    pub fn id(&self) -> &str {
+        self.config.device_id()
+    }
+
+    /// Get the connector for this device
+    pub fn connector(&self) -> Connector {
+        self.state().connector.clone()
+    }
+
+    /// Get the current connection status
+    pub fn status(&self) -> Status {
+        self.state().status
+    }
+
+    /// Get an authenticated client for this device, opening a session if
+    /// one isn't already open
+    pub fn client(&self) -> Result<Client, Error> {
+        self.current_client().map(|(_, client)| client)
+    }
+
+    /// Get the current client along with its generation
+    fn current_client(&self) -> Result<(u64, Client), Error> {
+        let mut state = self.wait_for_recovery();
+
+        if let Some(client) = &state.client {
+            return Ok((state.generation, client.clone()));
+        }
+
+        let client = self.open_client(state.connector.clone())?;
+        self.connected(&mut state, client.clone());
+        Ok((state.generation, client))
+    }
+
+    /// Recover from a failure encountered using a client of the given
+    /// generation: first re-authenticate, and if that fails, reconnect to the
+    /// device up to `MAX_RECONNECT_ATTEMPTS` times.
+    ///
+    /// The connection state isn't locked while recovering, so the status of
+    /// the device can be queried in the meantime. Concurrent callers wait for
+    /// the recovery in progress, and if the client has already been replaced
+    /// since (e.g. by another signer or the liveness probe) the replacement
+    /// is returned instead.
+    pub fn recover(&self, generation: u64) -> Result<(u64, Client), Error> {
+        let connector = {
+            let mut state = self.wait_for_recovery();
+
+            if state.generation != generation && state.status == Status::Connected {
+                if let Some(client) = &state.client {
+                    return Ok((state.generation, client.clone()));
+                }
+            }
+
+            self.transition(&mut state, Status::Reauthenticating);
+            state.connector.clone()
+        };
+
+        let result = self.reconnect(connector);
+        let mut state = self.state();
+
+        let result = match result {
+            Ok((connector, client)) => {
+                state.connector = connector;
+                self.connected(&mut state, client.clone());
+                Ok((state.generation, client))
+            }
+            Err(e) => {
+                state.client = None;
+                self.transition(&mut state, Status::Failed);
+                Err(e)
+            }
+        };
+
+        self.recovered.notify_all();
+        result
+    }
+
+    /// Re-authenticate with the device, and if that fails, reconnect to it
+    /// (without holding the connection state lock)
+    fn reconnect(&self, connector: Connector) -> Result<(Connector, Client), Error> {
+        match self.open_client(connector.clone()) {
+            Ok(client) => return Ok((connector, client)),
+            Err(e) => warn!("[yubihsm:{}] re-authentication failed: {}", self.id(), e),
+        }
+
+        self.transition(&mut self.state(), Status::Reconnecting);
+
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            thread::sleep(RECONNECT_DELAY * attempt);
+
+            let new_connector = reconnect_connector(&self.config, &connector);
+
+            match self.open_client(new_connector.clone()) {
+                Ok(client) => return Ok((new_connector, client)),
+                Err(e) => warn!(
+                    "[yubihsm:{}] reconnect attempt {}/{} failed: {}",
+                    self.id(),
+                    attempt,
+                    MAX_RECONNECT_ATTEMPTS,
+                    e
+                ),
+            }
+        }
+
+        fail!(
+            YubihsmError,
+            "couldn't reconnect to YubiHSM {} after {} attempts",
+            self.id(),
+            MAX_RECONNECT_ATTEMPTS
+        )
+    }
+
+    /// Probe the liveness of the current session (if any), recovering it
+    /// if the probe fails
+    pub fn probe(&self) -> Result<(), Error> {
+        let (generation, client, status) = {
+            let state = self.state();
+            (state.generation, state.client.clone(), state.status)
+        };
+
+        let client = match client {
+            Some(client) => client,
+            // Retry reconnecting to devices which previously failed
+            None if status == Status::Failed => return self.recover(generation).map(|_| ()),
+            None => return Ok(()),
+        };
+
+        let result = self
+            .check_fault()
+            .and_then(|_| client.echo(PROBE_MESSAGE).map_err(Error::from));
+
+        if let Err(e) = result {
+            warn!("[yubihsm:{}] liveness probe failed: {}", self.id(), e);
+            self.recover(generation)?;
+        }
+
+        Ok(())
+    }
+
+    /// Periodically probe the session in a background thread (if this
+    /// hasn't been started already)
+    pub fn spawn_liveness_probe(self: &Arc<Self>) {
+        if self.probing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let device = Arc::clone(self);
+
+        thread::spawn(move || loop {
+            thread::sleep(PROBE_INTERVAL);
+
+            if let Err(e) = device.probe() {
+                error!("[yubihsm:{}] {}", device.id(), e);
+            }
+        });
+    }
+
+    /// Make the next `count` operations on this device (signing, probing,
+    /// and opening sessions) fail
+    #[cfg(feature = "yubihsm-mock")]
+    pub fn inject_faults(&self, count: u32) {
+        self.faults.store(count, Ordering::SeqCst);
+    }
+
+    /// Fail if there are faults left to inject
+    #[cfg(feature = "yubihsm-mock")]
+    fn check_fault(&self) -> Result<(), Error> {
+        let injected = self
+            .faults
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+
+        if injected {
+            fail!(YubihsmError, "injected fault");
+        }
+
+        Ok(())
+    }
+
+    /// Fail if there are faults left to inject (never, without `yubihsm-mock`)
+    #[cfg(not(feature = "yubihsm-mock"))]
+    fn check_fault(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Open an authenticated session with the device
+    fn open_client(&self, connector: Connector) -> Result<Client, Error> {
+        self.check_fault()?;
+        let (credentials, reconnect) = client_config(&self.config)?;
+        Ok(Client::open(connector, credentials, reconnect)?)
+    }
+
+    /// Record that a new client has been opened
+    fn connected(&self, state: &mut State, client: Client) {
+        state.client = Some(client);
+        state.generation += 1;
+        self.transition(state, Status::Connected);
+    }
+
+    /// Update the status of this device, logging the transition
+    fn transition(&self, state: &mut State, status: Status) {
+        if state.status == status {
+            return;
+        }
+
+        match status {
+            Status::Connected if state.status == Status::Disconnected => {
+                debug!("[yubihsm:{}] {} -> {}", self.id(), state.status, status)
+            }
+            Status::Connected => info!("[yubihsm:{}] {} -> {}", self.id(), state.status, status),
+            Status::Failed => error!("[yubihsm:{}] {} -> {}", self.id(), state.status, status),
+            _ => warn!("[yubihsm:{}] {} -> {}", self.id(), state.status, status),
+        }
+
+        state.status = status;
+    }
+
+    /// Lock the connection state
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Lock the connection state once no recovery is in progress
+    fn wait_for_recovery(&self) -> MutexGuard<'_, State> {
+        self.recovered
+            .wait_while(self.state(), |state| state.status.is_recovering())
+            .unwrap()
+    }
+}
+
+/// Signer which transparently recovers the session with its YubiHSM if
+/// signing fails, recreating the inner signer with the new client
+pub struct Signer<S> {
+    /// Device the key resides in
+    device: Arc<Device>,
+
+    /// Create the inner signer from a client
+    create: Box<dyn Fn(Client) -> Result<S, Error> + Send + Sync>,
+
+    /// Inner signer and the generation of the client it uses
+    inner: Mutex<(u64, S)>,
+}
+
+impl<S> Signer<S> {
+    /// Create a new signer for a key in the given device, using the provided
+    /// function to instantiate the inner signer from a client
+    pub fn new<F>(device: Arc<Device>, create: F) -> Result<Self, Error>
+    where
+        F: Fn(Client) -> Result<S, Error> + Send + Sync + 'static,
+    {
+        let (generation, client) = device.current_client()?;
+        let signer = create(client)?;
+
+        Ok(Self {
+            device,
+            create: Box::new(create),
+            inner: Mutex::new((generation, signer)),
+        })
+    }
+
+    /// Borrow the inner signer
+    pub fn with_inner<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.inner.lock().unwrap().1)
+    }
+
+    /// Recover the device session and recreate the inner signer, unless
+    /// another thread has already replaced it with a newer one meanwhile
+    fn recover(&self, generation: u64) -> Result<MutexGuard<'_, (u64, S)>, Error> {
+        let (generation, client) = self.device.recover(generation)?;
+        let signer = (self.create)(client)?;
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.0 < generation {
+            *inner = (generation, signer);
+        }
+
+        Ok(inner)
+    }
+}
+
+impl<S, Sig> signature::Signer<Sig> for Signer<S>
+where
+    S: signature::Signer<Sig>,
+    Sig: signature::Signature,
+{
+    fn try_sign(&self, msg: &[u8]) -> Result<Sig, signature::Error> {
+        let generation = {
+            let inner = self.inner.lock().unwrap();
+
+            let result = self
+                .device
+                .check_fault()
+                .map_err(signature::Error::from_source)
+                .and_then(|_| inner.1.try_sign(msg));
+
+            match result {
+                Ok(signature) => return Ok(signature),
+                Err(e) => {
+                    warn!("[yubihsm:{}] signing failed: {}", self.device.id(), e);
+                    inner.0
+                }
+            }
+        };
+
+        // Don't hold the lock on the inner signer while recovering
+        let inner = self
+            .recover(generation)
+            .map_err(signature::Error::from_source)?;
+
+        inner.1.try_sign(msg)
+    }
+}
+
+#[cfg(all(test, feature = "yubihsm-mock"))]
+mod tests {
+    use super::*;
+    use signature::Signer as _;
+
+    const KEY_ID: u16 = 1;
+
+    fn device() -> Arc<Device> {
+        let config = serde_json::from_str::<YubihsmConfig>(
+            r#"{"adapter": {"type": "usb"}, "auth": {"key": 1, "password": "password"}}"#,
+        )
+        .unwrap();
+
+        let device = Device::new(config, Connector::mockhsm());
+
+        device
+            .client()
+            .unwrap()
+            .generate_asymmetric_key(
+                KEY_ID,
+                "test".into(),
+                yubihsm::Domain::DOM1,
+                yubihsm::Capability::SIGN_EDDSA,
+                yubihsm::asymmetric::Algorithm::Ed25519,
+            )
+            .unwrap();
+
+        Arc::new(device)
+    }
+
+    fn signer(device: &Arc<Device>) -> Signer<yubihsm::ed25519::Signer> {
+        Signer::new(device.clone(), |client| {
+            Ok(yubihsm::ed25519::Signer::create(client, KEY_ID).unwrap())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reauthenticates_after_signing_fault() {
+        let device = device();
+        let signer = signer(&device);
+        assert_eq!(device.status(), Status::Connected);
+
+        device.inject_faults(1);
+        let _: ed25519_dalek::Signature = signer.try_sign(b"msg").unwrap();
+        assert_eq!(device.status(), Status::Connected);
+        assert_eq!(device.state().generation, 2);
+    }
+
+    #[test]
+    fn reconnects_after_reauthentication_fault() {
+        let device = device();
+        let signer = signer(&device);
+
+        // Fail signing and re-authentication, then succeed reconnecting
+        device.inject_faults(2);
+        let _: ed25519_dalek::Signature = signer.try_sign(b"msg").unwrap();
+        assert_eq!(device.status(), Status::Connected);
+    }
+
+    #[test]
+    fn bounds_reconnect_attempts() {
+        let device = device();
+        let signer = signer(&device);
+
+        device.inject_faults(MAX_RECONNECT_ATTEMPTS + 2);
+        let result: Result<ed25519_dalek::Signature, _> = signer.try_sign(b"msg");
+        assert!(result.is_err());
+        assert_eq!(device.status(), Status::Failed);
+
+        // The liveness probe reconnects once the device is available again
+        device.probe().unwrap();
+        assert_eq!(device.status(), Status::Connected);
+
+        let _: ed25519_dalek::Signature = signer.try_sign(b"msg").unwrap();
+    }
+
+    #[test]
+    fn does_not_lock_while_recovering() {
+        let device = device();
+        let signer = Arc::new(signer(&device));
+
+        // Fail signing, re-authentication, and the first reconnect attempt
+        device.inject_faults(3);
+
+        let signing = {
+            let signer = signer.clone();
+            thread::spawn(move || signer.try_sign(b"msg"))
+        };
+
+        thread::sleep(RECONNECT_DELAY / 2);
+        assert_eq!(device.status(), Status::Reconnecting);
+        signer.with_inner(|_| ());
+
+        // Concurrent callers get the client from the recovery in progress
+        let (generation, _) = device.recover(1).unwrap();
+        let _: ed25519_dalek::Signature = signing.join().unwrap().unwrap();
+        assert_eq!(device.state().generation, generation);
+        assert_eq!(device.status(), Status::Connected);
+    }
+
+    #[test]
+    fn probe_recovers_session() {
+        let device = device();
+        device.inject_faults(1);
+        device.probe().unwrap();
+        assert_eq!(device.status(), Status::Connected);
+        assert_eq!(device.state().generation, 2);
+    }
+}
@@ -0,0 +1,58 @@
+//! Integration tests for the `bench` subcommand
+
+use crate::cli;
+use tempfile::NamedTempFile;
+
+/// Create a config file with a softsign consensus key
+fn create_config() -> NamedTempFile {
+    cli::create_config(&[("test_chain_id", r#"{ type = "hex" }"#)])
+}
+
+#[test]
+fn bench_signs_and_verifies() {
+    let config = create_config();
+    let output = cli::run_successfully(&[
+        "bench".to_owned(),
+        "-n".to_owned(),
+        "9".to_owned(),
+        "-c".to_owned(),
+        config.path().display().to_string(),
+    ]);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("test_chain_id Ed25519 consensus key"));
+    assert!(stdout.contains("signed and verified 9 messages"));
+}
+
+#[test]
+fn bench_rejects_unknown_chains() {
+    let config = create_config();
+    let output = cli::run(&[
+        "bench".to_owned(),
+        "-c".to_owned(),
+        config.path().display().to_string(),
+        "other_chain_id".to_owned(),
+    ]);
+
+    assert_eq!(output.status.code().unwrap(), 1);
+}
+
+#[test]
+fn bench_rejects_invalid_heights() {
+    let config = create_config();
+
+    for height in &["0", "-1", "9223372036854775807"] {
+        let output = cli::run(&[
+            "bench".to_owned(),
+            "-c".to_owned(),
+            config.path().display().to_string(),
+            "-h".to_owned(),
+            height.to_string(),
+        ]);
+
+        assert_eq!(output.status.code().unwrap(), 1);
+        assert!(String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("height must be between 1 and"));
+    }
+}
@@ -0,0 +1,71 @@
+//! Integration tests for the `keys` subcommand
+
+use crate::cli;
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+/// Create a config file with a softsign consensus key for one of two chains
+fn create_config() -> NamedTempFile {
+    cli::create_config(&[
+        (
+            "test_chain_id",
+            r#"{ type = "bech32", account_key_prefix = "cosmospub", consensus_key_prefix = "cosmosvalconspub" }"#,
+        ),
+        ("other_chain_id", r#"{ type = "hex" }"#),
+    ])
+}
+
+#[test]
+fn list_json() {
+    let config = create_config();
+    let output = cli::run_successfully(&[
+        "keys".to_owned(),
+        "list".to_owned(),
+        "--json".to_owned(),
+        "-c".to_owned(),
+        config.path().display().to_string(),
+    ]);
+
+    let keys: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let keys = keys.as_array().unwrap();
+    assert_eq!(keys.len(), 1);
+
+    let key = &keys[0];
+    assert_eq!(key["chain_id"], "test_chain_id");
+    assert_eq!(key["providers"][0], "softsign");
+    assert_eq!(key["role"], "consensus");
+    assert_eq!(key["algorithm"], "ed25519");
+    assert!(key["encoded"]
+        .as_str()
+        .unwrap()
+        .starts_with("cosmosvalconspub1"));
+    assert_eq!(key["pubkey"]["type"], "tendermint/PubKeyEd25519");
+    assert_eq!(key["address"].as_str().unwrap().len(), 40);
+}
+
+#[test]
+fn show_text() {
+    let config = create_config();
+    let output = cli::run_successfully(&[
+        "keys".to_owned(),
+        "show".to_owned(),
+        "-c".to_owned(),
+        config.path().display().to_string(),
+        "test_chain_id".to_owned(),
+    ]);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("providers: softsign"));
+    assert!(stdout.contains("role:      consensus"));
+    assert!(stdout.contains(r#"pubkey:    {"type":"tendermint/PubKeyEd25519","value":"#));
+
+    // Chains without keys aren't an error for `list`, but are for `show`
+    let output = cli::run(&[
+        "keys".to_owned(),
+        "show".to_owned(),
+        "-c".to_owned(),
+        config.path().display().to_string(),
+        "other_chain_id".to_owned(),
+    ]);
+    assert!(!output.status.success());
+}
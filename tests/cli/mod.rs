@@ -3,12 +3,19 @@
 use std::{
     ffi::OsStr,
     io::{self, Write},
-    process::{Command, Output},
+    process::{Command, Output, Stdio},
 };
+use tempfile::NamedTempFile;
 
-use super::KMS_EXE_PATH;
+use super::{KMS_EXE_PATH, SIGNING_KEY_PATH};
 
+#[cfg(feature = "softsign")]
+mod bench;
 mod init;
+#[cfg(feature = "softsign")]
+mod keys;
+#[cfg(feature = "softsign")]
+mod softsign;
 
 #[cfg(feature = "yubihsm")]
 mod yubihsm;
@@ -22,6 +29,32 @@ where
     Command::new(KMS_EXE_PATH).args(args).output().unwrap()
 }
 
+/// Run the `tmkms` CLI command with the given arguments, writing `input` to
+/// its standard input
+#[allow(dead_code)]
+pub fn run_with_input<I, S>(args: I, input: &str) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut child = Command::new(KMS_EXE_PATH)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
 /// Run the `tmkms` CLI command with the expectation that it will exit successfully,
 /// panicking and printing stdout/stderr if it does not
 #[allow(dead_code)]
@@ -43,6 +76,45 @@ where
     }
 }
 
+/// Create a config file for the given chains (ID and `key_format` pairs),
+/// with a softsign consensus key for the first one
+#[allow(dead_code)]
+pub fn create_config(chains: &[(&str, &str)]) -> NamedTempFile {
+    let mut config_file = NamedTempFile::new().unwrap();
+
+    // git doesn't preserve the permissions of the test keys
+    writeln!(config_file, "[secrets]\ncheck_permissions = false").unwrap();
+
+    for (id, key_format) in chains {
+        writeln!(
+            config_file,
+            r#"
+            [[chain]]
+            id = "{id}"
+            key_format = {key_format}
+            state_file = "/nonexistent/{id}_state.json"
+            "#,
+            id = id,
+            key_format = key_format
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        config_file,
+        r#"
+        [[providers.softsign]]
+        chain_ids = ["{}"]
+        key_format = "base64"
+        path = "{}"
+        "#,
+        chains[0].0, SIGNING_KEY_PATH
+    )
+    .unwrap();
+
+    config_file
+}
+
 #[test]
 fn test_usage() {
     let status_code = run(&[] as &[&OsStr]).status.code().unwrap();
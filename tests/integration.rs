@@ -12,6 +12,10 @@ use std::{
 use abscissa_core::prelude::warn;
 use chrono::{DateTime, Utc};
 use ed25519_dalek::{self as ed25519, Verifier};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
 use rand::Rng;
 use tempfile::NamedTempFile;
 
@@ -135,6 +139,10 @@ impl KmsProcess {
         writeln!(
             config_file,
             r#"
+            # git doesn't preserve the permissions of the test keys
+            [secrets]
+            check_permissions = false
+
             [[chain]]
             id = "test_chain_id"
             key_format = {{ type = "bech32", account_key_prefix = "cosmospub", consensus_key_prefix = "cosmosvalconspub" }}
@@ -165,6 +173,10 @@ impl KmsProcess {
         writeln!(
             config_file,
             r#"
+            # git doesn't preserve the permissions of the test keys
+            [secrets]
+            check_permissions = false
+
             [[chain]]
             id = "test_chain_id"
             key_format = {{ type = "bech32", account_key_prefix = "cosmospub", consensus_key_prefix = "cosmosvalconspub" }}
@@ -292,6 +304,12 @@ impl io::Read for ProtocolTester {
 
 /// Get the Ed25519 signing keypair used by the tests
 fn test_ed25519_keypair() -> ed25519::Keypair {
+    // git doesn't preserve the permissions of the test keys
+    tmkms::key_utils::permissions::init(tmkms::config::secrets::SecretsConfig {
+        check_permissions: false,
+        dir: None,
+    });
+
     tmkms::key_utils::load_base64_ed25519_key(SIGNING_KEY_PATH).unwrap()
 }
 
@@ -544,3 +562,24 @@ fn test_handle_and_sign_ping_pong() {
         PingResponse::decode(resp.as_ref()).expect("decoding ping response failed");
     });
 }
+
+#[test]
+fn test_graceful_shutdown() {
+    ProtocolTester::apply(|mut pt| {
+        // Make sure both KMS processes are up and handling requests
+        let mut buf = vec![];
+        PingRequest {}.encode(&mut buf).unwrap();
+        pt.write_all(&buf).unwrap();
+
+        let mut resp_buf = vec![0u8; 1024];
+        assert!(pt.read(&mut resp_buf).unwrap() > 0);
+
+        for device in &mut [&mut pt.tcp_device, &mut pt.unix_device] {
+            let pid = Pid::from_raw(device.process.id() as i32);
+            signal::kill(pid, Signal::SIGTERM).unwrap();
+
+            let status = device.process.wait().unwrap();
+            assert!(status.success(), "KMS exited with {}", status);
+        }
+    });
+}